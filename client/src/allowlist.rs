@@ -0,0 +1,109 @@
+use {
+    crate::{Error, Result},
+    solana_sdk::pubkey::Pubkey,
+    std::{collections::HashSet, fs, str::FromStr},
+    trader::{allowlist, instructions::AllowlistProof},
+};
+
+// A wallet allowed to fill a trade, and the most offer tokens, in base units, it can get from a fill
+#[derive(Debug, Clone, PartialEq)]
+pub struct AllowlistEntry {
+    pub wallet: Pubkey,
+    pub cap: u64,
+}
+
+/*
+ * Parses an allowlist from CSV, one `WALLET[,CAP]` line per wallet. CAP is in base units of the offer token, and
+ * wallets without one can fill trades of any size. Blank lines and lines starting with # are skipped.
+*/
+pub fn parse_allowlist(csv: &str) -> Result<Vec<AllowlistEntry>> {
+    let mut entries = Vec::<AllowlistEntry>::new();
+    let mut wallets = HashSet::<Pubkey>::new();
+
+    for (i, line) in csv.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let invalid = || Error::InvalidConfig(format!("invalid allowlist entry on line {}: {}", i + 1, line));
+        let mut fields = line.split(',').map(str::trim);
+        let wallet = fields.next().and_then(|wallet| Pubkey::from_str(wallet).ok()).ok_or_else(invalid)?;
+        let cap = match fields.next() {
+            Some(cap) => cap.parse().map_err(|_| invalid())?,
+            None => allowlist::NO_CAP,
+        };
+        if fields.next().is_some() {
+            Err(invalid())?;
+        }
+        // a wallet listed twice would have two leaves, and so two caps
+        if !wallets.insert(wallet) {
+            Err(Error::InvalidConfig(format!("wallet {} listed twice in the allowlist", wallet)))?;
+        }
+
+        entries.push(AllowlistEntry { wallet, cap });
+    }
+
+    if entries.is_empty() {
+        Err(Error::InvalidConfig(String::from("empty allowlist")))?;
+    }
+
+    Ok(entries)
+}
+
+pub fn load_allowlist(path: &str) -> Result<Vec<AllowlistEntry>> {
+    let csv = fs::read_to_string(path).map_err(Error::ConfigReadError)?;
+
+    parse_allowlist(&csv)
+}
+
+/*
+ * The Merkle tree of an allowlist, hashed as the program expects it. Each level holds the parents of the nodes of the
+ * one below, two by two, and the last node of a level with an odd number of them goes up as is.
+*/
+pub struct AllowlistTree {
+    entries: Vec<AllowlistEntry>,
+    levels: Vec<Vec<[u8; 32]>>,
+}
+
+impl AllowlistTree {
+    pub fn new(entries: Vec<AllowlistEntry>) -> Self {
+        let leaves: Vec<[u8; 32]> = entries.iter().map(|entry| allowlist::leaf(&entry.wallet, entry.cap)).collect();
+
+        let mut levels = vec![leaves];
+        while levels.last().unwrap().len() > 1 {
+            let level = levels.last().unwrap()
+                .chunks(2)
+                .map(|pair| match pair {
+                    [left, right] => allowlist::parent(left, right),
+                    [node] => *node,
+                    _ => unreachable!(),
+                })
+                .collect();
+            levels.push(level);
+        }
+
+        Self { entries, levels }
+    }
+
+    pub fn root(&self) -> [u8; 32] {
+        self.levels.last().and_then(|level| level.first()).copied().unwrap_or_default()
+    }
+
+    // The proof that wallet is in the allowlist, to fill a trade with, or None if it isn't in it
+    pub fn proof(&self, wallet: &Pubkey) -> Option<AllowlistProof> {
+        let entry = self.entries.iter().position(|entry| entry.wallet == *wallet)?;
+
+        let mut proof = Vec::<[u8; 32]>::new();
+        let mut index = entry;
+        for level in &self.levels[..self.levels.len() - 1] {
+            // the last node of an odd level has no sibling
+            if let Some(sibling) = level.get(index ^ 1) {
+                proof.push(*sibling);
+            }
+            index /= 2;
+        }
+
+        Some(AllowlistProof { cap: self.entries[entry].cap, proof })
+    }
+}
@@ -0,0 +1,64 @@
+use clap::{
+    Arg,
+    Command,
+};
+use trader_client::relay::{self, ChainStatus, OrderBook};
+use trader_client::utils::{
+    load_config,
+    ProgramConfig,
+};
+
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::{
+    commitment_config::CommitmentConfig,
+    pubkey::Pubkey,
+};
+use std::net::TcpListener;
+use std::str::FromStr;
+
+/*
+ * Keeps the orders signed with `sign-order` and posted by their makers, for takers to find with `orders`. Orders are
+ * stored in a JSON file, and the ones that expired or whose nonce was spent are dropped when orders are queried.
+*/
+fn main() {
+    const PROGRAM_CONFIG_PATH: &str = "./program_wallet.json";
+
+    let matches = Command::new("Trader relay")
+        .about("Relay orders signed off-chain between makers and takers")
+        .version("v0.0.0")
+        .arg(
+            Arg::new("bind")
+                .long("bind")
+                .value_name("ADDRESS")
+                .takes_value(true)
+                .help("Specify the address to listen on, 127.0.0.1:8900 by default."),
+        )
+        .arg(
+            Arg::new("store")
+                .long("store")
+                .value_name("FILE")
+                .takes_value(true)
+                .help("Specify the file the orders are stored in, ./orders.json by default."),
+        )
+        .get_matches();
+
+    let cfg = load_config().unwrap();
+    let cluster_url = cfg["json_rpc_url"].as_str().unwrap();
+    let conn = RpcClient::new_with_commitment(cluster_url.to_string(), CommitmentConfig::confirmed());
+
+    let program_addr = ProgramConfig::load_program_addr(PROGRAM_CONFIG_PATH.into()).unwrap();
+    let program_pubkey = Pubkey::from_str(&program_addr).unwrap();
+
+    let bind = match matches.value_of("bind") {
+        Some(addr) => addr.to_string(),
+        None => relay::DEFAULT_RELAY_URL.trim_start_matches("http://").to_string(),
+    };
+    let store = matches.value_of("store").unwrap_or("./orders.json");
+
+    let mut book = OrderBook::open(store.into()).unwrap();
+    let listener = TcpListener::bind(&bind).unwrap();
+    println!("Relaying orders on {}, stored in {}", bind, store);
+
+    let status = ChainStatus { trader_program_id: program_pubkey, conn: &conn };
+    relay::serve(listener, &mut book, &status).unwrap();
+}
@@ -1,19 +1,23 @@
-use borsh::BorshSerialize;
 use crate::{Error, Result};
+use crate::crank::load_stale_trades;
+use crate::relay;
+use crate::router::{find_route, load_open_trades, OpenTrade};
 use crate::utils::{
     create_mint_ix,
     create_account_ix,
+    get_or_create_ata,
     get_or_create_token_account,
+    resolve_decimals_of_mint,
     resolve_mint_info,
 };
-use solana_client::rpc_client::RpcClient;
+use solana_client::{rpc_client::RpcClient, rpc_request::TokenAccountsFilter};
+use solana_program::{program_option::COption, program_pack::Pack};
 use spl_associated_token_account;
 use solana_sdk::{
+    hash::Hash,
     message::Message,
-    instruction::{
-        AccountMeta, 
-        Instruction,
-    },
+    packet::PACKET_DATA_SIZE,
+    instruction::Instruction,
     pubkey::Pubkey,
     system_instruction,
     signer::{
@@ -24,11 +28,46 @@ use solana_sdk::{
 };
 //use std::fmt::Result;
 use std::str::FromStr;
+use std::thread;
+use std::time::Duration;
+use borsh::{BorshDeserialize, BorshSerialize};
 use trader::{
-    state,
-    instructions::Action,
+    instruction::{self, TradeFillAccounts},
+    instructions::{AllowlistProof, SignedOrder, TradeFill},
+    state::{self, MintLimits, MintListMode, StatsAccount},
 };
 
+// Compute units available to a transaction when no more are requested
+pub const MAX_COMPUTE_UNITS: u64 = 200_000;
+// Rough cost of filling a single trade: three token transfers, an authority change, the mint limits checks, and the
+// four stats updates
+pub const MAKE_TRADE_COMPUTE_UNITS: u64 = 60_000;
+// Rough cost of closing a single stale trade: an authority change at most, and the stats update of its maker
+pub const CLOSE_STALE_TRADE_COMPUTE_UNITS: u64 = 20_000;
+// Length of the ed25519 signature following a signed order once encoded
+const SIGNATURE_BYTES: usize = 64;
+// Lamports paid to whoever closes a trade once it's stale, if no other bounty is given
+pub const DEFAULT_CRANK_BOUNTY: u64 = 10_000;
+// Seconds an oracle price can be old when a pegged trade is accepted, if no other age is given
+pub const DEFAULT_MAX_PRICE_AGE: u32 = 60;
+// Seconds before the arbiter of a trade can release its offer on its own, if no other timeout is given
+pub const DEFAULT_DISPUTE_TIMEOUT: i64 = 7 * 24 * 60 * 60;
+
+/*
+ * A trade to be filled with make_trades. If no destination accounts are given ATAs will be used, the same way as
+ * in make_trade.
+*/
+pub struct TradeRequest {
+    pub trade_id: Pubkey,
+    pub offer_owner: Pubkey,
+    pub offer: u64,
+    pub offer_src: Pubkey,
+    pub offer_dst: Option<Pubkey>,
+    pub trade: u64,
+    pub trade_src: Pubkey,
+    pub trade_dst: Option<Pubkey>,
+}
+
 /*
  * Sets up all base accounts to test all oprations. Expects two wallets, wallet1 will hold Mint account A, wallet2
  * will hold Mintaccount B.
@@ -156,26 +195,29 @@ pub fn setup_accounts(
     println!("Trade accounts setup finished!");
 
     // report
-    println!("");
-    println!("Mint account for offer: {}", mint_a_keypair.pubkey().to_string());
-    println!("Mint account for trade: {}", mint_b_keypair.pubkey().to_string());
+    println!();
+    println!("Mint account for offer: {}", mint_a_keypair.pubkey());
+    println!("Mint account for trade: {}", mint_b_keypair.pubkey());
     println!("Offer");
-    println!("\t- src: {} with balance {}", account_1_keypair.pubkey().to_string(), offer);
-    println!("\t- dst: {}", account_3_keypair.pubkey().to_string());
+    println!("\t- src: {} with balance {}", account_1_keypair.pubkey(), offer);
+    println!("\t- dst: {}", account_3_keypair.pubkey());
     println!("Trade");
-    println!("\t- src: {} with balance {}", account_4_keypair.pubkey().to_string(), trade);
-    println!("\t- dst: {}", account_2_keypair.pubkey().to_string());
-    println!(""); 
+    println!("\t- src: {} with balance {}", account_4_keypair.pubkey(), trade);
+    println!("\t- dst: {}", account_2_keypair.pubkey());
+    println!();
 
     Ok(())
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn create_trade(
     trade: u64, 
     owner: Keypair,
     token_account: Pubkey,
     trade_mint: Pubkey,
     trade_dst: Option<Pubkey>,
+    args: instruction::CreateTradeArgs,
+    receipt: bool,
     trader_program_id: Pubkey, 
     conn: &RpcClient,
 ) -> Result<()> {
@@ -193,65 +235,99 @@ pub fn create_trade(
         Err(Error::InvalidConfig(String::from_str("not a program").unwrap()))?;
     }
 
+    // the bounty is deposited in the trade account on top of the rent. It goes back to the owner with the rent when
+    // the trade is filled
     let create_trader_account_ix = system_instruction::create_account(
         &owner.pubkey(),
         &trade_account_keypair.pubkey(),
-        conn.get_minimum_balance_for_rent_exemption(state::AccountTrade::size()).unwrap(),
+        conn.get_minimum_balance_for_rent_exemption(state::AccountTrade::size()).unwrap() + args.bounty,
         state::AccountTrade::size() as u64,
         &trader_program_id,
     );
 
-    // this should allow one to have as many trades as they want
-    // generate it off-chain to save computation credits
-    let (pda_pubkey, bump_seed) = Pubkey::find_program_address(
-        &[trade_account_keypair.pubkey().as_ref()],
-            &trader_program_id,
-    );
-
-    // if no destination is specified, we expect an ATA to be used
-    let trade_dst_pubkey = match trade_dst {
-        Some(addr) => addr,
-        None => {
+    // if no destination is specified, we expect an ATA to be used. With splits, the first one is the trade dst
+    let trade_dst_pubkey = match (trade_dst, args.splits.first()) {
+        (Some(addr), _) => addr,
+        (None, Some(split)) => split.destination,
+        (None, None) => {
             spl_associated_token_account::get_associated_token_address(&owner.pubkey(), &trade_mint)
         }
     };
 
-    let action = Action::CreateTrade {
-        bump_seed: bump_seed,
-        trade: trade,
-    };
-    let buf = &action.try_to_vec().unwrap()[..];
-
-    let init_trade_ix = Instruction::new_with_bytes(
-        trader_program_id,
-        buf,
-        vec![
-            AccountMeta::new_readonly(owner.pubkey(), true),
-            AccountMeta::new(trade_account_keypair.pubkey(), false),
-            AccountMeta::new(token_account, false),
-            AccountMeta::new_readonly(trade_mint, false),
-            AccountMeta::new_readonly(trade_dst_pubkey, false),
-            AccountMeta::new_readonly(pda_pubkey, false),
-            AccountMeta::new_readonly(spl_token::id(), false),
-        ],
+    let offer_mint = resolve_mint_info(&token_account, conn)?;
+
+    // the receipt mint is created for the trade, with its PDA as mint authority, along with the ATA of owner the
+    // receipt is minted to
+    let receipt_mint_keypair = Keypair::new();
+    let mut ixs = vec![create_trader_account_ix];
+    if receipt {
+        let (pda, _) = instruction::find_trade_pda(&trader_program_id, &trade_account_keypair.pubkey());
+        ixs.push(system_instruction::create_account(
+            &owner.pubkey(),
+            &receipt_mint_keypair.pubkey(),
+            conn.get_minimum_balance_for_rent_exemption(spl_token::state::Mint::LEN).unwrap(),
+            spl_token::state::Mint::LEN as u64,
+            &spl_token::id(),
+        ));
+        ixs.push(spl_token::instruction::initialize_mint(
+            &spl_token::id(),
+            &receipt_mint_keypair.pubkey(),
+            &pda,
+            None,
+            0,
+        ).unwrap());
+        ixs.push(spl_associated_token_account::create_associated_token_account(
+            &owner.pubkey(),
+            &owner.pubkey(),
+            &receipt_mint_keypair.pubkey(),
+        ));
+    }
+
+    // the PDA of the trade is derived from the trade account, which should allow one to have as many trades as they
+    // want. It is generated off-chain to save computation credits
+    let init_trade_ix = instruction::create_trade(
+        &trader_program_id,
+        &owner.pubkey(),
+        &trade_account_keypair.pubkey(),
+        &token_account,
+        &offer_mint,
+        &trade_mint,
+        &trade_dst_pubkey,
+        trade,
+        instruction::CreateTradeArgs {
+            receipt: if receipt { Some(receipt_mint_keypair.pubkey()) } else { None },
+            ..args
+        },
     );
-    let message = Message::new(&[create_trader_account_ix, init_trade_ix], Some(&owner.pubkey()));
-    let transaction = Transaction::new(&[&owner, &trade_account_keypair], message, conn.get_latest_blockhash().unwrap());
+    ixs.push(init_trade_ix);
+    let message = Message::new(&ixs, Some(&owner.pubkey()));
+    let mut signers = vec![&owner, trade_account_keypair];
+    if receipt {
+        signers.push(&receipt_mint_keypair);
+    }
+    let transaction = Transaction::new(&signers, message, conn.get_latest_blockhash().unwrap());
 
     conn.send_and_confirm_transaction(&transaction).unwrap();
 
-    println!("New trade id: {}", trade_account_keypair.pubkey().to_string());
+    println!("New trade id: {}", trade_account_keypair.pubkey());
+    if receipt {
+        println!("Receipt mint: {}", receipt_mint_keypair.pubkey());
+    }
 
     Ok(())
 }
 
 /*
- * owner will be funding ata accounts if any needs to be created.
+ * owner will be funding ata accounts if any needs to be created. Trades in Arbitrated mode need the keypair of their
+ * arbiter, who co-signs the fill, and allowlisted trades the proof that owner is in their allowlist.
 */
+#[allow(clippy::too_many_arguments)]
 pub fn make_trade(
     offer: u64,
     trade: u64,
     owner: Keypair,
+    arbiter: Option<Keypair>,
+    allowlist: Option<AllowlistProof>,
     wallet1: Pubkey,
     trade_id: Pubkey,
     trader_program_id: Pubkey,
@@ -264,56 +340,1221 @@ pub fn make_trade(
 ) -> Result<()> {
     println!("Making trade...");
 
-    let (pda_pubkey, _) = Pubkey::find_program_address(
-        &[trade_id.as_ref()],
-            &trader_program_id,
+    let trade_mint_addr = resolve_mint_info(&trade_src, conn).unwrap();
+    let fee_ata_addr = create_fee_account(&owner, &trade_mint_addr, &program_authority, conn);
+    let trade_account = load_trade(&trade_id, conn)?;
+    // the trade amount of a trade with a receipt goes to the holder of the receipt, by default to their ATA
+    let receipt = find_receipt(&trade_account, conn)?;
+    let trade_dst = match (trade_dst, receipt) {
+        (Some(addr), _) => addr,
+        (None, Some((_, holder))) => get_or_create_ata(&owner, holder, trade_mint_addr, conn)?,
+        (None, None) => get_or_create_token_account(&owner, wallet1, trade_src, conn).unwrap(),
+    };
+    let mut accounts = TradeFillAccounts {
+        trade: trade_id,
+        offer_src,
+        trade_dst,
+        trade_src,
+        offer_dst: offer_dst.unwrap_or_else(|| get_or_create_token_account(&owner, owner.pubkey(), offer_src, conn).unwrap()),
+        offer_owner: wallet1,
+        fee_account: fee_ata_addr,
+        ..TradeFillAccounts::default()
+    };
+    accounts.set_trailing_accounts(&trader_program_id, &trade_account);
+    accounts.receipt = receipt.map(|(receipt_account, _)| (trade_account.receipt_mint, receipt_account));
+    if accounts.trade_escrow.is_some() {
+        create_trade_escrow(&owner, &trader_program_id, &trade_id, trade_mint_addr, conn)?;
+    }
+    if accounts.vesting_escrow.is_some() {
+        create_vesting_escrow(&owner, &trader_program_id, &trade_id, trade_account.offer_mint, conn)?;
+    }
+    if accounts.arbiter.is_some() && accounts.arbiter != arbiter.as_ref().map(|keypair| keypair.pubkey()) {
+        Err(Error::InvalidConfig(format!("trade {} needs the keypair of its arbiter", trade_id)))?;
+    }
+    if trade_account.is_allowlisted() && allowlist.is_none() {
+        Err(Error::InvalidConfig(format!("trade {} needs a proof that the taker is in its allowlist", trade_id)))?;
+    }
+
+    let make_trade_ix = instruction::make_trade(
+        &trader_program_id,
+        &owner.pubkey(),
+        &accounts,
+        offer,
+        trade,
+        allowlist,
     );
+    let mut signers = vec![&owner];
+    if let (Some(keypair), Some(_)) = (&arbiter, accounts.arbiter) {
+        signers.push(keypair);
+    }
+    let message = Message::new(&[make_trade_ix], Some(&owner.pubkey()));
+    let transaction = Transaction::new(&signers, message, conn.get_latest_blockhash().unwrap());
+
+    conn.send_and_confirm_transaction(&transaction).unwrap();
+
+    println!("Trade done...");
+
+    Ok(())
+}
+
+/*
+ * Fills several trades with a single signature of owner, packing as many trades as fit in each transaction. Each
+ * transaction is atomic: either all of its trades are filled or none is.
+*/
+pub fn make_trades(
+    requests: Vec<TradeRequest>,
+    owner: Keypair,
+    trader_program_id: Pubkey,
+    program_authority: Pubkey,
+    conn: &RpcClient,
+) -> Result<()> {
+    println!("Making {} trades...", requests.len());
+
+    let mut fills = Vec::<(TradeFill, TradeFillAccounts)>::new();
+    for request in requests {
+        let trade_mint_addr = resolve_mint_info(&request.trade_src, conn).unwrap();
+        let fee_ata_addr = create_fee_account(&owner, &trade_mint_addr, &program_authority, conn);
+        let trade_account = load_trade(&request.trade_id, conn)?;
+        let receipt = find_receipt(&trade_account, conn)?;
+        let trade_dst = match (request.trade_dst, receipt) {
+            (Some(addr), _) => addr,
+            (None, Some((_, holder))) => get_or_create_ata(&owner, holder, trade_mint_addr, conn)?,
+            (None, None) => get_or_create_token_account(&owner, request.offer_owner, request.trade_src, conn).unwrap(),
+        };
+        let offer_dst = request.offer_dst.unwrap_or_else(
+            || get_or_create_token_account(&owner, owner.pubkey(), request.offer_src, conn).unwrap()
+        );
+
+        let fill = TradeFill {
+            expected_offer: request.offer,
+            expected_trade: request.trade,
+            allowlist: None,
+        };
+        let mut accounts = TradeFillAccounts {
+            trade: request.trade_id,
+            offer_src: request.offer_src,
+            trade_dst,
+            trade_src: request.trade_src,
+            offer_dst,
+            offer_owner: request.offer_owner,
+            fee_account: fee_ata_addr,
+            ..TradeFillAccounts::default()
+        };
+        accounts.set_trailing_accounts(&trader_program_id, &trade_account);
+        accounts.receipt = receipt.map(|(receipt_account, _)| (trade_account.receipt_mint, receipt_account));
+        if accounts.trade_escrow.is_some() {
+            create_trade_escrow(&owner, &trader_program_id, &request.trade_id, trade_mint_addr, conn)?;
+        }
+        if accounts.vesting_escrow.is_some() {
+            create_vesting_escrow(&owner, &trader_program_id, &request.trade_id, trade_account.offer_mint, conn)?;
+        }
+        // the transactions are only signed by owner
+        if accounts.arbiter.is_some() {
+            Err(Error::InvalidConfig(format!("trade {} needs the signature of its arbiter", request.trade_id)))?;
+        }
+        if trade_account.is_allowlisted() {
+            Err(Error::InvalidConfig(format!("trade {} needs a proof of its allowlist", request.trade_id)))?;
+        }
+        fills.push((fill, accounts));
+    }
+
+    for ix in pack_make_trades(&owner.pubkey(), &trader_program_id, &fills) {
+        let message = Message::new(&[ix], Some(&owner.pubkey()));
+        let transaction = Transaction::new(&[&owner], message, conn.get_latest_blockhash().unwrap());
+
+        conn.send_and_confirm_transaction(&transaction).unwrap();
+        println!("Batch of trades done...");
+    }
+
+    println!("Trades done...");
+
+    Ok(())
+}
+
+/*
+ * Swaps amount of from_mint tokens for to_mint tokens going through the open trades, in a single transaction. The
+ * taker's ATAs are used for every mint along the route, so that the offer received from each trade pays for the
+ * next one.
+*/
+pub fn route(
+    amount: u64,
+    from_mint: Pubkey,
+    to_mint: Pubkey,
+    owner: Keypair,
+    trader_program_id: Pubkey,
+    program_authority: Pubkey,
+    conn: &RpcClient,
+) -> Result<()> {
+    println!("Routing...");
+
+    let trades = load_open_trades(&trader_program_id, conn)?;
+    let route = find_route(&trades, &from_mint, &to_mint, amount)
+        .ok_or(Error::RouteNotFound(from_mint, to_mint))?;
+
+    let mut fills = Vec::<(TradeFill, TradeFillAccounts)>::new();
+    for hop in route.iter() {
+        println!(
+            "\t- trade {}: {} of {} for {} of {}",
+            hop.trade_id, hop.account.trade_amount, hop.account.trade_mint, hop.account.offer_amount, hop.offer_mint,
+        );
+
+        let trade_src = get_or_create_ata(&owner, owner.pubkey(), hop.account.trade_mint, conn)?;
+        let offer_dst = get_or_create_ata(&owner, owner.pubkey(), hop.offer_mint, conn)?;
+        let fee_ata_addr = create_fee_account(&owner, &hop.account.trade_mint, &program_authority, conn);
+
+        let fill = TradeFill {
+            expected_offer: hop.account.offer_amount,
+            expected_trade: hop.account.trade_amount,
+            allowlist: None,
+        };
+        // pegged and arbitrated trades are not routed, so the only trailing accounts are split destinations and hooks
+        let mut accounts = TradeFillAccounts {
+            trade: hop.trade_id,
+            offer_src: hop.account.offer_token_account,
+            trade_dst: hop.account.trade_dst_account,
+            trade_src,
+            offer_dst,
+            offer_owner: hop.account.authority,
+            fee_account: fee_ata_addr,
+            ..TradeFillAccounts::default()
+        };
+        accounts.set_trailing_accounts(&trader_program_id, &hop.account);
+        fills.push((fill, accounts));
+    }
+
+    // routes are short enough to always fit in a single transaction, which keeps the whole swap atomic
+    let ix = instruction::make_trades(&trader_program_id, &owner.pubkey(), &fills);
+    let message = Message::new(&[ix], Some(&owner.pubkey()));
+    let transaction = Transaction::new(&[&owner], message, conn.get_latest_blockhash().unwrap());
+
+    conn.send_and_confirm_transaction(&transaction).unwrap();
+
+    println!("Route done...");
+
+    Ok(())
+}
+
+/*
+ * Packs trade fills into as few MakeTrades instructions as possible, making sure each one fits in a transaction
+ * of its own, both in size and in compute units.
+*/
+pub fn pack_make_trades(
+    taker: &Pubkey,
+    trader_program_id: &Pubkey,
+    fills: &[(TradeFill, TradeFillAccounts)],
+) -> Vec<Instruction> {
+    let mut ixs = Vec::<Instruction>::new();
+    let mut batch = Vec::<(TradeFill, TradeFillAccounts)>::new();
+
+    for fill in fills {
+        batch.push(fill.clone());
+
+        let fits = batch.len() as u64 * MAKE_TRADE_COMPUTE_UNITS <= MAX_COMPUTE_UNITS
+            && transaction_size(&[instruction::make_trades(trader_program_id, taker, &batch)], taker) <= PACKET_DATA_SIZE;
+        // a single trade always goes on its own, even if too big, and let the cluster reject it
+        if !fits && batch.len() > 1 {
+            let last = batch.pop().unwrap();
+            ixs.push(instruction::make_trades(trader_program_id, taker, &batch));
+            batch = vec![last];
+        }
+    }
+
+    if !batch.is_empty() {
+        ixs.push(instruction::make_trades(trader_program_id, taker, &batch));
+    }
+
+    ixs
+}
+
+/*
+ * Closes every stale trade of the program, collecting their bounties. With an interval it keeps scanning the program
+ * accounts for new stale trades, waiting interval seconds between scans, otherwise it stops after the first one.
+*/
+pub fn crank(
+    owner: Keypair,
+    trader_program_id: Pubkey,
+    interval: Option<u64>,
+    conn: &RpcClient,
+) -> Result<()> {
+    loop {
+        let now = conn.get_slot()
+            .and_then(|slot| conn.get_block_time(slot))
+            .map_err(Error::RpcError)?;
+        let trades = load_stale_trades(&trader_program_id, now, conn)?;
+        println!("Found {} stale trades...", trades.len());
+
+        for ixs in pack_close_stale_trades(&owner.pubkey(), &trader_program_id, &trades) {
+            let message = Message::new(&ixs, Some(&owner.pubkey()));
+            let transaction = Transaction::new(&[&owner], message, conn.get_latest_blockhash().unwrap());
+
+            // some other crank may have closed them first, which is fine
+            match conn.send_and_confirm_transaction(&transaction) {
+                Ok(_) => println!("Closed a batch of {} trades...", ixs.len()),
+                Err(err) => println!("Ignoring error: {}", err),
+            }
+        }
+
+        match interval {
+            Some(secs) => thread::sleep(Duration::from_secs(secs)),
+            None => break,
+        }
+    }
+
+    Ok(())
+}
+
+/*
+ * Packs the CloseStaleTrade instructions of trades into as few transactions as possible, making sure each one fits,
+ * both in size and in compute units.
+*/
+pub fn pack_close_stale_trades(
+    cranker: &Pubkey,
+    trader_program_id: &Pubkey,
+    trades: &[OpenTrade],
+) -> Vec<Vec<Instruction>> {
+    let mut batches = Vec::<Vec<Instruction>>::new();
+    let mut batch = Vec::<Instruction>::new();
+
+    for trade in trades {
+        batch.push(instruction::close_stale_trade(
+            trader_program_id,
+            cranker,
+            &trade.trade_id,
+            &trade.account.offer_token_account,
+            &trade.account.authority,
+        ));
+
+        let fits = batch.len() as u64 * CLOSE_STALE_TRADE_COMPUTE_UNITS <= MAX_COMPUTE_UNITS
+            && transaction_size(&batch, cranker) <= PACKET_DATA_SIZE;
+        if !fits && batch.len() > 1 {
+            let last = batch.pop().unwrap();
+            batches.push(batch);
+            batch = vec![last];
+        }
+    }
+
+    if !batch.is_empty() {
+        batches.push(batch);
+    }
+
+    batches
+}
+
+/*
+ * Adds amount to the offer of a trade, or withdraws it when increase is false. The tokens come from, or go to,
+ * token_account, which defaults to the ATA of owner.
+*/
+#[allow(clippy::too_many_arguments)]
+pub fn change_offer(
+    amount: u64,
+    increase: bool,
+    proportional: bool,
+    owner: Keypair,
+    trade_id: Pubkey,
+    token_account: Option<Pubkey>,
+    trader_program_id: Pubkey,
+    conn: &RpcClient,
+) -> Result<()> {
+    println!("{} offer...", if increase { "Increasing" } else { "Decreasing" });
+
+    let trade = load_trade(&trade_id, conn)?;
 
-    let action = Action::MakeTrade { 
-        expected_offer: offer,
-        expected_trade: trade,
+    let token_account = match token_account {
+        Some(addr) => addr,
+        None => get_or_create_token_account(&owner, owner.pubkey(), trade.offer_token_account, conn)?,
     };
-    let buf = &action.try_to_vec().unwrap()[..];
 
-    let trade_mint_addr = resolve_mint_info(&trade_src, conn).unwrap();
-    let fee_ata_ix = spl_associated_token_account::create_associated_token_account(
+    let ix = if increase {
+        instruction::increase_offer(
+            &trader_program_id,
+            &owner.pubkey(),
+            &trade_id,
+            &trade.offer_token_account,
+            &token_account,
+            amount,
+            proportional,
+        )
+    } else {
+        instruction::decrease_offer(
+            &trader_program_id,
+            &owner.pubkey(),
+            &trade_id,
+            &trade.offer_token_account,
+            &token_account,
+            amount,
+            proportional,
+        )
+    };
+    let message = Message::new(&[ix], Some(&owner.pubkey()));
+    let transaction = Transaction::new(&[&owner], message, conn.get_latest_blockhash().unwrap());
+
+    conn.send_and_confirm_transaction(&transaction).unwrap();
+
+    quote(trade_id, conn)
+}
+
+/*
+ * Hands a trade over to new_authority. The trade amount goes to trade_dst from then on, the ATA of new_authority by
+ * default. The new authority co-signs if its keypair is given.
+*/
+pub fn transfer_trade_authority(
+    owner: Keypair,
+    trade_id: Pubkey,
+    new_authority: Pubkey,
+    new_authority_keypair: Option<Keypair>,
+    trade_dst: Option<Pubkey>,
+    trader_program_id: Pubkey,
+    conn: &RpcClient,
+) -> Result<()> {
+    println!("Transfering trade to {}...", new_authority);
+
+    let trade = load_trade(&trade_id, conn)?;
+    let trade_dst = trade_dst.unwrap_or_else(|| {
+        spl_associated_token_account::get_associated_token_address(&new_authority, &trade.trade_mint)
+    });
+
+    let ix = instruction::transfer_trade_authority(
+        &trader_program_id,
         &owner.pubkey(),
-        &program_authority,
-        &trade_mint_addr,
+        &trade_id,
+        &new_authority,
+        new_authority_keypair.is_some(),
+        Some(&trade_dst),
     );
-    let message = Message::new(&[fee_ata_ix], Some(&owner.pubkey()));
+    let mut signers = vec![&owner];
+    if let Some(keypair) = &new_authority_keypair {
+        signers.push(keypair);
+    }
+    let message = Message::new(&[ix], Some(&owner.pubkey()));
+    let transaction = Transaction::new(&signers, message, conn.get_latest_blockhash().unwrap());
+
+    conn.send_and_confirm_transaction(&transaction).unwrap();
+
+    println!("Trade {} now belongs to {}", trade_id, new_authority);
+
+    Ok(())
+}
+
+/*
+ * Settles a dispute over a trade in Arbitrated mode, owner being its arbiter. The offer goes to the offer_dst token
+ * account of the taker if given, otherwise back to the owner of the trade.
+*/
+pub fn arbiter_release(
+    owner: Keypair,
+    trade_id: Pubkey,
+    offer_dst: Option<Pubkey>,
+    trader_program_id: Pubkey,
+    conn: &RpcClient,
+) -> Result<()> {
+    println!("Releasing trade {}...", trade_id);
+
+    let trade = load_trade(&trade_id, conn)?;
+
+    let ix = instruction::arbiter_release(
+        &trader_program_id,
+        &owner.pubkey(),
+        &trade_id,
+        &trade.offer_token_account,
+        &trade.authority,
+        offer_dst.as_ref(),
+    );
+    let message = Message::new(&[ix], Some(&owner.pubkey()));
     let transaction = Transaction::new(&[&owner], message, conn.get_latest_blockhash().unwrap());
-    // TODO: filter AlreadyInUse errors
-    match conn.send_and_confirm_transaction(&transaction) {
-        Ok(_) => println!("Account created to store a trade fee."),
-        Err(err) => println!("Ignoring error: {}", err)
+
+    conn.send_and_confirm_transaction(&transaction).unwrap();
+
+    match offer_dst {
+        Some(addr) => println!("Offer released to {}", addr),
+        None => println!("Offer returned to {}", trade.authority),
     }
 
-    let fee_ata_addr = spl_associated_token_account::get_associated_token_address(&program_authority, &trade_mint_addr);
-    println!("Fee account address: {}", fee_ata_addr.to_string());
-
-    let make_trade_ix = Instruction::new_with_bytes(
-        trader_program_id,
-        buf,
-        vec![
-            AccountMeta::new_readonly(owner.pubkey(), true),
-            AccountMeta::new(trade_id, false),
-            AccountMeta::new_readonly(pda_pubkey, false),
-            AccountMeta::new(offer_src, false),
-            AccountMeta::new(trade_dst.unwrap_or_else(|| get_or_create_token_account(&owner, wallet1, trade_src, conn).unwrap()), false),
-            AccountMeta::new(trade_src, false),
-            AccountMeta::new(offer_dst.unwrap_or_else(|| get_or_create_token_account(&owner, owner.pubkey(), offer_src, conn).unwrap()), false),
-            AccountMeta::new(wallet1, false),
-            AccountMeta::new(fee_ata_addr, false),
-            AccountMeta::new_readonly(spl_token::id(), false),
-        ],
+    Ok(())
+}
+
+/*
+ * Buys the option of a trade in CoveredCall mode, paying its premium from premium_src. owner becomes the holder of the
+ * option.
+*/
+pub fn buy_option(
+    owner: Keypair,
+    trade_id: Pubkey,
+    premium_src: Pubkey,
+    trader_program_id: Pubkey,
+    conn: &RpcClient,
+) -> Result<()> {
+    println!("Buying option {}...", trade_id);
+
+    let trade = load_trade(&trade_id, conn)?;
+
+    let ix = instruction::buy_option(
+        &trader_program_id,
+        &owner.pubkey(),
+        &trade_id,
+        &premium_src,
+        &trade.trade_dst_account,
+        trade.premium,
     );
-    let message = Message::new(&[make_trade_ix], Some(&owner.pubkey()));
+    let message = Message::new(&[ix], Some(&owner.pubkey()));
     let transaction = Transaction::new(&[&owner], message, conn.get_latest_blockhash().unwrap());
 
     conn.send_and_confirm_transaction(&transaction).unwrap();
 
-    println!("Trade done...");
+    println!("Option bought, it can be exercised until {}", trade.expires_at);
+
+    Ok(())
+}
+
+/*
+ * Exercises the option of a trade in CoveredCall mode, owner being its holder. The strike is paid from trade_src and
+ * the offer goes to offer_dst, the ATA of owner by default, which is created if needed.
+*/
+pub fn exercise_option(
+    owner: Keypair,
+    trade_id: Pubkey,
+    trade_src: Pubkey,
+    offer_dst: Option<Pubkey>,
+    trader_program_id: Pubkey,
+    program_authority: Pubkey,
+    conn: &RpcClient,
+) -> Result<()> {
+    println!("Exercising option {}...", trade_id);
+
+    let trade = load_trade(&trade_id, conn)?;
+    let fee_ata_addr = create_fee_account(&owner, &trade.trade_mint, &program_authority, conn);
+    let mut accounts = TradeFillAccounts {
+        trade: trade_id,
+        offer_src: trade.offer_token_account,
+        trade_dst: trade.trade_dst_account,
+        trade_src,
+        offer_dst: offer_dst.unwrap_or_else(|| {
+            get_or_create_token_account(&owner, owner.pubkey(), trade.offer_token_account, conn).unwrap()
+        }),
+        offer_owner: trade.authority,
+        fee_account: fee_ata_addr,
+        ..TradeFillAccounts::default()
+    };
+    accounts.set_trailing_accounts(&trader_program_id, &trade);
+    let strike = trade.trade_amount_for(trade.offer_amount).unwrap_or(trade.trade_amount);
+
+    let ix = instruction::exercise_option(&trader_program_id, &owner.pubkey(), &accounts, trade.offer_amount, strike);
+    let message = Message::new(&[ix], Some(&owner.pubkey()));
+    let transaction = Transaction::new(&[&owner], message, conn.get_latest_blockhash().unwrap());
+
+    conn.send_and_confirm_transaction(&transaction).unwrap();
+
+    println!("Option exercised...");
+
+    Ok(())
+}
+
+/*
+ * Returns the offer account of an expired option to owner, the owner of the trade.
+*/
+pub fn reclaim_underlying(
+    owner: Keypair,
+    trade_id: Pubkey,
+    trader_program_id: Pubkey,
+    conn: &RpcClient,
+) -> Result<()> {
+    println!("Reclaiming underlying of {}...", trade_id);
+
+    let trade = load_trade(&trade_id, conn)?;
+
+    let ix = instruction::reclaim_underlying(&trader_program_id, &owner.pubkey(), &trade_id, &trade.offer_token_account);
+    let message = Message::new(&[ix], Some(&owner.pubkey()));
+    let transaction = Transaction::new(&[&owner], message, conn.get_latest_blockhash().unwrap());
+
+    conn.send_and_confirm_transaction(&transaction).unwrap();
+
+    println!("Offer account {} returned", trade.offer_token_account);
+
+    Ok(())
+}
+
+/*
+ * Settles a trade in Forward mode once its settlement date has passed, delivering the offer and the trade amount if it
+ * was filled, or returning the offer account to its owner otherwise. Anyone can settle a trade, owner only pays for
+ * the transaction.
+*/
+pub fn settle(
+    owner: Keypair,
+    trade_id: Pubkey,
+    trader_program_id: Pubkey,
+    conn: &RpcClient,
+) -> Result<()> {
+    println!("Settling trade {}...", trade_id);
+
+    let trade = load_trade(&trade_id, conn)?;
+
+    let ix = instruction::settle(&trader_program_id, &trade_id, &trade);
+    let message = Message::new(&[ix], Some(&owner.pubkey()));
+    let transaction = Transaction::new(&[&owner], message, conn.get_latest_blockhash().unwrap());
+
+    conn.send_and_confirm_transaction(&transaction).unwrap();
+
+    if trade.is_committed() {
+        println!("Offer delivered to {}, trade amount to {}", trade.offer_dst_account, trade.trade_dst_account);
+    } else {
+        println!("Trade never filled, offer account {} returned", trade.offer_token_account);
+    }
 
     Ok(())
 }
+
+/*
+ * Claims the share of the offer of a vesting trade that vested since the last claim. owner must be the taker of the
+ * trade, and gets the rent of the vesting escrow back once all of the offer is claimed.
+*/
+pub fn claim_vested(
+    owner: Keypair,
+    trade_id: Pubkey,
+    trader_program_id: Pubkey,
+    conn: &RpcClient,
+) -> Result<()> {
+    println!("Claiming vested offer of trade {}...", trade_id);
+
+    let trade = load_trade(&trade_id, conn)?;
+    if trade.taker != owner.pubkey() {
+        Err(Error::InvalidConfig(format!("trade {} wasn't filled by {}", trade_id, owner.pubkey())))?;
+    }
+
+    let ix = instruction::claim_vested(&trader_program_id, &trade_id, &trade);
+    let message = Message::new(&[ix], Some(&owner.pubkey()));
+    let transaction = Transaction::new(&[&owner], message, conn.get_latest_blockhash().unwrap());
+
+    conn.send_and_confirm_transaction(&transaction).unwrap();
+
+    println!("Vested offer claimed to {}", trade.offer_dst_account);
+
+    Ok(())
+}
+
+/*
+ * Signs an order of offer_amount of the tokens in offer_src for trade_amount of trade_mint, to be filled by anyone
+ * with fill_order before expires_at. The trade amount goes to trade_dst, the ATA of owner by default. The nonces
+ * account of owner is created if it doesn't exist yet, and approved as delegate of offer_src for the offer amount on
+ * top of whatever it was already approved for. Prints the order, signed and encoded as expected by fill_order, and
+ * returns it.
+*/
+#[allow(clippy::too_many_arguments)]
+pub fn sign_order(
+    offer_amount: u64,
+    trade_amount: u64,
+    owner: Keypair,
+    offer_src: Pubkey,
+    trade_mint: Pubkey,
+    trade_dst: Option<Pubkey>,
+    nonce: u64,
+    expires_at: i64,
+    trader_program_id: Pubkey,
+    conn: &RpcClient,
+) -> Result<String> {
+    println!("Signing order...");
+
+    let (nonces, _) = instruction::find_nonces_pda(&trader_program_id, &owner.pubkey());
+    let mut ixs = Vec::<Instruction>::new();
+    if conn.get_account(&nonces).is_err() {
+        ixs.push(instruction::init_nonces(&trader_program_id, &owner.pubkey()));
+    }
+
+    let offer_account = conn.get_account(&offer_src).map_err(Error::RpcError)?;
+    let offer_token = spl_token::state::Account::unpack_from_slice(&offer_account.data)
+        .map_err(|e| Error::InvalidConfig(format!("{} is not a token account: ({})", offer_src, e)))?;
+    // an account has a single delegate, so other signed orders filled from it share the allowance
+    let allowance = if offer_token.delegate == COption::Some(nonces) {
+        offer_token.delegated_amount.saturating_add(offer_amount)
+    } else {
+        offer_amount
+    };
+    ixs.push(spl_token::instruction::approve(
+        &spl_token::id(),
+        &offer_src,
+        &nonces,
+        &owner.pubkey(),
+        &[&owner.pubkey()],
+        allowance,
+    ).unwrap());
+
+    let message = Message::new(&ixs, Some(&owner.pubkey()));
+    let transaction = Transaction::new(&[&owner], message, conn.get_latest_blockhash().unwrap());
+    conn.send_and_confirm_transaction(&transaction).unwrap();
+
+    let order = SignedOrder {
+        maker: owner.pubkey(),
+        offer_mint: offer_token.mint,
+        trade_mint,
+        trade_dst: trade_dst.unwrap_or_else(|| {
+            spl_associated_token_account::get_associated_token_address(&owner.pubkey(), &trade_mint)
+        }),
+        offer_amount,
+        trade_amount,
+        nonce,
+        expires_at,
+    };
+    let signature = owner.sign_message(&order.try_to_vec().unwrap());
+
+    let encoded = encode_signed_order(&order, signature.as_ref());
+    println!("Signed order: {}", encoded);
+
+    Ok(encoded)
+}
+
+/*
+ * Fills an order signed with sign_order, owner being the taker. The offer goes to offer_dst, the ATA of owner by
+ * default, which is created if needed.
+*/
+pub fn fill_order(
+    encoded: &str,
+    owner: Keypair,
+    trade_src: Pubkey,
+    offer_dst: Option<Pubkey>,
+    trader_program_id: Pubkey,
+    program_authority: Pubkey,
+    conn: &RpcClient,
+) -> Result<()> {
+    let (order, signature) = decode_signed_order(encoded)?;
+    println!("Filling order {} of {}...", order.nonce, order.maker);
+
+    let offer_src = find_order_offer_src(&order, &trader_program_id, conn)?;
+    let offer_dst = match offer_dst {
+        Some(addr) => addr,
+        None => get_or_create_ata(&owner, owner.pubkey(), order.offer_mint, conn)?,
+    };
+    let fee_ata_addr = create_fee_account(&owner, &order.trade_mint, &program_authority, conn);
+
+    let ixs = instruction::fill_signed_order(
+        &trader_program_id,
+        &owner.pubkey(),
+        &order,
+        &signature,
+        &offer_src,
+        &trade_src,
+        &offer_dst,
+        &fee_ata_addr,
+    );
+    let message = Message::new(&ixs, Some(&owner.pubkey()));
+    let transaction = Transaction::new(&[&owner], message, conn.get_latest_blockhash().unwrap());
+
+    conn.send_and_confirm_transaction(&transaction).unwrap();
+
+    println!("Order filled...");
+
+    Ok(())
+}
+
+/*
+ * Cancels the orders signed by owner with the given nonces, along with every one below min_nonce.
+*/
+pub fn cancel_orders(
+    owner: Keypair,
+    min_nonce: u64,
+    nonces: Vec<u64>,
+    trader_program_id: Pubkey,
+    conn: &RpcClient,
+) -> Result<()> {
+    println!("Cancelling orders...");
+
+    let ix = instruction::cancel_orders(&trader_program_id, &owner.pubkey(), min_nonce, nonces);
+    let message = Message::new(&[ix], Some(&owner.pubkey()));
+    let transaction = Transaction::new(&[&owner], message, conn.get_latest_blockhash().unwrap());
+
+    conn.send_and_confirm_transaction(&transaction).unwrap();
+
+    let (nonces, _) = instruction::find_nonces_pda(&trader_program_id, &owner.pubkey());
+    let data = conn.get_account_data(&nonces).map_err(Error::RpcError)?;
+    let nonces = state::AccountNonces::try_from_slice(&data).map_err(Error::SerializationError)?;
+    println!("Orders cancelled, the lowest nonce that can be used is {}", nonces.base);
+
+    Ok(())
+}
+
+// A signed order as shared between maker and taker: the base64 of the borsh encoded order followed by the signature
+pub fn encode_signed_order(order: &SignedOrder, signature: &[u8]) -> String {
+    let mut data = order.try_to_vec().unwrap();
+    data.extend_from_slice(signature);
+
+    base64::encode(data)
+}
+
+pub fn decode_signed_order(encoded: &str) -> Result<(SignedOrder, Vec<u8>)> {
+    let data = base64::decode(encoded.trim())
+        .map_err(|e| Error::InvalidConfig(format!("invalid signed order: ({})", e)))?;
+    if data.len() < SIGNATURE_BYTES {
+        Err(Error::InvalidConfig("invalid signed order: too short".into()))?;
+    }
+
+    let (order, signature) = data.split_at(data.len() - SIGNATURE_BYTES);
+    let order = SignedOrder::try_from_slice(order).map_err(Error::SerializationError)?;
+
+    Ok((order, signature.to_vec()))
+}
+
+/*
+ * Prints the orders of the relay at relay_url that can still be filled, of the pair or of either mint, in UI amounts.
+*/
+pub fn list_orders(
+    relay_url: &str,
+    offer_mint: Option<Pubkey>,
+    trade_mint: Option<Pubkey>,
+    conn: &RpcClient,
+) -> Result<()> {
+    let orders = relay::load_orders(relay_url, offer_mint.as_ref(), trade_mint.as_ref())?;
+    println!("{} orders", orders.len());
+
+    for order in orders {
+        let offer_decimals = resolve_decimals_of_mint(&Pubkey::from_str(&order.offer_mint).unwrap(), conn)?;
+        let trade_decimals = resolve_decimals_of_mint(&Pubkey::from_str(&order.trade_mint).unwrap(), conn)?;
+        println!(
+            "{} of {} for {} of {}, nonce {} of {}{}\n  {}",
+            spl_token::amount_to_ui_amount(order.offer_amount, offer_decimals),
+            order.offer_mint,
+            spl_token::amount_to_ui_amount(order.trade_amount, trade_decimals),
+            order.trade_mint,
+            order.nonce,
+            order.maker,
+            if order.expires_at != 0 { format!(", expires at {}", order.expires_at) } else { "".into() },
+            order.order,
+        );
+    }
+
+    Ok(())
+}
+
+/*
+ * The order doesn't name the token account its offer is taken from, so the first one of the maker with the offer
+ * mint and enough of it delegated to its nonces account is used.
+*/
+fn find_order_offer_src(order: &SignedOrder, trader_program_id: &Pubkey, conn: &RpcClient) -> Result<Pubkey> {
+    let (nonces, _) = instruction::find_nonces_pda(trader_program_id, &order.maker);
+    let accounts = conn.get_token_accounts_by_owner(&order.maker, TokenAccountsFilter::Mint(order.offer_mint))
+        .map_err(Error::RpcError)?;
+
+    for keyed_account in accounts {
+        let addr = Pubkey::from_str(&keyed_account.pubkey).unwrap();
+        let account = conn.get_account(&addr).map_err(Error::RpcError)?;
+        let token = match spl_token::state::Account::unpack_from_slice(&account.data) {
+            Ok(token) => token,
+            Err(_) => continue,
+        };
+        if token.delegate == COption::Some(nonces) && token.delegated_amount >= order.offer_amount
+            && token.amount >= order.offer_amount {
+            return Ok(addr);
+        }
+    }
+
+    Err(Error::InvalidConfig(format!("no token account of {} can pay for the order", order.maker)))
+}
+
+/*
+ * Prints the trade in UI amounts of both mints.
+*/
+pub fn quote(trade_id: Pubkey, conn: &RpcClient) -> Result<()> {
+    let trade = load_trade(&trade_id, conn)?;
+    let offer_mint = resolve_mint_info(&trade.offer_token_account, conn)?;
+
+    println!("Trade {}", trade_id);
+    println!("{}", format_quote(&trade, &offer_mint));
+
+    Ok(())
+}
+
+pub fn format_quote(trade: &state::AccountTrade, offer_mint: &Pubkey) -> String {
+    let offer = spl_token::amount_to_ui_amount(trade.offer_amount, trade.offer_decimals);
+    let amount = trade.trade_amount_for(trade.offer_amount).unwrap_or(trade.trade_amount);
+    let amount = spl_token::amount_to_ui_amount(amount, trade.trade_decimals);
+    // the amounts of pegged trades are only known when filled
+    let limit = if trade.is_pegged() { "at least " } else { "" };
+
+    let mut quote = format!("\t- offer: {} of {}\n", offer, offer_mint);
+    quote += &format!("\t- trade: {}{} of {}\n", limit, amount, trade.trade_mint);
+    quote += &format!("\t- price: {}{} {} per {}", limit, trade.ui_price(), trade.trade_mint, offer_mint);
+    if trade.is_pegged() {
+        quote += &format!("\n\t- priced by: {} plus {} bps", trade.price_feed, trade.spread_bps);
+    }
+    if trade.is_arbitrated() {
+        quote += &format!("\n\t- arbiter: {}, dispute ends at {}", trade.arbiter, trade.dispute_ends_at);
+    }
+    if trade.is_hooked() {
+        quote += &format!("\n\t- screened by: {} with {}", trade.hook_program, trade.hook_account);
+    }
+    if trade.is_forward() {
+        quote += &format!("\n\t- forward: settles at {}", trade.settles_at);
+        if trade.is_committed() {
+            quote += &format!(", filled by {}", trade.taker);
+        }
+    }
+    if trade.is_allowlisted() {
+        quote += &format!("\n\t- allowlist: {}", Hash::new_from_array(trade.allowlist_root));
+    }
+    if trade.is_vesting() {
+        quote += &format!("\n\t- vesting: over {}s, with a cliff of {}s", trade.vesting_duration, trade.vesting_cliff);
+        if trade.is_committed() {
+            let claimed = spl_token::amount_to_ui_amount(trade.claimed, trade.offer_decimals);
+            quote += &format!(", filled by {} at {}, {} claimed", trade.taker, trade.vesting_start, claimed);
+        }
+    }
+    if trade.has_receipt() {
+        quote += &format!("\n\t- pays the holder of the receipt: {}", trade.receipt_mint);
+    }
+    if trade.is_option() {
+        let premium = spl_token::amount_to_ui_amount(trade.premium, trade.trade_decimals);
+        quote += &format!("\n\t- call option: premium of {}, expires at {}", premium, trade.expires_at);
+        if trade.is_option_bought() {
+            quote += &format!(", held by {}", trade.holder);
+        }
+    }
+
+    quote
+}
+
+pub fn load_trade(trade_id: &Pubkey, conn: &RpcClient) -> Result<state::AccountTrade> {
+    let data = conn.get_account_data(trade_id).map_err(Error::RpcError)?;
+
+    state::AccountTrade::try_from_slice(&data).map_err(Error::SerializationError)
+}
+
+/*
+ * Finds the token account holding the receipt of a trade and the wallet it belongs to, None for trades without a
+ * receipt. The receipt is the one token of its mint, so it's the largest account of the mint.
+*/
+pub fn find_receipt(trade: &state::AccountTrade, conn: &RpcClient) -> Result<Option<(Pubkey, Pubkey)>> {
+    if !trade.has_receipt() {
+        return Ok(None);
+    }
+
+    let largest = conn.get_token_largest_accounts(&trade.receipt_mint).map_err(Error::RpcError)?;
+    let receipt_account = largest.iter()
+        .find(|balance| balance.amount.amount == "1")
+        .and_then(|balance| Pubkey::from_str(&balance.address).ok())
+        .ok_or_else(|| Error::InvalidConfig(format!("receipt {} not held by anyone", trade.receipt_mint)))?;
+    let data = conn.get_account_data(&receipt_account).map_err(Error::RpcError)?;
+    let holder = spl_token::state::Account::unpack(&data)
+        .map_err(|_| Error::InvalidConfig(format!("{} is not a token account", receipt_account)))?
+        .owner;
+
+    Ok(Some((receipt_account, holder)))
+}
+
+/*
+ * Transfers the receipt of a trade held by owner to the ATA of wallet, which is created if needed. The new holder
+ * must delegate it to the trade with delegate_receipt before the trade is filled.
+*/
+pub fn transfer_receipt(
+    owner: Keypair,
+    trade_id: Pubkey,
+    wallet: Pubkey,
+    conn: &RpcClient,
+) -> Result<()> {
+    let trade_account = load_trade(&trade_id, conn)?;
+    let (receipt_account, holder) = find_receipt(&trade_account, conn)?
+        .ok_or_else(|| Error::InvalidConfig(format!("trade {} has no receipt", trade_id)))?;
+    if holder != owner.pubkey() {
+        Err(Error::InvalidConfig(format!("the receipt of trade {} is held by {}", trade_id, holder)))?;
+    }
+
+    let receipt_dst = get_or_create_ata(&owner, wallet, trade_account.receipt_mint, conn)?;
+    let transfer_ix = spl_token::instruction::transfer(
+        &spl_token::id(),
+        &receipt_account,
+        &receipt_dst,
+        &owner.pubkey(),
+        &[&owner.pubkey()],
+        1,
+    ).unwrap();
+    let message = Message::new(&[transfer_ix], Some(&owner.pubkey()));
+    let transaction = Transaction::new(&[&owner], message, conn.get_latest_blockhash().unwrap());
+
+    conn.send_and_confirm_transaction(&transaction).unwrap();
+
+    println!("Receipt transfered to {}", receipt_dst);
+
+    Ok(())
+}
+
+/*
+ * Approves the PDA of a trade as delegate of its receipt, held by owner, so that the trade can be filled, burning the
+ * receipt and paying owner.
+*/
+pub fn delegate_receipt(
+    owner: Keypair,
+    trade_id: Pubkey,
+    trader_program_id: Pubkey,
+    conn: &RpcClient,
+) -> Result<()> {
+    let trade_account = load_trade(&trade_id, conn)?;
+    let (receipt_account, holder) = find_receipt(&trade_account, conn)?
+        .ok_or_else(|| Error::InvalidConfig(format!("trade {} has no receipt", trade_id)))?;
+    if holder != owner.pubkey() {
+        Err(Error::InvalidConfig(format!("the receipt of trade {} is held by {}", trade_id, holder)))?;
+    }
+
+    let (pda, _) = instruction::find_trade_pda(&trader_program_id, &trade_id);
+    let approve_ix = spl_token::instruction::approve(
+        &spl_token::id(),
+        &receipt_account,
+        &pda,
+        &owner.pubkey(),
+        &[&owner.pubkey()],
+        1,
+    ).unwrap();
+    let message = Message::new(&[approve_ix], Some(&owner.pubkey()));
+    let transaction = Transaction::new(&[&owner], message, conn.get_latest_blockhash().unwrap());
+
+    conn.send_and_confirm_transaction(&transaction).unwrap();
+
+    println!("Receipt delegated to trade {}", trade_id);
+
+    Ok(())
+}
+
+/*
+ * Removes mints from the mint list of the program, sets its mode, creating it if needed, then adds mints to it. The
+ * mode of the list can only be changed once it is empty. Only the program authority can change the mint list, and it
+ * pays for the accounts of the mints added.
+*/
+pub fn update_mint_list(
+    authority: Keypair,
+    mode: Option<MintListMode>,
+    add: Vec<Pubkey>,
+    remove: Vec<Pubkey>,
+    trader_program_id: Pubkey,
+    conn: &RpcClient,
+) -> Result<()> {
+    let mut ixs: Vec<Instruction> =
+        remove.iter().map(|mint| instruction::remove_mint(&trader_program_id, &authority.pubkey(), mint)).collect();
+    if let Some(mode) = mode {
+        ixs.push(instruction::set_mint_list_mode(&trader_program_id, &authority.pubkey(), mode));
+    }
+    ixs.extend(add.iter().map(|mint| instruction::add_mint(&trader_program_id, &authority.pubkey(), mint)));
+
+    let message = Message::new(&ixs, Some(&authority.pubkey()));
+    let transaction = Transaction::new(&[&authority], message, conn.get_latest_blockhash().unwrap());
+
+    conn.send_and_confirm_transaction(&transaction).unwrap();
+
+    println!("Mint list updated");
+
+    Ok(())
+}
+
+// Shows the mode of the mint list of the program, and whether each of mints can be traded
+pub fn show_mint_list(mints: Vec<Pubkey>, trader_program_id: Pubkey, conn: &RpcClient) -> Result<()> {
+    let (mint_list, _) = instruction::find_mint_list(&trader_program_id);
+    let mint_list = match conn.get_account_data(&mint_list) {
+        Ok(data) => state::AccountMintList::try_from_slice(&data).map_err(Error::SerializationError)?,
+        Err(_) => {
+            println!("No mint list, any mint can be traded");
+            return Ok(());
+        },
+    };
+    println!("Mint list in {:?} mode, {} mints listed", mint_list.mode, mint_list.listed);
+
+    for mint in mints {
+        let (listed_mint, _) = instruction::find_listed_mint(&trader_program_id, &mint);
+        let listed = conn.get_account(&listed_mint).is_ok();
+        let allowed = if mint_list.allows(listed) { "allowed" } else { "not allowed" };
+        println!("\t- {}: {}", mint, allowed);
+    }
+
+    Ok(())
+}
+
+/*
+ * Sets the limits of mint, creating its limits account if needed. Only the program authority can set them, and it
+ * pays for the account.
+*/
+pub fn set_mint_limits(
+    authority: Keypair,
+    mint: Pubkey,
+    limits: MintLimits,
+    trader_program_id: Pubkey,
+    conn: &RpcClient,
+) -> Result<()> {
+    let ix = instruction::set_mint_limits(&trader_program_id, &authority.pubkey(), &mint, limits);
+    let message = Message::new(&[ix], Some(&authority.pubkey()));
+    let transaction = Transaction::new(&[&authority], message, conn.get_latest_blockhash().unwrap());
+
+    conn.send_and_confirm_transaction(&transaction).unwrap();
+
+    println!("Mint limits set");
+
+    Ok(())
+}
+
+// Shows the limits of mint in UI amounts, and how much of the mint was filled in the current window
+pub fn show_mint_limits(mint: Pubkey, trader_program_id: Pubkey, conn: &RpcClient) -> Result<()> {
+    let (mint_limits, _) = instruction::find_mint_limits(&trader_program_id, &mint);
+    let mint_limits = match conn.get_account_data(&mint_limits) {
+        Ok(data) => state::AccountMintLimits::try_from_slice(&data).map_err(Error::SerializationError)?,
+        Err(_) => {
+            println!("No limits on {}", mint);
+            return Ok(());
+        },
+    };
+    let decimals = resolve_decimals_of_mint(&mint, conn)?;
+    let ui_amount = |amount: u64| -> String {
+        if amount == 0 { "none".to_string() } else { spl_token::amount_to_ui_amount(amount, decimals).to_string() }
+    };
+
+    let limits = mint_limits.limits;
+    println!("Limits on {}", mint);
+    println!("\t- min amount: {}", ui_amount(limits.min_amount));
+    println!("\t- max amount: {}", ui_amount(limits.max_amount));
+    println!("\t- volume cap: {}", ui_amount(limits.volume_cap));
+    if limits.volume_cap != 0 {
+        println!(
+            "\t- {} filled in the window of {}s started at {}",
+            spl_token::amount_to_ui_amount(mint_limits.window_volume, decimals),
+            limits.window,
+            mint_limits.window_start,
+        );
+    }
+
+    Ok(())
+}
+
+/*
+ * Creates the stats accounts which don't exist yet, paid by payer. They only count what happens once they exist.
+*/
+pub fn init_stats(
+    payer: Keypair,
+    stats: Vec<StatsAccount>,
+    trader_program_id: Pubkey,
+    conn: &RpcClient,
+) -> Result<()> {
+    let ixs: Vec<Instruction> = stats.into_iter()
+        .filter(|stats| conn.get_account_data(&instruction::find_stats(&trader_program_id, stats).0).is_err())
+        .map(|stats| instruction::init_stats(&trader_program_id, &payer.pubkey(), stats))
+        .collect();
+    if ixs.is_empty() {
+        return Ok(());
+    }
+
+    let message = Message::new(&ixs, Some(&payer.pubkey()));
+    let transaction = Transaction::new(&[&payer], message, conn.get_latest_blockhash().unwrap());
+
+    conn.send_and_confirm_transaction(&transaction).unwrap();
+
+    println!("{} stats accounts created", ixs.len());
+
+    Ok(())
+}
+
+// Loads a stats account, which may not have been created
+fn load_stats<T: BorshDeserialize>(
+    stats: &StatsAccount,
+    trader_program_id: &Pubkey,
+    conn: &RpcClient,
+) -> Result<Option<T>> {
+    let (stats_account, _) = instruction::find_stats(trader_program_id, stats);
+    match conn.get_account_data(&stats_account) {
+        Ok(data) => Ok(Some(T::try_from_slice(&data).map_err(Error::SerializationError)?)),
+        Err(_) => Ok(None),
+    }
+}
+
+// Shows the trades filled by the program, then the trades, volume and fees of each mint in UI amounts, and the
+// trades of each maker
+pub fn show_stats(mints: Vec<Pubkey>, makers: Vec<Pubkey>, trader_program_id: Pubkey, conn: &RpcClient) -> Result<()> {
+    match load_stats::<state::AccountStats>(&StatsAccount::Program, &trader_program_id, conn)? {
+        Some(stats) => println!("Trades filled: {}", stats.trades),
+        None => println!("No stats of the program"),
+    }
+
+    for mint in mints {
+        let stats = match load_stats::<state::AccountMintStats>(&StatsAccount::Mint(mint), &trader_program_id, conn)? {
+            Some(stats) => stats,
+            None => {
+                println!("No stats of {}", mint);
+                continue;
+            },
+        };
+        let decimals = resolve_decimals_of_mint(&mint, conn)?;
+        // the totals can outgrow a u64, so they are shown as floats
+        let ui_amount = |amount: u128| amount as f64 / 10f64.powi(decimals as i32);
+
+        println!("Stats of {}", mint);
+        println!("	- trades: {}", stats.trades);
+        println!("	- volume: {}", ui_amount(stats.volume));
+        println!("	- fees: {}", ui_amount(stats.fees));
+    }
+
+    for maker in makers {
+        match load_stats::<state::AccountMakerStats>(&StatsAccount::Maker(maker), &trader_program_id, conn)? {
+            Some(stats) => {
+                println!("Trades of {}", maker);
+                println!("	- created: {}", stats.created);
+                println!("	- filled: {}", stats.filled);
+                println!("	- cancelled: {}", stats.cancelled);
+            },
+            None => println!("No stats of {}", maker),
+        }
+    }
+
+    Ok(())
+}
+
+fn transaction_size(ixs: &[Instruction], payer: &Pubkey) -> usize {
+    let message = Message::new(ixs, Some(payer));
+    // the signatures are prefixed by their count, which takes a single byte for less than 128 signatures
+    1 + message.header.num_required_signatures as usize * 64 + message.serialize().len()
+}
+
+/*
+ * Creates the trade escrow of a trade in Forward mode, where the trade amount is held until the trade is settled, if
+ * it doesn't exist yet. owner funds the account, and gets its rent back on settlement.
+*/
+fn create_trade_escrow(
+    owner: &Keypair,
+    trader_program_id: &Pubkey,
+    trade_id: &Pubkey,
+    trade_mint: Pubkey,
+    conn: &RpcClient,
+) -> Result<Pubkey> {
+    let (pda, _) = instruction::find_trade_pda(trader_program_id, trade_id);
+    get_or_create_ata(owner, pda, trade_mint, conn)
+}
+
+/*
+ * Creates the vesting escrow of a vesting trade, where the offer is held until it is claimed, if it doesn't exist
+ * yet. owner funds the account, and gets its rent back once all of the offer is claimed.
+*/
+fn create_vesting_escrow(
+    owner: &Keypair,
+    trader_program_id: &Pubkey,
+    trade_id: &Pubkey,
+    offer_mint: Pubkey,
+    conn: &RpcClient,
+) -> Result<Pubkey> {
+    let (pda, _) = instruction::find_trade_pda(trader_program_id, trade_id);
+    get_or_create_ata(owner, pda, offer_mint, conn)
+}
+
+/*
+ * Creates the ATA of the program authority where the trade fee is stored, if it doesn't exist yet, and returns its
+ * address. owner funds the account.
+*/
+fn create_fee_account(
+    owner: &Keypair,
+    trade_mint_addr: &Pubkey,
+    program_authority: &Pubkey,
+    conn: &RpcClient,
+) -> Pubkey {
+    let fee_ata_ix = spl_associated_token_account::create_associated_token_account(
+        &owner.pubkey(),
+        program_authority,
+        trade_mint_addr,
+    );
+    let message = Message::new(&[fee_ata_ix], Some(&owner.pubkey()));
+    let transaction = Transaction::new(&[owner], message, conn.get_latest_blockhash().unwrap());
+    // TODO: filter AlreadyInUse errors
+    match conn.send_and_confirm_transaction(&transaction) {
+        Ok(_) => println!("Account created to store a trade fee."),
+        Err(err) => println!("Ignoring error: {}", err)
+    }
+
+    let fee_ata_addr = spl_associated_token_account::get_associated_token_address(program_authority, trade_mint_addr);
+    println!("Fee account address: {}", fee_ata_addr);
+
+    fee_ata_addr
+}
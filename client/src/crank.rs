@@ -0,0 +1,41 @@
+use crate::{Error, Result};
+use crate::router::{load_open_trades, OpenTrade};
+use solana_client::rpc_client::RpcClient;
+use solana_program::program_pack::Pack;
+use solana_sdk::pubkey::Pubkey;
+use spl_token::state::Account;
+use trader::{instruction::find_trade_pda, state::TradeMode};
+
+/*
+ * Loads the open trades of the program that anyone can close with CloseStaleTrade at the unix timestamp now: the
+ * expired ones, and the ones in Delegate mode that can't be filled anymore.
+*/
+pub fn load_stale_trades(trader_program_id: &Pubkey, now: i64, conn: &RpcClient) -> Result<Vec<OpenTrade>> {
+    let mut stale = Vec::<OpenTrade>::new();
+
+    for trade in load_open_trades(trader_program_id, conn)? {
+        // filled forward trades are only closed when settled, and vesting ones once all of their offer is claimed
+        if trade.account.is_committed() {
+            continue;
+        }
+        if trade.account.is_expired(now) {
+            stale.push(trade);
+            continue;
+        }
+        if trade.account.mode != TradeMode::Delegate {
+            continue;
+        }
+
+        let offer_account = conn.get_account(&trade.account.offer_token_account).map_err(Error::RpcError)?;
+        let offer_token = match Account::unpack_from_slice(&offer_account.data) {
+            Ok(token) => token,
+            Err(_) => continue,
+        };
+        let (pda, _) = find_trade_pda(trader_program_id, &trade.trade_id);
+        if trade.account.is_abandoned(&offer_token, &pda) {
+            stale.push(trade);
+        }
+    }
+
+    Ok(stale)
+}
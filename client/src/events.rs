@@ -0,0 +1,53 @@
+use borsh::BorshDeserialize;
+use crate::{Error, Result};
+use solana_sdk::pubkey::Pubkey;
+use std::str::FromStr;
+use trader::events::TradeEvent;
+
+const PROGRAM_DATA_PREFIX: &str = "Program data: ";
+
+/*
+ * Extracts the events emitted by the trader program from the log messages of a transaction. The logs are walked
+ * keeping track of the invocation stack so that data logged by other programs (ex: the spl-token CPIs) is ignored.
+*/
+pub fn parse_events(trader_program_id: &Pubkey, logs: &[String]) -> Result<Vec<TradeEvent>> {
+    let mut events = Vec::<TradeEvent>::new();
+    let mut stack = Vec::<Pubkey>::new();
+
+    for line in logs {
+        if let Some(data) = line.strip_prefix(PROGRAM_DATA_PREFIX) {
+            if stack.last() != Some(trader_program_id) {
+                continue;
+            }
+
+            for field in data.split_whitespace() {
+                let bytes = base64::decode(field).map_err(|e| {
+                    Error::SerializationError(std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+                })?;
+                let event = TradeEvent::try_from_slice(&bytes).map_err(Error::SerializationError)?;
+                events.push(event);
+            }
+
+            continue;
+        }
+
+        // "Program <id> invoke [<depth>]", "Program <id> success" and "Program <id> failed: <reason>"
+        let mut words = line.split_whitespace();
+        if words.next() != Some("Program") {
+            continue;
+        }
+        let program = match words.next().map(Pubkey::from_str) {
+            Some(Ok(program)) => program,
+            _ => continue,
+        };
+        match words.next() {
+            Some("invoke") => stack.push(program),
+            Some("success") | Some("failed:") => {
+                stack.pop();
+            },
+            _ => (),
+        }
+    }
+
+    Ok(events)
+}
@@ -1,7 +1,12 @@
 use thiserror::Error;
 
 pub mod utils;
+pub mod allowlist;
 pub mod client;
+pub mod crank;
+pub mod events;
+pub mod relay;
+pub mod router;
 
 #[derive(Error, Debug)]
 pub enum Error {
@@ -11,6 +16,12 @@ pub enum Error {
     InvalidConfig(String),
     #[error("serialization error: ({0})")]
     SerializationError(std::io::Error),
+    #[error("rpc error: ({0})")]
+    RpcError(solana_client::client_error::ClientError),
+    #[error("no route found from {0} to {1}")]
+    RouteNotFound(solana_sdk::pubkey::Pubkey, solana_sdk::pubkey::Pubkey),
+    #[error("relay error: ({0})")]
+    RelayError(String),
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
@@ -2,10 +2,16 @@ use clap::{
     Arg,
     Command,
 };
-use trader_client::client;
+use trader::{
+    instruction::CreateTradeArgs,
+    instructions::{Arbitration, ComplianceHook, CoveredCall, OraclePeg, Settlement, Vesting},
+    state::{MintLimits, MintListMode, ProceedsSplit, StatsAccount, TradeMode},
+};
+use trader_client::{allowlist::{load_allowlist, AllowlistTree}, client, relay};
 use trader_client::utils::{
     get_wallet,
     load_config,
+    resolve_decimals_of_mint,
     resolve_mint_decimals,
     ProgramConfig,
 };
@@ -13,7 +19,9 @@ use trader_client::utils::{
 use solana_client::rpc_client::RpcClient;
 use solana_sdk::{
     commitment_config::CommitmentConfig,
+    hash::Hash,
     pubkey::Pubkey,
+    signature::Signer,
 };
 // use solana_clap_utils::{
 //     input_validators::{
@@ -94,6 +102,145 @@ fn main() {
                     .index(4)
                     .help("Specify the account we want to receive the trade amount."),
             )
+            .arg(
+                Arg::new("delegate")
+                    .long("delegate")
+                    .takes_value(false)
+                    .help("Keep the offer tokens in OFFER_ACCOUNT and approve the trade as delegate of the \
+                        offer amount, instead of transfering the account to the trade."),
+            )
+            .arg(
+                Arg::new("expires-in")
+                    .long("expires-in")
+                    .value_name("SECONDS")
+                    .takes_value(true)
+                    .help("Expire the trade after SECONDS, after which anyone can close it and return the offer."),
+            )
+            .arg(
+                Arg::new("bounty")
+                    .long("bounty")
+                    .value_name("LAMPORTS")
+                    .takes_value(true)
+                    .help("Specify the bounty paid to whoever closes the trade once it is stale."),
+            )
+            .arg(
+                Arg::new("price-feed")
+                    .long("price-feed")
+                    .value_name("PRICE_ACCOUNT")
+                    .takes_value(true)
+                    .help("Price the trade from PRICE_ACCOUNT when it is accepted. \
+                        TRADE_AMOUNT becomes the minimum amount accepted."),
+            )
+            .arg(
+                Arg::new("spread-bps")
+                    .long("spread-bps")
+                    .value_name("BPS")
+                    .takes_value(true)
+                    .requires("price-feed")
+                    .help("Specify the spread over the oracle price, in basis points."),
+            )
+            .arg(
+                Arg::new("max-price-age")
+                    .long("max-price-age")
+                    .value_name("SECONDS")
+                    .takes_value(true)
+                    .requires("price-feed")
+                    .help("Specify how old the oracle price can be when the trade is accepted."),
+            )
+            .arg(
+                Arg::new("split")
+                    .long("split")
+                    .value_name("DST:BPS")
+                    .takes_value(true)
+                    .multiple_occurrences(true)
+                    .help("Send BPS basis points of the trade amount to the token account DST. Can be given up to 4 \
+                        times, with shares adding up to 10000. The first DST is the trade dst, and gets any remainder."),
+            )
+            .arg(
+                Arg::new("arbiter")
+                    .long("arbiter")
+                    .value_name("ARBITER")
+                    .takes_value(true)
+                    .conflicts_with("delegate")
+                    .help("Have the wallet ARBITER co-sign the settlement of the trade. Once the dispute timeout is \
+                        over the arbiter alone can release the offer to either side."),
+            )
+            .arg(
+                Arg::new("dispute-timeout")
+                    .long("dispute-timeout")
+                    .value_name("SECONDS")
+                    .takes_value(true)
+                    .requires("arbiter")
+                    .help("Specify the SECONDS after which the arbiter can release the offer on its own."),
+            )
+            .arg(
+                Arg::new("hook")
+                    .long("hook")
+                    .value_name("HOOK_PROGRAM")
+                    .takes_value(true)
+                    .requires("hook-account")
+                    .help("Have HOOK_PROGRAM screen the trade every time it's accepted, before any funds move."),
+            )
+            .arg(
+                Arg::new("hook-account")
+                    .long("hook-account")
+                    .value_name("ACCOUNT")
+                    .takes_value(true)
+                    .requires("hook")
+                    .help("Specify the account HOOK_PROGRAM is invoked with, ex: its list of allowed wallets."),
+            )
+            .arg(
+                Arg::new("premium")
+                    .long("premium")
+                    .value_name("AMOUNT")
+                    .takes_value(true)
+                    .requires("expires-in")
+                    .conflicts_with_all(&["delegate", "arbiter", "price-feed", "split", "hook"])
+                    .help("Write a covered call on the offer instead, bought for AMOUNT trade tokens. Its holder can \
+                        exercise it, paying TRADE_AMOUNT, until it expires."),
+            )
+            .arg(
+                Arg::new("settles-in")
+                    .long("settles-in")
+                    .value_name("SECONDS")
+                    .takes_value(true)
+                    .conflicts_with_all(&["delegate", "arbiter", "price-feed", "split", "premium"])
+                    .help("Make it a forward trade, accepted until SECONDS from now and settled from then on. The \
+                        taker pays when accepting it, but both sides are only delivered when settled."),
+            )
+            .arg(
+                Arg::new("vest-duration")
+                    .long("vest-duration")
+                    .value_name("SECONDS")
+                    .takes_value(true)
+                    .conflicts_with_all(&["arbiter", "premium", "settles-in"])
+                    .help("Vest the offer linearly over SECONDS from when the trade is accepted, instead of delivering \
+                        it at once. The taker claims what vested with the claim command."),
+            )
+            .arg(
+                Arg::new("vest-cliff")
+                    .long("vest-cliff")
+                    .value_name("SECONDS")
+                    .takes_value(true)
+                    .requires("vest-duration")
+                    .help("Specify how long after the trade is accepted nothing of the offer vests [default: 0]"),
+            )
+            .arg(
+                Arg::new("allowlist")
+                    .long("allowlist")
+                    .value_name("CSV")
+                    .takes_value(true)
+                    .conflicts_with("premium")
+                    .help("Only let the wallets listed in CSV accept the trade, see the allowlist command."),
+            )
+            .arg(
+                Arg::new("receipt")
+                    .long("receipt")
+                    .takes_value(false)
+                    .conflicts_with_all(&["delegate", "arbiter", "split", "premium", "settles-in"])
+                    .help("Mint a receipt token to the wallet, whose holder gets the trade amount when the trade is \
+                        accepted. The receipt can be sold with transfer-receipt."),
+            )
         )
         .subcommand(Command::new("trade").about("Accept a trade")
             .arg(
@@ -161,126 +308,1022 @@ fn main() {
                     .index(8)
                     .help("Specify token account to where the trade amount will be sent to."),
             )
+            .arg(
+                Arg::new("arbiter-keypair")
+                    .long("arbiter-keypair")
+                    .value_name("KEYPAIR")
+                    .takes_value(true)
+                    .help("Specify the keypair of the arbiter of the trade, which co-signs trades in arbitrated mode."),
+            )
+            .arg(
+                Arg::new("allowlist")
+                    .long("allowlist")
+                    .value_name("CSV")
+                    .takes_value(true)
+                    .help("Prove the wallet is in the allowlist of the trade, built from CSV."),
+            )
         )
-        .subcommand(Command::new("bootstrap").about("Create all accounts needed to test the program")
+        .subcommand(Command::new("increase").about("Add tokens to the offer of a trade")
             .arg(
-                Arg::new("wallet1")
-                    .value_name("WALLET1")
+                Arg::new("id")
+                    .value_name("TRADE_ID")
                     .takes_value(true)
                     .required(true)
                     .index(1)
-                    .help("Specify the path to the wallet of the user making the offer."),
+                    .help("Specify the trade id."),
             )
             .arg(
-                Arg::new("wallet2")
-                    .value_name("WALLET2")
+                Arg::new("amount")
+                    .value_name("AMOUNT")
                     .takes_value(true)
                     .required(true)
                     .index(2)
-                    .help("Specify the path to the wallet of the user taking the offer."),
+                    .help("Specify the amount of offer tokens."),
+            )
+            .arg(
+                Arg::new("token-account")
+                    .value_name("TOKEN_SRC")
+                    .takes_value(true)
+                    .index(3)
+                    .help("Specify the token account the tokens are taken from, the ATA of the wallet by default."),
+            )
+            .arg(
+                Arg::new("proportional")
+                    .long("proportional")
+                    .takes_value(false)
+                    .help("Change the trade amount along with the offer, keeping the price of the trade. \
+                        Otherwise the trade amount stays the same."),
             )
         )
-        .get_matches();
-    
-    let (sub_command, sub_matches) = app_matches.subcommand().unwrap();
-
-    let cfg = load_config().unwrap();
-    let cluster_url = cfg["json_rpc_url"].as_str().unwrap();
-    
-    let conn = RpcClient::new_with_commitment(
-        cluster_url,
-        CommitmentConfig::confirmed(),
-    );
-    println!(
-        "Connected to cluster at {}.", cluster_url
-    );
-
-    let wallet = get_wallet(None).unwrap();
-    //let balance = conn.get_account(&wallet.pubkey()).unwrap().lamports;
-    //println!("Account balance: {}", balance); // TODO: this prints a different value from 'solana balance' ?
-    
-    //println!("fees = {:?}", rpc.get_fees()?);
-    //println!("signature fee = {}", rpc.get_fees()?.fee_calculator.lamports_per_signature);
-
-    match sub_command {
-        "create" => {
-            let program_addr = ProgramConfig::load_program_addr(PROGRAM_CONFIG_PATH.into()).unwrap();
-            let program_pubkey = Pubkey::from_str(&program_addr).unwrap();
-            
-            let src = Pubkey::from_str(sub_matches.value_of("offer_account").unwrap().into()).unwrap();
-            let trade_mint = Pubkey::from_str(sub_matches.value_of("trade_token").unwrap().into()).unwrap();
-            let amount_arg: f64 = sub_matches.value_of("amount").unwrap().parse().unwrap();
-            let trade_dst = match sub_matches.value_of("trade-dst") {
-                Some(addr) => Some(Pubkey::from_str(addr.into()).unwrap()),
-                None => None
-            };
-
-            let decimals = resolve_mint_decimals(&src, None, &conn).unwrap();
-            let ammount = spl_token::ui_amount_to_amount(amount_arg, decimals);
-            client::create_trade(ammount, wallet, src, trade_mint, trade_dst, program_pubkey, &conn).unwrap();
-        }
-        "trade" => {
-            let program_addr = ProgramConfig::load_program_addr(PROGRAM_CONFIG_PATH.into()).unwrap();
-            let program_pubkey = Pubkey::from_str(&program_addr).unwrap();
-
-            let trade_account_id = Pubkey::from_str(sub_matches.value_of("id").unwrap().into()).unwrap();
-            let offer_src = Pubkey::from_str(sub_matches.value_of("offersrc").unwrap().into()).unwrap();
-            let trade_src = Pubkey::from_str(sub_matches.value_of("tradesrc").unwrap().into()).unwrap();
-            let wallet1 = Pubkey::from_str(sub_matches.value_of("offer-owner").unwrap().into()).unwrap();
-            let offer_dst = match sub_matches.value_of("offerdst") {
-                Some(addr) => Some(Pubkey::from_str(addr.into()).unwrap()),
-                None => None
-            };
-            let trade_dst = match sub_matches.value_of("tradedst") {
-                Some(addr) => Some(Pubkey::from_str(addr.into()).unwrap()),
-                None => None
-            };
-
-            let offer_decimals = resolve_mint_decimals(&offer_src, None, &conn).unwrap();
-            let amount: f64 = sub_matches.value_of("offer-amount").unwrap().parse().unwrap();
-            let offer_ammount = spl_token::ui_amount_to_amount(amount, offer_decimals);
-            let trade_decimals = resolve_mint_decimals(&trade_src, None, &conn).unwrap();
-            let amount: f64 = sub_matches.value_of("trade-amount").unwrap().parse().unwrap();
-            let trade_ammount = spl_token::ui_amount_to_amount(amount, trade_decimals);
-
-            let pa = ProgramConfig::load_wallet_addr(PROGRAM_CONFIG_PATH.into()).unwrap();
-            let program_authority = Pubkey::from_str(pa.as_ref()).unwrap();
-
-            client::make_trade(
-                offer_ammount,
-                trade_ammount,
-                wallet,
-                wallet1,
-                trade_account_id,
-                program_pubkey,
-                trade_dst,
-                trade_src,
-                offer_dst,
-                offer_src,
-                program_authority, 
-                &conn,
-            ).unwrap();
-        }
-        "bootstrap" => {
-            let wallet1 = get_wallet(sub_matches.value_of("wallet1")).unwrap();
-            let wallet2 = get_wallet(sub_matches.value_of("wallet2")).unwrap();
-            client::setup_accounts(10, 20, wallet1, wallet2, &conn).unwrap();
-        }
-        "config" => {
-            match sub_matches.value_of("program") {
-                Some(addr) => {
-                    ProgramConfig::store_program_addr(PROGRAM_CONFIG_PATH.into(), addr.into()).unwrap();
-                    ()
-                },
-                None => ()
-            }
-
-            match sub_matches.value_of("wallet") {
-                Some(addr) => {
-                    ProgramConfig::store_wallet_addr(PROGRAM_CONFIG_PATH.into(), addr.into()).unwrap();
-                    ()
-                },
-                None => ()
+        .subcommand(Command::new("decrease").about("Withdraw tokens from the offer of a trade")
+            .arg(
+                Arg::new("id")
+                    .value_name("TRADE_ID")
+                    .takes_value(true)
+                    .required(true)
+                    .index(1)
+                    .help("Specify the trade id."),
+            )
+            .arg(
+                Arg::new("amount")
+                    .value_name("AMOUNT")
+                    .takes_value(true)
+                    .required(true)
+                    .index(2)
+                    .help("Specify the amount of offer tokens."),
+            )
+            .arg(
+                Arg::new("token-account")
+                    .value_name("TOKEN_DST")
+                    .takes_value(true)
+                    .index(3)
+                    .help("Specify the token account the tokens go to, the ATA of the wallet by default."),
+            )
+            .arg(
+                Arg::new("proportional")
+                    .long("proportional")
+                    .takes_value(false)
+                    .help("Change the trade amount along with the offer, keeping the price of the trade. \
+                        Otherwise the trade amount stays the same."),
+            )
+        )
+        .subcommand(Command::new("transfer").about("Hand a trade over to another wallet")
+            .arg(
+                Arg::new("id")
+                    .value_name("TRADE_ID")
+                    .takes_value(true)
+                    .required(true)
+                    .index(1)
+                    .help("Specify the trade id."),
+            )
+            .arg(
+                Arg::new("new-authority")
+                    .value_name("NEW_AUTHORITY")
+                    .takes_value(true)
+                    .required(true)
+                    .index(2)
+                    .help("Specify the wallet address of the new owner of the trade."),
+            )
+            .arg(
+                Arg::new("trade-dst")
+                    .value_name("TRADE_DST")
+                    .takes_value(true)
+                    .index(3)
+                    .help("Specify the account to receive the trade amount, the ATA of NEW_AUTHORITY by default."),
+            )
+            .arg(
+                Arg::new("new-authority-keypair")
+                    .long("new-authority-keypair")
+                    .value_name("KEYPAIR")
+                    .takes_value(true)
+                    .help("Specify the keypair of NEW_AUTHORITY, to have it co-sign the transfer."),
+            )
+        )
+        .subcommand(Command::new("release").about("Settle a dispute over an arbitrated trade, as its arbiter")
+            .arg(
+                Arg::new("id")
+                    .value_name("TRADE_ID")
+                    .takes_value(true)
+                    .required(true)
+                    .index(1)
+                    .help("Specify the trade id."),
+            )
+            .arg(
+                Arg::new("offer-dst")
+                    .value_name("OFFER_DST")
+                    .takes_value(true)
+                    .index(2)
+                    .help("Specify the token account of the taker to release the offer to. \
+                        Otherwise the offer is returned to the owner of the trade."),
+            )
+        )
+        .subcommand(Command::new("buy-option").about("Buy a covered call, paying its premium")
+            .arg(
+                Arg::new("id")
+                    .value_name("TRADE_ID")
+                    .takes_value(true)
+                    .required(true)
+                    .index(1)
+                    .help("Specify the trade id."),
+            )
+            .arg(
+                Arg::new("premium-src")
+                    .value_name("PREMIUM_SRC")
+                    .takes_value(true)
+                    .required(true)
+                    .index(2)
+                    .help("Specify token account from where the premium will be taken from."),
+            )
+        )
+        .subcommand(Command::new("exercise").about("Exercise a covered call as its holder")
+            .arg(
+                Arg::new("id")
+                    .value_name("TRADE_ID")
+                    .takes_value(true)
+                    .required(true)
+                    .index(1)
+                    .help("Specify the trade id."),
+            )
+            .arg(
+                Arg::new("tradesrc")
+                    .value_name("TRADE_SRC")
+                    .takes_value(true)
+                    .required(true)
+                    .index(2)
+                    .help("Specify token account from where the strike will be taken from."),
+            )
+            .arg(
+                Arg::new("offerdst")
+                    .value_name("OFFER_DST")
+                    .takes_value(true)
+                    .index(3)
+                    .help("Specify token account to where the offer amount will be sent to."),
+            )
+        )
+        .subcommand(Command::new("reclaim").about("Take back the offer of an expired covered call")
+            .arg(
+                Arg::new("id")
+                    .value_name("TRADE_ID")
+                    .takes_value(true)
+                    .required(true)
+                    .index(1)
+                    .help("Specify the trade id."),
+            )
+        )
+        .subcommand(Command::new("settle").about("Settle a forward trade once its settlement date has passed")
+            .arg(
+                Arg::new("id")
+                    .value_name("TRADE_ID")
+                    .takes_value(true)
+                    .required(true)
+                    .index(1)
+                    .help("Specify the trade id."),
+            )
+        )
+        .subcommand(Command::new("claim").about("Claim what vested of the offer of a trade accepted by the wallet")
+            .arg(
+                Arg::new("id")
+                    .value_name("TRADE_ID")
+                    .takes_value(true)
+                    .required(true)
+                    .index(1)
+                    .help("Specify the trade id."),
+            )
+        )
+        .subcommand(Command::new("transfer-receipt").about("Transfer the receipt of a trade held by the wallet")
+            .arg(
+                Arg::new("id")
+                    .value_name("TRADE_ID")
+                    .takes_value(true)
+                    .required(true)
+                    .index(1)
+                    .help("Specify the trade id."),
+            )
+            .arg(
+                Arg::new("recipient")
+                    .value_name("WALLET")
+                    .takes_value(true)
+                    .required(true)
+                    .index(2)
+                    .help("Specify the wallet getting the receipt, which must delegate it before the trade is \
+                        accepted."),
+            )
+        )
+        .subcommand(Command::new("delegate-receipt").about("Let a trade burn its receipt held by the wallet when \
+            accepted, paying the wallet")
+            .arg(
+                Arg::new("id")
+                    .value_name("TRADE_ID")
+                    .takes_value(true)
+                    .required(true)
+                    .index(1)
+                    .help("Specify the trade id."),
+            )
+        )
+        .subcommand(Command::new("mint-list").about("Manage the mints that can be traded, as the program authority")
+            .arg(
+                Arg::new("mode")
+                    .long("mode")
+                    .value_name("MODE")
+                    .takes_value(true)
+                    .possible_values(["allow", "block"])
+                    .help("Only let listed mints be traded with allow, or keep them from being traded with block. \
+                        Creates the mint list the first time."),
+            )
+            .arg(
+                Arg::new("add")
+                    .long("add")
+                    .value_name("MINT")
+                    .takes_value(true)
+                    .multiple_occurrences(true)
+                    .help("Add MINT to the mint list. Can be given more than once."),
+            )
+            .arg(
+                Arg::new("remove")
+                    .long("remove")
+                    .value_name("MINT")
+                    .takes_value(true)
+                    .multiple_occurrences(true)
+                    .help("Remove MINT from the mint list. Can be given more than once."),
+            )
+            .arg(
+                Arg::new("mints")
+                    .value_name("MINT")
+                    .takes_value(true)
+                    .multiple_values(true)
+                    .help("Show whether each MINT can be traded. The mint list is shown when nothing is changed."),
+            )
+        )
+        .subcommand(Command::new("mint-limits").about("Manage the limits on trades of a mint, as the program authority")
+            .arg(
+                Arg::new("mint")
+                    .value_name("MINT")
+                    .takes_value(true)
+                    .required(true)
+                    .index(1)
+                    .help("The mint to show or set the limits of"),
+            )
+            .arg(
+                Arg::new("min")
+                    .long("min")
+                    .value_name("AMOUNT")
+                    .takes_value(true)
+                    .help("The smallest amount of the mint a trade can be created or filled for"),
+            )
+            .arg(
+                Arg::new("max")
+                    .long("max")
+                    .value_name("AMOUNT")
+                    .takes_value(true)
+                    .help("The largest amount of the mint a trade can be created or filled for"),
+            )
+            .arg(
+                Arg::new("volume-cap")
+                    .long("volume-cap")
+                    .value_name("AMOUNT")
+                    .takes_value(true)
+                    .requires("window")
+                    .help("The most of the mint all fills together can trade within the window"),
+            )
+            .arg(
+                Arg::new("window")
+                    .long("window")
+                    .value_name("SECONDS")
+                    .takes_value(true)
+                    .requires("volume-cap")
+                    .help("How long the window of the volume cap lasts, before the volume starts over"),
+            )
+        )
+        .subcommand(Command::new("stats").about("Show the trades and volume of the program, of mints and of makers")
+            .arg(
+                Arg::new("mint")
+                    .long("mint")
+                    .value_name("MINT")
+                    .takes_value(true)
+                    .multiple_occurrences(true)
+                    .help("Show the trades, volume and fees of MINT. Can be given more than once."),
+            )
+            .arg(
+                Arg::new("maker")
+                    .long("maker")
+                    .value_name("WALLET")
+                    .takes_value(true)
+                    .multiple_occurrences(true)
+                    .help("Show the trades WALLET created, got filled and got cancelled. Can be given more than once."),
+            )
+            .arg(
+                Arg::new("init")
+                    .long("init")
+                    .takes_value(false)
+                    .help("Create the stats accounts shown which don't exist yet, paid by the wallet"),
+            )
+        )
+        .subcommand(Command::new("allowlist").about("Build the Merkle allowlist of a trade, and the proof of a wallet")
+            .arg(
+                Arg::new("csv")
+                    .value_name("CSV")
+                    .takes_value(true)
+                    .required(true)
+                    .index(1)
+                    .help("Specify the file listing the allowed wallets, one WALLET[,CAP] per line. CAP is the most \
+                        offer tokens, in base units, the wallet can get from the trade."),
+            )
+            .arg(
+                Arg::new("wallet")
+                    .value_name("WALLET")
+                    .takes_value(true)
+                    .index(2)
+                    .help("Show the proof of WALLET as well."),
+            )
+        )
+        .subcommand(Command::new("sign-order").about("Sign an order anyone can fill, without a trade account")
+            .arg(
+                Arg::new("offer_account")
+                    .value_name("OFFER_ACCOUNT")
+                    .takes_value(true)
+                    .required(true)
+                    .index(1)
+                    .help("Specify the token account the offer is taken from when the order is filled."),
+            )
+            .arg(
+                Arg::new("offer-amount")
+                    .value_name("OFFER_AMOUNT")
+                    .takes_value(true)
+                    .required(true)
+                    .index(2)
+                    .help("Specify the amount of offer tokens."),
+            )
+            .arg(
+                Arg::new("trade_token")
+                    .value_name("TRADE_TOKEN")
+                    .takes_value(true)
+                    .required(true)
+                    .index(3)
+                    .help("Specify the token address of the token wanted in exchange."),
+            )
+            .arg(
+                Arg::new("trade-amount")
+                    .value_name("TRADE_AMOUNT")
+                    .takes_value(true)
+                    .required(true)
+                    .index(4)
+                    .help("Specify the amount of trade tokens wanted."),
+            )
+            .arg(
+                Arg::new("nonce")
+                    .value_name("NONCE")
+                    .takes_value(true)
+                    .required(true)
+                    .index(5)
+                    .help("Specify the nonce of the order, which can only be used once."),
+            )
+            .arg(
+                Arg::new("trade-dst")
+                    .value_name("TRADE_DST")
+                    .takes_value(true)
+                    .index(6)
+                    .help("Specify the account to receive the trade amount, the ATA of the wallet by default."),
+            )
+            .arg(
+                Arg::new("expires-in")
+                    .long("expires-in")
+                    .value_name("SECONDS")
+                    .takes_value(true)
+                    .help("Specify the SECONDS after which the order can't be filled anymore."),
+            )
+            .arg(
+                Arg::new("relay")
+                    .long("relay")
+                    .value_name("URL")
+                    .takes_value(true)
+                    .min_values(0)
+                    .help("Post the order to the relay at URL, http://127.0.0.1:8900 by default."),
+            )
+        )
+        .subcommand(Command::new("fill-order").about("Fill an order signed by its maker")
+            .arg(
+                Arg::new("order")
+                    .value_name("ORDER")
+                    .takes_value(true)
+                    .required(true)
+                    .index(1)
+                    .help("Specify the signed order, as printed by sign-order."),
+            )
+            .arg(
+                Arg::new("tradesrc")
+                    .value_name("TRADE_SRC")
+                    .takes_value(true)
+                    .required(true)
+                    .index(2)
+                    .help("Specify token account from where the trade amount will be taken from."),
+            )
+            .arg(
+                Arg::new("offerdst")
+                    .value_name("OFFER_DST")
+                    .takes_value(true)
+                    .index(3)
+                    .help("Specify token account to where the offer amount will be sent to."),
+            )
+        )
+        .subcommand(Command::new("cancel-orders").about("Cancel orders signed by the wallet")
+            .arg(
+                Arg::new("nonces")
+                    .value_name("NONCE")
+                    .takes_value(true)
+                    .multiple_occurrences(true)
+                    .index(1)
+                    .help("Specify the nonces of the orders to cancel."),
+            )
+            .arg(
+                Arg::new("below")
+                    .long("below")
+                    .value_name("NONCE")
+                    .takes_value(true)
+                    .help("Cancel every order with a nonce below NONCE as well."),
+            )
+        )
+        .subcommand(Command::new("orders").about("List the signed orders of a relay")
+            .arg(
+                Arg::new("offer_token")
+                    .value_name("OFFER_TOKEN")
+                    .takes_value(true)
+                    .index(1)
+                    .help("Specify the token address of the token offered."),
+            )
+            .arg(
+                Arg::new("trade_token")
+                    .value_name("TRADE_TOKEN")
+                    .takes_value(true)
+                    .index(2)
+                    .help("Specify the token address of the token wanted in exchange."),
+            )
+            .arg(
+                Arg::new("relay")
+                    .long("relay")
+                    .value_name("URL")
+                    .takes_value(true)
+                    .help("Specify the URL of the relay, http://127.0.0.1:8900 by default."),
+            )
+        )
+        .subcommand(Command::new("quote").about("Show a trade in UI amounts")
+            .arg(
+                Arg::new("id")
+                    .value_name("TRADE_ID")
+                    .takes_value(true)
+                    .required(true)
+                    .index(1)
+                    .help("Specify the trade id."),
+            )
+        )
+        .subcommand(Command::new("route").about("Swap tokens going through one or more trades")
+            .arg(
+                Arg::new("from")
+                    .value_name("FROM_MINT")
+                    .takes_value(true)
+                    .required(true)
+                    .index(1)
+                    .help("Specify the token address of the token to pay with."),
+            )
+            .arg(
+                Arg::new("to")
+                    .value_name("TO_MINT")
+                    .takes_value(true)
+                    .required(true)
+                    .index(2)
+                    .help("Specify the token address of the token wanted."),
+            )
+            .arg(
+                Arg::new("amount")
+                    .value_name("AMOUNT")
+                    .takes_value(true)
+                    .required(true)
+                    .index(3)
+                    .help("Specify the maximum amount of FROM_MINT tokens to pay."),
+            )
+        )
+        .subcommand(Command::new("crank").about("Close stale trades, collecting their bounties")
+            .arg(
+                Arg::new("interval")
+                    .long("interval")
+                    .value_name("SECONDS")
+                    .takes_value(true)
+                    .help("Keep looking for stale trades every SECONDS, instead of stopping after the first scan."),
+            )
+        )
+        .subcommand(Command::new("bootstrap").about("Create all accounts needed to test the program")
+            .arg(
+                Arg::new("wallet1")
+                    .value_name("WALLET1")
+                    .takes_value(true)
+                    .required(true)
+                    .index(1)
+                    .help("Specify the path to the wallet of the user making the offer."),
+            )
+            .arg(
+                Arg::new("wallet2")
+                    .value_name("WALLET2")
+                    .takes_value(true)
+                    .required(true)
+                    .index(2)
+                    .help("Specify the path to the wallet of the user taking the offer."),
+            )
+        )
+        .get_matches();
+    
+    let (sub_command, sub_matches) = app_matches.subcommand().unwrap();
+
+    let cfg = load_config().unwrap();
+    let cluster_url = cfg["json_rpc_url"].as_str().unwrap();
+    
+    let conn = RpcClient::new_with_commitment(
+        cluster_url,
+        CommitmentConfig::confirmed(),
+    );
+    println!(
+        "Connected to cluster at {}.", cluster_url
+    );
+
+    let wallet = get_wallet(None).unwrap();
+    //let balance = conn.get_account(&wallet.pubkey()).unwrap().lamports;
+    //println!("Account balance: {}", balance); // TODO: this prints a different value from 'solana balance' ?
+    
+    //println!("fees = {:?}", rpc.get_fees()?);
+    //println!("signature fee = {}", rpc.get_fees()?.fee_calculator.lamports_per_signature);
+
+    match sub_command {
+        "create" => {
+            let program_addr = ProgramConfig::load_program_addr(PROGRAM_CONFIG_PATH.into()).unwrap();
+            let program_pubkey = Pubkey::from_str(&program_addr).unwrap();
+            
+            let src = Pubkey::from_str(sub_matches.value_of("offer_account").unwrap()).unwrap();
+            let trade_mint = Pubkey::from_str(sub_matches.value_of("trade_token").unwrap()).unwrap();
+            let amount_arg: f64 = sub_matches.value_of("amount").unwrap().parse().unwrap();
+            let trade_dst = sub_matches.value_of("trade-dst").map(|addr| Pubkey::from_str(addr).unwrap());
+
+            let mode = if sub_matches.is_present("delegate") {
+                TradeMode::Delegate
+            } else if sub_matches.is_present("arbiter") {
+                TradeMode::Arbitrated
+            } else if sub_matches.is_present("premium") {
+                TradeMode::CoveredCall
+            } else if sub_matches.is_present("settles-in") {
+                TradeMode::Forward
+            } else {
+                TradeMode::Escrow
+            };
+
+            let expires_at = match sub_matches.value_of("expires-in") {
+                Some(secs) => {
+                    let now = conn.get_block_time(conn.get_slot().unwrap()).unwrap();
+                    now + secs.parse::<i64>().unwrap()
+                },
+                None => 0
+            };
+            let bounty = match sub_matches.value_of("bounty") {
+                Some(lamports) => lamports.parse().unwrap(),
+                None => client::DEFAULT_CRANK_BOUNTY
+            };
+
+            let peg = match sub_matches.value_of("price-feed") {
+                Some(addr) => {
+                    let price_feed = Pubkey::from_str(addr).unwrap();
+                    let spread_bps = match sub_matches.value_of("spread-bps") {
+                        Some(bps) => bps.parse().unwrap(),
+                        None => 0
+                    };
+                    let max_price_age = match sub_matches.value_of("max-price-age") {
+                        Some(secs) => secs.parse().unwrap(),
+                        None => client::DEFAULT_MAX_PRICE_AGE
+                    };
+                    Some((price_feed, OraclePeg { spread_bps, max_price_age }))
+                },
+                None => None
+            };
+
+            let splits = match sub_matches.values_of("split") {
+                Some(values) => values
+                    .map(|value| {
+                        let (dst, bps) = value.split_once(':').expect("splits are given as DST:BPS");
+                        ProceedsSplit {
+                            destination: Pubkey::from_str(dst).unwrap(),
+                            share_bps: bps.parse().unwrap(),
+                        }
+                    })
+                    .collect(),
+                None => vec![]
+            };
+
+            let arbitration = match sub_matches.value_of("arbiter") {
+                Some(addr) => {
+                    let timeout = match sub_matches.value_of("dispute-timeout") {
+                        Some(secs) => secs.parse().unwrap(),
+                        None => client::DEFAULT_DISPUTE_TIMEOUT
+                    };
+                    let now = conn.get_block_time(conn.get_slot().unwrap()).unwrap();
+                    Some(Arbitration { arbiter: Pubkey::from_str(addr).unwrap(), dispute_ends_at: now + timeout })
+                },
+                None => None
+            };
+
+            let hook = match (sub_matches.value_of("hook"), sub_matches.value_of("hook-account")) {
+                (Some(program), Some(account)) => Some(ComplianceHook {
+                    program: Pubkey::from_str(program).unwrap(),
+                    account: Pubkey::from_str(account).unwrap(),
+                }),
+                _ => None
+            };
+
+            // the amount is in trade tokens, and so is the premium
+            let decimals = resolve_decimals_of_mint(&trade_mint, &conn).unwrap();
+            let ammount = spl_token::ui_amount_to_amount(amount_arg, decimals);
+            let call = sub_matches.value_of("premium").map(|premium| CoveredCall {
+                premium: spl_token::ui_amount_to_amount(premium.parse().unwrap(), decimals),
+            });
+            let settlement = sub_matches.value_of("settles-in").map(|secs| {
+                let now = conn.get_block_time(conn.get_slot().unwrap()).unwrap();
+                Settlement { settles_at: now + secs.parse::<i64>().unwrap() }
+            });
+            let vesting = sub_matches.value_of("vest-duration").map(|secs| Vesting {
+                cliff: sub_matches.value_of("vest-cliff").map(|cliff| cliff.parse().unwrap()).unwrap_or(0),
+                duration: secs.parse().unwrap(),
+            });
+            let allowlist = sub_matches.value_of("allowlist")
+                .map(|path| AllowlistTree::new(load_allowlist(path).unwrap()).root());
+            let args = CreateTradeArgs {
+                mode,
+                expires_at,
+                bounty,
+                peg,
+                splits,
+                arbitration,
+                hook,
+                call,
+                settlement,
+                vesting,
+                allowlist,
+                receipt: None,
+            };
+            client::create_trade(
+                ammount, wallet, src, trade_mint, trade_dst, args, sub_matches.is_present("receipt"), program_pubkey,
+                &conn,
+            ).unwrap();
+        }
+        "trade" => {
+            let program_addr = ProgramConfig::load_program_addr(PROGRAM_CONFIG_PATH.into()).unwrap();
+            let program_pubkey = Pubkey::from_str(&program_addr).unwrap();
+
+            let trade_account_id = Pubkey::from_str(sub_matches.value_of("id").unwrap()).unwrap();
+            let offer_src = Pubkey::from_str(sub_matches.value_of("offersrc").unwrap()).unwrap();
+            let trade_src = Pubkey::from_str(sub_matches.value_of("tradesrc").unwrap()).unwrap();
+            let wallet1 = Pubkey::from_str(sub_matches.value_of("offer-owner").unwrap()).unwrap();
+            let offer_dst = sub_matches.value_of("offerdst").map(|addr| Pubkey::from_str(addr).unwrap());
+            let trade_dst = sub_matches.value_of("tradedst").map(|addr| Pubkey::from_str(addr).unwrap());
+
+            let offer_decimals = resolve_mint_decimals(&offer_src, None, &conn).unwrap();
+            let amount: f64 = sub_matches.value_of("offer-amount").unwrap().parse().unwrap();
+            let offer_ammount = spl_token::ui_amount_to_amount(amount, offer_decimals);
+            let trade_decimals = resolve_mint_decimals(&trade_src, None, &conn).unwrap();
+            let amount: f64 = sub_matches.value_of("trade-amount").unwrap().parse().unwrap();
+            let trade_ammount = spl_token::ui_amount_to_amount(amount, trade_decimals);
+
+            let arbiter = sub_matches.value_of("arbiter-keypair").map(|path| get_wallet(Some(path)).unwrap());
+
+            let allowlist = sub_matches.value_of("allowlist").map(|path| {
+                let tree = AllowlistTree::new(load_allowlist(path).unwrap());
+                tree.proof(&wallet.pubkey()).expect("the wallet isn't in the allowlist")
+            });
+
+            let pa = ProgramConfig::load_wallet_addr(PROGRAM_CONFIG_PATH.into()).unwrap();
+            let program_authority = Pubkey::from_str(pa.as_ref()).unwrap();
+
+            client::make_trade(
+                offer_ammount,
+                trade_ammount,
+                wallet,
+                arbiter,
+                allowlist,
+                wallet1,
+                trade_account_id,
+                program_pubkey,
+                trade_dst,
+                trade_src,
+                offer_dst,
+                offer_src,
+                program_authority, 
+                &conn,
+            ).unwrap();
+        }
+        "increase" | "decrease" => {
+            let program_addr = ProgramConfig::load_program_addr(PROGRAM_CONFIG_PATH.into()).unwrap();
+            let program_pubkey = Pubkey::from_str(&program_addr).unwrap();
+
+            let trade_id = Pubkey::from_str(sub_matches.value_of("id").unwrap()).unwrap();
+            let token_account = sub_matches.value_of("token-account").map(|addr| Pubkey::from_str(addr).unwrap());
+
+            let trade = client::load_trade(&trade_id, &conn).unwrap();
+            let amount: f64 = sub_matches.value_of("amount").unwrap().parse().unwrap();
+            let amount = spl_token::ui_amount_to_amount(amount, trade.offer_decimals);
+
+            client::change_offer(
+                amount,
+                sub_command == "increase",
+                sub_matches.is_present("proportional"),
+                wallet,
+                trade_id,
+                token_account,
+                program_pubkey,
+                &conn,
+            ).unwrap();
+        }
+        "transfer" => {
+            let program_addr = ProgramConfig::load_program_addr(PROGRAM_CONFIG_PATH.into()).unwrap();
+            let program_pubkey = Pubkey::from_str(&program_addr).unwrap();
+
+            let trade_id = Pubkey::from_str(sub_matches.value_of("id").unwrap()).unwrap();
+            let new_authority = Pubkey::from_str(sub_matches.value_of("new-authority").unwrap()).unwrap();
+            let trade_dst = sub_matches.value_of("trade-dst").map(|addr| Pubkey::from_str(addr).unwrap());
+            let new_authority_keypair = sub_matches.value_of("new-authority-keypair")
+                .map(|path| get_wallet(Some(path)).unwrap());
+
+            client::transfer_trade_authority(
+                wallet, trade_id, new_authority, new_authority_keypair, trade_dst, program_pubkey, &conn,
+            ).unwrap();
+        }
+        "release" => {
+            let program_addr = ProgramConfig::load_program_addr(PROGRAM_CONFIG_PATH.into()).unwrap();
+            let program_pubkey = Pubkey::from_str(&program_addr).unwrap();
+
+            let trade_id = Pubkey::from_str(sub_matches.value_of("id").unwrap()).unwrap();
+            let offer_dst = sub_matches.value_of("offer-dst").map(|addr| Pubkey::from_str(addr).unwrap());
+
+            client::arbiter_release(wallet, trade_id, offer_dst, program_pubkey, &conn).unwrap();
+        }
+        "buy-option" => {
+            let program_addr = ProgramConfig::load_program_addr(PROGRAM_CONFIG_PATH.into()).unwrap();
+            let program_pubkey = Pubkey::from_str(&program_addr).unwrap();
+
+            let trade_id = Pubkey::from_str(sub_matches.value_of("id").unwrap()).unwrap();
+            let premium_src = Pubkey::from_str(sub_matches.value_of("premium-src").unwrap()).unwrap();
+
+            client::buy_option(wallet, trade_id, premium_src, program_pubkey, &conn).unwrap();
+        }
+        "exercise" => {
+            let program_addr = ProgramConfig::load_program_addr(PROGRAM_CONFIG_PATH.into()).unwrap();
+            let program_pubkey = Pubkey::from_str(&program_addr).unwrap();
+
+            let trade_id = Pubkey::from_str(sub_matches.value_of("id").unwrap()).unwrap();
+            let trade_src = Pubkey::from_str(sub_matches.value_of("tradesrc").unwrap()).unwrap();
+            let offer_dst = sub_matches.value_of("offerdst").map(|addr| Pubkey::from_str(addr).unwrap());
+
+            let pa = ProgramConfig::load_wallet_addr(PROGRAM_CONFIG_PATH.into()).unwrap();
+            let program_authority = Pubkey::from_str(pa.as_ref()).unwrap();
+
+            client::exercise_option(
+                wallet, trade_id, trade_src, offer_dst, program_pubkey, program_authority, &conn,
+            ).unwrap();
+        }
+        "reclaim" => {
+            let program_addr = ProgramConfig::load_program_addr(PROGRAM_CONFIG_PATH.into()).unwrap();
+            let program_pubkey = Pubkey::from_str(&program_addr).unwrap();
+
+            let trade_id = Pubkey::from_str(sub_matches.value_of("id").unwrap()).unwrap();
+            client::reclaim_underlying(wallet, trade_id, program_pubkey, &conn).unwrap();
+        }
+        "settle" => {
+            let program_addr = ProgramConfig::load_program_addr(PROGRAM_CONFIG_PATH.into()).unwrap();
+            let program_pubkey = Pubkey::from_str(&program_addr).unwrap();
+
+            let trade_id = Pubkey::from_str(sub_matches.value_of("id").unwrap()).unwrap();
+            client::settle(wallet, trade_id, program_pubkey, &conn).unwrap();
+        }
+        "claim" => {
+            let program_addr = ProgramConfig::load_program_addr(PROGRAM_CONFIG_PATH.into()).unwrap();
+            let program_pubkey = Pubkey::from_str(&program_addr).unwrap();
+
+            let trade_id = Pubkey::from_str(sub_matches.value_of("id").unwrap()).unwrap();
+            client::claim_vested(wallet, trade_id, program_pubkey, &conn).unwrap();
+        }
+        "transfer-receipt" => {
+            let trade_id = Pubkey::from_str(sub_matches.value_of("id").unwrap()).unwrap();
+            let recipient = Pubkey::from_str(sub_matches.value_of("recipient").unwrap()).unwrap();
+            client::transfer_receipt(wallet, trade_id, recipient, &conn).unwrap();
+        }
+        "delegate-receipt" => {
+            let program_addr = ProgramConfig::load_program_addr(PROGRAM_CONFIG_PATH.into()).unwrap();
+            let program_pubkey = Pubkey::from_str(&program_addr).unwrap();
+
+            let trade_id = Pubkey::from_str(sub_matches.value_of("id").unwrap()).unwrap();
+            client::delegate_receipt(wallet, trade_id, program_pubkey, &conn).unwrap();
+        }
+        "mint-list" => {
+            let program_addr = ProgramConfig::load_program_addr(PROGRAM_CONFIG_PATH.into()).unwrap();
+            let program_pubkey = Pubkey::from_str(&program_addr).unwrap();
+
+            let mints = |name: &str| -> Vec<Pubkey> {
+                sub_matches.values_of(name)
+                    .map(|values| values.map(|mint| Pubkey::from_str(mint).unwrap()).collect())
+                    .unwrap_or_default()
+            };
+            let mode = sub_matches.value_of("mode").map(|mode| match mode {
+                "allow" => MintListMode::Allowlist,
+                _ => MintListMode::Blocklist,
+            });
+            let (add, remove) = (mints("add"), mints("remove"));
+            if mode.is_some() || !add.is_empty() || !remove.is_empty() {
+                client::update_mint_list(wallet, mode, add, remove, program_pubkey, &conn).unwrap();
+            }
+            client::show_mint_list(mints("mints"), program_pubkey, &conn).unwrap();
+        }
+        "mint-limits" => {
+            let program_addr = ProgramConfig::load_program_addr(PROGRAM_CONFIG_PATH.into()).unwrap();
+            let program_pubkey = Pubkey::from_str(&program_addr).unwrap();
+            let mint = Pubkey::from_str(sub_matches.value_of("mint").unwrap()).unwrap();
+
+            // the limits not given are lifted, as every limit is set at once
+            if ["min", "max", "volume-cap"].iter().any(|name| sub_matches.is_present(name)) {
+                let decimals = resolve_decimals_of_mint(&mint, &conn).unwrap();
+                let amount = |name: &str| -> u64 {
+                    sub_matches.value_of(name)
+                        .map(|amount| spl_token::ui_amount_to_amount(amount.parse().unwrap(), decimals))
+                        .unwrap_or(0)
+                };
+                let limits = MintLimits {
+                    min_amount: amount("min"),
+                    max_amount: amount("max"),
+                    volume_cap: amount("volume-cap"),
+                    window: sub_matches.value_of("window").map(|secs| secs.parse::<i64>().unwrap()).unwrap_or(0),
+                };
+                client::set_mint_limits(wallet, mint, limits, program_pubkey, &conn).unwrap();
+            }
+            client::show_mint_limits(mint, program_pubkey, &conn).unwrap();
+        }
+        "stats" => {
+            let program_addr = ProgramConfig::load_program_addr(PROGRAM_CONFIG_PATH.into()).unwrap();
+            let program_pubkey = Pubkey::from_str(&program_addr).unwrap();
+            let pubkeys = |name: &str| -> Vec<Pubkey> {
+                sub_matches.values_of(name)
+                    .map(|values| values.map(|pubkey| Pubkey::from_str(pubkey).unwrap()).collect())
+                    .unwrap_or_default()
+            };
+            let (mints, makers) = (pubkeys("mint"), pubkeys("maker"));
+
+            if sub_matches.is_present("init") {
+                let stats = std::iter::once(StatsAccount::Program)
+                    .chain(mints.iter().map(|mint| StatsAccount::Mint(*mint)))
+                    .chain(makers.iter().map(|maker| StatsAccount::Maker(*maker)))
+                    .collect();
+                client::init_stats(wallet, stats, program_pubkey, &conn).unwrap();
+            }
+            client::show_stats(mints, makers, program_pubkey, &conn).unwrap();
+        }
+        "allowlist" => {
+            let entries = load_allowlist(sub_matches.value_of("csv").unwrap()).unwrap();
+            let count = entries.len();
+            let tree = AllowlistTree::new(entries);
+            println!("Allowlist of {} wallets, root: {}", count, Hash::new_from_array(tree.root()));
+
+            if let Some(addr) = sub_matches.value_of("wallet") {
+                let wallet = Pubkey::from_str(addr).unwrap();
+                match tree.proof(&wallet) {
+                    Some(proof) => {
+                        println!("\t- cap: {}", proof.cap);
+                        for hash in proof.proof {
+                            println!("\t- {}", Hash::new_from_array(hash));
+                        }
+                    },
+                    None => println!("{} isn't in the allowlist", wallet),
+                }
+            }
+        }
+        "sign-order" => {
+            let program_addr = ProgramConfig::load_program_addr(PROGRAM_CONFIG_PATH.into()).unwrap();
+            let program_pubkey = Pubkey::from_str(&program_addr).unwrap();
+
+            let offer_src = Pubkey::from_str(sub_matches.value_of("offer_account").unwrap()).unwrap();
+            let trade_mint = Pubkey::from_str(sub_matches.value_of("trade_token").unwrap()).unwrap();
+            let nonce: u64 = sub_matches.value_of("nonce").unwrap().parse().unwrap();
+            let trade_dst = sub_matches.value_of("trade-dst").map(|addr| Pubkey::from_str(addr).unwrap());
+            let expires_at = match sub_matches.value_of("expires-in") {
+                Some(secs) => {
+                    let now = conn.get_block_time(conn.get_slot().unwrap()).unwrap();
+                    now + secs.parse::<i64>().unwrap()
+                },
+                None => 0
+            };
+
+            let offer_decimals = resolve_mint_decimals(&offer_src, None, &conn).unwrap();
+            let amount: f64 = sub_matches.value_of("offer-amount").unwrap().parse().unwrap();
+            let offer_ammount = spl_token::ui_amount_to_amount(amount, offer_decimals);
+            let trade_decimals = resolve_decimals_of_mint(&trade_mint, &conn).unwrap();
+            let amount: f64 = sub_matches.value_of("trade-amount").unwrap().parse().unwrap();
+            let trade_ammount = spl_token::ui_amount_to_amount(amount, trade_decimals);
+
+            let order = client::sign_order(
+                offer_ammount, trade_ammount, wallet, offer_src, trade_mint, trade_dst, nonce, expires_at,
+                program_pubkey, &conn,
+            ).unwrap();
+
+            if sub_matches.is_present("relay") {
+                let relay_url = sub_matches.value_of("relay").unwrap_or(relay::DEFAULT_RELAY_URL);
+                relay::post_order(relay_url, &order).unwrap();
+                println!("Order posted to {}", relay_url);
+            }
+        }
+        "fill-order" => {
+            let program_addr = ProgramConfig::load_program_addr(PROGRAM_CONFIG_PATH.into()).unwrap();
+            let program_pubkey = Pubkey::from_str(&program_addr).unwrap();
+
+            let order = sub_matches.value_of("order").unwrap();
+            let trade_src = Pubkey::from_str(sub_matches.value_of("tradesrc").unwrap()).unwrap();
+            let offer_dst = sub_matches.value_of("offerdst").map(|addr| Pubkey::from_str(addr).unwrap());
+
+            let pa = ProgramConfig::load_wallet_addr(PROGRAM_CONFIG_PATH.into()).unwrap();
+            let program_authority = Pubkey::from_str(pa.as_ref()).unwrap();
+
+            client::fill_order(order, wallet, trade_src, offer_dst, program_pubkey, program_authority, &conn).unwrap();
+        }
+        "cancel-orders" => {
+            let program_addr = ProgramConfig::load_program_addr(PROGRAM_CONFIG_PATH.into()).unwrap();
+            let program_pubkey = Pubkey::from_str(&program_addr).unwrap();
+
+            let nonces = match sub_matches.values_of("nonces") {
+                Some(values) => values.map(|nonce| nonce.parse().unwrap()).collect(),
+                None => vec![]
+            };
+            let min_nonce = match sub_matches.value_of("below") {
+                Some(nonce) => nonce.parse().unwrap(),
+                None => 0
+            };
+
+            client::cancel_orders(wallet, min_nonce, nonces, program_pubkey, &conn).unwrap();
+        }
+        "orders" => {
+            let offer_mint = sub_matches.value_of("offer_token").map(|addr| Pubkey::from_str(addr).unwrap());
+            let trade_mint = sub_matches.value_of("trade_token").map(|addr| Pubkey::from_str(addr).unwrap());
+            let relay_url = sub_matches.value_of("relay").unwrap_or(relay::DEFAULT_RELAY_URL);
+
+            client::list_orders(relay_url, offer_mint, trade_mint, &conn).unwrap();
+        }
+        "quote" => {
+            let trade_id = Pubkey::from_str(sub_matches.value_of("id").unwrap()).unwrap();
+            client::quote(trade_id, &conn).unwrap();
+        }
+        "route" => {
+            let program_addr = ProgramConfig::load_program_addr(PROGRAM_CONFIG_PATH.into()).unwrap();
+            let program_pubkey = Pubkey::from_str(&program_addr).unwrap();
+
+            let from_mint = Pubkey::from_str(sub_matches.value_of("from").unwrap()).unwrap();
+            let to_mint = Pubkey::from_str(sub_matches.value_of("to").unwrap()).unwrap();
+            let amount_arg: f64 = sub_matches.value_of("amount").unwrap().parse().unwrap();
+
+            let decimals = resolve_decimals_of_mint(&from_mint, &conn).unwrap();
+            let amount = spl_token::ui_amount_to_amount(amount_arg, decimals);
+
+            let pa = ProgramConfig::load_wallet_addr(PROGRAM_CONFIG_PATH.into()).unwrap();
+            let program_authority = Pubkey::from_str(pa.as_ref()).unwrap();
+
+            client::route(amount, from_mint, to_mint, wallet, program_pubkey, program_authority, &conn).unwrap();
+        }
+        "crank" => {
+            let program_addr = ProgramConfig::load_program_addr(PROGRAM_CONFIG_PATH.into()).unwrap();
+            let program_pubkey = Pubkey::from_str(&program_addr).unwrap();
+
+            let interval = sub_matches.value_of("interval").map(|secs| secs.parse().unwrap());
+
+            client::crank(wallet, program_pubkey, interval, &conn).unwrap();
+        }
+        "bootstrap" => {
+            let wallet1 = get_wallet(sub_matches.value_of("wallet1")).unwrap();
+            let wallet2 = get_wallet(sub_matches.value_of("wallet2")).unwrap();
+            client::setup_accounts(10, 20, wallet1, wallet2, &conn).unwrap();
+        }
+        "config" => {
+            if let Some(addr) = sub_matches.value_of("program") {
+                ProgramConfig::store_program_addr(PROGRAM_CONFIG_PATH.into(), addr.into()).unwrap();
+            }
+
+            if let Some(addr) = sub_matches.value_of("wallet") {
+                ProgramConfig::store_wallet_addr(PROGRAM_CONFIG_PATH.into(), addr.into()).unwrap();
             }
         }
         op => {
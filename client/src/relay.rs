@@ -0,0 +1,341 @@
+use crate::{Error, Result};
+use crate::client::decode_signed_order;
+use borsh::{BorshDeserialize, BorshSerialize};
+use serde::{Deserialize, Serialize};
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::{pubkey::Pubkey, signature::Signature};
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::str::FromStr;
+use trader::{
+    instruction,
+    instructions::Action,
+    state::AccountNonces,
+};
+
+pub const DEFAULT_RELAY_URL: &str = "http://127.0.0.1:8900";
+// Largest request body the relay reads, well above the size of a signed order
+const MAX_BODY_BYTES: usize = 4096;
+
+// An order signed with sign_order as kept by the relay. verify_data and fill_data are the base64 instruction data of
+// the ed25519 program instruction and of the FillSignedOrder instruction that fill it, see
+// `instruction::fill_signed_order`, so only the accounts of the taker are left to add.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RelayOrder {
+    pub order: String,
+    pub maker: String,
+    pub offer_mint: String,
+    pub trade_mint: String,
+    pub offer_amount: u64,
+    pub trade_amount: u64,
+    pub nonce: u64,
+    pub expires_at: i64,
+    pub verify_data: String,
+    pub fill_data: String,
+}
+
+impl RelayOrder {
+    // Decodes the order and checks it is signed by its maker
+    pub fn from_encoded(encoded: &str) -> Result<RelayOrder> {
+        let (order, signature) = decode_signed_order(encoded)?;
+        let message = order.try_to_vec().unwrap();
+        if !Signature::new(&signature).verify(order.maker.as_ref(), &message) {
+            return Err(Error::RelayError(format!("order {} is not signed by {}", order.nonce, order.maker)));
+        }
+
+        let verify_ix = instruction::verify_signature(&order.maker, &signature, &message);
+        let fill_data = Action::FillSignedOrder { order: order.clone() }.try_to_vec().unwrap();
+
+        Ok(RelayOrder {
+            order: encoded.trim().to_string(),
+            maker: order.maker.to_string(),
+            offer_mint: order.offer_mint.to_string(),
+            trade_mint: order.trade_mint.to_string(),
+            offer_amount: order.offer_amount,
+            trade_amount: order.trade_amount,
+            nonce: order.nonce,
+            expires_at: order.expires_at,
+            verify_data: base64::encode(verify_ix.data),
+            fill_data: base64::encode(fill_data),
+        })
+    }
+
+    pub fn is_expired(&self, now: i64) -> bool {
+        self.expires_at != 0 && now >= self.expires_at
+    }
+}
+
+/*
+ * What the relay needs to know from the chain to drop orders that can't be filled anymore.
+*/
+pub trait OrderStatus {
+    // the current unix timestamp
+    fn now(&self) -> Result<i64>;
+    // the nonces account of the maker, None if it doesn't exist
+    fn load_nonces(&self, maker: &Pubkey) -> Result<Option<AccountNonces>>;
+}
+
+pub struct ChainStatus<'a> {
+    pub trader_program_id: Pubkey,
+    pub conn: &'a RpcClient,
+}
+
+impl OrderStatus for ChainStatus<'_> {
+    fn now(&self) -> Result<i64> {
+        let slot = self.conn.get_slot().map_err(Error::RpcError)?;
+        self.conn.get_block_time(slot).map_err(Error::RpcError)
+    }
+
+    fn load_nonces(&self, maker: &Pubkey) -> Result<Option<AccountNonces>> {
+        let (nonces, _) = instruction::find_nonces_pda(&self.trader_program_id, maker);
+        match self.conn.get_account_data(&nonces) {
+            Ok(data) => Ok(Some(AccountNonces::try_from_slice(&data).map_err(Error::SerializationError)?)),
+            Err(_) => Ok(None),
+        }
+    }
+}
+
+/*
+ * The orders posted to the relay, kept as a JSON file that is rewritten on every change.
+*/
+pub struct OrderBook {
+    path: PathBuf,
+    orders: Vec<RelayOrder>,
+}
+
+impl OrderBook {
+    // Loads the orders stored at path, none if the file doesn't exist yet. Any other error is returned, as the next
+    // save would overwrite the file
+    pub fn open(path: PathBuf) -> Result<OrderBook> {
+        let orders = match std::fs::read_to_string(&path) {
+            Ok(json) => serde_json::from_str(&json)
+                .map_err(|e| Error::RelayError(format!("invalid order book {}: ({})", path.display(), e)))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => vec![],
+            Err(e) => return Err(Error::RelayError(format!("failed to read order book {}: ({})", path.display(), e))),
+        };
+
+        Ok(OrderBook { path, orders })
+    }
+
+    // Adds a signed order, replacing the one of the same maker with the same nonce if any
+    pub fn add(&mut self, encoded: &str) -> Result<RelayOrder> {
+        let order = RelayOrder::from_encoded(encoded)?;
+        self.orders.retain(|other| other.maker != order.maker || other.nonce != order.nonce);
+        self.orders.push(order.clone());
+        self.save()?;
+
+        Ok(order)
+    }
+
+    // The orders of the pair, offer mint and trade mint, or of either when only one of them is given
+    pub fn orders(&self, offer_mint: Option<&Pubkey>, trade_mint: Option<&Pubkey>) -> Vec<RelayOrder> {
+        self.orders.iter()
+            .filter(|order| offer_mint.iter().all(|mint| order.offer_mint == mint.to_string()))
+            .filter(|order| trade_mint.iter().all(|mint| order.trade_mint == mint.to_string()))
+            .cloned()
+            .collect()
+    }
+
+    // Drops the orders that expired or whose nonce was spent, by a fill or a cancel. Returns how many were dropped
+    pub fn prune(&mut self, status: &dyn OrderStatus) -> Result<usize> {
+        let now = status.now()?;
+        let count = self.orders.len();
+        let mut nonces = HashMap::<String, Option<AccountNonces>>::new();
+        let mut kept = Vec::<RelayOrder>::new();
+
+        for order in self.orders.drain(..) {
+            if order.is_expired(now) {
+                continue;
+            }
+            if !nonces.contains_key(&order.maker) {
+                let maker = Pubkey::from_str(&order.maker).unwrap();
+                nonces.insert(order.maker.clone(), status.load_nonces(&maker)?);
+            }
+            if let Some(maker_nonces) = &nonces[&order.maker] {
+                if maker_nonces.is_spent(order.nonce) {
+                    continue;
+                }
+            }
+            kept.push(order);
+        }
+
+        let dropped = count - kept.len();
+        self.orders = kept;
+        if dropped > 0 {
+            self.save()?;
+        }
+
+        Ok(dropped)
+    }
+
+    fn save(&self) -> Result<()> {
+        let json = serde_json::to_string_pretty(&self.orders).unwrap();
+        std::fs::write(&self.path, json).map_err(Error::SerializationError)
+    }
+}
+
+/*
+ * Serves the order book over HTTP, one request at a time:
+ * `POST /orders` with a signed order, as printed by sign_order, as the body adds it and returns it as a RelayOrder.
+ * `GET /orders?offer_mint=<MINT>&trade_mint=<MINT>` returns the RelayOrders that can still be filled, of the pair or
+ * of either mint when only one is given.
+*/
+pub fn serve(listener: TcpListener, book: &mut OrderBook, status: &dyn OrderStatus) -> Result<()> {
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(_) => continue,
+        };
+        if let Err(e) = handle_connection(stream, book, status) {
+            eprintln!("Failed to handle request: {}", e);
+        }
+    }
+
+    Ok(())
+}
+
+fn handle_connection(mut stream: TcpStream, book: &mut OrderBook, status: &dyn OrderStatus) -> Result<()> {
+    let mut reader = BufReader::new(stream.try_clone().map_err(Error::SerializationError)?);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line).map_err(Error::SerializationError)?;
+
+    let mut content_length = 0;
+    loop {
+        let mut header = String::new();
+        reader.read_line(&mut header).map_err(Error::SerializationError)?;
+        let header = header.trim();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse().unwrap_or(0);
+            }
+        }
+    }
+
+    // the body isn't read at all when it's too large
+    let (status_code, response) = if content_length > MAX_BODY_BYTES {
+        (413, error_json(&format!("body over {} bytes", MAX_BODY_BYTES)))
+    } else {
+        let mut body = vec![0; content_length];
+        reader.read_exact(&mut body).map_err(Error::SerializationError)?;
+
+        let mut parts = request_line.split_whitespace();
+        let method = parts.next().unwrap_or_default();
+        let target = parts.next().unwrap_or_default();
+        handle_request(method, target, &String::from_utf8_lossy(&body), book, status)
+    };
+
+    let reason = match status_code {
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        413 => "Payload Too Large",
+        _ => "Internal Server Error",
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status_code, reason, response.len(), response,
+    ).map_err(Error::SerializationError)
+}
+
+fn handle_request(
+    method: &str,
+    target: &str,
+    body: &str,
+    book: &mut OrderBook,
+    status: &dyn OrderStatus,
+) -> (u16, String) {
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    if path != "/orders" {
+        return (404, error_json("not found"));
+    }
+
+    match method {
+        "POST" => match book.add(body) {
+            Ok(order) => (200, serde_json::to_string(&order).unwrap()),
+            Err(e) => (400, error_json(&e.to_string())),
+        },
+        "GET" => {
+            let mut offer_mint = None;
+            let mut trade_mint = None;
+            for param in query.split('&').filter(|param| !param.is_empty()) {
+                let (name, value) = param.split_once('=').unwrap_or((param, ""));
+                let mint = match Pubkey::from_str(value) {
+                    Ok(mint) => mint,
+                    Err(_) => return (400, error_json(&format!("invalid {}: {}", name, value))),
+                };
+                match name {
+                    "offer_mint" => offer_mint = Some(mint),
+                    "trade_mint" => trade_mint = Some(mint),
+                    _ => return (400, error_json(&format!("unknown parameter {}", name))),
+                }
+            }
+
+            if let Err(e) = book.prune(status) {
+                return (500, error_json(&e.to_string()));
+            }
+            let orders = book.orders(offer_mint.as_ref(), trade_mint.as_ref());
+            (200, serde_json::to_string(&orders).unwrap())
+        },
+        _ => (404, error_json("not found")),
+    }
+}
+
+fn error_json(message: &str) -> String {
+    serde_json::json!({ "error": message }).to_string()
+}
+
+/*
+ * Posts a signed order to the relay at relay_url.
+*/
+pub fn post_order(relay_url: &str, encoded: &str) -> Result<RelayOrder> {
+    let response = request(relay_url, "POST", "/orders", encoded)?;
+    serde_json::from_str(&response).map_err(|e| Error::RelayError(format!("invalid response: ({})", e)))
+}
+
+/*
+ * Loads the orders of the relay at relay_url that can still be filled, of the pair or of either mint.
+*/
+pub fn load_orders(relay_url: &str, offer_mint: Option<&Pubkey>, trade_mint: Option<&Pubkey>) -> Result<Vec<RelayOrder>> {
+    let mut params = Vec::<String>::new();
+    if let Some(mint) = offer_mint {
+        params.push(format!("offer_mint={}", mint));
+    }
+    if let Some(mint) = trade_mint {
+        params.push(format!("trade_mint={}", mint));
+    }
+
+    let response = request(relay_url, "GET", &format!("/orders?{}", params.join("&")), "")?;
+    serde_json::from_str(&response).map_err(|e| Error::RelayError(format!("invalid response: ({})", e)))
+}
+
+// Sends a request to the relay and returns the body of the response, or its error
+fn request(relay_url: &str, method: &str, path: &str, body: &str) -> Result<String> {
+    let host = relay_url.trim_start_matches("http://").trim_end_matches('/');
+    let mut stream = TcpStream::connect(host)
+        .map_err(|e| Error::RelayError(format!("failed to connect to {}: ({})", relay_url, e)))?;
+    write!(
+        stream,
+        "{} {} HTTP/1.1\r\nHost: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        method, path, host, body.len(), body,
+    ).map_err(Error::SerializationError)?;
+
+    let mut response = String::new();
+    stream.read_to_string(&mut response).map_err(Error::SerializationError)?;
+    let (head, body) = response.split_once("\r\n\r\n")
+        .ok_or_else(|| Error::RelayError("invalid response".into()))?;
+    let status_code = head.split_whitespace().nth(1).unwrap_or_default();
+    if status_code != "200" {
+        let message = serde_json::from_str::<serde_json::Value>(body).ok()
+            .and_then(|error| error["error"].as_str().map(|message| message.to_string()))
+            .unwrap_or_else(|| body.to_string());
+        return Err(Error::RelayError(format!("{} {}", status_code, message)));
+    }
+
+    Ok(body.to_string())
+}
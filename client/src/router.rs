@@ -0,0 +1,122 @@
+use borsh::BorshDeserialize;
+use crate::{Error, Result};
+use solana_client::rpc_client::RpcClient;
+use solana_program::program_pack::Pack;
+use solana_sdk::pubkey::Pubkey;
+use spl_token::state::Account;
+use trader::state::AccountTrade;
+
+// Longest route searched, in number of trades
+pub const MAX_HOPS: usize = 3;
+
+#[derive(Clone, Debug)]
+pub struct OpenTrade {
+    pub trade_id: Pubkey,
+    pub offer_mint: Pubkey,
+    pub account: AccountTrade,
+}
+
+/*
+ * Loads every open trade of the program. The trade account doesn't store the offer mint, so it is taken from the
+ * offer token account.
+*/
+pub fn load_open_trades(trader_program_id: &Pubkey, conn: &RpcClient) -> Result<Vec<OpenTrade>> {
+    let accounts = conn.get_program_accounts(trader_program_id).map_err(Error::RpcError)?;
+
+    let mut trades = Vec::<OpenTrade>::new();
+    for (trade_id, account) in accounts {
+        if account.data.len() != AccountTrade::size() {
+            continue;
+        }
+        let trade = match AccountTrade::try_from_slice(&account.data) {
+            Ok(trade) if trade.initialized => trade,
+            _ => continue,
+        };
+
+        let offer_account = match conn.get_account(&trade.offer_token_account) {
+            Ok(account) => account,
+            Err(_) => continue,
+        };
+        let offer_token = match Account::unpack_from_slice(&offer_account.data) {
+            Ok(token) => token,
+            Err(_) => continue,
+        };
+
+        trades.push(OpenTrade {
+            trade_id,
+            offer_mint: offer_token.mint,
+            account: trade,
+        });
+    }
+
+    Ok(trades)
+}
+
+/*
+ * Searches the open trades as a graph where each trade is an edge from its trade mint to its offer mint, and returns
+ * the route from `from` to `to` that yields the most `to` tokens for `amount` of `from` tokens.
+ *
+ * Trades are filled as a whole, so each hop must be able to pay for the next one: the offer of a hop must cover the
+ * trade amount of the following one. Whatever is left over of the intermediate tokens stays with the taker.
+*/
+pub fn find_route(trades: &[OpenTrade], from: &Pubkey, to: &Pubkey, amount: u64) -> Option<Vec<OpenTrade>> {
+    let mut best: Option<(u64, Vec<OpenTrade>)> = None;
+    let mut route = Vec::<&OpenTrade>::new();
+
+    search(trades, from, to, amount, &mut route, &mut best);
+
+    best.map(|(_, route)| route)
+}
+
+fn search<'a>(
+    trades: &'a [OpenTrade],
+    mint: &Pubkey,
+    to: &Pubkey,
+    amount: u64,
+    route: &mut Vec<&'a OpenTrade>,
+    best: &mut Option<(u64, Vec<OpenTrade>)>,
+) {
+    if route.len() == MAX_HOPS {
+        return;
+    }
+
+    for trade in trades {
+        if trade.account.trade_mint != *mint || trade.account.trade_amount > amount {
+            continue;
+        }
+        // the trade amount of a pegged trade is only known when filled, so they are left out
+        if trade.account.is_pegged() {
+            continue;
+        }
+        // and so are trades in Arbitrated mode, which need the signature of their arbiter, options, which are only
+        // filled by their holder, forward and vesting trades, whose offer is only delivered once settled or vested,
+        // allowlisted trades, which need a proof for the taker, and trades with a receipt, paying whoever holds it
+        if trade.account.is_arbitrated() || trade.account.is_option() || trade.account.is_forward()
+            || trade.account.is_vesting() || trade.account.is_allowlisted() || trade.account.has_receipt()
+        {
+            continue;
+        }
+        // a trade can't be filled twice and there's no point in going through the same mint again
+        if route.iter().any(|hop| hop.trade_id == trade.trade_id || hop.account.trade_mint == trade.offer_mint) {
+            continue;
+        }
+
+        route.push(trade);
+
+        if trade.offer_mint == *to {
+            let out = trade.account.offer_amount;
+            let better = match best {
+                // on a tie prefer the shortest route
+                Some((best_out, best_route)) => out > *best_out || (out == *best_out && route.len() < best_route.len()),
+                None => true,
+            };
+            if better {
+                *best = Some((out, route.iter().map(|hop| (*hop).clone()).collect()));
+            }
+        } else {
+            search(trades, &trade.offer_mint, to, trade.account.offer_amount, route, best);
+        }
+
+        route.pop();
+    }
+}
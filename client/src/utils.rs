@@ -103,7 +103,7 @@ pub fn load_config() -> Result<yaml_rust::Yaml> {
             )));
         }
     };
-    let config = std::fs::read_to_string(path).map_err(|e| Error::ConfigReadError(e))?;
+    let config = std::fs::read_to_string(path).map_err(Error::ConfigReadError)?;
     let mut config = YamlLoader::load_from_str(&config).unwrap();
     match config.len() {
         1 => Ok(config.remove(0)),
@@ -204,6 +204,18 @@ pub fn resolve_mint_decimals(
     Ok(source_account.token_amount.decimals)
 }
 
+pub fn resolve_decimals_of_mint(
+    mint: &Pubkey,
+    conn: &RpcClient,
+) -> Result<u8> {
+    let mint_account = conn.get_account(mint).map_err(Error::RpcError)?;
+    let mint = Mint::unpack_from_slice(&mint_account.data).map_err(|e| {
+        Error::InvalidConfig(format!("{} is not a mint: ({})", mint, e))
+    })?;
+
+    Ok(mint.decimals)
+}
+
 pub fn resolve_mint_info(
     token_account: &Pubkey,
     conn: &RpcClient,
@@ -224,29 +236,38 @@ pub fn get_or_create_token_account(
     let token_account = conn.get_token_account(&token_account).unwrap().unwrap();
     let offer_src_addr = Pubkey::from_str(&token_account.mint).unwrap();
 
-    let offer_ata = spl_associated_token_account::get_associated_token_address(&wallet, &offer_src_addr);
+    get_or_create_ata(payer, wallet, offer_src_addr, conn)
+}
+
+pub fn get_or_create_ata(
+    payer: &Keypair,
+    wallet: Pubkey,
+    mint: Pubkey,
+    conn: &RpcClient,
+) -> Result<Pubkey> {
+    let ata = spl_associated_token_account::get_associated_token_address(&wallet, &mint);
 
     // TODO: handle error properly - if AccountNotFound, create it, otherwise raise it
-    let _offer_ata_account = match conn.get_account(&offer_ata) {
+    let _ata_account = match conn.get_account(&ata) {
         Err(_) => {
             println!("Creating ATA...");
 
             let ix = spl_associated_token_account::create_associated_token_account(
                 &payer.pubkey(),
                 &wallet,
-                &offer_src_addr,
+                &mint,
             );
 
             let message = Message::new(&[ix], Some(&payer.pubkey()));
             let transaction = Transaction::new(&[payer], message, conn.get_latest_blockhash().unwrap());
             conn.send_and_confirm_transaction(&transaction).unwrap();
 
-            println!("ATA created with address {}", offer_ata.to_string());
+            println!("ATA created with address {}", ata);
 
-            conn.get_account(&offer_ata).unwrap()
+            conn.get_account(&ata).unwrap()
         },
         Ok(account ) => account
     };
 
-    Ok(offer_ata)
+    Ok(ata)
 }
@@ -0,0 +1,161 @@
+mod lib;
+
+use {
+    lib::*,
+    solana_program_test::*,
+    solana_sdk::{
+        instruction::{AccountMeta, Instruction},
+        pubkey::Pubkey,
+        signature::Signer,
+    },
+};
+use ::trader::instruction;
+
+// How the account at a given index of a valid instruction is replaced
+enum Substitution {
+    // another account, which never signs
+    Key(Pubkey),
+    // the same account, but read only
+    Readonly,
+}
+
+// Applies every substitution to ix, one at a time, and checks the transaction fails with the expected error. A failed
+// transaction leaves no changes behind, so all of them run against the same accounts.
+async fn assert_substitutions(test: &TestData, ix: &Instruction, cases: Vec<(usize, Substitution, &str)>) {
+    for (index, substitution, expected) in cases {
+        let mut ix = ix.clone();
+        let meta = &ix.accounts[index];
+        ix.accounts[index] = match substitution {
+            Substitution::Key(pubkey) => AccountMeta { pubkey, is_signer: false, is_writable: meta.is_writable },
+            Substitution::Readonly => AccountMeta { pubkey: meta.pubkey, is_signer: meta.is_signer, is_writable: false },
+        };
+
+        let mut signers = vec![&test.payer];
+        if ix.accounts.iter().any(|meta| meta.is_signer && meta.pubkey == test.wallet2.pubkey()) {
+            signers.push(&test.wallet2);
+        }
+
+        let err = process_ix(&signers, test.payer.pubkey(), ix, &test.conn).await.unwrap_err();
+        assert!(
+            err.to_string().ends_with(expected),
+            "account {}: expected \"{}\", got \"{}\"", index, expected, err,
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_create_trade_accounts() {
+    let test = TestData::init().await;
+
+    let (ix, _, _) = init_trade_ix(&test, 2, None);
+    // the authority is also the fee payer, so it is always a writable signer
+    let cases = vec![
+        (0, Substitution::Key(Pubkey::new_unique()), "custom program error: 0x0"),
+        (1, Substitution::Readonly, "custom program error: 0x12"),
+        (2, Substitution::Readonly, "custom program error: 0x12"),
+        (2, Substitution::Key(Pubkey::new_unique()), "incorrect program id for instruction"),
+        (3, Substitution::Key(Pubkey::new_unique()), "custom program error: 0x7"),
+        (3, Substitution::Key(test.offer_src), "custom program error: 0x13"),
+        (4, Substitution::Key(test.offer_dst), "custom program error: 0x7"),
+        (4, Substitution::Key(test.offer_src), "custom program error: 0x13"),
+        (5, Substitution::Key(Pubkey::new_unique()), "custom program error: 0x10"),
+        (6, Substitution::Key(Pubkey::new_unique()), "custom program error: 0x11"),
+        (7, Substitution::Key(test.trade_mint), "custom program error: 0x14"),
+        (8, Substitution::Key(Pubkey::new_unique()), "custom program error: 0x10"),
+        (9, Substitution::Key(Pubkey::new_unique()), "custom program error: 0x10"),
+        (10, Substitution::Key(Pubkey::new_unique()), "custom program error: 0x10"),
+        (11, Substitution::Key(Pubkey::new_unique()), "custom program error: 0x10"),
+        (12, Substitution::Key(Pubkey::new_unique()), "custom program error: 0x10"),
+        (13, Substitution::Key(Pubkey::new_unique()), "custom program error: 0x10"),
+        (13, Substitution::Readonly, "custom program error: 0x12"),
+    ];
+    assert_substitutions(&test, &ix, cases).await;
+}
+
+#[tokio::test]
+async fn test_make_trade_accounts() {
+    let test = TestData::init().await;
+    create_test_trade(&test).await;
+
+    let recent_blockhash = test.conn.borrow_mut().get_latest_blockhash().await.unwrap();
+    let uninitialised_trade = TestData::create_trade_account(test.program_id, &test.payer, None, &test.conn).await;
+    let other_mint = mint_account(&test.wallet2, &test.payer, recent_blockhash, &test.conn).await;
+    let other_mint_account = token_account(&test.wallet2, &test.payer, other_mint, recent_blockhash, &test.conn).await;
+    let trade_mint_account = token_account(&test.wallet2, &test.payer, test.trade_mint, recent_blockhash, &test.conn).await;
+
+    let offer_amount: u64 = spl_token::ui_amount_to_amount(10.0, 9);
+    let trade_amount: u64 = spl_token::ui_amount_to_amount(2.0, 9);
+    let (ix, _, _) = make_trade_ix(&test, offer_amount, trade_amount, None);
+    // the offer owner is also the fee payer, so it is always writable
+    let cases = vec![
+        (0, Substitution::Key(Pubkey::new_unique()), "custom program error: 0x0"),
+        (1, Substitution::Key(uninitialised_trade.pubkey()), "custom program error: 0x4"),
+        (1, Substitution::Readonly, "custom program error: 0x12"),
+        (2, Substitution::Key(Pubkey::new_unique()), "custom program error: 0x10"),
+        (3, Substitution::Key(Pubkey::new_unique()), "custom program error: 0x6"),
+        (3, Substitution::Readonly, "custom program error: 0x12"),
+        (4, Substitution::Key(Pubkey::new_unique()), "custom program error: 0x8"),
+        (4, Substitution::Readonly, "custom program error: 0x12"),
+        (5, Substitution::Key(other_mint_account), "custom program error: 0x7"),
+        (5, Substitution::Readonly, "custom program error: 0x12"),
+        (6, Substitution::Key(trade_mint_account), "custom program error: 0x14"),
+        (6, Substitution::Key(test.offer_src), "custom program error: 0x13"),
+        (6, Substitution::Readonly, "custom program error: 0x12"),
+        (7, Substitution::Key(test.wallet2.pubkey()), "custom program error: 0x0"),
+        (8, Substitution::Key(Pubkey::new_unique()), "custom program error: 0x0"),
+        (8, Substitution::Readonly, "custom program error: 0x12"),
+        (9, Substitution::Key(Pubkey::new_unique()), "custom program error: 0x11"),
+        (11, Substitution::Key(Pubkey::new_unique()), "custom program error: 0x10"),
+        (11, Substitution::Readonly, "custom program error: 0x12"),
+        (12, Substitution::Key(Pubkey::new_unique()), "custom program error: 0x10"),
+        (12, Substitution::Readonly, "custom program error: 0x12"),
+        (13, Substitution::Key(Pubkey::new_unique()), "custom program error: 0x10"),
+        (13, Substitution::Readonly, "custom program error: 0x12"),
+        (14, Substitution::Key(Pubkey::new_unique()), "custom program error: 0x10"),
+        (15, Substitution::Key(Pubkey::new_unique()), "custom program error: 0x10"),
+        (16, Substitution::Key(Pubkey::new_unique()), "custom program error: 0x10"),
+        (16, Substitution::Readonly, "custom program error: 0x12"),
+    ];
+    assert_substitutions(&test, &ix, cases).await;
+
+    // the same checks apply to every trade in MakeTrades, where the token program comes before the trade accounts
+    let ix = make_trades_ix(
+        test.program_id,
+        test.wallet2.pubkey(),
+        vec![(offer_amount, trade_amount, trade_fill_accounts(&test))],
+    );
+    let cases = vec![
+        (1, Substitution::Key(Pubkey::new_unique()), "custom program error: 0x11"),
+        (3, Substitution::Key(Pubkey::new_unique()), "custom program error: 0x10"),
+        (7, Substitution::Key(test.offer_src), "custom program error: 0x13"),
+    ];
+    assert_substitutions(&test, &ix, cases).await;
+}
+
+#[tokio::test]
+async fn test_close_stale_trade_accounts() {
+    let test = TestData::init().await;
+    create_test_trade(&test).await;
+
+    let ix = instruction::close_stale_trade(
+        &test.program_id,
+        &test.wallet2.pubkey(),
+        &test.trade_account_keypair.pubkey(),
+        &test.offer_src,
+        &test.payer.pubkey(),
+    );
+    // the offer owner is also the fee payer, so it is always writable
+    let cases = vec![
+        (0, Substitution::Key(Pubkey::new_unique()), "custom program error: 0x0"),
+        (0, Substitution::Readonly, "custom program error: 0x12"),
+        (1, Substitution::Readonly, "custom program error: 0x12"),
+        (2, Substitution::Key(Pubkey::new_unique()), "custom program error: 0x10"),
+        (3, Substitution::Key(Pubkey::new_unique()), "custom program error: 0x6"),
+        (3, Substitution::Readonly, "custom program error: 0x12"),
+        (4, Substitution::Key(test.wallet2.pubkey()), "custom program error: 0x0"),
+        (5, Substitution::Key(Pubkey::new_unique()), "custom program error: 0x11"),
+        (6, Substitution::Key(Pubkey::new_unique()), "custom program error: 0x10"),
+        (6, Substitution::Readonly, "custom program error: 0x12"),
+    ];
+    assert_substitutions(&test, &ix, cases).await;
+}
@@ -0,0 +1,136 @@
+mod lib;
+
+use {
+    lib::*,
+    solana_program_test::*,
+    solana_sdk::{
+        instruction::Instruction,
+        pubkey::Pubkey,
+        signature::Signer,
+    },
+};
+use ::trader::{
+    allowlist,
+    instruction::{self, CreateTradeArgs},
+    instructions::AllowlistProof,
+};
+use trader_client::allowlist::{parse_allowlist, AllowlistEntry, AllowlistTree};
+
+// Creates the trade of test, only filled by wallet2 with cap and a couple of other wallets, returning their tree
+async fn create_allowlisted_trade(test: &TestData, cap: u64) -> AllowlistTree {
+    let tree = AllowlistTree::new(vec![
+        AllowlistEntry { wallet: Pubkey::new_unique(), cap: allowlist::NO_CAP },
+        AllowlistEntry { wallet: test.wallet2.pubkey(), cap },
+        AllowlistEntry { wallet: Pubkey::new_unique(), cap: allowlist::NO_CAP },
+    ]);
+    let args = CreateTradeArgs { allowlist: Some(tree.root()), ..CreateTradeArgs::default() };
+    create_trade_with(test, args).await.unwrap();
+
+    tree
+}
+
+fn fill_ix(test: &TestData, proof: Option<AllowlistProof>) -> Instruction {
+    let offer_amount: u64 = spl_token::ui_amount_to_amount(10.0, 9);
+    let trade_amount: u64 = spl_token::ui_amount_to_amount(2.0, 9);
+    instruction::make_trade(
+        &test.program_id,
+        &test.wallet2.pubkey(),
+        &trade_fill_accounts(test),
+        offer_amount,
+        trade_amount,
+        proof,
+    )
+}
+
+#[tokio::test]
+async fn test_make_allowlisted_trade() {
+    let test = TestData::init().await;
+    let tree = create_allowlisted_trade(&test, allowlist::NO_CAP).await;
+
+    let ix = fill_ix(&test, tree.proof(&test.wallet2.pubkey()));
+    process_ix(&vec![&test.payer, &test.wallet2], test.payer.pubkey(), ix, &test.conn).await.unwrap();
+
+    let offer_amount: u64 = spl_token::ui_amount_to_amount(10.0, 9);
+    assert_eq!(get_spl_account(test.offer_dst, &test.conn).await.amount, offer_amount);
+}
+
+#[tokio::test]
+#[should_panic(expected = "transport transaction error: Error processing Instruction 0: custom program error: 0x28")]
+async fn test_make_allowlisted_trade_without_proof() {
+    let test = TestData::init().await;
+    create_allowlisted_trade(&test, allowlist::NO_CAP).await;
+
+    let ix = fill_ix(&test, None);
+    let panic_on = process_ix(&vec![&test.payer, &test.wallet2], test.payer.pubkey(), ix, &test.conn).await.unwrap_err();
+    panic!("{}", panic_on.to_string());
+}
+
+#[tokio::test]
+#[should_panic(expected = "transport transaction error: Error processing Instruction 0: custom program error: 0x28")]
+async fn test_make_allowlisted_trade_raising_cap() {
+    let test = TestData::init().await;
+    let tree = create_allowlisted_trade(&test, 1).await;
+
+    // the cap is part of the leaf, so it can't be changed without breaking the proof
+    let proof = AllowlistProof { cap: allowlist::NO_CAP, ..tree.proof(&test.wallet2.pubkey()).unwrap() };
+    let ix = fill_ix(&test, Some(proof));
+    let panic_on = process_ix(&vec![&test.payer, &test.wallet2], test.payer.pubkey(), ix, &test.conn).await.unwrap_err();
+    panic!("{}", panic_on.to_string());
+}
+
+#[tokio::test]
+#[should_panic(expected = "transport transaction error: Error processing Instruction 0: custom program error: 0x29")]
+async fn test_make_allowlisted_trade_over_cap() {
+    let test = TestData::init().await;
+    let tree = create_allowlisted_trade(&test, spl_token::ui_amount_to_amount(5.0, 9)).await;
+
+    let ix = fill_ix(&test, tree.proof(&test.wallet2.pubkey()));
+    let panic_on = process_ix(&vec![&test.payer, &test.wallet2], test.payer.pubkey(), ix, &test.conn).await.unwrap_err();
+    panic!("{}", panic_on.to_string());
+}
+
+#[tokio::test]
+#[should_panic(expected = "transport transaction error: Error processing Instruction 0: invalid program argument")]
+async fn test_create_trade_with_empty_allowlist_root() {
+    let test = TestData::init().await;
+
+    let args = CreateTradeArgs { allowlist: Some([0; 32]), ..CreateTradeArgs::default() };
+    let panic_on = create_trade_with(&test, args).await.unwrap_err();
+    panic!("{}", panic_on.to_string());
+}
+
+#[test]
+fn test_allowlist_proofs() {
+    // odd levels leave a node without a sibling, at any depth
+    for size in 1..=9 {
+        let entries: Vec<AllowlistEntry> = (0..size)
+            .map(|i| AllowlistEntry { wallet: Pubkey::new_unique(), cap: i })
+            .collect();
+        let tree = AllowlistTree::new(entries.clone());
+
+        for entry in entries {
+            let proof = tree.proof(&entry.wallet).unwrap();
+            assert_eq!(proof.cap, entry.cap);
+            assert!(allowlist::verify(&tree.root(), allowlist::leaf(&entry.wallet, entry.cap), &proof.proof));
+            assert!(!allowlist::verify(&tree.root(), allowlist::leaf(&entry.wallet, entry.cap + 1), &proof.proof));
+        }
+        assert_eq!(tree.proof(&Pubkey::new_unique()), None);
+    }
+}
+
+#[test]
+fn test_parse_allowlist() {
+    let (first, second) = (Pubkey::new_unique(), Pubkey::new_unique());
+
+    let csv = format!("# wallet,cap\n{}, 1000\n\n{}\n", first, second);
+    assert_eq!(parse_allowlist(&csv).unwrap(), vec![
+        AllowlistEntry { wallet: first, cap: 1000 },
+        AllowlistEntry { wallet: second, cap: allowlist::NO_CAP },
+    ]);
+
+    assert!(parse_allowlist(&format!("{}\n{},5\n", first, first)).is_err());
+    assert!(parse_allowlist(&format!("{},-5\n", first)).is_err());
+    assert!(parse_allowlist(&format!("{},5,6\n", first)).is_err());
+    assert!(parse_allowlist("not a wallet\n").is_err());
+    assert!(parse_allowlist("# nothing\n").is_err());
+}
@@ -0,0 +1,179 @@
+mod lib;
+
+use {
+    lib::*,
+    solana_program_test::*,
+    solana_sdk::{
+        signature::{Keypair, Signer},
+        sysvar::clock::Clock,
+    },
+};
+use ::trader::{
+    instruction::{self, CreateTradeArgs, TradeFillAccounts},
+    instructions::Arbitration,
+    state::TradeMode,
+};
+
+// Creates the trade of test in Arbitrated mode, with the dispute ending an hour from now
+async fn create_arbitrated_trade(test: &TestData, arbiter: &Keypair) {
+    let clock = test.conn.borrow_mut().get_sysvar::<Clock>().await.unwrap();
+    let args = CreateTradeArgs {
+        mode: TradeMode::Arbitrated,
+        arbitration: Some(Arbitration { arbiter: arbiter.pubkey(), dispute_ends_at: clock.unix_timestamp + 3600 }),
+        ..CreateTradeArgs::default()
+    };
+    create_trade_with(test, args).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_make_arbitrated_trade() {
+    let test = TestData::init().await;
+    let arbiter = Keypair::new();
+    create_arbitrated_trade(&test, &arbiter).await;
+
+    let trade_account = load_trade(&test).await;
+    assert_eq!(trade_account.arbiter, arbiter.pubkey());
+    // the offer is held in escrow
+    let (pda, _) = instruction::find_trade_pda(&test.program_id, &test.trade_account_keypair.pubkey());
+    assert_eq!(get_spl_account(test.offer_src, &test.conn).await.owner, pda);
+
+    let offer_amount: u64 = spl_token::ui_amount_to_amount(10.0, 9);
+    let trade_amount: u64 = spl_token::ui_amount_to_amount(2.0, 9);
+    let accounts = TradeFillAccounts { arbiter: Some(arbiter.pubkey()), ..trade_fill_accounts(&test) };
+    let ix = instruction::make_trade(
+        &test.program_id,
+        &test.wallet2.pubkey(),
+        &accounts,
+        offer_amount,
+        trade_amount,
+        None,
+    );
+    process_ix(&vec![&test.payer, &test.wallet2, &arbiter], test.payer.pubkey(), ix, &test.conn).await.unwrap();
+
+    assert_eq!(get_spl_account(test.offer_dst, &test.conn).await.amount, offer_amount);
+    assert_eq!(get_spl_account(test.offer_src, &test.conn).await.owner, test.payer.pubkey());
+}
+
+#[tokio::test]
+#[should_panic(expected = "transport transaction error: Error processing Instruction 0: custom program error: 0x1a")]
+async fn test_make_arbitrated_trade_without_arbiter() {
+    let test = TestData::init().await;
+    let arbiter = Keypair::new();
+    create_arbitrated_trade(&test, &arbiter).await;
+
+    let offer_amount: u64 = spl_token::ui_amount_to_amount(10.0, 9);
+    let trade_amount: u64 = spl_token::ui_amount_to_amount(2.0, 9);
+    let accounts = TradeFillAccounts { arbiter: Some(arbiter.pubkey()), ..trade_fill_accounts(&test) };
+    let mut ix = instruction::make_trade(
+        &test.program_id,
+        &test.wallet2.pubkey(),
+        &accounts,
+        offer_amount,
+        trade_amount,
+        None,
+    );
+    ix.accounts.last_mut().unwrap().is_signer = false;
+    let panic_on = process_ix(&vec![&test.payer, &test.wallet2], test.payer.pubkey(), ix, &test.conn).await.unwrap_err();
+    panic!("{}", panic_on.to_string());
+}
+
+#[tokio::test]
+async fn test_arbiter_release_to_owner() {
+    let (test, mut context) = TestData::init_with_context().await;
+    let arbiter = Keypair::new();
+    create_arbitrated_trade(&test, &arbiter).await;
+
+    let clock = test.conn.borrow_mut().get_sysvar::<Clock>().await.unwrap();
+    warp_clock_to(&mut context, clock.unix_timestamp + 3600).await;
+    let ix = instruction::arbiter_release(
+        &test.program_id,
+        &arbiter.pubkey(),
+        &test.trade_account_keypair.pubkey(),
+        &test.offer_src,
+        &test.payer.pubkey(),
+        None,
+    );
+    process_ix(&vec![&test.payer, &arbiter], test.payer.pubkey(), ix, &test.conn).await.unwrap();
+
+    // the offer account goes back to its owner, untouched
+    let offer_src_account = get_spl_account(test.offer_src, &test.conn).await;
+    assert_eq!(offer_src_account.owner, test.payer.pubkey());
+    assert_eq!(offer_src_account.amount, spl_token::ui_amount_to_amount(10.0, 9));
+    let trade_ai = test.conn.borrow_mut().get_account(test.trade_account_keypair.pubkey()).await.unwrap();
+    assert_eq!(trade_ai, None);
+}
+
+#[tokio::test]
+async fn test_arbiter_release_to_taker() {
+    let (test, mut context) = TestData::init_with_context().await;
+    let arbiter = Keypair::new();
+    create_arbitrated_trade(&test, &arbiter).await;
+
+    let clock = test.conn.borrow_mut().get_sysvar::<Clock>().await.unwrap();
+    warp_clock_to(&mut context, clock.unix_timestamp + 3600).await;
+    let ix = instruction::arbiter_release(
+        &test.program_id,
+        &arbiter.pubkey(),
+        &test.trade_account_keypair.pubkey(),
+        &test.offer_src,
+        &test.payer.pubkey(),
+        Some(&test.offer_dst),
+    );
+    process_ix(&vec![&test.payer, &arbiter], test.payer.pubkey(), ix, &test.conn).await.unwrap();
+
+    // the whole offer goes to the taker, and the empty offer account back to its owner
+    assert_eq!(get_spl_account(test.offer_dst, &test.conn).await.amount, spl_token::ui_amount_to_amount(10.0, 9));
+    let offer_src_account = get_spl_account(test.offer_src, &test.conn).await;
+    assert_eq!(offer_src_account.owner, test.payer.pubkey());
+    assert_eq!(offer_src_account.amount, 0);
+    let trade_ai = test.conn.borrow_mut().get_account(test.trade_account_keypair.pubkey()).await.unwrap();
+    assert_eq!(trade_ai, None);
+}
+
+#[tokio::test]
+#[should_panic(expected = "transport transaction error: Error processing Instruction 0: custom program error: 0x1b")]
+async fn test_arbiter_release_during_dispute() {
+    let test = TestData::init().await;
+    let arbiter = Keypair::new();
+    create_arbitrated_trade(&test, &arbiter).await;
+
+    let ix = instruction::arbiter_release(
+        &test.program_id,
+        &arbiter.pubkey(),
+        &test.trade_account_keypair.pubkey(),
+        &test.offer_src,
+        &test.payer.pubkey(),
+        None,
+    );
+    let panic_on = process_ix(&vec![&test.payer, &arbiter], test.payer.pubkey(), ix, &test.conn).await.unwrap_err();
+    panic!("{}", panic_on.to_string());
+}
+
+#[tokio::test]
+#[should_panic(expected = "transport transaction error: Error processing Instruction 0: custom program error: 0x0")]
+async fn test_arbiter_release_wrong_arbiter() {
+    let test = TestData::init().await;
+    create_arbitrated_trade(&test, &Keypair::new()).await;
+
+    // the taker can't release the offer to itself
+    let ix = instruction::arbiter_release(
+        &test.program_id,
+        &test.wallet2.pubkey(),
+        &test.trade_account_keypair.pubkey(),
+        &test.offer_src,
+        &test.payer.pubkey(),
+        Some(&test.offer_dst),
+    );
+    let panic_on = process_ix(&vec![&test.payer, &test.wallet2], test.payer.pubkey(), ix, &test.conn).await.unwrap_err();
+    panic!("{}", panic_on.to_string());
+}
+
+#[tokio::test]
+#[should_panic(expected = "transport transaction error: Error processing Instruction 0: invalid instruction data")]
+async fn test_create_arbitrated_trade_without_arbiter() {
+    let test = TestData::init().await;
+
+    let (ix, _, _) = init_trade_with_mode_ix(&test, 2, TradeMode::Arbitrated, None);
+    let panic_on = process_ix(&vec![&test.payer], test.payer.pubkey(), ix, &test.conn).await.unwrap_err();
+    panic!("{}", panic_on.to_string());
+}
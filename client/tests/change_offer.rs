@@ -0,0 +1,191 @@
+mod lib;
+
+use {
+    lib::*,
+    solana_program_test::*,
+    solana_sdk::{
+        program_option::COption,
+        pubkey::Pubkey,
+        signature::Signer,
+    },
+};
+use ::trader::{
+    instruction,
+    state::TradeMode,
+};
+
+// a new token account of the owner of the trade holding amount offer tokens
+async fn offer_token_account(test: &TestData, amount: u64) -> Pubkey {
+    let recent_blockhash = test.conn.borrow_mut().get_latest_blockhash().await.unwrap();
+    let account = token_account(&test.payer, &test.payer, test.offer_mint, recent_blockhash, &test.conn).await;
+    if amount > 0 {
+        mint_to_account(&test.payer, &test.payer, test.offer_mint, account, amount, recent_blockhash, &test.conn).await;
+    }
+
+    account
+}
+
+#[tokio::test]
+async fn test_increase_offer() {
+    let test = TestData::init().await;
+    create_test_trade(&test).await;
+
+    let added: u64 = spl_token::ui_amount_to_amount(5.0, 9);
+    let token_src = offer_token_account(&test, added).await;
+    let ix = instruction::increase_offer(
+        &test.program_id,
+        &test.payer.pubkey(),
+        &test.trade_account_keypair.pubkey(),
+        &test.offer_src,
+        &token_src,
+        added,
+        false,
+    );
+    process_ix(&vec![&test.payer], test.payer.pubkey(), ix, &test.conn).await.unwrap();
+
+    let offer_amount: u64 = spl_token::ui_amount_to_amount(15.0, 9);
+    let offer_src_account = get_spl_account(test.offer_src, &test.conn).await;
+    assert_eq!(offer_src_account.amount, offer_amount);
+    assert_eq!(get_spl_account(token_src, &test.conn).await.amount, 0);
+
+    // the trade amount stays the same, so the price drops
+    let trade = load_trade(&test).await;
+    assert_eq!(trade.offer_amount, offer_amount);
+    assert_eq!(trade.trade_amount, spl_token::ui_amount_to_amount(2.0, 9));
+    assert_eq!((trade.price_num, trade.price_den), (2, 15));
+
+    // the trade is filled with the new amounts
+    let (ix, _, _) = make_trade_ix(&test, offer_amount, trade.trade_amount, None);
+    process_ix(&vec![&test.payer, &test.wallet2], test.payer.pubkey(), ix, &test.conn).await.unwrap();
+    assert_eq!(get_spl_account(test.offer_dst, &test.conn).await.amount, offer_amount);
+}
+
+#[tokio::test]
+async fn test_decrease_offer_proportional() {
+    let test = TestData::init().await;
+    create_test_trade(&test).await;
+
+    let token_dst = offer_token_account(&test, 0).await;
+    let withdrawn: u64 = spl_token::ui_amount_to_amount(5.0, 9);
+    let ix = instruction::decrease_offer(
+        &test.program_id,
+        &test.payer.pubkey(),
+        &test.trade_account_keypair.pubkey(),
+        &test.offer_src,
+        &token_dst,
+        withdrawn,
+        true,
+    );
+    process_ix(&vec![&test.payer], test.payer.pubkey(), ix, &test.conn).await.unwrap();
+
+    assert_eq!(get_spl_account(token_dst, &test.conn).await.amount, withdrawn);
+
+    // half the offer for half the trade amount, at the same price
+    let trade = load_trade(&test).await;
+    assert_eq!(trade.offer_amount, spl_token::ui_amount_to_amount(5.0, 9));
+    assert_eq!(trade.trade_amount, spl_token::ui_amount_to_amount(1.0, 9));
+    assert_eq!((trade.price_num, trade.price_den), (1, 5));
+}
+
+#[tokio::test]
+async fn test_change_delegated_offer() {
+    let test = TestData::init().await;
+
+    let (ix, pda, _) = init_trade_with_mode_ix(&test, 2, TradeMode::Delegate, None);
+    process_ix(&vec![&test.payer], test.payer.pubkey(), ix, &test.conn).await.unwrap();
+
+    let token_account = offer_token_account(&test, 0).await;
+    let withdrawn: u64 = spl_token::ui_amount_to_amount(4.0, 9);
+    let ix = instruction::decrease_offer(
+        &test.program_id,
+        &test.payer.pubkey(),
+        &test.trade_account_keypair.pubkey(),
+        &test.offer_src,
+        &token_account,
+        withdrawn,
+        false,
+    );
+    process_ix(&vec![&test.payer], test.payer.pubkey(), ix, &test.conn).await.unwrap();
+
+    // the delegation follows the offer
+    let offer_src_account = get_spl_account(test.offer_src, &test.conn).await;
+    assert_eq!(offer_src_account.delegate, COption::Some(pda));
+    assert_eq!(offer_src_account.delegated_amount, spl_token::ui_amount_to_amount(6.0, 9));
+
+    let added: u64 = spl_token::ui_amount_to_amount(1.0, 9);
+    let ix = instruction::increase_offer(
+        &test.program_id,
+        &test.payer.pubkey(),
+        &test.trade_account_keypair.pubkey(),
+        &test.offer_src,
+        &token_account,
+        added,
+        false,
+    );
+    process_ix(&vec![&test.payer], test.payer.pubkey(), ix, &test.conn).await.unwrap();
+
+    let offer_amount = spl_token::ui_amount_to_amount(7.0, 9);
+    let offer_src_account = get_spl_account(test.offer_src, &test.conn).await;
+    assert_eq!(offer_src_account.amount, offer_amount);
+    assert_eq!(offer_src_account.delegated_amount, offer_amount);
+    assert_eq!(load_trade(&test).await.offer_amount, offer_amount);
+}
+
+#[tokio::test]
+#[should_panic(expected = "transport transaction error: Error processing Instruction 0: custom program error: 0x0")]
+async fn test_change_offer_checks_authority() {
+    let test = TestData::init().await;
+    create_test_trade(&test).await;
+
+    // wallet2 didn't create the trade
+    let ix = instruction::decrease_offer(
+        &test.program_id,
+        &test.wallet2.pubkey(),
+        &test.trade_account_keypair.pubkey(),
+        &test.offer_src,
+        &test.offer_dst,
+        1,
+        false,
+    );
+    let panic_on = process_ix(&vec![&test.payer, &test.wallet2], test.payer.pubkey(), ix, &test.conn).await.unwrap_err();
+    panic!("{}", panic_on.to_string());
+}
+
+#[tokio::test]
+#[should_panic(expected = "transport transaction error: Error processing Instruction 0: insufficient funds for instruction")]
+async fn test_decrease_whole_offer() {
+    let test = TestData::init().await;
+    create_test_trade(&test).await;
+
+    let token_dst = offer_token_account(&test, 0).await;
+    let ix = instruction::decrease_offer(
+        &test.program_id,
+        &test.payer.pubkey(),
+        &test.trade_account_keypair.pubkey(),
+        &test.offer_src,
+        &token_dst,
+        spl_token::ui_amount_to_amount(10.0, 9),
+        false,
+    );
+    let panic_on = process_ix(&vec![&test.payer], test.payer.pubkey(), ix, &test.conn).await.unwrap_err();
+    panic!("{}", panic_on.to_string());
+}
+
+#[tokio::test]
+#[should_panic(expected = "transport transaction error: Error processing Instruction 0: custom program error: 0x14")]
+async fn test_increase_offer_checks_mint() {
+    let test = TestData::init().await;
+    create_test_trade(&test).await;
+
+    let ix = instruction::increase_offer(
+        &test.program_id,
+        &test.payer.pubkey(),
+        &test.trade_account_keypair.pubkey(),
+        &test.offer_src,
+        &test.trade_dst,
+        1,
+        false,
+    );
+    let panic_on = process_ix(&vec![&test.payer], test.payer.pubkey(), ix, &test.conn).await.unwrap_err();
+    panic!("{}", panic_on.to_string());
+}
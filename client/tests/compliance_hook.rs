@@ -0,0 +1,125 @@
+mod lib;
+
+use {
+    borsh::BorshSerialize,
+    lib::*,
+    solana_program_test::*,
+    solana_sdk::{
+        account::Account,
+        pubkey::Pubkey,
+        signature::Signer,
+    },
+};
+use ::trader::{
+    instruction::{self, CreateTradeArgs, TradeFillAccounts},
+    instructions::ComplianceHook,
+};
+use compliance_hook::AccessList;
+use trader_client::utils::get_wallet;
+
+// A list account of the hook program
+fn list_account(hook_program: Pubkey, list: AccessList) -> Account {
+    let data = list.try_to_vec().unwrap();
+    Account {
+        lamports: minimum_balance_rent_exempt(data.len()),
+        data,
+        owner: hook_program,
+        executable: false,
+        rent_epoch: 0,
+    }
+}
+
+// Starts a test with the hook program and list in place, and creates the trade of test screened by them
+async fn create_hooked_trade(list: AccessList) -> (TestData, ComplianceHook) {
+    let hook = ComplianceHook { program: Pubkey::new_unique(), account: Pubkey::new_unique() };
+    let account = list_account(hook.program, list);
+    let test = TestData::init_with(|program_test| {
+        program_test.add_program("compliance_hook", hook.program, processor!(compliance_hook::process_instruction));
+        program_test.add_account(hook.account, account);
+    }).await;
+
+    let args = CreateTradeArgs { hook: Some(hook.clone()), ..CreateTradeArgs::default() };
+    create_trade_with(&test, args).await.unwrap();
+
+    (test, hook)
+}
+
+async fn make_hooked_trade(test: &TestData, hook: &ComplianceHook) -> Result<(), BanksClientError> {
+    let offer_amount: u64 = spl_token::ui_amount_to_amount(10.0, 9);
+    let trade_amount: u64 = spl_token::ui_amount_to_amount(2.0, 9);
+    let accounts = TradeFillAccounts { hook: Some((hook.program, hook.account)), ..trade_fill_accounts(test) };
+    let ix = instruction::make_trade(
+        &test.program_id,
+        &test.wallet2.pubkey(),
+        &accounts,
+        offer_amount,
+        trade_amount,
+        None,
+    );
+
+    process_ix(&vec![&test.payer, &test.wallet2], test.payer.pubkey(), ix, &test.conn).await
+}
+
+#[tokio::test]
+async fn test_make_hooked_trade() {
+    // nobody is denied
+    let (test, hook) = create_hooked_trade(AccessList { allow: false, wallets: vec![Pubkey::new_unique()] }).await;
+    make_hooked_trade(&test, &hook).await.unwrap();
+
+    let offer_dst_account = get_spl_account(test.offer_dst, &test.conn).await;
+    assert_eq!(offer_dst_account.amount, spl_token::ui_amount_to_amount(10.0, 9));
+}
+
+#[tokio::test]
+#[should_panic(expected = "transport transaction error: Error processing Instruction 0: custom program error: 0x0")]
+async fn test_make_hooked_trade_denied_taker() {
+    // the same wallet TestData takes the trades with
+    let taker = get_wallet("../wallet0.json".into()).unwrap().pubkey();
+    let (test, hook) = create_hooked_trade(AccessList { allow: false, wallets: vec![taker] }).await;
+
+    let panic_on = make_hooked_trade(&test, &hook).await.unwrap_err();
+    panic!("{}", panic_on.to_string());
+}
+
+#[tokio::test]
+#[should_panic(expected = "transport transaction error: Error processing Instruction 0: custom program error: 0x0")]
+async fn test_make_hooked_trade_maker_not_allowed() {
+    // only the taker is allowed
+    let taker = get_wallet("../wallet0.json".into()).unwrap().pubkey();
+    let (test, hook) = create_hooked_trade(AccessList { allow: true, wallets: vec![taker] }).await;
+
+    let panic_on = make_hooked_trade(&test, &hook).await.unwrap_err();
+    panic!("{}", panic_on.to_string());
+}
+
+#[tokio::test]
+#[should_panic(expected = "transport transaction error: Error processing Instruction 0: custom program error: 0x8")]
+async fn test_make_hooked_trade_skipping_hook() {
+    let (test, hook) = create_hooked_trade(AccessList::default()).await;
+
+    // a list of the taker's own, that allows everyone
+    let accounts = TradeFillAccounts { hook: Some((hook.program, Pubkey::new_unique())), ..trade_fill_accounts(&test) };
+    let ix = instruction::make_trade(
+        &test.program_id,
+        &test.wallet2.pubkey(),
+        &accounts,
+        10_000_000_000,
+        2_000_000_000,
+        None,
+    );
+    let panic_on = process_ix(&vec![&test.payer, &test.wallet2], test.payer.pubkey(), ix, &test.conn).await.unwrap_err();
+    panic!("{}", panic_on.to_string());
+}
+
+#[test]
+fn test_access_list() {
+    let (listed, other) = (Pubkey::new_unique(), Pubkey::new_unique());
+
+    let list = AccessList { allow: true, wallets: vec![listed] };
+    assert!(list.allows(&listed));
+    assert!(!list.allows(&other));
+
+    let list = AccessList { allow: false, wallets: vec![listed] };
+    assert!(!list.allows(&listed));
+    assert!(list.allows(&other));
+}
@@ -0,0 +1,198 @@
+mod lib;
+
+use {
+    lib::*,
+    solana_program_test::*,
+    solana_sdk::{
+        signature::Signer,
+        sysvar::clock::Clock,
+    },
+};
+use ::trader::{
+    instruction::{self, CreateTradeArgs},
+    instructions::CoveredCall,
+    state::TradeMode,
+};
+
+const PREMIUM: u64 = 1_000_000_000;
+
+// Writes an option on the trade of test, expiring an hour from now, returning its expiry
+async fn create_option(test: &TestData) -> i64 {
+    let clock = test.conn.borrow_mut().get_sysvar::<Clock>().await.unwrap();
+    let expires_at = clock.unix_timestamp + 3600;
+    let args = CreateTradeArgs {
+        mode: TradeMode::CoveredCall,
+        expires_at,
+        call: Some(CoveredCall { premium: PREMIUM }),
+        ..CreateTradeArgs::default()
+    };
+    create_trade_with(test, args).await.unwrap();
+
+    expires_at
+}
+
+async fn buy_option(test: &TestData) {
+    let ix = instruction::buy_option(
+        &test.program_id,
+        &test.wallet2.pubkey(),
+        &test.trade_account_keypair.pubkey(),
+        &test.trade_src,
+        &test.trade_dst,
+        PREMIUM,
+    );
+    process_ix(&vec![&test.payer, &test.wallet2], test.payer.pubkey(), ix, &test.conn).await.unwrap();
+}
+
+fn exercise_ix(test: &TestData) -> solana_sdk::instruction::Instruction {
+    let offer_amount: u64 = spl_token::ui_amount_to_amount(10.0, 9);
+    let trade_amount: u64 = spl_token::ui_amount_to_amount(2.0, 9);
+    instruction::exercise_option(
+        &test.program_id,
+        &test.wallet2.pubkey(),
+        &trade_fill_accounts(test),
+        offer_amount,
+        trade_amount,
+    )
+}
+
+#[tokio::test]
+async fn test_buy_and_exercise_option() {
+    let test = TestData::init().await;
+    create_option(&test).await;
+
+    // the underlying is held in escrow from the start
+    let (pda, _) = instruction::find_trade_pda(&test.program_id, &test.trade_account_keypair.pubkey());
+    assert_eq!(get_spl_account(test.offer_src, &test.conn).await.owner, pda);
+
+    buy_option(&test).await;
+    assert_eq!(get_spl_account(test.trade_dst, &test.conn).await.amount, PREMIUM);
+
+    let trade_account = load_trade(&test).await;
+    assert_eq!(trade_account.holder, test.wallet2.pubkey());
+    assert!(trade_account.is_option_bought());
+
+    let ix = exercise_ix(&test);
+    process_ix(&vec![&test.payer, &test.wallet2], test.payer.pubkey(), ix, &test.conn).await.unwrap();
+
+    let offer_amount: u64 = spl_token::ui_amount_to_amount(10.0, 9);
+    assert_eq!(get_spl_account(test.offer_dst, &test.conn).await.amount, offer_amount);
+    assert_eq!(get_spl_account(test.offer_src, &test.conn).await.owner, test.payer.pubkey());
+    assert!(get_spl_account(test.trade_dst, &test.conn).await.amount > PREMIUM);
+}
+
+#[tokio::test]
+#[should_panic(expected = "transport transaction error: Error processing Instruction 0: custom program error: 0x0")]
+async fn test_exercise_option_not_bought() {
+    let test = TestData::init().await;
+    create_option(&test).await;
+
+    let ix = exercise_ix(&test);
+    let panic_on = process_ix(&vec![&test.payer, &test.wallet2], test.payer.pubkey(), ix, &test.conn).await.unwrap_err();
+    panic!("{}", panic_on.to_string());
+}
+
+#[tokio::test]
+#[should_panic(expected = "transport transaction error: Error processing Instruction 1: custom program error: 0x21")]
+async fn test_buy_option_twice() {
+    let test = TestData::init().await;
+    create_option(&test).await;
+
+    let ix = instruction::buy_option(
+        &test.program_id,
+        &test.wallet2.pubkey(),
+        &test.trade_account_keypair.pubkey(),
+        &test.trade_src,
+        &test.trade_dst,
+        PREMIUM,
+    );
+    let panic_on = process_ixs(&vec![&test.payer, &test.wallet2], test.payer.pubkey(), &[ix.clone(), ix], &test.conn)
+        .await.unwrap_err();
+    panic!("{}", panic_on.to_string());
+}
+
+#[tokio::test]
+#[should_panic(expected = "transport transaction error: Error processing Instruction 0: custom program error: 0x20")]
+async fn test_make_trade_on_option() {
+    let test = TestData::init().await;
+    create_option(&test).await;
+    buy_option(&test).await;
+
+    // an option is only filled by its holder exercising it
+    let offer_amount: u64 = spl_token::ui_amount_to_amount(10.0, 9);
+    let trade_amount: u64 = spl_token::ui_amount_to_amount(2.0, 9);
+    let (ix, _, _) = make_trade_ix(&test, offer_amount, trade_amount, None);
+    let panic_on = process_ix(&vec![&test.payer, &test.wallet2], test.payer.pubkey(), ix, &test.conn).await.unwrap_err();
+    panic!("{}", panic_on.to_string());
+}
+
+#[tokio::test]
+#[should_panic(expected = "transport transaction error: Error processing Instruction 0: custom program error: 0xb")]
+async fn test_exercise_expired_option() {
+    let (test, mut context) = TestData::init_with_context().await;
+    let expires_at = create_option(&test).await;
+    buy_option(&test).await;
+
+    warp_clock_to(&mut context, expires_at).await;
+    let ix = exercise_ix(&test);
+    let panic_on = process_ix(&vec![&test.payer, &test.wallet2], test.payer.pubkey(), ix, &test.conn).await.unwrap_err();
+    panic!("{}", panic_on.to_string());
+}
+
+#[tokio::test]
+async fn test_reclaim_underlying() {
+    let (test, mut context) = TestData::init_with_context().await;
+    let expires_at = create_option(&test).await;
+    buy_option(&test).await;
+
+    let owner_balance = test.conn.borrow_mut().get_balance(test.payer.pubkey()).await.unwrap();
+    warp_clock_to(&mut context, expires_at).await;
+    let ix = instruction::reclaim_underlying(
+        &test.program_id,
+        &test.payer.pubkey(),
+        &test.trade_account_keypair.pubkey(),
+        &test.offer_src,
+    );
+    process_ix(&vec![&test.payer], test.payer.pubkey(), ix, &test.conn).await.unwrap();
+
+    let trade_account_ai = test.conn.borrow_mut().get_account(test.trade_account_keypair.pubkey()).await.unwrap();
+    assert_eq!(trade_account_ai, None);
+    // the rent of the trade account covers the fee of the transaction
+    assert!(test.conn.borrow_mut().get_balance(test.payer.pubkey()).await.unwrap() > owner_balance);
+
+    // the writer keeps both the underlying and the premium
+    let offer_amount: u64 = spl_token::ui_amount_to_amount(10.0, 9);
+    let offer_src_account = get_spl_account(test.offer_src, &test.conn).await;
+    assert_eq!(offer_src_account.owner, test.payer.pubkey());
+    assert_eq!(offer_src_account.amount, offer_amount);
+    assert_eq!(get_spl_account(test.trade_dst, &test.conn).await.amount, PREMIUM);
+}
+
+#[tokio::test]
+#[should_panic(expected = "transport transaction error: Error processing Instruction 0: custom program error: 0x22")]
+async fn test_reclaim_underlying_before_expiry() {
+    let test = TestData::init().await;
+    create_option(&test).await;
+
+    let ix = instruction::reclaim_underlying(
+        &test.program_id,
+        &test.payer.pubkey(),
+        &test.trade_account_keypair.pubkey(),
+        &test.offer_src,
+    );
+    let panic_on = process_ix(&vec![&test.payer], test.payer.pubkey(), ix, &test.conn).await.unwrap_err();
+    panic!("{}", panic_on.to_string());
+}
+
+#[tokio::test]
+#[should_panic(expected = "invalid instruction data")]
+async fn test_create_option_without_expiry() {
+    let test = TestData::init().await;
+
+    let args = CreateTradeArgs {
+        mode: TradeMode::CoveredCall,
+        call: Some(CoveredCall { premium: PREMIUM }),
+        ..CreateTradeArgs::default()
+    };
+    let panic_on = create_trade_with(&test, args).await.unwrap_err();
+    panic!("{}", panic_on.to_string());
+}
@@ -0,0 +1,189 @@
+mod lib;
+
+use {
+    borsh::BorshDeserialize,
+    lib::*,
+    solana_program_test::*,
+    solana_sdk::{
+        clock::Clock,
+        message::Message,
+        packet::PACKET_DATA_SIZE,
+        pubkey::Pubkey,
+        signature::Signer,
+    },
+};
+use ::trader::{
+    instruction,
+    state::{AccountMakerStats, AccountTrade, StatsAccount, TradeMode},
+};
+use trader_client::{
+    client::{pack_close_stale_trades, CLOSE_STALE_TRADE_COMPUTE_UNITS, MAX_COMPUTE_UNITS},
+    router::OpenTrade,
+};
+
+#[tokio::test]
+async fn test_close_abandoned_trade() {
+    let test = TestData::init().await;
+
+    let bounty = 5000;
+    let trade_amount: u64 = spl_token::ui_amount_to_amount(2.0, 9);
+    let (ix, _, _) = init_expiring_trade_ix(&test, trade_amount, TradeMode::Delegate, 0, bounty, None);
+    process_ix(&vec![&test.payer], test.payer.pubkey(), ix, &test.conn).await.unwrap();
+
+    // the owner takes the allowance back, so the trade can't be filled anymore
+    let revoke_ix = spl_token::instruction::revoke(
+        &spl_token::id(),
+        &test.offer_src,
+        &test.payer.pubkey(),
+        &[&test.payer.pubkey()],
+    ).unwrap();
+    process_ix(&vec![&test.payer], test.payer.pubkey(), revoke_ix, &test.conn).await.unwrap();
+
+    let cranker_balance = test.conn.borrow_mut().get_balance(test.wallet2.pubkey()).await.unwrap();
+
+    let ix = instruction::close_stale_trade(
+        &test.program_id,
+        &test.wallet2.pubkey(),
+        &test.trade_account_keypair.pubkey(),
+        &test.offer_src,
+        &test.payer.pubkey(),
+    );
+    process_ix(&vec![&test.payer, &test.wallet2], test.payer.pubkey(), ix, &test.conn).await.unwrap();
+
+    let trade_account_ai = test.conn.borrow_mut().get_account(test.trade_account_keypair.pubkey()).await.unwrap();
+    assert_eq!(trade_account_ai, None);
+
+    let balance = test.conn.borrow_mut().get_balance(test.wallet2.pubkey()).await.unwrap();
+    assert_eq!(balance, cranker_balance + bounty);
+
+    let offer_amount: u64 = spl_token::ui_amount_to_amount(10.0, 9);
+    let offer_src_account = get_spl_account(test.offer_src, &test.conn).await;
+    assert_eq!(offer_src_account.owner, test.payer.pubkey());
+    assert_eq!(offer_src_account.amount, offer_amount);
+}
+
+#[tokio::test]
+#[should_panic(expected = "transport transaction error: Error processing Instruction 0: custom program error: 0xb")]
+async fn test_create_expired_trade() {
+    let test = TestData::init().await;
+
+    let clock = test.conn.borrow_mut().get_sysvar::<Clock>().await.unwrap();
+    let trade_amount: u64 = spl_token::ui_amount_to_amount(2.0, 9);
+    let (ix, _, _) = init_expiring_trade_ix(&test, trade_amount, TradeMode::Escrow, clock.unix_timestamp, 0, None);
+    let panic_on = process_ix(&vec![&test.payer], test.payer.pubkey(), ix, &test.conn).await.unwrap_err();
+    panic!("{}", panic_on.to_string());
+}
+
+#[tokio::test]
+#[should_panic(expected = "transport transaction error: Error processing Instruction 0: custom program error: 0xc")]
+async fn test_close_trade_not_stale() {
+    let test = TestData::init().await;
+    create_test_trade(&test).await;
+
+    let ix = instruction::close_stale_trade(
+        &test.program_id,
+        &test.wallet2.pubkey(),
+        &test.trade_account_keypair.pubkey(),
+        &test.offer_src,
+        &test.payer.pubkey(),
+    );
+    let panic_on = process_ix(&vec![&test.payer, &test.wallet2], test.payer.pubkey(), ix, &test.conn).await.unwrap_err();
+    panic!("{}", panic_on.to_string());
+}
+
+#[tokio::test]
+#[should_panic(expected = "transport transaction error: Error processing Instruction 0: custom program error: 0x0")]
+async fn test_close_stale_trade_wrong_owner() {
+    let test = TestData::init().await;
+    create_test_trade(&test).await;
+
+    // the rent would go to the cranker instead of the owner of the trade
+    let ix = instruction::close_stale_trade(
+        &test.program_id,
+        &test.wallet2.pubkey(),
+        &test.trade_account_keypair.pubkey(),
+        &test.offer_src,
+        &test.wallet2.pubkey(),
+    );
+    let panic_on = process_ix(&vec![&test.payer, &test.wallet2], test.payer.pubkey(), ix, &test.conn).await.unwrap_err();
+    panic!("{}", panic_on.to_string());
+}
+
+#[tokio::test]
+async fn test_close_stale_trades_full_batch() {
+    // as many trades as fit in a batch by compute units, each escrowing its offer, so closing it returns the account
+    let count = (MAX_COMPUTE_UNITS / CLOSE_STALE_TRADE_COMPUTE_UNITS) as usize;
+    let (test, mut context) = TestData::init_with_context().await;
+    let mut tests = vec![test];
+    while tests.len() < count {
+        let test = TestData::init_alongside(&tests[0]).await;
+        tests.push(test);
+    }
+    init_trailing_accounts(&tests).await;
+
+    let clock = tests[0].conn.borrow_mut().get_sysvar::<Clock>().await.unwrap();
+    let expires_at = clock.unix_timestamp + 60;
+    let trade_amount: u64 = spl_token::ui_amount_to_amount(2.0, 9);
+    let mut trades = Vec::<OpenTrade>::new();
+    for test in &tests {
+        let (ix, _, _) = init_expiring_trade_ix(test, trade_amount, TradeMode::Escrow, expires_at, 0, None);
+        process_ix(&vec![&test.payer], test.payer.pubkey(), ix, &test.conn).await.unwrap();
+        trades.push(OpenTrade {
+            trade_id: test.trade_account_keypair.pubkey(),
+            offer_mint: test.offer_mint,
+            account: load_trade(test).await,
+        });
+    }
+    warp_clock_to(&mut context, expires_at).await;
+
+    // some trades still share a transaction
+    let test = &tests[0];
+    let batches = pack_close_stale_trades(&test.wallet2.pubkey(), &test.program_id, &trades);
+    assert!(batches.len() < count);
+
+    // the cranker pays for the transactions, the same as in the client
+    for ixs in batches {
+        process_ixs(&vec![&test.wallet2], test.wallet2.pubkey(), &ixs, &test.conn).await.unwrap();
+    }
+
+    for trade in &trades {
+        let trade_account_ai = test.conn.borrow_mut().get_account(trade.trade_id).await.unwrap();
+        assert_eq!(trade_account_ai, None);
+    }
+    let (maker_stats, _) = instruction::find_stats(&test.program_id, &StatsAccount::Maker(test.payer.pubkey()));
+    let maker_stats_ai = test.conn.borrow_mut().get_account(maker_stats).await.unwrap().unwrap();
+    assert_eq!(AccountMakerStats::try_from_slice(&maker_stats_ai.data).unwrap().cancelled, count as u64);
+}
+
+#[test]
+fn test_pack_close_stale_trades() {
+    let program_id = Pubkey::new_unique();
+    let cranker = Pubkey::new_unique();
+
+    let trades: Vec<OpenTrade> = (0..30)
+        .map(|_| OpenTrade {
+            trade_id: Pubkey::new_unique(),
+            offer_mint: Pubkey::new_unique(),
+            account: AccountTrade {
+                offer_token_account: Pubkey::new_unique(),
+                authority: Pubkey::new_unique(),
+                initialized: true,
+                ..AccountTrade::default()
+            },
+        })
+        .collect();
+
+    let batches = pack_close_stale_trades(&cranker, &program_id, &trades);
+    assert!(batches.len() > 1);
+
+    let mut packed = Vec::<Pubkey>::new();
+    for ixs in batches {
+        let message = Message::new(&ixs, Some(&cranker));
+        assert!(1 + 64 + message.serialize().len() <= PACKET_DATA_SIZE);
+
+        packed.extend(ixs.iter().map(|ix| ix.accounts[1].pubkey));
+    }
+
+    // every trade is packed, in order
+    assert_eq!(packed, trades.iter().map(|trade| trade.trade_id).collect::<Vec<Pubkey>>());
+}
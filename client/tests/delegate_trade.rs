@@ -0,0 +1,114 @@
+mod lib;
+
+use {
+    lib::*,
+    solana_program_test::*,
+    solana_sdk::{
+        program_option::COption,
+        signature::Signer,
+    },
+};
+use ::trader::state;
+
+async fn create_delegated_trade(test: &TestData) {
+    let trade_amount: u64 = spl_token::ui_amount_to_amount(2.0, 9);
+    let (ix, _, _) = init_trade_with_mode_ix(test, trade_amount, state::TradeMode::Delegate, None);
+    process_ix(&vec![&test.payer], test.payer.pubkey(), ix, &test.conn).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_init_delegated_trade() {
+    let test = TestData::init().await;
+
+    let trade_amount: u64 = spl_token::ui_amount_to_amount(2.0, 9);
+    let (ix, pda_account, _) = init_trade_with_mode_ix(&test, trade_amount, state::TradeMode::Delegate, None);
+    process_ix(&vec![&test.payer], test.payer.pubkey(), ix, &test.conn).await.unwrap();
+
+    let trade_account = load_trade(&test).await;
+    assert_eq!(trade_account.mode, state::TradeMode::Delegate);
+
+    // the offer account stays with its owner, with the pda as delegate of the offer amount
+    let offer_amount: u64 = spl_token::ui_amount_to_amount(10.0, 9);
+    let offer_src_account = get_spl_account(test.offer_src, &test.conn).await;
+    assert_eq!(offer_src_account.owner, test.payer.pubkey());
+    assert_eq!(offer_src_account.delegate, COption::Some(pda_account));
+    assert_eq!(offer_src_account.delegated_amount, offer_amount);
+}
+
+#[tokio::test]
+async fn test_make_delegated_trade() {
+    let test = TestData::init().await;
+
+    create_delegated_trade(&test).await;
+
+    let offer_amount: u64 = spl_token::ui_amount_to_amount(10.0, 9);
+    let trade_amount: u64 = spl_token::ui_amount_to_amount(2.0, 9);
+    let (ix, _, _) = make_trade_ix(&test, offer_amount, trade_amount, None);
+    process_ix(&vec![&test.payer, &test.wallet2], test.payer.pubkey(), ix, &test.conn).await.unwrap();
+
+    let offer_src_account = get_spl_account(test.offer_src, &test.conn).await;
+    assert_eq!(offer_src_account.owner, test.payer.pubkey());
+    assert_eq!(offer_src_account.amount, 0);
+    // the whole delegated amount was used
+    assert_eq!(offer_src_account.delegate, COption::None);
+
+    let offer_dst_account = get_spl_account(test.offer_dst, &test.conn).await;
+    assert_eq!(offer_dst_account.amount, offer_amount);
+
+    let trade_fee: u64 = 100000000;
+    let trade_dst_account = get_spl_account(test.trade_dst, &test.conn).await;
+    assert_eq!(trade_dst_account.amount, trade_amount - trade_fee);
+
+    let trade_account_ai = test.conn.borrow_mut().get_account(test.trade_account_keypair.pubkey()).await.unwrap();
+    assert_eq!(trade_account_ai, None);
+}
+
+#[tokio::test]
+#[should_panic(expected = "transport transaction error: Error processing Instruction 0: custom program error: 0x9")]
+async fn test_make_delegated_trade_checks_delegation() {
+    let test = TestData::init().await;
+
+    create_delegated_trade(&test).await;
+
+    // the owner takes the allowance back
+    let revoke_ix = spl_token::instruction::revoke(
+        &spl_token::id(),
+        &test.offer_src,
+        &test.payer.pubkey(),
+        &[&test.payer.pubkey()],
+    ).unwrap();
+    process_ix(&vec![&test.payer], test.payer.pubkey(), revoke_ix, &test.conn).await.unwrap();
+
+    let offer_amount: u64 = spl_token::ui_amount_to_amount(10.0, 9);
+    let trade_amount: u64 = spl_token::ui_amount_to_amount(2.0, 9);
+    let (ix, _, _) = make_trade_ix(&test, offer_amount, trade_amount, None);
+    let panic_on = process_ix(&vec![&test.payer, &test.wallet2], test.payer.pubkey(), ix, &test.conn).await.unwrap_err();
+    panic!("{}", panic_on.to_string());
+}
+
+#[tokio::test]
+#[should_panic(expected = "transport transaction error: Error processing Instruction 0: custom program error: 0xa")]
+async fn test_make_delegated_trade_checks_offer_balance() {
+    let test = TestData::init().await;
+
+    create_delegated_trade(&test).await;
+
+    // the owner moves part of the offer somewhere else
+    let recent_blockhash = test.conn.borrow_mut().get_latest_blockhash().await.unwrap();
+    let other_account = token_account(&test.payer, &test.payer, test.offer_mint, recent_blockhash, &test.conn).await;
+    let transfer_ix = spl_token::instruction::transfer(
+        &spl_token::id(),
+        &test.offer_src,
+        &other_account,
+        &test.payer.pubkey(),
+        &[&test.payer.pubkey()],
+        spl_token::ui_amount_to_amount(5.0, 9),
+    ).unwrap();
+    process_ix(&vec![&test.payer], test.payer.pubkey(), transfer_ix, &test.conn).await.unwrap();
+
+    let offer_amount: u64 = spl_token::ui_amount_to_amount(10.0, 9);
+    let trade_amount: u64 = spl_token::ui_amount_to_amount(2.0, 9);
+    let (ix, _, _) = make_trade_ix(&test, offer_amount, trade_amount, None);
+    let panic_on = process_ix(&vec![&test.payer, &test.wallet2], test.payer.pubkey(), ix, &test.conn).await.unwrap_err();
+    panic!("{}", panic_on.to_string());
+}
@@ -0,0 +1,76 @@
+use {
+    borsh::BorshSerialize,
+    solana_sdk::pubkey::Pubkey,
+};
+use ::trader::events::TradeEvent;
+use trader_client::events::parse_events;
+
+fn data_log(event: &TradeEvent) -> String {
+    format!("Program data: {}", base64::encode(event.try_to_vec().unwrap()))
+}
+
+#[test]
+fn test_parse_events() {
+    let program_id = Pubkey::new_unique();
+    let created = TradeEvent::TradeCreated {
+        trade: Pubkey::new_unique(),
+        authority: Pubkey::new_unique(),
+        offer_token_account: Pubkey::new_unique(),
+        trade_mint: Pubkey::new_unique(),
+        offer_amount: 10,
+        trade_amount: 2,
+    };
+    let fee = TradeEvent::FeeCollected {
+        trade: Pubkey::new_unique(),
+        fee_account: Pubkey::new_unique(),
+        mint: Pubkey::new_unique(),
+        amount: 1,
+    };
+
+    let logs = vec![
+        format!("Program {} invoke [1]", program_id),
+        "Program log: Creating trade...".to_string(),
+        format!("Program {} invoke [2]", spl_token::id()),
+        // data logged by a CPI must be ignored
+        data_log(&fee),
+        format!("Program {} success", spl_token::id()),
+        data_log(&created),
+        format!("Program {} consumed 1000 of 200000 compute units", program_id),
+        format!("Program {} success", program_id),
+    ];
+
+    let events = parse_events(&program_id, &logs).unwrap();
+    assert_eq!(events, vec![created]);
+}
+
+#[test]
+fn test_parse_events_ignores_other_programs() {
+    let program_id = Pubkey::new_unique();
+    let other_program_id = Pubkey::new_unique();
+    let cancelled = TradeEvent::TradeCancelled {
+        trade: Pubkey::new_unique(),
+        authority: Pubkey::new_unique(),
+    };
+
+    let logs = vec![
+        format!("Program {} invoke [1]", other_program_id),
+        data_log(&cancelled),
+        format!("Program {} success", other_program_id),
+    ];
+
+    let events = parse_events(&program_id, &logs).unwrap();
+    assert_eq!(events, vec![]);
+}
+
+#[test]
+fn test_parse_events_rejects_invalid_data() {
+    let program_id = Pubkey::new_unique();
+
+    let logs = vec![
+        format!("Program {} invoke [1]", program_id),
+        "Program data: not-base64!".to_string(),
+        format!("Program {} success", program_id),
+    ];
+
+    assert!(parse_events(&program_id, &logs).is_err());
+}
@@ -0,0 +1,186 @@
+mod lib;
+
+use {
+    lib::*,
+    solana_program_test::*,
+    solana_sdk::{
+        instruction::Instruction,
+        signature::Signer,
+        sysvar::clock::Clock,
+    },
+};
+use ::trader::{
+    instruction::{self, CreateTradeArgs, TradeFillAccounts},
+    instructions::Settlement,
+    state::TradeMode,
+};
+
+// Creates the trade of test in Forward mode, settling an hour from now, returning its settlement date
+async fn create_forward_trade(test: &TestData) -> i64 {
+    let clock = test.conn.borrow_mut().get_sysvar::<Clock>().await.unwrap();
+    let settles_at = clock.unix_timestamp + 3600;
+    let args = CreateTradeArgs {
+        mode: TradeMode::Forward,
+        settlement: Some(Settlement { settles_at }),
+        ..CreateTradeArgs::default()
+    };
+    create_trade_with(test, args).await.unwrap();
+
+    settles_at
+}
+
+// Creates the trade escrow, the ATA of the pda for the trade mint, and returns the fill of the trade by wallet2
+async fn fill_forward_ix(test: &TestData) -> Instruction {
+    let (pda, _) = instruction::find_trade_pda(&test.program_id, &test.trade_account_keypair.pubkey());
+    let escrow_ix = spl_associated_token_account::create_associated_token_account(
+        &test.payer.pubkey(),
+        &pda,
+        &test.trade_mint,
+    );
+    process_ix(&vec![&test.payer], test.payer.pubkey(), escrow_ix, &test.conn).await.unwrap();
+
+    let escrow = instruction::find_trade_escrow(
+        &test.program_id,
+        &test.trade_account_keypair.pubkey(),
+        &test.trade_mint,
+    );
+    let accounts = TradeFillAccounts { trade_escrow: Some(escrow), ..trade_fill_accounts(test) };
+    let offer_amount: u64 = spl_token::ui_amount_to_amount(10.0, 9);
+    let trade_amount: u64 = spl_token::ui_amount_to_amount(2.0, 9);
+
+    instruction::make_trade(&test.program_id, &test.wallet2.pubkey(), &accounts, offer_amount, trade_amount, None)
+}
+
+#[tokio::test]
+async fn test_settle_forward_trade() {
+    let (test, mut context) = TestData::init_with_context().await;
+    let settles_at = create_forward_trade(&test).await;
+
+    let ix = fill_forward_ix(&test).await;
+    process_ix(&vec![&test.payer, &test.wallet2], test.payer.pubkey(), ix, &test.conn).await.unwrap();
+
+    // both sides are held by the pda until the trade is settled
+    let (pda, _) = instruction::find_trade_pda(&test.program_id, &test.trade_account_keypair.pubkey());
+    let escrow = instruction::find_trade_escrow(
+        &test.program_id,
+        &test.trade_account_keypair.pubkey(),
+        &test.trade_mint,
+    );
+    let fee: u64 = spl_token::ui_amount_to_amount(0.1, 9);
+    let trade_amount: u64 = spl_token::ui_amount_to_amount(2.0, 9);
+    assert_eq!(get_spl_account(escrow, &test.conn).await.amount, trade_amount - fee);
+    assert_eq!(get_spl_account(test.offer_src, &test.conn).await.owner, pda);
+    assert_eq!(get_spl_account(test.offer_dst, &test.conn).await.amount, 0);
+    let trade_account = load_trade(&test).await;
+    assert_eq!(trade_account.taker, test.wallet2.pubkey());
+    assert_eq!(trade_account.offer_dst_account, test.offer_dst);
+
+    // anyone can settle it once the settlement date has passed
+    warp_clock_to(&mut context, settles_at).await;
+    let ix = instruction::settle(&test.program_id, &test.trade_account_keypair.pubkey(), &trade_account);
+    process_ix(&vec![&test.payer], test.payer.pubkey(), ix, &test.conn).await.unwrap();
+
+    let offer_amount: u64 = spl_token::ui_amount_to_amount(10.0, 9);
+    assert_eq!(get_spl_account(test.offer_dst, &test.conn).await.amount, offer_amount);
+    assert_eq!(get_spl_account(test.trade_dst, &test.conn).await.amount, trade_amount - fee);
+    assert_eq!(get_spl_account(test.offer_src, &test.conn).await.owner, test.payer.pubkey());
+    assert_eq!(test.conn.borrow_mut().get_account(escrow).await.unwrap(), None);
+    assert_eq!(test.conn.borrow_mut().get_account(test.trade_account_keypair.pubkey()).await.unwrap(), None);
+}
+
+#[tokio::test]
+async fn test_settle_unfilled_forward_trade() {
+    let (test, mut context) = TestData::init_with_context().await;
+    let settles_at = create_forward_trade(&test).await;
+
+    warp_clock_to(&mut context, settles_at).await;
+    let ix = instruction::settle(&test.program_id, &test.trade_account_keypair.pubkey(), &load_trade(&test).await);
+    assert_eq!(ix.accounts.len(), 6);
+    process_ix(&vec![&test.payer], test.payer.pubkey(), ix, &test.conn).await.unwrap();
+
+    // no one paid for the offer, so it goes back to its owner
+    let offer_amount: u64 = spl_token::ui_amount_to_amount(10.0, 9);
+    let offer_src_account = get_spl_account(test.offer_src, &test.conn).await;
+    assert_eq!(offer_src_account.owner, test.payer.pubkey());
+    assert_eq!(offer_src_account.amount, offer_amount);
+    assert_eq!(test.conn.borrow_mut().get_account(test.trade_account_keypair.pubkey()).await.unwrap(), None);
+}
+
+#[tokio::test]
+#[should_panic(expected = "transport transaction error: Error processing Instruction 0: custom program error: 0x25")]
+async fn test_settle_forward_trade_early() {
+    let test = TestData::init().await;
+    create_forward_trade(&test).await;
+    let ix = fill_forward_ix(&test).await;
+    process_ix(&vec![&test.payer, &test.wallet2], test.payer.pubkey(), ix, &test.conn).await.unwrap();
+
+    let ix = instruction::settle(&test.program_id, &test.trade_account_keypair.pubkey(), &load_trade(&test).await);
+    let panic_on = process_ix(&vec![&test.payer], test.payer.pubkey(), ix, &test.conn).await.unwrap_err();
+    panic!("{}", panic_on.to_string());
+}
+
+#[tokio::test]
+#[should_panic(expected = "transport transaction error: Error processing Instruction 0: custom program error: 0x24")]
+async fn test_fill_forward_trade_twice() {
+    let test = TestData::init().await;
+    create_forward_trade(&test).await;
+    let ix = fill_forward_ix(&test).await;
+    process_ix(&vec![&test.payer, &test.wallet2], test.payer.pubkey(), ix.clone(), &test.conn).await.unwrap();
+
+    // paid for by the taker this time, so it isn't the same transaction
+    let panic_on = process_ix(&vec![&test.wallet2], test.wallet2.pubkey(), ix, &test.conn).await.unwrap_err();
+    panic!("{}", panic_on.to_string());
+}
+
+#[tokio::test]
+#[should_panic(expected = "transport transaction error: Error processing Instruction 0: custom program error: 0xb")]
+async fn test_fill_forward_trade_after_settlement_date() {
+    let (test, mut context) = TestData::init_with_context().await;
+    let settles_at = create_forward_trade(&test).await;
+    let ix = fill_forward_ix(&test).await;
+
+    warp_clock_to(&mut context, settles_at).await;
+    let panic_on = process_ix(&vec![&test.payer, &test.wallet2], test.payer.pubkey(), ix, &test.conn).await.unwrap_err();
+    panic!("{}", panic_on.to_string());
+}
+
+#[tokio::test]
+#[should_panic(expected = "transport transaction error: Error processing Instruction 0: custom program error: 0x24")]
+async fn test_close_filled_forward_trade() {
+    let test = TestData::init().await;
+    create_forward_trade(&test).await;
+    let ix = fill_forward_ix(&test).await;
+    process_ix(&vec![&test.payer, &test.wallet2], test.payer.pubkey(), ix, &test.conn).await.unwrap();
+
+    // the taker already paid, so the offer can't go back to its owner
+    let ix = instruction::close_stale_trade(
+        &test.program_id,
+        &test.wallet2.pubkey(),
+        &test.trade_account_keypair.pubkey(),
+        &test.offer_src,
+        &test.payer.pubkey(),
+    );
+    let panic_on = process_ix(&vec![&test.payer, &test.wallet2], test.payer.pubkey(), ix, &test.conn).await.unwrap_err();
+    panic!("{}", panic_on.to_string());
+}
+
+#[tokio::test]
+#[should_panic(expected = "transport transaction error: Error processing Instruction 0: custom program error: 0x23")]
+async fn test_settle_trade_not_forward() {
+    let test = TestData::init().await;
+    create_test_trade(&test).await;
+
+    let ix = instruction::settle(&test.program_id, &test.trade_account_keypair.pubkey(), &load_trade(&test).await);
+    let panic_on = process_ix(&vec![&test.payer], test.payer.pubkey(), ix, &test.conn).await.unwrap_err();
+    panic!("{}", panic_on.to_string());
+}
+
+#[tokio::test]
+#[should_panic(expected = "invalid instruction data")]
+async fn test_create_forward_trade_without_settlement() {
+    let test = TestData::init().await;
+
+    let (ix, _, _) = init_trade_with_mode_ix(&test, spl_token::ui_amount_to_amount(2.0, 9), TradeMode::Forward, None);
+    let panic_on = process_ix(&vec![&test.payer], test.payer.pubkey(), ix, &test.conn).await.unwrap_err();
+    panic!("{}", panic_on.to_string());
+}
@@ -6,13 +6,20 @@ use {
     solana_program_test::*,
     solana_sdk::{
         instruction::AccountMeta,
+        message::Message,
+        program_option::COption,
         pubkey::Pubkey,
-        signature::Signer,
+        signature::{Keypair, Signer},
+        system_instruction,
+        transaction::Transaction,
         program_pack::Pack,
     },
-    spl_token::state::Account as SPLAccount,
+    spl_token::{
+        instruction::AuthorityType,
+        state::{Account as SPLAccount, Mint},
+    },
 };
-use ::trader::state;
+use ::trader::{instruction, state};
 
 #[tokio::test]
 async fn test_init_trade() {
@@ -36,9 +43,12 @@ async fn test_init_trade() {
     assert_eq!(trade_account.authority, test.payer.pubkey());
     assert_eq!(trade_account.offer_amount, expected_offer_amount);
     assert_eq!(trade_account.trade_amount, expected_trade_amount);
-    assert_eq!(trade_account.initialized, true);
+    assert!(trade_account.initialized);
     assert_eq!(trade_account.trade_mint, test.trade_mint);
     assert_eq!(trade_account.program_id, test.program_id);
+    // 2 trade tokens for 10 offer tokens
+    assert_eq!((trade_account.price_num, trade_account.price_den), (1, 5));
+    assert_eq!((trade_account.offer_decimals, trade_account.trade_decimals), (9, 9));
 }
 
 #[tokio::test]
@@ -68,15 +78,24 @@ async fn test_init_trade_check_account_not_initialised() {
     // tests if the program rejects creating a trad on an account marked as initialised
 
     let test1 = TestData::init().await;
-    let fake_pda = Pubkey::new_unique();
+    let (pda, _) = instruction::find_trade_pda(&test1.program_id, &test1.trade_account_keypair.pubkey());
+    let maker = state::StatsAccount::Maker(test1.payer.pubkey());
+    let (maker_stats, _) = instruction::find_stats(&test1.program_id, &maker);
     let accounts = vec![
         AccountMeta::new_readonly(test1.payer.pubkey(), false),
         AccountMeta::new(test1.trade_account_keypair.pubkey(), false),
         AccountMeta::new(test1.offer_src, false),
         AccountMeta::new_readonly(test1.trade_mint, false),
         AccountMeta::new_readonly(test1.trade_dst, false),
-        AccountMeta::new_readonly(fake_pda, false),
+        AccountMeta::new_readonly(pda, false),
         AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new_readonly(test1.offer_mint, false),
+        AccountMeta::new_readonly(instruction::find_mint_list(&test1.program_id).0, false),
+        AccountMeta::new_readonly(instruction::find_listed_mint(&test1.program_id, &test1.offer_mint).0, false),
+        AccountMeta::new_readonly(instruction::find_listed_mint(&test1.program_id, &test1.trade_mint).0, false),
+        AccountMeta::new_readonly(instruction::find_mint_limits(&test1.program_id, &test1.offer_mint).0, false),
+        AccountMeta::new_readonly(instruction::find_mint_limits(&test1.program_id, &test1.trade_mint).0, false),
+        AccountMeta::new(maker_stats, false),
     ];
     let (ix, _, _) = init_trade_ix(&test1, 2, Some(accounts));
     process_ix(&vec![&test1.payer], test1.payer.pubkey(), ix, &test1.conn).await.unwrap();
@@ -90,8 +109,9 @@ async fn test_init_trade_check_account_not_initialised() {
         AccountMeta::new(test2.offer_src, false),
         AccountMeta::new_readonly(test2.trade_mint, false),
         AccountMeta::new_readonly(test2.trade_dst, false),
-        AccountMeta::new_readonly(fake_pda, false),
+        AccountMeta::new_readonly(pda, false),
         AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new_readonly(test2.offer_mint, false),
     ];
     let (ix, _, _) = init_trade_ix(&test2, 2, Some(accounts));
     let panic_on = process_ix(&vec![&test2.payer], test2.payer.pubkey(), ix, &test2.conn).await.unwrap_err();
@@ -155,3 +175,149 @@ async fn test_offer_account_must_have_a_balance_equal_to_the_trade_offer() {
     let panic_on = process_ix(&vec![&test.payer], test.payer.pubkey(), ix, &test.conn).await.unwrap_err();
     panic!("{}", panic_on.to_string());
 }
+
+async fn set_close_authority(test: &TestData, close_authority: Pubkey) {
+    let ix = spl_token::instruction::set_authority(
+        &spl_token::id(),
+        &test.offer_src,
+        Some(&close_authority),
+        AuthorityType::CloseAccount,
+        &test.payer.pubkey(),
+        &[&test.payer.pubkey()],
+    ).unwrap();
+    process_ix(&vec![&test.payer], test.payer.pubkey(), ix, &test.conn).await.unwrap();
+}
+
+async fn approve_delegate(test: &TestData, delegate: Pubkey) {
+    let ix = spl_token::instruction::approve(
+        &spl_token::id(),
+        &test.offer_src,
+        &delegate,
+        &test.payer.pubkey(),
+        &[&test.payer.pubkey()],
+        1,
+    ).unwrap();
+    process_ix(&vec![&test.payer], test.payer.pubkey(), ix, &test.conn).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_init_trade_clears_delegate() {
+    let test = TestData::init().await;
+    approve_delegate(&test, test.wallet2.pubkey()).await;
+
+    create_test_trade(&test).await;
+
+    let offer_src_account = get_spl_account(test.offer_src, &test.conn).await;
+    assert_eq!(offer_src_account.delegate, COption::None);
+    assert_eq!(offer_src_account.delegated_amount, 0);
+}
+
+#[tokio::test]
+async fn test_init_delegated_trade_replaces_delegate() {
+    let test = TestData::init().await;
+    approve_delegate(&test, test.wallet2.pubkey()).await;
+
+    let (ix, pda_account, _) = init_trade_with_mode_ix(&test, 2, state::TradeMode::Delegate, None);
+    process_ix(&vec![&test.payer], test.payer.pubkey(), ix, &test.conn).await.unwrap();
+
+    let offer_amount: u64 = spl_token::ui_amount_to_amount(10.0, 9);
+    let offer_src_account = get_spl_account(test.offer_src, &test.conn).await;
+    assert_eq!(offer_src_account.delegate, COption::Some(pda_account));
+    assert_eq!(offer_src_account.delegated_amount, offer_amount);
+}
+
+#[tokio::test]
+async fn test_init_trade_clears_close_authority_of_owner() {
+    let test = TestData::init().await;
+    set_close_authority(&test, test.payer.pubkey()).await;
+
+    create_test_trade(&test).await;
+
+    let offer_src_account = get_spl_account(test.offer_src, &test.conn).await;
+    assert_eq!(offer_src_account.close_authority, COption::None);
+}
+
+#[tokio::test]
+#[should_panic(expected = "transport transaction error: Error processing Instruction 0: custom program error: 0xf")]
+async fn test_init_trade_rejects_close_authority() {
+    let test = TestData::init().await;
+    set_close_authority(&test, test.wallet2.pubkey()).await;
+
+    let (ix, _, _) = init_trade_ix(&test, 2, None);
+    let panic_on = process_ix(&vec![&test.payer], test.payer.pubkey(), ix, &test.conn).await.unwrap_err();
+    panic!("{}", panic_on.to_string());
+}
+
+#[tokio::test]
+#[should_panic(expected = "transport transaction error: Error processing Instruction 0: custom program error: 0xd")]
+async fn test_init_trade_rejects_frozen_offer_account() {
+    let mut test = TestData::init().await;
+
+    // a mint with a freeze authority, to be able to freeze the offer account
+    let mint = Keypair::new();
+    let create_ix = system_instruction::create_account(
+        &test.payer.pubkey(),
+        &mint.pubkey(),
+        minimum_balance_rent_exempt(Mint::LEN),
+        Mint::LEN as u64,
+        &spl_token::id(),
+    );
+    let init_ix = spl_token::instruction::initialize_mint(
+        &spl_token::id(),
+        &mint.pubkey(),
+        &test.payer.pubkey(),
+        Some(&test.payer.pubkey()),
+        9,
+    ).unwrap();
+    let hash = test.conn.borrow_mut().get_latest_blockhash().await.unwrap();
+    let message = Message::new(&[create_ix, init_ix], Some(&test.payer.pubkey()));
+    let transaction = Transaction::new(&[&test.payer, &mint], message, hash);
+    test.conn.borrow_mut().process_transaction(transaction).await.unwrap();
+
+    let offer_src = token_account(&test.payer, &test.payer, mint.pubkey(), hash, &test.conn).await;
+    mint_to_account(&test.payer, &test.payer, mint.pubkey(), offer_src, 10, hash, &test.conn).await;
+    let freeze_ix = spl_token::instruction::freeze_account(
+        &spl_token::id(),
+        &offer_src,
+        &mint.pubkey(),
+        &test.payer.pubkey(),
+        &[&test.payer.pubkey()],
+    ).unwrap();
+    process_ix(&vec![&test.payer], test.payer.pubkey(), freeze_ix, &test.conn).await.unwrap();
+
+    test.offer_src = offer_src;
+    let (ix, _, _) = init_trade_ix(&test, 2, None);
+    let panic_on = process_ix(&vec![&test.payer], test.payer.pubkey(), ix, &test.conn).await.unwrap_err();
+    panic!("{}", panic_on.to_string());
+}
+
+#[tokio::test]
+#[should_panic(expected = "transport transaction error: Error processing Instruction 0: custom program error: 0xe")]
+async fn test_init_trade_rejects_native_offer_account() {
+    let mut test = TestData::init().await;
+
+    // a wrapped SOL account, where the balance is whatever is above the rent
+    let offer_src = Keypair::new();
+    let create_ix = system_instruction::create_account(
+        &test.payer.pubkey(),
+        &offer_src.pubkey(),
+        minimum_balance_rent_exempt(SPLAccount::LEN) + 1_000_000,
+        SPLAccount::LEN as u64,
+        &spl_token::id(),
+    );
+    let init_ix = spl_token::instruction::initialize_account(
+        &spl_token::id(),
+        &offer_src.pubkey(),
+        &spl_token::native_mint::id(),
+        &test.payer.pubkey(),
+    ).unwrap();
+    let hash = test.conn.borrow_mut().get_latest_blockhash().await.unwrap();
+    let message = Message::new(&[create_ix, init_ix], Some(&test.payer.pubkey()));
+    let transaction = Transaction::new(&[&test.payer, &offer_src], message, hash);
+    test.conn.borrow_mut().process_transaction(transaction).await.unwrap();
+
+    test.offer_src = offer_src.pubkey();
+    let (ix, _, _) = init_trade_ix(&test, 2, None);
+    let panic_on = process_ix(&vec![&test.payer], test.payer.pubkey(), ix, &test.conn).await.unwrap_err();
+    panic!("{}", panic_on.to_string());
+}
@@ -0,0 +1,714 @@
+use {
+    borsh::{BorshDeserialize, BorshSerialize},
+    solana_sdk::{
+        ed25519_instruction,
+        ed25519_program,
+        feature_set::FeatureSet,
+        pubkey::Pubkey,
+        signature::{Keypair, Signer},
+        system_program,
+        sysvar,
+    },
+    std::sync::Arc,
+};
+use ::trader::{
+    instruction::{self, CreateTradeArgs, TradeFillAccounts},
+    instructions::{Action, AllowlistProof, OraclePeg, SignedOrder, TradeFill},
+    state::{AccountMintLimits, AccountTrade, MintLimits, MintListMode, StatsAccount, TradeMode},
+};
+
+fn fill_accounts() -> TradeFillAccounts {
+    TradeFillAccounts {
+        trade: Pubkey::new_unique(),
+        offer_src: Pubkey::new_unique(),
+        trade_dst: Pubkey::new_unique(),
+        trade_src: Pubkey::new_unique(),
+        offer_dst: Pubkey::new_unique(),
+        offer_owner: Pubkey::new_unique(),
+        fee_account: Pubkey::new_unique(),
+        price_feed: None,
+        split_dsts: vec![],
+        arbiter: None,
+        hook: None,
+        trade_escrow: None,
+        vesting_escrow: None,
+        receipt: None,
+        mints: None,
+    }
+}
+
+#[test]
+fn test_create_trade_ix() {
+    let program_id = Pubkey::new_unique();
+    let (authority, trade, offer_src, offer_mint, trade_mint, trade_dst) = (
+        Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique(),
+        Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique(),
+    );
+
+    let ix = instruction::create_trade(
+        &program_id, &authority, &trade, &offer_src, &offer_mint, &trade_mint, &trade_dst, 2,
+        CreateTradeArgs { mode: TradeMode::Delegate, expires_at: 100, bounty: 5000, ..CreateTradeArgs::default() },
+    );
+    let (pda, bump_seed) = instruction::find_trade_pda(&program_id, &trade);
+
+    let (mint_list, _) = instruction::find_mint_list(&program_id);
+    let (offer_listed, _) = instruction::find_listed_mint(&program_id, &offer_mint);
+    let (trade_listed, _) = instruction::find_listed_mint(&program_id, &trade_mint);
+    let (offer_limits, _) = instruction::find_mint_limits(&program_id, &offer_mint);
+    let (trade_limits, _) = instruction::find_mint_limits(&program_id, &trade_mint);
+    let (maker_stats, _) = instruction::find_stats(&program_id, &StatsAccount::Maker(authority));
+
+    // the mint list, then the listed mint and the mint limits accounts of both mints, and the maker stats go last
+    let keys: Vec<Pubkey> = ix.accounts.iter().map(|meta| meta.pubkey).collect();
+    assert_eq!(keys, vec![
+        authority,
+        trade,
+        offer_src,
+        trade_mint,
+        trade_dst,
+        pda,
+        spl_token::id(),
+        offer_mint,
+        mint_list,
+        offer_listed,
+        trade_listed,
+        offer_limits,
+        trade_limits,
+        maker_stats,
+    ]);
+    assert!(ix.accounts[0].is_signer);
+    assert!(ix.accounts[1].is_writable && ix.accounts[2].is_writable);
+    assert!(ix.accounts[8..13].iter().all(|meta| !meta.is_writable && !meta.is_signer));
+    assert!(ix.accounts[13].is_writable && !ix.accounts[13].is_signer);
+
+    match Action::try_from_slice(&ix.data).unwrap() {
+        Action::CreateTrade {
+            bump_seed: seed, trade, mode, expires_at, bounty, peg, splits, arbitration, hook, call, settlement,
+            vesting, allowlist, receipt,
+        } => {
+            assert_eq!(seed, bump_seed);
+            assert_eq!(trade, 2);
+            assert_eq!(mode, TradeMode::Delegate);
+            assert_eq!(expires_at, 100);
+            assert_eq!(bounty, 5000);
+            assert_eq!(peg, None);
+            assert!(splits.is_empty());
+            assert_eq!(arbitration, None);
+            assert_eq!(hook, None);
+            assert_eq!(call, None);
+            assert_eq!(settlement, None);
+            assert_eq!(vesting, None);
+            assert_eq!(allowlist, None);
+            assert!(!receipt);
+        },
+        action => panic!("unexpected action {:?}", action),
+    }
+}
+
+#[test]
+fn test_create_pegged_trade_ix() {
+    let program_id = Pubkey::new_unique();
+    let (authority, trade, offer_src, offer_mint, trade_mint, trade_dst, price_feed) = (
+        Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique(),
+        Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique(),
+    );
+    let peg = OraclePeg { spread_bps: 100, max_price_age: 60 };
+
+    let ix = instruction::create_trade(
+        &program_id, &authority, &trade, &offer_src, &offer_mint, &trade_mint, &trade_dst, 2,
+        CreateTradeArgs { peg: Some((price_feed, peg.clone())), ..CreateTradeArgs::default() },
+    );
+
+    // the price account follows the offer mint
+    assert_eq!(ix.accounts.len(), 15);
+    assert_eq!(ix.accounts[8].pubkey, price_feed);
+    assert!(!ix.accounts[8].is_writable);
+
+    match Action::try_from_slice(&ix.data).unwrap() {
+        Action::CreateTrade { peg: Some(ix_peg), .. } => assert_eq!(ix_peg, peg),
+        action => panic!("unexpected action {:?}", action),
+    }
+}
+
+#[test]
+fn test_create_receipt_trade_ix() {
+    let program_id = Pubkey::new_unique();
+    let (authority, trade, offer_src, offer_mint, trade_mint, trade_dst, price_feed, receipt_mint) = (
+        Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique(),
+        Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique(),
+    );
+    let peg = OraclePeg { spread_bps: 100, max_price_age: 60 };
+
+    let ix = instruction::create_trade(
+        &program_id, &authority, &trade, &offer_src, &offer_mint, &trade_mint, &trade_dst, 2,
+        CreateTradeArgs { peg: Some((price_feed, peg)), receipt: Some(receipt_mint), ..CreateTradeArgs::default() },
+    );
+
+    // the receipt mint and the ATA of the owner it is minted to follow the price account, both written to
+    let receipt_account = spl_associated_token_account::get_associated_token_address(&authority, &receipt_mint);
+    let trailing: Vec<Pubkey> = ix.accounts[8..11].iter().map(|meta| meta.pubkey).collect();
+    assert_eq!(trailing, vec![price_feed, receipt_mint, receipt_account]);
+    assert!(ix.accounts[9..11].iter().all(|meta| meta.is_writable && !meta.is_signer));
+
+    match Action::try_from_slice(&ix.data).unwrap() {
+        Action::CreateTrade { receipt, .. } => assert!(receipt),
+        action => panic!("unexpected action {:?}", action),
+    }
+}
+
+#[test]
+fn test_make_trade_ix() {
+    let program_id = Pubkey::new_unique();
+    let taker = Pubkey::new_unique();
+    let accounts = fill_accounts();
+
+    let ix = instruction::make_trade(&program_id, &taker, &accounts, 10, 2, None);
+    let (pda, _) = instruction::find_trade_pda(&program_id, &accounts.trade);
+
+    let keys: Vec<Pubkey> = ix.accounts.iter().map(|meta| meta.pubkey).collect();
+    assert_eq!(keys, vec![
+        taker,
+        accounts.trade,
+        pda,
+        accounts.offer_src,
+        accounts.trade_dst,
+        accounts.trade_src,
+        accounts.offer_dst,
+        accounts.offer_owner,
+        accounts.fee_account,
+        spl_token::id(),
+    ]);
+    assert!(ix.accounts[0].is_signer);
+    assert!(!ix.accounts[2].is_writable);
+
+    // the allowlist proof only goes in the instruction data
+    let proof = AllowlistProof { cap: 10, proof: vec![[1; 32], [2; 32]] };
+    let ix = instruction::make_trade(&program_id, &taker, &accounts, 10, 2, Some(proof.clone()));
+    assert_eq!(ix.accounts.len(), keys.len());
+    match Action::try_from_slice(&ix.data).unwrap() {
+        Action::MakeTrade { expected_offer, expected_trade, allowlist } => {
+            assert_eq!((expected_offer, expected_trade), (10, 2));
+            assert_eq!(allowlist, Some(proof));
+        },
+        action => panic!("unexpected action {:?}", action),
+    }
+}
+
+#[test]
+fn test_make_trades_ix() {
+    let program_id = Pubkey::new_unique();
+    let taker = Pubkey::new_unique();
+    let fills = vec![
+        (TradeFill { expected_offer: 10, expected_trade: 2, allowlist: None }, fill_accounts()),
+        (TradeFill { expected_offer: 20, expected_trade: 4, allowlist: None }, fill_accounts()),
+    ];
+
+    let ix = instruction::make_trades(&program_id, &taker, &fills);
+
+    assert_eq!(ix.accounts.len(), 2 + 8 * fills.len());
+    assert_eq!(ix.accounts[0].pubkey, taker);
+    assert_eq!(ix.accounts[1].pubkey, spl_token::id());
+    assert_eq!(ix.accounts[2].pubkey, fills[0].1.trade);
+    assert_eq!(ix.accounts[10].pubkey, fills[1].1.trade);
+}
+
+#[test]
+fn test_make_pegged_trades_ix() {
+    let program_id = Pubkey::new_unique();
+    let taker = Pubkey::new_unique();
+    let (first_feed, second_feed) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let fills = vec![
+        (TradeFill { expected_offer: 10, expected_trade: 2, allowlist: None }, TradeFillAccounts { price_feed: Some(first_feed), ..fill_accounts() }),
+        (TradeFill { expected_offer: 20, expected_trade: 4, allowlist: None }, fill_accounts()),
+        (TradeFill { expected_offer: 30, expected_trade: 6, allowlist: None }, TradeFillAccounts { price_feed: Some(second_feed), ..fill_accounts() }),
+    ];
+
+    let ix = instruction::make_trades(&program_id, &taker, &fills);
+
+    // the price accounts trail the trade accounts, in the order of the pegged trades
+    assert_eq!(ix.accounts.len(), 2 + 8 * fills.len() + 2);
+    assert_eq!(ix.accounts[2 + 8 * fills.len()].pubkey, first_feed);
+    assert_eq!(ix.accounts[3 + 8 * fills.len()].pubkey, second_feed);
+
+    let ix = instruction::make_trade(&program_id, &taker, &fills[0].1, 10, 2, None);
+    assert_eq!(ix.accounts.len(), 11);
+    assert_eq!(ix.accounts[10].pubkey, first_feed);
+}
+
+#[test]
+fn test_make_split_trades_ix() {
+    let program_id = Pubkey::new_unique();
+    let taker = Pubkey::new_unique();
+    let (price_feed, first_dst, second_dst) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+    let fills = vec![
+        (TradeFill { expected_offer: 10, expected_trade: 2, allowlist: None }, TradeFillAccounts {
+            price_feed: Some(price_feed),
+            split_dsts: vec![first_dst],
+            ..fill_accounts()
+        }),
+        (TradeFill { expected_offer: 20, expected_trade: 4, allowlist: None }, TradeFillAccounts {
+            split_dsts: vec![second_dst],
+            ..fill_accounts()
+        }),
+    ];
+
+    let ix = instruction::make_trades(&program_id, &taker, &fills);
+
+    // each trade has its price account first, then its split destinations
+    let trailing: Vec<Pubkey> = ix.accounts[2 + 8 * fills.len()..].iter().map(|meta| meta.pubkey).collect();
+    assert_eq!(trailing, vec![price_feed, first_dst, second_dst]);
+    assert!(!ix.accounts[2 + 8 * fills.len()].is_writable);
+    assert!(ix.accounts[3 + 8 * fills.len()].is_writable && ix.accounts[4 + 8 * fills.len()].is_writable);
+}
+
+#[test]
+fn test_close_stale_trade_ix() {
+    let program_id = Pubkey::new_unique();
+    let (cranker, trade, offer_src, offer_owner) = (
+        Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique(),
+    );
+
+    let ix = instruction::close_stale_trade(&program_id, &cranker, &trade, &offer_src, &offer_owner);
+    let (pda, _) = instruction::find_trade_pda(&program_id, &trade);
+    let (maker_stats, _) = instruction::find_stats(&program_id, &StatsAccount::Maker(offer_owner));
+
+    let keys: Vec<Pubkey> = ix.accounts.iter().map(|meta| meta.pubkey).collect();
+    assert_eq!(keys, vec![cranker, trade, pda, offer_src, offer_owner, spl_token::id(), maker_stats]);
+    assert!(ix.accounts[6].is_writable);
+    // the bounty goes to the cranker
+    assert!(ix.accounts[0].is_signer && ix.accounts[0].is_writable);
+    assert!(matches!(Action::try_from_slice(&ix.data).unwrap(), Action::CloseStaleTrade));
+}
+
+#[test]
+fn test_change_offer_ix() {
+    let program_id = Pubkey::new_unique();
+    let (authority, trade, offer_src, token_account) = (
+        Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique(),
+    );
+    let (pda, _) = instruction::find_trade_pda(&program_id, &trade);
+
+    let ix = instruction::increase_offer(&program_id, &authority, &trade, &offer_src, &token_account, 5, true);
+    let keys: Vec<Pubkey> = ix.accounts.iter().map(|meta| meta.pubkey).collect();
+    assert_eq!(keys, vec![authority, trade, pda, offer_src, token_account, spl_token::id()]);
+    assert!(ix.accounts[0].is_signer);
+    assert!(ix.accounts[3].is_writable && ix.accounts[4].is_writable);
+    assert!(matches!(
+        Action::try_from_slice(&ix.data).unwrap(),
+        Action::IncreaseOffer { amount: 5, proportional: true },
+    ));
+
+    let ix = instruction::decrease_offer(&program_id, &authority, &trade, &offer_src, &token_account, 3, false);
+    let decrease_keys: Vec<Pubkey> = ix.accounts.iter().map(|meta| meta.pubkey).collect();
+    assert_eq!(decrease_keys, keys);
+    assert!(matches!(
+        Action::try_from_slice(&ix.data).unwrap(),
+        Action::DecreaseOffer { amount: 3, proportional: false },
+    ));
+}
+
+#[test]
+fn test_transfer_trade_authority_ix() {
+    let program_id = Pubkey::new_unique();
+    let (authority, trade, new_authority, trade_dst) = (
+        Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique(),
+    );
+
+    let ix = instruction::transfer_trade_authority(&program_id, &authority, &trade, &new_authority, true, Some(&trade_dst));
+    let keys: Vec<Pubkey> = ix.accounts.iter().map(|meta| meta.pubkey).collect();
+    assert_eq!(keys, vec![authority, trade, new_authority, trade_dst]);
+    assert!(ix.accounts[0].is_signer && ix.accounts[2].is_signer);
+    assert!(ix.accounts[1].is_writable);
+    assert!(matches!(Action::try_from_slice(&ix.data).unwrap(), Action::TransferTradeAuthority));
+
+    // the trade dst is optional, and so is the signature of the new authority
+    let ix = instruction::transfer_trade_authority(&program_id, &authority, &trade, &new_authority, false, None);
+    assert_eq!(ix.accounts.len(), 3);
+    assert!(!ix.accounts[2].is_signer);
+}
+
+#[test]
+fn test_make_arbitrated_trade_ix() {
+    let program_id = Pubkey::new_unique();
+    let taker = Pubkey::new_unique();
+    let (split_dst, arbiter) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let accounts = TradeFillAccounts { split_dsts: vec![split_dst], arbiter: Some(arbiter), ..fill_accounts() };
+
+    let ix = instruction::make_trade(&program_id, &taker, &accounts, 10, 2, None);
+
+    // the arbiter signs, after the split destinations
+    assert_eq!(ix.accounts.len(), 12);
+    assert_eq!(ix.accounts[10].pubkey, split_dst);
+    assert_eq!(ix.accounts[11].pubkey, arbiter);
+    assert!(ix.accounts[11].is_signer && !ix.accounts[11].is_writable);
+}
+
+#[test]
+fn test_arbiter_release_ix() {
+    let program_id = Pubkey::new_unique();
+    let (arbiter, trade, offer_src, offer_owner, offer_dst) = (
+        Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique(),
+    );
+
+    let ix = instruction::arbiter_release(&program_id, &arbiter, &trade, &offer_src, &offer_owner, Some(&offer_dst));
+    let (pda, _) = instruction::find_trade_pda(&program_id, &trade);
+
+    let keys: Vec<Pubkey> = ix.accounts.iter().map(|meta| meta.pubkey).collect();
+    let (maker_stats, _) = instruction::find_stats(&program_id, &StatsAccount::Maker(offer_owner));
+    assert_eq!(keys, vec![arbiter, trade, pda, offer_src, offer_owner, spl_token::id(), maker_stats, offer_dst]);
+    assert!(ix.accounts[0].is_signer);
+    assert!(ix.accounts[3].is_writable && ix.accounts[4].is_writable && ix.accounts[6].is_writable);
+    assert!(ix.accounts[7].is_writable);
+    assert!(matches!(Action::try_from_slice(&ix.data).unwrap(), Action::ArbiterRelease { to_taker: true }));
+
+    // without a token account of the taker, the offer goes back to the owner
+    let ix = instruction::arbiter_release(&program_id, &arbiter, &trade, &offer_src, &offer_owner, None);
+    assert_eq!(ix.accounts.len(), 7);
+    assert!(matches!(Action::try_from_slice(&ix.data).unwrap(), Action::ArbiterRelease { to_taker: false }));
+}
+
+#[test]
+fn test_make_hooked_trade_ix() {
+    let program_id = Pubkey::new_unique();
+    let taker = Pubkey::new_unique();
+    let (arbiter, hook_program, hook_account) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+    let accounts = TradeFillAccounts {
+        arbiter: Some(arbiter),
+        hook: Some((hook_program, hook_account)),
+        ..fill_accounts()
+    };
+
+    let ix = instruction::make_trade(&program_id, &taker, &accounts, 10, 2, None);
+
+    // the hook program and its account go after the arbiter, read-only
+    let trailing: Vec<Pubkey> = ix.accounts[10..].iter().map(|meta| meta.pubkey).collect();
+    assert_eq!(trailing, vec![arbiter, hook_program, hook_account]);
+    assert!(ix.accounts[11..].iter().all(|meta| !meta.is_writable && !meta.is_signer));
+}
+
+#[test]
+fn test_make_receipt_trade_ix() {
+    let program_id = Pubkey::new_unique();
+    let taker = Pubkey::new_unique();
+    let (vesting_escrow, receipt_mint, receipt_account) = (
+        Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique(),
+    );
+    let accounts = TradeFillAccounts {
+        vesting_escrow: Some(vesting_escrow),
+        receipt: Some((receipt_mint, receipt_account)),
+        ..fill_accounts()
+    };
+
+    let ix = instruction::make_trade(&program_id, &taker, &accounts, 10, 2, None);
+
+    // the receipt mint and the account holding the receipt go last, both burnt from
+    let trailing: Vec<Pubkey> = ix.accounts[10..].iter().map(|meta| meta.pubkey).collect();
+    assert_eq!(trailing, vec![vesting_escrow, receipt_mint, receipt_account]);
+    assert!(ix.accounts[11..].iter().all(|meta| meta.is_writable && !meta.is_signer));
+}
+
+#[test]
+fn test_fill_signed_order_ix() {
+    let program_id = Pubkey::new_unique();
+    let maker = Keypair::new();
+    let (taker, offer_src, trade_src, offer_dst, fee_account) = (
+        Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique(),
+    );
+    let order = SignedOrder {
+        maker: maker.pubkey(),
+        offer_mint: Pubkey::new_unique(),
+        trade_mint: Pubkey::new_unique(),
+        trade_dst: Pubkey::new_unique(),
+        offer_amount: 10,
+        trade_amount: 2,
+        nonce: 7,
+        expires_at: 0,
+    };
+    let signature = maker.sign_message(&order.try_to_vec().unwrap());
+
+    let ixs = instruction::fill_signed_order(
+        &program_id, &taker, &order, signature.as_ref(), &offer_src, &trade_src, &offer_dst, &fee_account,
+    );
+    assert_eq!(ixs.len(), 2);
+
+    // the signature is verified by the ed25519 program right before the fill, the same way the runtime does it
+    assert_eq!(ixs[0].program_id, ed25519_program::id());
+    ed25519_instruction::verify(&ixs[0].data, &[&ixs[0].data], &Arc::new(FeatureSet::all_enabled())).unwrap();
+
+    let (nonces, _) = instruction::find_nonces_pda(&program_id, &maker.pubkey());
+    let keys: Vec<Pubkey> = ixs[1].accounts.iter().map(|meta| meta.pubkey).collect();
+    assert_eq!(keys, vec![
+        taker, nonces, offer_src, order.trade_dst, trade_src, offer_dst, fee_account, spl_token::id(),
+        sysvar::instructions::id(),
+        instruction::find_mint_list(&program_id).0,
+        instruction::find_listed_mint(&program_id, &order.offer_mint).0,
+        instruction::find_listed_mint(&program_id, &order.trade_mint).0,
+        instruction::find_mint_limits(&program_id, &order.offer_mint).0,
+        instruction::find_mint_limits(&program_id, &order.trade_mint).0,
+        instruction::find_stats(&program_id, &StatsAccount::Program).0,
+        instruction::find_stats(&program_id, &StatsAccount::Mint(order.offer_mint)).0,
+        instruction::find_stats(&program_id, &StatsAccount::Mint(order.trade_mint)).0,
+        instruction::find_stats(&program_id, &StatsAccount::Maker(maker.pubkey())).0,
+    ]);
+    assert!(ixs[1].accounts[0].is_signer);
+    assert!(ixs[1].accounts[1..7].iter().all(|meta| meta.is_writable));
+    assert!(ixs[1].accounts[12..].iter().all(|meta| meta.is_writable));
+    match Action::try_from_slice(&ixs[1].data).unwrap() {
+        Action::FillSignedOrder { order: filled } => assert_eq!(filled, order),
+        _ => panic!("not a FillSignedOrder"),
+    }
+
+    // a signature over anything else doesn't verify
+    let other = maker.sign_message(b"something else");
+    let ix = instruction::verify_signature(&maker.pubkey(), other.as_ref(), &order.try_to_vec().unwrap());
+    assert!(ed25519_instruction::verify(&ix.data, &[&ix.data], &Arc::new(FeatureSet::all_enabled())).is_err());
+}
+
+#[test]
+fn test_settle_ix() {
+    let program_id = Pubkey::new_unique();
+    let trade = Pubkey::new_unique();
+    let mut trade_account = AccountTrade {
+        offer_token_account: Pubkey::new_unique(),
+        trade_dst_account: Pubkey::new_unique(),
+        authority: Pubkey::new_unique(),
+        trade_mint: Pubkey::new_unique(),
+        mode: TradeMode::Forward,
+        settles_at: 100,
+        ..AccountTrade::default()
+    };
+    let (pda, _) = instruction::find_trade_pda(&program_id, &trade);
+
+    // a trade that was never filled only returns the offer account to its owner, and counts as cancelled
+    let ix = instruction::settle(&program_id, &trade, &trade_account);
+    let keys: Vec<Pubkey> = ix.accounts.iter().map(|meta| meta.pubkey).collect();
+    let (maker_stats, _) = instruction::find_stats(&program_id, &StatsAccount::Maker(trade_account.authority));
+    assert_eq!(keys, vec![
+        trade, pda, trade_account.offer_token_account, trade_account.authority, spl_token::id(), maker_stats,
+    ]);
+    assert!(ix.accounts.iter().all(|meta| !meta.is_signer));
+    assert!(matches!(Action::try_from_slice(&ix.data).unwrap(), Action::Settle));
+
+    trade_account.taker = Pubkey::new_unique();
+    trade_account.offer_dst_account = Pubkey::new_unique();
+    let ix = instruction::settle(&program_id, &trade, &trade_account);
+    let escrow = instruction::find_trade_escrow(&program_id, &trade, &trade_account.trade_mint);
+    assert_eq!(escrow, spl_associated_token_account::get_associated_token_address(&pda, &trade_account.trade_mint));
+    let keys: Vec<Pubkey> = ix.accounts[5..].iter().map(|meta| meta.pubkey).collect();
+    assert_eq!(keys, vec![trade_account.offer_dst_account, escrow, trade_account.trade_dst_account, trade_account.taker]);
+    assert!(ix.accounts[5..].iter().all(|meta| meta.is_writable));
+
+    // the trade escrow goes after every other trailing account but the mint limits when the trade is filled
+    let mut accounts = TradeFillAccounts { trade, ..fill_accounts() };
+    accounts.set_trailing_accounts(&program_id, &trade_account);
+    assert_eq!(accounts.trade_escrow, Some(escrow));
+    let ix = instruction::make_trade(&program_id, &Pubkey::new_unique(), &accounts, 10, 2, None);
+    let escrow_meta = &ix.accounts[ix.accounts.len() - 7];
+    assert_eq!(escrow_meta.pubkey, escrow);
+    assert!(escrow_meta.is_writable);
+}
+
+#[test]
+fn test_claim_vested_ix() {
+    let program_id = Pubkey::new_unique();
+    let trade = Pubkey::new_unique();
+    let trade_account = AccountTrade {
+        authority: Pubkey::new_unique(),
+        offer_mint: Pubkey::new_unique(),
+        taker: Pubkey::new_unique(),
+        offer_dst_account: Pubkey::new_unique(),
+        vesting_duration: 100,
+        ..AccountTrade::default()
+    };
+    let (pda, _) = instruction::find_trade_pda(&program_id, &trade);
+    let escrow = instruction::find_vesting_escrow(&program_id, &trade, &trade_account.offer_mint);
+    assert_eq!(escrow, spl_associated_token_account::get_associated_token_address(&pda, &trade_account.offer_mint));
+
+    // only the taker signs, and gets the rent of the escrow back once all is claimed
+    let ix = instruction::claim_vested(&program_id, &trade, &trade_account);
+    let keys: Vec<Pubkey> = ix.accounts.iter().map(|meta| meta.pubkey).collect();
+    assert_eq!(keys, vec![
+        trade_account.taker, trade, pda, escrow, trade_account.offer_dst_account, trade_account.authority,
+        spl_token::id(),
+    ]);
+    assert!(ix.accounts[0].is_signer && ix.accounts[1..].iter().all(|meta| !meta.is_signer));
+    assert!(matches!(Action::try_from_slice(&ix.data).unwrap(), Action::ClaimVested));
+
+    // the vesting escrow goes after every other trailing account but the mint limits
+    let mut accounts = TradeFillAccounts { trade, ..fill_accounts() };
+    accounts.set_trailing_accounts(&program_id, &trade_account);
+    assert_eq!(accounts.trade_escrow, None);
+    assert_eq!(accounts.vesting_escrow, Some(escrow));
+    let ix = instruction::make_trade(&program_id, &Pubkey::new_unique(), &accounts, 10, 2, None);
+    let escrow_meta = &ix.accounts[ix.accounts.len() - 7];
+    assert_eq!(escrow_meta.pubkey, escrow);
+    assert!(escrow_meta.is_writable);
+}
+
+#[test]
+fn test_vested_amount() {
+    let trade_account = AccountTrade {
+        offer_amount: 1000,
+        taker: Pubkey::new_unique(),
+        vesting_cliff: 25,
+        vesting_duration: 100,
+        vesting_start: 1000,
+        ..AccountTrade::default()
+    };
+
+    // nothing vests before the cliff, then linearly from the start
+    assert_eq!(trade_account.vested_amount(1024), 0);
+    assert_eq!(trade_account.vested_amount(1025), 250);
+    assert_eq!(trade_account.vested_amount(1050), 500);
+    assert_eq!(trade_account.vested_amount(1100), 1000);
+    assert_eq!(trade_account.vested_amount(2000), 1000);
+    // or not at all until the trade is filled
+    assert_eq!(AccountTrade { taker: Pubkey::default(), ..trade_account }.vested_amount(2000), 0);
+}
+
+#[test]
+fn test_mint_list_ixs() {
+    let program_id = Pubkey::new_unique();
+    let (authority, mint) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let (mint_list, list_bump) = instruction::find_mint_list(&program_id);
+    let (listed_mint, mint_bump) = instruction::find_listed_mint(&program_id, &mint);
+
+    let ix = instruction::set_mint_list_mode(&program_id, &authority, MintListMode::Allowlist);
+    let keys: Vec<Pubkey> = ix.accounts.iter().map(|meta| meta.pubkey).collect();
+    assert_eq!(keys, vec![authority, mint_list, system_program::id()]);
+    assert!(ix.accounts[0].is_signer && ix.accounts[0].is_writable && ix.accounts[1].is_writable);
+    match Action::try_from_slice(&ix.data).unwrap() {
+        Action::SetMintListMode { bump_seed, mode } => {
+            assert_eq!(bump_seed, list_bump);
+            assert_eq!(mode, MintListMode::Allowlist);
+        },
+        action => panic!("unexpected action {:?}", action),
+    }
+
+    let ix = instruction::add_mint(&program_id, &authority, &mint);
+    let keys: Vec<Pubkey> = ix.accounts.iter().map(|meta| meta.pubkey).collect();
+    assert_eq!(keys, vec![authority, listed_mint, mint, mint_list, system_program::id()]);
+    assert!(ix.accounts[3].is_writable);
+    match Action::try_from_slice(&ix.data).unwrap() {
+        Action::AddMint { bump_seed } => assert_eq!(bump_seed, mint_bump),
+        action => panic!("unexpected action {:?}", action),
+    }
+
+    // the rent of the listed mint account goes back to the authority
+    let ix = instruction::remove_mint(&program_id, &authority, &mint);
+    let keys: Vec<Pubkey> = ix.accounts.iter().map(|meta| meta.pubkey).collect();
+    assert_eq!(keys, vec![authority, listed_mint, mint_list]);
+    assert!(ix.accounts.iter().all(|meta| meta.is_writable));
+}
+
+#[test]
+fn test_mint_limits_ixs() {
+    let program_id = Pubkey::new_unique();
+    let (authority, mint) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let (mint_limits, limits_bump) = instruction::find_mint_limits(&program_id, &mint);
+    let limits = MintLimits { min_amount: 10, max_amount: 1000, volume_cap: 5000, window: 3600 };
+
+    let ix = instruction::set_mint_limits(&program_id, &authority, &mint, limits);
+    let keys: Vec<Pubkey> = ix.accounts.iter().map(|meta| meta.pubkey).collect();
+    assert_eq!(keys, vec![authority, mint_limits, mint, system_program::id()]);
+    assert!(ix.accounts[0].is_signer && ix.accounts[0].is_writable && ix.accounts[1].is_writable);
+    match Action::try_from_slice(&ix.data).unwrap() {
+        Action::SetMintLimits { bump_seed, limits: ix_limits } => {
+            assert_eq!(bump_seed, limits_bump);
+            assert_eq!(ix_limits, limits);
+        },
+        action => panic!("unexpected action {:?}", action),
+    }
+
+    // every fill takes the mint limits accounts of both mints, then the stats accounts, written to, after all other
+    // accounts
+    let trade_account = AccountTrade {
+        offer_mint: mint,
+        trade_mint: Pubkey::new_unique(),
+        ..AccountTrade::default()
+    };
+    let (trade_limits, _) = instruction::find_mint_limits(&program_id, &trade_account.trade_mint);
+    let mut accounts = fill_accounts();
+    accounts.set_trailing_accounts(&program_id, &trade_account);
+    assert_eq!(accounts.mints, Some((mint, trade_account.trade_mint)));
+    let ix = instruction::make_trade(&program_id, &Pubkey::new_unique(), &accounts, 10, 2, None);
+    assert_eq!(ix.accounts.len(), 16);
+    let keys: Vec<Pubkey> = ix.accounts[10..12].iter().map(|meta| meta.pubkey).collect();
+    assert_eq!(keys, vec![mint_limits, trade_limits]);
+    assert!(ix.accounts[10..].iter().all(|meta| meta.is_writable && !meta.is_signer));
+}
+
+#[test]
+fn test_mint_limits_volume() {
+    let mut mint_limits = AccountMintLimits {
+        limits: MintLimits { min_amount: 10, max_amount: 100, volume_cap: 150, window: 60 },
+        window_start: 1000,
+        ..AccountMintLimits::default()
+    };
+
+    assert!(!mint_limits.allows_amount(9));
+    assert!(mint_limits.allows_amount(10) && mint_limits.allows_amount(100));
+    assert!(!mint_limits.allows_amount(101));
+
+    // fills add up within the window, and stop at the cap without counting
+    assert!(mint_limits.add_volume(100, 1000));
+    assert!(mint_limits.add_volume(50, 1059));
+    assert!(!mint_limits.add_volume(1, 1059));
+    assert_eq!(mint_limits.window_volume, 150);
+    // until the window is over
+    assert!(mint_limits.add_volume(100, 1060));
+    assert_eq!((mint_limits.window_start, mint_limits.window_volume), (1060, 100));
+
+    // without a cap nothing is counted
+    mint_limits.limits = MintLimits::default();
+    assert!(mint_limits.allows_amount(u64::MAX));
+    assert!(mint_limits.add_volume(u64::MAX, 1061));
+    assert_eq!(mint_limits.window_volume, 100);
+}
+
+#[test]
+fn test_init_stats_ix() {
+    let program_id = Pubkey::new_unique();
+    let (payer, mint, maker) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+
+    // each stats account has its own pda, even with the same key as a mint and as a maker
+    let stats = [
+        StatsAccount::Program,
+        StatsAccount::Mint(mint),
+        StatsAccount::Maker(maker),
+        StatsAccount::Mint(maker),
+    ];
+    let pdas: Vec<Pubkey> = stats.iter().map(|stats| instruction::find_stats(&program_id, stats).0).collect();
+    assert!(pdas.iter().enumerate().all(|(i, pda)| !pdas[i + 1..].contains(pda)));
+
+    let (maker_stats, stats_bump) = instruction::find_stats(&program_id, &StatsAccount::Maker(maker));
+    let ix = instruction::init_stats(&program_id, &payer, StatsAccount::Maker(maker));
+    let keys: Vec<Pubkey> = ix.accounts.iter().map(|meta| meta.pubkey).collect();
+    assert_eq!(keys, vec![payer, maker_stats, system_program::id()]);
+    assert!(ix.accounts[0].is_signer && ix.accounts[0].is_writable && ix.accounts[1].is_writable);
+    match Action::try_from_slice(&ix.data).unwrap() {
+        Action::InitStats { bump_seed, stats } => {
+            assert_eq!(bump_seed, stats_bump);
+            assert_eq!(stats, StatsAccount::Maker(maker));
+        },
+        action => panic!("unexpected action {:?}", action),
+    }
+
+    // the stats of the program, of both mints and of the offer owner follow the mint limits of every fill
+    let trade_account = AccountTrade {
+        offer_mint: mint,
+        trade_mint: Pubkey::new_unique(),
+        ..AccountTrade::default()
+    };
+    let mut accounts = fill_accounts();
+    accounts.set_trailing_accounts(&program_id, &trade_account);
+    let ix = instruction::make_trade(&program_id, &Pubkey::new_unique(), &accounts, 10, 2, None);
+    let keys: Vec<Pubkey> = ix.accounts[12..].iter().map(|meta| meta.pubkey).collect();
+    assert_eq!(keys, vec![
+        pdas[0],
+        pdas[1],
+        instruction::find_stats(&program_id, &StatsAccount::Mint(trade_account.trade_mint)).0,
+        instruction::find_stats(&program_id, &StatsAccount::Maker(accounts.offer_owner)).0,
+    ]);
+    assert!(ix.accounts[12..].iter().all(|meta| meta.is_writable && !meta.is_signer));
+}
@@ -1,8 +1,12 @@
+// Shared by every test file, each of which only uses some of it
+#![allow(dead_code)]
+
 use {
     assert_matches::*,
-    borsh::BorshSerialize,
+    borsh::BorshDeserialize,
     solana_program_test::*,
     solana_sdk::{
+        account::Account,
         message::Message,
         hash::Hash,
         instruction::{
@@ -13,7 +17,7 @@ use {
         transaction::Transaction,
         signature::{Keypair, Signer},
         system_instruction,
-        sysvar::rent::Rent,
+        sysvar::{clock::Clock, rent::Rent},
         program_pack::Pack,
     },
     spl_token:: {
@@ -26,7 +30,8 @@ use {
     },
 };
 use ::trader::{
-    instructions::Action,
+    instruction::{self, CreateTradeArgs, TradeFillAccounts},
+    instructions::TradeFill,
     entrypoint as trader,
     state,
 };
@@ -37,7 +42,7 @@ pub fn minimum_balance_rent_exempt(size: usize) -> u64 {
     Rent::default().minimum_balance(size)
 }
 
-pub async fn mint_account<'a>(
+pub async fn mint_account(
     authority: &Keypair,
     payer: &Keypair, 
     recent_blockhash: Hash, 
@@ -63,7 +68,7 @@ pub async fn mint_account<'a>(
 
     let message = Message::new(&[create_ix, init_ix], Some(&payer.pubkey()));
     let transaction = Transaction::new(
-        &[&payer, &mint_key],
+        &[payer, &mint_key],
         message, recent_blockhash,
     );
     assert_matches!(conn.borrow_mut().process_transaction(transaction).await, Ok(()));
@@ -97,7 +102,7 @@ pub async fn token_account(
 
     let message = Message::new(&[create_ix, init_ix], Some(&payer.pubkey()));
     let transaction = Transaction::new(
-        &[&payer, &account_key],
+        &[payer, &account_key],
         message, recent_blockhash,
     );
     assert_matches!(conn.borrow_mut().process_transaction(transaction).await, Ok(()));
@@ -137,49 +142,98 @@ pub async fn get_spl_account(addr: Pubkey, conn: &RefCell<BanksClient>) -> SPLAc
     SPLAccount::unpack_from_slice(&offer_src_ai.data).unwrap()
 }
 
+// Moves the clock of the test to unix_timestamp, e.g. past the expiry of a trade
+pub async fn warp_clock_to(context: &mut ProgramTestContext, unix_timestamp: i64) {
+    let mut clock = context.banks_client.get_sysvar::<Clock>().await.unwrap();
+    clock.unix_timestamp = unix_timestamp;
+    context.set_sysvar(&clock);
+}
+
 pub fn init_trade_ix(
     test: &TestData,
     trade_amount: u64,
     accounts: Option<Vec<AccountMeta>>,
 ) -> (Instruction, Pubkey, u8) {
-    let (pda_pubkey, bump_seed) = Pubkey::find_program_address(
-        &[test.trade_account_keypair.pubkey().as_ref()],
-            &test.program_id,
-    );
+    init_trade_with_mode_ix(test, trade_amount, state::TradeMode::Escrow, accounts)
+}
 
-    let action = Action::CreateTrade {
-        bump_seed: bump_seed,
-        trade: trade_amount,
-    };
-    let buf = &action.try_to_vec().unwrap()[..];
-
-    let accounts_list = if let Some(list) = accounts {
-        list
-    } else {
-        vec![
-            AccountMeta::new_readonly(test.payer.pubkey(), true),
-            AccountMeta::new(test.trade_account_keypair.pubkey(), false),
-            AccountMeta::new(test.offer_src, false),
-            AccountMeta::new_readonly(test.trade_mint, false),
-            AccountMeta::new_readonly(test.trade_dst, false),
-            AccountMeta::new_readonly(pda_pubkey, false),
-            AccountMeta::new_readonly(spl_token::id(), false),
-        ]
-    };
-    let ix = Instruction::new_with_bytes(test.program_id, buf, accounts_list);
+pub fn init_trade_with_mode_ix(
+    test: &TestData,
+    trade_amount: u64,
+    mode: state::TradeMode,
+    accounts: Option<Vec<AccountMeta>>,
+) -> (Instruction, Pubkey, u8) {
+    init_expiring_trade_ix(test, trade_amount, mode, 0, 0, accounts)
+}
+
+pub fn init_expiring_trade_ix(
+    test: &TestData,
+    trade_amount: u64,
+    mode: state::TradeMode,
+    expires_at: i64,
+    bounty: u64,
+    accounts: Option<Vec<AccountMeta>>,
+) -> (Instruction, Pubkey, u8) {
+    let (pda_pubkey, bump_seed) = instruction::find_trade_pda(&test.program_id, &test.trade_account_keypair.pubkey());
+
+    let mut ix = create_trade_ix(
+        test,
+        trade_amount,
+        CreateTradeArgs { mode, expires_at, bounty, ..CreateTradeArgs::default() },
+    );
+    if let Some(list) = accounts {
+        ix.accounts = list;
+    }
 
     (ix, pda_pubkey, bump_seed)
 }
 
+// The CreateTrade instruction for the trade of test, with the options in args
+pub fn create_trade_ix(test: &TestData, trade_amount: u64, args: CreateTradeArgs) -> Instruction {
+    instruction::create_trade(
+        &test.program_id,
+        &test.payer.pubkey(),
+        &test.trade_account_keypair.pubkey(),
+        &test.offer_src,
+        &test.offer_mint,
+        &test.trade_mint,
+        &test.trade_dst,
+        trade_amount,
+        args,
+    )
+}
+
+// Same as create_test_trade, with the options in args
+pub async fn create_trade_with(test: &TestData, args: CreateTradeArgs) -> std::result::Result<(), BanksClientError> {
+    let trade_balance: u64 = spl_token::ui_amount_to_amount(2.0, 9);
+    let ix = create_trade_ix(test, trade_balance, args);
+    process_ix(&vec![&test.payer], test.payer.pubkey(), ix, &test.conn).await
+}
+
+pub async fn load_trade(test: &TestData) -> state::AccountTrade {
+    let trade_ai = test.conn.borrow_mut().get_account(test.trade_account_keypair.pubkey()).await.unwrap().unwrap();
+    state::AccountTrade::try_from_slice(&trade_ai.data).unwrap()
+}
+
 pub async fn process_ix(
     signers: &Vec<&Keypair>,
     payer: Pubkey,
     ix: Instruction,
     conn: &RefCell<BanksClient>,
+) -> std::result::Result<(), BanksClientError> {
+    process_ixs(signers, payer, &[ix], conn).await
+}
+
+// Same as process_ix, with every instruction in the same transaction
+pub async fn process_ixs(
+    signers: &Vec<&Keypair>,
+    payer: Pubkey,
+    ixs: &[Instruction],
+    conn: &RefCell<BanksClient>,
 ) -> std::result::Result<(), BanksClientError> {
     let transaction = Transaction::new(
-        signers.into(),
-        Message::new(&[ix], Some(&payer)),
+        signers,
+        Message::new(ixs, Some(&payer)),
         conn.borrow_mut().get_latest_blockhash().await.unwrap(),
     );
 
@@ -191,7 +245,7 @@ pub async fn process_ix(
 
 pub async fn create_test_trade(test: &TestData) {
     let trade_balance: u64 = spl_token::ui_amount_to_amount(2.0, 9);
-    let (ix, _, _) = init_trade_ix(&test, trade_balance, None);
+    let (ix, _, _) = init_trade_ix(test, trade_balance, None);
     process_ix(&vec![&test.payer], test.payer.pubkey(), ix, &test.conn).await.unwrap();
 }
 
@@ -201,36 +255,86 @@ pub fn make_trade_ix(
     trade_amount: u64,
     accounts: Option<Vec<AccountMeta>>,
 ) -> (Instruction, Pubkey, u8) {
-    let (pda_pubkey, bump_seed) = Pubkey::find_program_address(
-        &[test.trade_account_keypair.pubkey().as_ref()],
-            &test.program_id,
+    let (pda_pubkey, bump_seed) = instruction::find_trade_pda(&test.program_id, &test.trade_account_keypair.pubkey());
+
+    let mut ix = instruction::make_trade(
+        &test.program_id,
+        &test.wallet2.pubkey(),
+        &trade_fill_accounts(test),
+        offer_amount,
+        trade_amount,
+        None,
     );
+    if let Some(list) = accounts {
+        ix.accounts = list;
+    }
 
-    let action = Action::MakeTrade {
-        expected_offer: offer_amount,
-        expected_trade: trade_amount,
-    };
-    let buf = &action.try_to_vec().unwrap()[..];
-
-    let accounts_list = if let Some(list) = accounts {
-        list
-    } else {
-        vec![
-            AccountMeta::new_readonly(test.wallet2.pubkey(), true),
-            AccountMeta::new(test.trade_account_keypair.pubkey(), false),
-            AccountMeta::new_readonly(pda_pubkey, false),
-            AccountMeta::new(test.offer_src, false),
-            AccountMeta::new(test.trade_dst, false),
-            AccountMeta::new(test.trade_src, false),
-            AccountMeta::new(test.offer_dst, false),
-            AccountMeta::new(test.payer.pubkey(), false),
-            AccountMeta::new(test.fee_ata, false),
-            AccountMeta::new_readonly(spl_token::id(), false),
-        ]
+    (ix, pda_pubkey, bump_seed)
+}
+
+// the accounts of the trade of test, as expected by MakeTrade and MakeTrades
+pub fn trade_fill_accounts(test: &TestData) -> TradeFillAccounts {
+    TradeFillAccounts {
+        trade: test.trade_account_keypair.pubkey(),
+        offer_src: test.offer_src,
+        trade_dst: test.trade_dst,
+        trade_src: test.trade_src,
+        offer_dst: test.offer_dst,
+        offer_owner: test.payer.pubkey(),
+        fee_account: test.fee_ata,
+        price_feed: None,
+        split_dsts: vec![],
+        arbiter: None,
+        hook: None,
+        trade_escrow: None,
+        vesting_escrow: None,
+        receipt: None,
+        mints: Some((test.offer_mint, test.trade_mint)),
+    }
+}
+
+pub fn make_trades_ix(
+    program_id: Pubkey,
+    taker: Pubkey,
+    fills: Vec<(u64, u64, TradeFillAccounts)>,
+) -> Instruction {
+    let fills: Vec<(TradeFill, TradeFillAccounts)> = fills.into_iter()
+        .map(|(offer, trade, accounts)| {
+            (TradeFill { expected_offer: offer, expected_trade: trade, allowlist: None }, accounts)
+        })
+        .collect();
+
+    instruction::make_trades(&program_id, &taker, &fills)
+}
+
+// Creates every account trailing the fills of the trades of tests, so they are all loaded and updated: the mint list
+// allowing their mints, the limits of those mints, and the stats of the program, of the mints and of their maker
+pub async fn init_trailing_accounts(tests: &[TestData]) {
+    let test = &tests[0];
+    let authority = test.wallet2.pubkey();
+    let mints: Vec<Pubkey> = tests.iter().flat_map(|test| [test.offer_mint, test.trade_mint]).collect();
+    let limits = state::MintLimits {
+        max_amount: spl_token::ui_amount_to_amount(100.0, 9),
+        volume_cap: spl_token::ui_amount_to_amount(1000.0, 9),
+        window: 3600,
+        ..state::MintLimits::default()
     };
-    let ix = Instruction::new_with_bytes(test.program_id, buf, accounts_list);
 
-    (ix, pda_pubkey, bump_seed)
+    let mut ixs = vec![instruction::set_mint_list_mode(&test.program_id, &authority, state::MintListMode::Allowlist)];
+    for mint in &mints {
+        ixs.push(instruction::add_mint(&test.program_id, &authority, mint));
+        ixs.push(instruction::set_mint_limits(&test.program_id, &authority, mint, limits));
+    }
+    for ix in ixs {
+        process_ix(&vec![&test.payer, &test.wallet2], test.payer.pubkey(), ix, &test.conn).await.unwrap();
+    }
+
+    let mut stats = vec![state::StatsAccount::Program, state::StatsAccount::Maker(test.payer.pubkey())];
+    stats.extend(mints.iter().map(|mint| state::StatsAccount::Mint(*mint)));
+    for stats in stats {
+        let ix = instruction::init_stats(&test.program_id, &test.payer.pubkey(), stats);
+        process_ix(&vec![&test.payer], test.payer.pubkey(), ix, &test.conn).await.unwrap();
+    }
 }
 
 pub struct TestData {
@@ -266,7 +370,7 @@ impl TestData {
         let ix = system_instruction::create_account(&payer.pubkey(), &kp.pubkey(), r, size, &authority);
         let message = Message::new(&[ix], Some(&payer.pubkey()));
         let transaction = Transaction::new(
-            &[&payer, &kp],
+            &[payer, &kp],
             message, conn.borrow_mut().get_latest_blockhash().await.unwrap(),
         );
         conn.borrow_mut().process_transaction(transaction).await.unwrap();
@@ -295,6 +399,12 @@ impl TestData {
 
         // User B
         let wallet2 = get_wallet("../wallet0.json".into()).unwrap();
+        // also the program authority, which pays for the accounts it creates, e.g. the mint list
+        if conn.borrow_mut().get_balance(wallet2.pubkey()).await.unwrap() == 0 {
+            let sol: u64 = 1_000_000_000;
+            let ix = system_instruction::transfer(&payer.pubkey(), &wallet2.pubkey(), sol);
+            process_ix(&vec![&payer], payer.pubkey(), ix, &conn).await.unwrap();
+        }
 
         let offer_mint = mint_account(&payer, &payer, recent_blockhash, &conn).await;
         let trade_mint = mint_account(&wallet2, &payer, recent_blockhash, &conn).await;
@@ -349,15 +459,55 @@ impl TestData {
     }
 
     pub async fn init() -> TestData {
+        TestData::init_with_accounts(vec![]).await
+    }
+
+    // Same as init, with extra accounts already in place when the test starts, e.g. price accounts
+    pub async fn init_with_accounts(accounts: Vec<(Pubkey, Account)>) -> TestData {
+        TestData::init_with(|program_test| {
+            for (pubkey, account) in accounts {
+                program_test.add_account(pubkey, account);
+            }
+        }).await
+    }
+
+    // Same as init, with setup adding whatever else the test needs before it starts, e.g. other programs
+    pub async fn init_with(setup: impl FnOnce(&mut ProgramTest)) -> TestData {
+        let program_id = Pubkey::new_unique();
+        let mut program_test = TestData::program_test(program_id);
+        setup(&mut program_test);
+        let (conn, payer, recent_blockhash) = program_test.start().await;
+
+        TestData::init_with_conn(RefCell::new(conn), program_id, payer, recent_blockhash).await
+    }
+
+    // Same as init, keeping the context of the test around to move the clock forward with warp_clock_to
+    pub async fn init_with_context() -> (TestData, ProgramTestContext) {
         let program_id = Pubkey::new_unique();
-        let (conn, payer, recent_blockhash) = ProgramTest::new(
-            "trader_program",
+        let context = TestData::program_test(program_id).start_with_context().await;
+        let payer = Keypair::from_bytes(&context.payer.to_bytes()).unwrap();
+        let conn = RefCell::new(context.banks_client.clone());
+
+        let test = TestData::init_with_conn(conn, program_id, payer, context.last_blockhash).await;
+        (test, context)
+    }
+
+    // Same as init, for another trade with mints of its own, on the bank and with the payer of test
+    pub async fn init_alongside(test: &TestData) -> TestData {
+        let conn = RefCell::new(test.conn.borrow().clone());
+        let payer = Keypair::from_bytes(&test.payer.to_bytes()).unwrap();
+        let recent_blockhash = test.conn.borrow_mut().get_latest_blockhash().await.unwrap();
+
+        TestData::init_with_conn(conn, test.program_id, payer, recent_blockhash).await
+    }
+
+    fn program_test(program_id: Pubkey) -> ProgramTest {
+        // the program is named after the shared object cargo build-bpf builds, which cargo test-bpf runs instead of
+        // the processor
+        ProgramTest::new(
+            "trader",
             program_id,
             processor!(trader::process_instruction),
         )
-        .start()
-        .await;
-
-        TestData::init_with_conn(RefCell::new(conn), program_id, payer, recent_blockhash).await
     }
 }
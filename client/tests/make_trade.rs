@@ -9,6 +9,7 @@ use {
         signature::Signer, 
     },
 };
+use ::trader::{instruction, state::StatsAccount};
 
 #[tokio::test]
 async fn test_make_trade() {
@@ -23,7 +24,7 @@ async fn test_make_trade() {
 
     // trade fee transfered?
     let fee_account = get_spl_account(test.fee_ata, &test.conn).await;
-    let trade_fee = 100000000 as u64;
+    let trade_fee: u64 = 100000000;
     assert_eq!(fee_account.amount, trade_fee);
 
     let offer_src_account = get_spl_account(test.offer_src, &test.conn).await;
@@ -112,11 +113,11 @@ async fn test_make_trade_checks_trade_given_trade_dst_is_correct() {
     create_test_trade(&test).await;
 
     let fake_trade_dst = Pubkey::new_unique();
-    let fake_pda = Pubkey::new_unique();
+    let (pda, _) = instruction::find_trade_pda(&test.program_id, &test.trade_account_keypair.pubkey());
     let accounts = vec![
         AccountMeta::new_readonly(test.wallet2.pubkey(), true),
         AccountMeta::new(test.trade_account_keypair.pubkey(), false),
-        AccountMeta::new_readonly(fake_pda, false),
+        AccountMeta::new_readonly(pda, false),
         AccountMeta::new(test.offer_src, false),
         AccountMeta::new(fake_trade_dst, false),
         AccountMeta::new(test.trade_src, false),
@@ -140,11 +141,11 @@ async fn test_make_trade_checks_trade_mint() {
     let recent_blockhash = test.conn.borrow_mut().get_latest_blockhash().await.unwrap();
     let fake_trade_mint = mint_account(&test.wallet2, &test.payer, recent_blockhash, &test.conn).await;
     let fake_trade_src = token_account(&test.wallet2, &test.payer, fake_trade_mint, recent_blockhash, &test.conn).await;
-    let fake_pda = Pubkey::new_unique();
+    let (pda, _) = instruction::find_trade_pda(&test.program_id, &test.trade_account_keypair.pubkey());
     let accounts = vec![
         AccountMeta::new_readonly(test.wallet2.pubkey(), true),
         AccountMeta::new(test.trade_account_keypair.pubkey(), false),
-        AccountMeta::new_readonly(fake_pda, false),
+        AccountMeta::new_readonly(pda, false),
         AccountMeta::new(test.offer_src, false),
         AccountMeta::new(test.trade_dst, false),
         AccountMeta::new(fake_trade_src, false),
@@ -166,11 +167,11 @@ async fn test_make_trade_checks_offer_src_match() {
     create_test_trade(&test).await;
 
     let fake_offer_src = Pubkey::new_unique();
-    let fake_pda = Pubkey::new_unique();
+    let (pda, _) = instruction::find_trade_pda(&test.program_id, &test.trade_account_keypair.pubkey());
     let accounts = vec![
         AccountMeta::new_readonly(test.wallet2.pubkey(), true),
         AccountMeta::new(test.trade_account_keypair.pubkey(), false),
-        AccountMeta::new_readonly(fake_pda, false),
+        AccountMeta::new_readonly(pda, false),
         AccountMeta::new(fake_offer_src, false),
         AccountMeta::new(test.trade_dst, false),
         AccountMeta::new(test.trade_src, false),
@@ -217,11 +218,11 @@ async fn test_make_trade_checks_fee_account_dst() {
     create_test_trade(&test).await;
 
     let fake_fee_ata = Pubkey::new_unique();
-    let fake_pda = Pubkey::new_unique();
+    let (pda, _) = instruction::find_trade_pda(&test.program_id, &test.trade_account_keypair.pubkey());
     let accounts = vec![
         AccountMeta::new_readonly(test.wallet2.pubkey(), true),
         AccountMeta::new(test.trade_account_keypair.pubkey(), false),
-        AccountMeta::new_readonly(fake_pda, false),
+        AccountMeta::new_readonly(pda, false),
         AccountMeta::new(test.offer_src, false),
         AccountMeta::new(test.trade_dst, false),
         AccountMeta::new(test.trade_src, false),
@@ -229,6 +230,12 @@ async fn test_make_trade_checks_fee_account_dst() {
         AccountMeta::new(test.payer.pubkey(), false),
         AccountMeta::new(fake_fee_ata, false),
         AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new(instruction::find_mint_limits(&test.program_id, &test.offer_mint).0, false),
+        AccountMeta::new(instruction::find_mint_limits(&test.program_id, &test.trade_mint).0, false),
+        AccountMeta::new(instruction::find_stats(&test.program_id, &StatsAccount::Program).0, false),
+        AccountMeta::new(instruction::find_stats(&test.program_id, &StatsAccount::Mint(test.offer_mint)).0, false),
+        AccountMeta::new(instruction::find_stats(&test.program_id, &StatsAccount::Mint(test.trade_mint)).0, false),
+        AccountMeta::new(instruction::find_stats(&test.program_id, &StatsAccount::Maker(test.payer.pubkey())).0, false),
     ];
     let offer: u64 = spl_token::ui_amount_to_amount(10.0, 9);
     let trade: u64 = spl_token::ui_amount_to_amount(2.0, 9);
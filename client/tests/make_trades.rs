@@ -0,0 +1,220 @@
+mod lib;
+
+use {
+    borsh::BorshDeserialize,
+    lib::*,
+    solana_program_test::*,
+    solana_sdk::{
+        instruction::AccountMeta,
+        message::Message,
+        packet::PACKET_DATA_SIZE,
+        pubkey::Pubkey,
+        signature::Signer,
+    },
+};
+use ::trader::{
+    instruction::{self, TradeFillAccounts},
+    instructions::{Action, TradeFill},
+    state::{AccountStats, StatsAccount},
+};
+use trader_client::client::{pack_make_trades, MAKE_TRADE_COMPUTE_UNITS, MAX_COMPUTE_UNITS};
+
+#[tokio::test]
+async fn test_make_trades() {
+    let test1 = TestData::init().await;
+    create_test_trade(&test1).await;
+    let accounts1 = trade_fill_accounts(&test1);
+
+    let recent_blockhash = test1.conn.borrow_mut().get_latest_blockhash().await.unwrap();
+    let test2 = TestData::init_with_conn(test1.conn, test1.program_id, test1.payer, recent_blockhash).await;
+    create_test_trade(&test2).await;
+    let accounts2 = trade_fill_accounts(&test2);
+
+    let offer_amount: u64 = spl_token::ui_amount_to_amount(10.0, 9);
+    let trade_amount: u64 = spl_token::ui_amount_to_amount(2.0, 9);
+    let ix = make_trades_ix(
+        test2.program_id,
+        test2.wallet2.pubkey(),
+        vec![(offer_amount, trade_amount, accounts1), (offer_amount, trade_amount, accounts2)],
+    );
+    process_ix(&vec![&test2.payer, &test2.wallet2], test2.payer.pubkey(), ix, &test2.conn).await.unwrap();
+
+    let trade_fee: u64 = 100000000;
+    for (offer_dst, trade_dst, fee_ata) in [
+        (test1.offer_dst, test1.trade_dst, test1.fee_ata),
+        (test2.offer_dst, test2.trade_dst, test2.fee_ata),
+    ] {
+        let offer_dst_account = get_spl_account(offer_dst, &test2.conn).await;
+        assert_eq!(offer_dst_account.amount, offer_amount);
+
+        let trade_dst_account = get_spl_account(trade_dst, &test2.conn).await;
+        assert_eq!(trade_dst_account.amount, trade_amount - trade_fee);
+
+        let fee_account = get_spl_account(fee_ata, &test2.conn).await;
+        assert_eq!(fee_account.amount, trade_fee);
+    }
+
+    for trade in [test1.trade_account_keypair.pubkey(), test2.trade_account_keypair.pubkey()] {
+        let trade_account_ai = test2.conn.borrow_mut().get_account(trade).await.unwrap();
+        assert_eq!(trade_account_ai, None);
+    }
+}
+
+#[tokio::test]
+async fn test_make_trades_is_atomic() {
+    let test1 = TestData::init().await;
+    create_test_trade(&test1).await;
+    let accounts1 = trade_fill_accounts(&test1);
+
+    let recent_blockhash = test1.conn.borrow_mut().get_latest_blockhash().await.unwrap();
+    let test2 = TestData::init_with_conn(test1.conn, test1.program_id, test1.payer, recent_blockhash).await;
+    create_test_trade(&test2).await;
+    let accounts2 = trade_fill_accounts(&test2);
+
+    // the second trade has the wrong offer amount, so the first must not be filled either
+    let offer_amount: u64 = spl_token::ui_amount_to_amount(10.0, 9);
+    let trade_amount: u64 = spl_token::ui_amount_to_amount(2.0, 9);
+    let ix = make_trades_ix(
+        test2.program_id,
+        test2.wallet2.pubkey(),
+        vec![(offer_amount, trade_amount, accounts1), (20, trade_amount, accounts2)],
+    );
+    let err = process_ix(&vec![&test2.payer, &test2.wallet2], test2.payer.pubkey(), ix, &test2.conn).await.unwrap_err();
+    assert!(err.to_string().contains("custom program error: 0x2"));
+
+    let offer_dst_account = get_spl_account(test1.offer_dst, &test2.conn).await;
+    assert_eq!(offer_dst_account.amount, 0);
+
+    let trade_account_ai = test2.conn.borrow_mut().get_account(test1.trade_account_keypair.pubkey()).await.unwrap();
+    assert!(trade_account_ai.is_some());
+}
+
+#[tokio::test]
+async fn test_make_trades_rejects_same_trade_twice() {
+    let test = TestData::init().await;
+    create_test_trade(&test).await;
+
+    let accounts = trade_fill_accounts(&test);
+
+    let offer_amount: u64 = spl_token::ui_amount_to_amount(10.0, 9);
+    let trade_amount: u64 = spl_token::ui_amount_to_amount(2.0, 9);
+    let ix = make_trades_ix(
+        test.program_id,
+        test.wallet2.pubkey(),
+        vec![(offer_amount, trade_amount, accounts.clone()), (offer_amount, trade_amount, accounts)],
+    );
+    let result = process_ix(&vec![&test.payer, &test.wallet2], test.payer.pubkey(), ix, &test.conn).await;
+    assert!(result.is_err());
+
+    let trade_account_ai = test.conn.borrow_mut().get_account(test.trade_account_keypair.pubkey()).await.unwrap();
+    assert!(trade_account_ai.is_some());
+}
+
+#[tokio::test]
+#[should_panic(expected = "transport transaction error: Error processing Instruction 0: custom program error: 0x8")]
+async fn test_make_trades_rejects_extra_accounts() {
+    let test = TestData::init().await;
+    create_test_trade(&test).await;
+
+    let offer_amount: u64 = spl_token::ui_amount_to_amount(10.0, 9);
+    let trade_amount: u64 = spl_token::ui_amount_to_amount(2.0, 9);
+    let mut ix = make_trades_ix(
+        test.program_id,
+        test.wallet2.pubkey(),
+        vec![(offer_amount, trade_amount, trade_fill_accounts(&test))],
+    );
+    ix.accounts.push(AccountMeta::new(Pubkey::new_unique(), false));
+    let panic_on = process_ix(&vec![&test.payer, &test.wallet2], test.payer.pubkey(), ix, &test.conn).await.unwrap_err();
+    panic!("{}", panic_on.to_string());
+}
+
+#[tokio::test]
+async fn test_make_trades_full_batch() {
+    // as many trades as fit in a batch by compute units, each with every trailing account to load and update
+    let count = (MAX_COMPUTE_UNITS / MAKE_TRADE_COMPUTE_UNITS) as usize;
+    let mut tests = vec![TestData::init().await];
+    while tests.len() < count {
+        let test = TestData::init_alongside(&tests[0]).await;
+        tests.push(test);
+    }
+    init_trailing_accounts(&tests).await;
+    for test in &tests {
+        create_test_trade(test).await;
+    }
+
+    let test = &tests[0];
+    let offer_amount: u64 = spl_token::ui_amount_to_amount(10.0, 9);
+    let trade_amount: u64 = spl_token::ui_amount_to_amount(2.0, 9);
+    let fills: Vec<(TradeFill, TradeFillAccounts)> = tests.iter()
+        .map(|test| {
+            let fill = TradeFill { expected_offer: offer_amount, expected_trade: trade_amount, allowlist: None };
+            (fill, trade_fill_accounts(test))
+        })
+        .collect();
+    // with all their trailing accounts the trades don't all fit in a single transaction, but some still share one
+    let ixs = pack_make_trades(&test.wallet2.pubkey(), &test.program_id, &fills);
+    assert!(ixs.len() < count);
+
+    // the taker pays for the transactions, the same as in the client
+    for ix in ixs {
+        process_ix(&vec![&test.wallet2], test.wallet2.pubkey(), ix, &test.conn).await.unwrap();
+    }
+
+    for test in &tests {
+        let offer_dst_account = get_spl_account(test.offer_dst, &test.conn).await;
+        assert_eq!(offer_dst_account.amount, offer_amount);
+    }
+    let (stats, _) = instruction::find_stats(&test.program_id, &StatsAccount::Program);
+    let stats_ai = test.conn.borrow_mut().get_account(stats).await.unwrap().unwrap();
+    assert_eq!(AccountStats::try_from_slice(&stats_ai.data).unwrap().trades, count as u64);
+}
+
+#[test]
+fn test_pack_make_trades() {
+    let program_id = Pubkey::new_unique();
+    let taker = Pubkey::new_unique();
+
+    let fills: Vec<(TradeFill, TradeFillAccounts)> = (0..20u64)
+        .map(|i| {
+            let fill = TradeFill { expected_offer: i, expected_trade: i, allowlist: None };
+            let accounts = TradeFillAccounts {
+                trade: Pubkey::new_unique(),
+                offer_src: Pubkey::new_unique(),
+                trade_dst: Pubkey::new_unique(),
+                trade_src: Pubkey::new_unique(),
+                offer_dst: Pubkey::new_unique(),
+                offer_owner: Pubkey::new_unique(),
+                fee_account: Pubkey::new_unique(),
+                price_feed: None,
+                split_dsts: vec![],
+                arbiter: None,
+                hook: None,
+                trade_escrow: None,
+                vesting_escrow: None,
+                receipt: None,
+                mints: None,
+            };
+            (fill, accounts)
+        })
+        .collect();
+
+    let ixs = pack_make_trades(&taker, &program_id, &fills);
+    assert!(ixs.len() > 1);
+
+    let mut packed = Vec::<u64>::new();
+    for ix in ixs {
+        let message = Message::new(std::slice::from_ref(&ix), Some(&taker));
+        assert!(1 + 64 + message.serialize().len() <= PACKET_DATA_SIZE);
+
+        match Action::try_from_slice(&ix.data).unwrap() {
+            Action::MakeTrades { trades } => {
+                assert_eq!(ix.accounts.len(), 2 + 8 * trades.len());
+                packed.extend(trades.iter().map(|fill| fill.expected_offer));
+            },
+            action => panic!("unexpected action {:?}", action),
+        }
+    }
+
+    // every trade is packed, in order
+    assert_eq!(packed, (0..20u64).collect::<Vec<u64>>());
+}
@@ -0,0 +1,162 @@
+mod lib;
+
+use {
+    borsh::BorshDeserialize,
+    lib::*,
+    solana_program_test::*,
+    solana_sdk::{
+        instruction::Instruction,
+        pubkey::Pubkey,
+        signature::Signer,
+        system_instruction,
+        sysvar::clock::Clock,
+    },
+};
+use ::trader::{
+    instruction,
+    state::{AccountMintLimits, MintLimits},
+};
+
+// Sets the limits of mint, signed by wallet2, the program authority in tests
+async fn set_limits(test: &TestData, mint: &Pubkey, limits: MintLimits) {
+    let ix = instruction::set_mint_limits(&test.program_id, &test.wallet2.pubkey(), mint, limits);
+    process_ix(&vec![&test.payer, &test.wallet2], test.payer.pubkey(), ix, &test.conn).await.unwrap();
+}
+
+async fn load_limits(test: &TestData, mint: &Pubkey) -> AccountMintLimits {
+    let (mint_limits, _) = instruction::find_mint_limits(&test.program_id, mint);
+    let mint_limits_ai = test.conn.borrow_mut().get_account(mint_limits).await.unwrap().unwrap();
+    AccountMintLimits::try_from_slice(&mint_limits_ai.data).unwrap()
+}
+
+fn create_ix(test: &TestData) -> Instruction {
+    let trade_amount: u64 = spl_token::ui_amount_to_amount(2.0, 9);
+    let (ix, _, _) = init_trade_ix(test, trade_amount, None);
+
+    ix
+}
+
+fn fill_ix(test: &TestData) -> Instruction {
+    let offer_amount: u64 = spl_token::ui_amount_to_amount(10.0, 9);
+    let trade_amount: u64 = spl_token::ui_amount_to_amount(2.0, 9);
+    let (ix, _, _) = make_trade_ix(test, offer_amount, trade_amount, None);
+
+    ix
+}
+
+#[tokio::test]
+#[should_panic(expected = "transport transaction error: Error processing Instruction 0: custom program error: 0x2e")]
+async fn test_create_trade_over_max_offer() {
+    let test = TestData::init().await;
+    let limits = MintLimits { max_amount: spl_token::ui_amount_to_amount(5.0, 9), ..MintLimits::default() };
+    set_limits(&test, &test.offer_mint, limits).await;
+
+    let panic_on = process_ix(&vec![&test.payer], test.payer.pubkey(), create_ix(&test), &test.conn).await.unwrap_err();
+    panic!("{}", panic_on.to_string());
+}
+
+#[tokio::test]
+#[should_panic(expected = "transport transaction error: Error processing Instruction 0: custom program error: 0x2e")]
+async fn test_create_trade_under_min_trade() {
+    let test = TestData::init().await;
+    let limits = MintLimits { min_amount: spl_token::ui_amount_to_amount(3.0, 9), ..MintLimits::default() };
+    set_limits(&test, &test.trade_mint, limits).await;
+
+    let panic_on = process_ix(&vec![&test.payer], test.payer.pubkey(), create_ix(&test), &test.conn).await.unwrap_err();
+    panic!("{}", panic_on.to_string());
+}
+
+#[tokio::test]
+async fn test_make_trade_within_limits() {
+    let test = TestData::init().await;
+    let limits = MintLimits {
+        min_amount: 1,
+        max_amount: spl_token::ui_amount_to_amount(10.0, 9),
+        volume_cap: spl_token::ui_amount_to_amount(10.0, 9),
+        window: 3600,
+    };
+    set_limits(&test, &test.offer_mint, limits).await;
+    set_limits(&test, &test.trade_mint, limits).await;
+    create_test_trade(&test).await;
+
+    process_ix(&vec![&test.payer, &test.wallet2], test.payer.pubkey(), fill_ix(&test), &test.conn).await.unwrap();
+
+    // each mint counts its side of the fill
+    let offer_limits = load_limits(&test, &test.offer_mint).await;
+    assert_eq!(offer_limits.mint, test.offer_mint);
+    assert_eq!(offer_limits.limits, limits);
+    assert_eq!(offer_limits.window_volume, spl_token::ui_amount_to_amount(10.0, 9));
+    assert_eq!(load_limits(&test, &test.trade_mint).await.window_volume, spl_token::ui_amount_to_amount(2.0, 9));
+}
+
+#[tokio::test]
+#[should_panic(expected = "transport transaction error: Error processing Instruction 0: custom program error: 0x2f")]
+async fn test_make_trade_over_volume_cap() {
+    let test = TestData::init().await;
+    create_test_trade(&test).await;
+
+    // the cap set after the trade was created still stops it from being filled
+    let volume_cap = spl_token::ui_amount_to_amount(5.0, 9);
+    let limits = MintLimits { volume_cap, window: 3600, ..MintLimits::default() };
+    set_limits(&test, &test.offer_mint, limits).await;
+
+    let ix = fill_ix(&test);
+    let panic_on = process_ix(&vec![&test.payer, &test.wallet2], test.payer.pubkey(), ix, &test.conn).await.unwrap_err();
+    panic!("{}", panic_on.to_string());
+}
+
+#[tokio::test]
+async fn test_volume_cap_window_resets() {
+    let (test, mut context) = TestData::init_with_context().await;
+    let volume_cap = spl_token::ui_amount_to_amount(10.0, 9);
+    let limits = MintLimits { volume_cap, window: 60, ..MintLimits::default() };
+    set_limits(&test, &test.offer_mint, limits).await;
+    create_test_trade(&test).await;
+
+    // the window started when the limits were set, and a new one starts with the first fill after it is over
+    let window_start = load_limits(&test, &test.offer_mint).await.window_start;
+    let now = test.conn.borrow_mut().get_sysvar::<Clock>().await.unwrap().unix_timestamp;
+    warp_clock_to(&mut context, now + 120).await;
+    process_ix(&vec![&test.payer, &test.wallet2], test.payer.pubkey(), fill_ix(&test), &test.conn).await.unwrap();
+
+    let offer_limits = load_limits(&test, &test.offer_mint).await;
+    assert_eq!(offer_limits.window_start, now + 120);
+    assert!(offer_limits.window_start >= window_start + 60);
+    assert_eq!(offer_limits.window_volume, spl_token::ui_amount_to_amount(10.0, 9));
+}
+
+#[tokio::test]
+async fn test_set_limits_of_prefunded_mint() {
+    let test = TestData::init().await;
+
+    // lamports sent to the limits account beforehand, enough for an empty account, don't keep it from being created
+    let (mint_limits, _) = instruction::find_mint_limits(&test.program_id, &test.offer_mint);
+    let ix = system_instruction::transfer(&test.payer.pubkey(), &mint_limits, minimum_balance_rent_exempt(0));
+    process_ix(&vec![&test.payer], test.payer.pubkey(), ix, &test.conn).await.unwrap();
+
+    let limits = MintLimits { max_amount: 5, ..MintLimits::default() };
+    set_limits(&test, &test.offer_mint, limits).await;
+    assert_eq!(load_limits(&test, &test.offer_mint).await.limits, limits);
+}
+
+#[tokio::test]
+#[should_panic(expected = "transport transaction error: Error processing Instruction 0: invalid program argument")]
+async fn test_set_mint_limits_min_over_max() {
+    let test = TestData::init().await;
+
+    let limits = MintLimits { min_amount: 10, max_amount: 5, ..MintLimits::default() };
+    let ix = instruction::set_mint_limits(&test.program_id, &test.wallet2.pubkey(), &test.offer_mint, limits);
+    let panic_on = process_ix(&vec![&test.payer, &test.wallet2], test.payer.pubkey(), ix, &test.conn).await.unwrap_err();
+    panic!("{}", panic_on.to_string());
+}
+
+#[tokio::test]
+#[should_panic(expected = "transport transaction error: Error processing Instruction 0: custom program error: 0x0")]
+async fn test_set_mint_limits_without_program_authority() {
+    let test = TestData::init().await;
+
+    let limits = MintLimits::default();
+    let ix = instruction::set_mint_limits(&test.program_id, &test.payer.pubkey(), &test.offer_mint, limits);
+    let panic_on = process_ix(&vec![&test.payer], test.payer.pubkey(), ix, &test.conn).await.unwrap_err();
+    panic!("{}", panic_on.to_string());
+}
@@ -0,0 +1,154 @@
+mod lib;
+
+use {
+    borsh::BorshDeserialize,
+    lib::*,
+    solana_program_test::*,
+    solana_sdk::{
+        instruction::Instruction,
+        pubkey::Pubkey,
+        signature::Signer,
+        system_instruction,
+    },
+};
+use ::trader::{
+    instruction,
+    state::{AccountMintList, MintListMode},
+};
+
+// Sets the mode of the mint list and adds mints to it, signed by wallet2, the program authority in tests
+async fn list_mints(test: &TestData, mode: MintListMode, mints: &[Pubkey]) {
+    let mut ixs = vec![instruction::set_mint_list_mode(&test.program_id, &test.wallet2.pubkey(), mode)];
+    ixs.extend(mints.iter().map(|mint| instruction::add_mint(&test.program_id, &test.wallet2.pubkey(), mint)));
+    process_ixs(&vec![&test.payer, &test.wallet2], test.payer.pubkey(), &ixs, &test.conn).await.unwrap();
+}
+
+fn create_ix(test: &TestData) -> Instruction {
+    let trade_amount: u64 = spl_token::ui_amount_to_amount(2.0, 9);
+    let (ix, _, _) = init_trade_ix(test, trade_amount, None);
+
+    ix
+}
+
+#[tokio::test]
+#[should_panic(expected = "transport transaction error: Error processing Instruction 0: custom program error: 0x2d")]
+async fn test_create_trade_blocklisted_offer_mint() {
+    let test = TestData::init().await;
+    list_mints(&test, MintListMode::Blocklist, &[test.offer_mint]).await;
+
+    let panic_on = process_ix(&vec![&test.payer], test.payer.pubkey(), create_ix(&test), &test.conn).await.unwrap_err();
+    panic!("{}", panic_on.to_string());
+}
+
+#[tokio::test]
+#[should_panic(expected = "transport transaction error: Error processing Instruction 0: custom program error: 0x2d")]
+async fn test_create_trade_blocklisted_trade_mint() {
+    let test = TestData::init().await;
+    list_mints(&test, MintListMode::Blocklist, &[test.trade_mint]).await;
+
+    let panic_on = process_ix(&vec![&test.payer], test.payer.pubkey(), create_ix(&test), &test.conn).await.unwrap_err();
+    panic!("{}", panic_on.to_string());
+}
+
+#[tokio::test]
+#[should_panic(expected = "transport transaction error: Error processing Instruction 0: custom program error: 0x10")]
+async fn test_create_trade_with_listed_mint_of_other_mint() {
+    let test = TestData::init().await;
+    list_mints(&test, MintListMode::Allowlist, &[test.trade_mint]).await;
+
+    // the listed mint account of the trade mint doesn't allow the offer mint
+    let (offer_listed, _) = instruction::find_listed_mint(&test.program_id, &test.offer_mint);
+    let (trade_listed, _) = instruction::find_listed_mint(&test.program_id, &test.trade_mint);
+    let mut ix = create_ix(&test);
+    for meta in ix.accounts.iter_mut() {
+        if meta.pubkey == offer_listed {
+            meta.pubkey = trade_listed;
+        } else if meta.pubkey == trade_listed {
+            meta.pubkey = offer_listed;
+        }
+    }
+    let panic_on = process_ix(&vec![&test.payer], test.payer.pubkey(), ix, &test.conn).await.unwrap_err();
+    panic!("{}", panic_on.to_string());
+}
+
+#[tokio::test]
+async fn test_create_trade_allowlisted_mints() {
+    let test = TestData::init().await;
+    list_mints(&test, MintListMode::Allowlist, &[test.offer_mint]).await;
+
+    // both mints must be listed
+    let err = process_ix(&vec![&test.payer], test.payer.pubkey(), create_ix(&test), &test.conn).await.unwrap_err();
+    assert!(err.to_string().ends_with("custom program error: 0x2d"));
+
+    let ix = instruction::add_mint(&test.program_id, &test.wallet2.pubkey(), &test.trade_mint);
+    process_ix(&vec![&test.payer, &test.wallet2], test.payer.pubkey(), ix, &test.conn).await.unwrap();
+    // paid for by wallet2 this time, so it isn't the same transaction
+    process_ix(&vec![&test.payer, &test.wallet2], test.wallet2.pubkey(), create_ix(&test), &test.conn).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_remove_blocklisted_mint() {
+    let test = TestData::init().await;
+    list_mints(&test, MintListMode::Blocklist, &[test.offer_mint]).await;
+    let (mint_list, _) = instruction::find_mint_list(&test.program_id);
+    let mint_list_ai = test.conn.borrow_mut().get_account(mint_list).await.unwrap().unwrap();
+    assert_eq!(AccountMintList::try_from_slice(&mint_list_ai.data).unwrap().listed, 1);
+
+    let ix = instruction::remove_mint(&test.program_id, &test.wallet2.pubkey(), &test.offer_mint);
+    process_ix(&vec![&test.payer, &test.wallet2], test.payer.pubkey(), ix, &test.conn).await.unwrap();
+    let (listed_mint, _) = instruction::find_listed_mint(&test.program_id, &test.offer_mint);
+    assert_eq!(test.conn.borrow_mut().get_account(listed_mint).await.unwrap(), None);
+    let mint_list_ai = test.conn.borrow_mut().get_account(mint_list).await.unwrap().unwrap();
+    assert_eq!(AccountMintList::try_from_slice(&mint_list_ai.data).unwrap().listed, 0);
+
+    process_ix(&vec![&test.payer], test.payer.pubkey(), create_ix(&test), &test.conn).await.unwrap();
+
+    // an empty list can change mode
+    list_mints(&test, MintListMode::Allowlist, &[]).await;
+    let mint_list_ai = test.conn.borrow_mut().get_account(mint_list).await.unwrap().unwrap();
+    assert_eq!(AccountMintList::try_from_slice(&mint_list_ai.data).unwrap().mode, MintListMode::Allowlist);
+}
+
+#[tokio::test]
+#[should_panic(expected = "transport transaction error: Error processing Instruction 0: custom program error: 0x30")]
+async fn test_switch_mode_of_listed_mints() {
+    let test = TestData::init().await;
+    list_mints(&test, MintListMode::Blocklist, &[test.offer_mint]).await;
+
+    // the blocked mint would become the only one allowed
+    let ix = instruction::set_mint_list_mode(&test.program_id, &test.wallet2.pubkey(), MintListMode::Allowlist);
+    let panic_on = process_ix(&vec![&test.payer, &test.wallet2], test.payer.pubkey(), ix, &test.conn).await.unwrap_err();
+    panic!("{}", panic_on.to_string());
+}
+
+#[tokio::test]
+async fn test_list_prefunded_mint() {
+    let test = TestData::init().await;
+
+    // lamports sent to the accounts of the list beforehand, enough for empty accounts, don't keep it from being created
+    let (mint_list, _) = instruction::find_mint_list(&test.program_id);
+    let (listed_mint, _) = instruction::find_listed_mint(&test.program_id, &test.offer_mint);
+    let ixs = [
+        system_instruction::transfer(&test.payer.pubkey(), &mint_list, minimum_balance_rent_exempt(0)),
+        system_instruction::transfer(&test.payer.pubkey(), &listed_mint, minimum_balance_rent_exempt(0)),
+    ];
+    process_ixs(&vec![&test.payer], test.payer.pubkey(), &ixs, &test.conn).await.unwrap();
+
+    list_mints(&test, MintListMode::Blocklist, &[test.offer_mint]).await;
+    let listed_mint_ai = test.conn.borrow_mut().get_account(listed_mint).await.unwrap().unwrap();
+    assert_eq!(listed_mint_ai.owner, test.program_id);
+    assert_eq!(listed_mint_ai.lamports, minimum_balance_rent_exempt(listed_mint_ai.data.len()));
+
+    let err = process_ix(&vec![&test.payer], test.payer.pubkey(), create_ix(&test), &test.conn).await.unwrap_err();
+    assert!(err.to_string().ends_with("custom program error: 0x2d"));
+}
+
+#[tokio::test]
+#[should_panic(expected = "transport transaction error: Error processing Instruction 0: custom program error: 0x0")]
+async fn test_add_mint_without_program_authority() {
+    let test = TestData::init().await;
+
+    let ix = instruction::add_mint(&test.program_id, &test.payer.pubkey(), &test.offer_mint);
+    let panic_on = process_ix(&vec![&test.payer], test.payer.pubkey(), ix, &test.conn).await.unwrap_err();
+    panic!("{}", panic_on.to_string());
+}
@@ -0,0 +1,179 @@
+mod lib;
+
+use {
+    borsh::BorshSerialize,
+    lib::*,
+    solana_program_test::*,
+    solana_sdk::{
+        account::Account,
+        pubkey::Pubkey,
+        signature::Signer,
+    },
+    std::time::{SystemTime, UNIX_EPOCH},
+};
+use ::trader::{
+    instruction::{self, CreateTradeArgs, TradeFillAccounts},
+    instructions::OraclePeg,
+    state::PriceFeed,
+};
+
+// A price account as read by the program, owned by a stand-in oracle program
+fn price_account(price: i64, expo: i32, publish_time: i64) -> Account {
+    Account {
+        lamports: minimum_balance_rent_exempt(64),
+        data: PriceFeed { price, expo, publish_time }.try_to_vec().unwrap(),
+        owner: Pubkey::new_unique(),
+        executable: false,
+        rent_epoch: 0,
+    }
+}
+
+// the clock of the test validator starts at the time it's created
+fn now() -> i64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() as i64
+}
+
+// Creates the trade of test pegged to price_feed, with a spread of 1% and a minimum of 2 trade tokens
+async fn create_pegged_trade(test: &TestData, price_feed: Pubkey) {
+    let args = CreateTradeArgs {
+        peg: Some((price_feed, OraclePeg { spread_bps: 100, max_price_age: 60 })),
+        ..CreateTradeArgs::default()
+    };
+    create_trade_with(test, args).await.unwrap();
+}
+
+async fn make_pegged_trade(test: &TestData, price_feed: Pubkey, max_trade: u64) -> Result<(), BanksClientError> {
+    let offer_amount: u64 = spl_token::ui_amount_to_amount(10.0, 9);
+    let accounts = TradeFillAccounts { price_feed: Some(price_feed), ..trade_fill_accounts(test) };
+    let ix = instruction::make_trade(
+        &test.program_id,
+        &test.wallet2.pubkey(),
+        &accounts,
+        offer_amount,
+        max_trade,
+        None,
+    );
+
+    process_ix(&vec![&test.payer, &test.wallet2], test.payer.pubkey(), ix, &test.conn).await
+}
+
+#[tokio::test]
+async fn test_make_pegged_trade() {
+    let price_feed = Pubkey::new_unique();
+    let test = TestData::init_with_accounts(vec![(price_feed, price_account(25, -2, now()))]).await;
+    create_pegged_trade(&test, price_feed).await;
+
+    let max_trade: u64 = spl_token::ui_amount_to_amount(3.0, 9);
+    make_pegged_trade(&test, price_feed, max_trade).await.unwrap();
+
+    // 10 offer tokens at 0.25, plus 1%
+    let trade_amount: u64 = 2_525_000_000;
+    let trade_fee: u64 = 100000000;
+    let trade_dst_account = get_spl_account(test.trade_dst, &test.conn).await;
+    assert_eq!(trade_dst_account.amount, trade_amount - trade_fee);
+
+    let trade_src_account = get_spl_account(test.trade_src, &test.conn).await;
+    assert_eq!(trade_src_account.amount, 5_000_000_000 - trade_amount);
+
+    let offer_dst_account = get_spl_account(test.offer_dst, &test.conn).await;
+    assert_eq!(offer_dst_account.amount, spl_token::ui_amount_to_amount(10.0, 9));
+}
+
+#[tokio::test]
+#[should_panic(expected = "transport transaction error: Error processing Instruction 0: custom program error: 0x15")]
+async fn test_make_pegged_trade_stale_price() {
+    let price_feed = Pubkey::new_unique();
+    let test = TestData::init_with_accounts(vec![(price_feed, price_account(25, -2, 0))]).await;
+    create_pegged_trade(&test, price_feed).await;
+
+    let max_trade: u64 = spl_token::ui_amount_to_amount(3.0, 9);
+    let panic_on = make_pegged_trade(&test, price_feed, max_trade).await.unwrap_err();
+    panic!("{}", panic_on.to_string());
+}
+
+#[tokio::test]
+#[should_panic(expected = "transport transaction error: Error processing Instruction 0: custom program error: 0x17")]
+async fn test_make_pegged_trade_below_limit() {
+    let price_feed = Pubkey::new_unique();
+    // 10 offer tokens at 0.10 is below the minimum of 2 trade tokens
+    let test = TestData::init_with_accounts(vec![(price_feed, price_account(10, -2, now()))]).await;
+    create_pegged_trade(&test, price_feed).await;
+
+    let max_trade: u64 = spl_token::ui_amount_to_amount(3.0, 9);
+    let panic_on = make_pegged_trade(&test, price_feed, max_trade).await.unwrap_err();
+    panic!("{}", panic_on.to_string());
+}
+
+#[tokio::test]
+#[should_panic(expected = "transport transaction error: Error processing Instruction 0: custom program error: 0x3")]
+async fn test_make_pegged_trade_above_taker_max() {
+    let price_feed = Pubkey::new_unique();
+    let test = TestData::init_with_accounts(vec![(price_feed, price_account(25, -2, now()))]).await;
+    create_pegged_trade(&test, price_feed).await;
+
+    let max_trade: u64 = spl_token::ui_amount_to_amount(2.5, 9);
+    let panic_on = make_pegged_trade(&test, price_feed, max_trade).await.unwrap_err();
+    panic!("{}", panic_on.to_string());
+}
+
+#[tokio::test]
+#[should_panic(expected = "transport transaction error: Error processing Instruction 0: custom program error: 0x8")]
+async fn test_make_pegged_trade_wrong_price_feed() {
+    let (price_feed, other_feed) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let test = TestData::init_with_accounts(vec![
+        (price_feed, price_account(25, -2, now())),
+        (other_feed, price_account(1, 0, now())),
+    ]).await;
+    create_pegged_trade(&test, price_feed).await;
+
+    let max_trade: u64 = spl_token::ui_amount_to_amount(3.0, 9);
+    let panic_on = make_pegged_trade(&test, other_feed, max_trade).await.unwrap_err();
+    panic!("{}", panic_on.to_string());
+}
+
+#[tokio::test]
+#[should_panic(expected = "transport transaction error: Error processing Instruction 0: custom program error: 0x16")]
+async fn test_make_pegged_trade_future_price() {
+    let price_feed = Pubkey::new_unique();
+    let test = TestData::init_with_accounts(vec![(price_feed, price_account(25, -2, now() + 3600))]).await;
+    create_pegged_trade(&test, price_feed).await;
+
+    let max_trade: u64 = spl_token::ui_amount_to_amount(3.0, 9);
+    let panic_on = make_pegged_trade(&test, price_feed, max_trade).await.unwrap_err();
+    panic!("{}", panic_on.to_string());
+}
+
+#[tokio::test]
+#[should_panic(expected = "transport transaction error: Error processing Instruction 0: custom program error: 0x8")]
+async fn test_make_pegged_trade_price_feed_new_owner() {
+    let price_feed = Pubkey::new_unique();
+    let (test, mut context) = TestData::init_with_context().await;
+    context.set_account(&price_feed, &price_account(25, -2, now()).into());
+    create_pegged_trade(&test, price_feed).await;
+
+    // the same price, published by another program
+    context.set_account(&price_feed, &price_account(25, -2, now()).into());
+    let max_trade: u64 = spl_token::ui_amount_to_amount(3.0, 9);
+    let panic_on = make_pegged_trade(&test, price_feed, max_trade).await.unwrap_err();
+    panic!("{}", panic_on.to_string());
+}
+
+#[test]
+fn test_price_feed_trade_amount() {
+    let feed = PriceFeed { price: 25, expo: -2, publish_time: 0 };
+    assert_eq!(feed.trade_amount(10_000, 0), Some(2_500));
+    assert_eq!(feed.trade_amount(10_000, 100), Some(2_525));
+    // rounded up in favour of the owner of the trade
+    assert_eq!(feed.trade_amount(1, 0), Some(1));
+    assert_eq!(feed.trade_amount(0, 0), Some(0));
+
+    let feed = PriceFeed { price: 3, expo: 2, publish_time: 0 };
+    assert_eq!(feed.trade_amount(7, 0), Some(2_100));
+
+    // overflow of the final amount, and of the exponent
+    assert_eq!(feed.trade_amount(u64::MAX, 0), None);
+    assert_eq!(PriceFeed { price: 1, expo: 100, publish_time: 0 }.trade_amount(1, 0), None);
+
+    assert_eq!(PriceFeed { price: 0, expo: 0, publish_time: 0 }.trade_amount(1, 0), None);
+    assert_eq!(PriceFeed { price: -1, expo: 0, publish_time: 0 }.trade_amount(1, 0), None);
+}
@@ -0,0 +1,58 @@
+use solana_sdk::pubkey::Pubkey;
+use ::trader::state::AccountTrade;
+use trader_client::client::format_quote;
+
+#[test]
+fn test_set_price_reduces_the_fraction() {
+    let mut trade = AccountTrade::default();
+
+    trade.set_price(10_000_000_000, 2_000_000_000);
+    assert_eq!((trade.price_num, trade.price_den), (1, 5));
+
+    trade.set_price(7, 3);
+    assert_eq!((trade.price_num, trade.price_den), (3, 7));
+
+    trade.set_price(10, 0);
+    assert_eq!((trade.price_num, trade.price_den), (0, 1));
+}
+
+#[test]
+fn test_trade_amount_for() {
+    let mut trade = AccountTrade::default();
+    assert_eq!(trade.trade_amount_for(10), None);
+
+    trade.set_price(7, 3);
+    // the amount the price was set from is exact
+    assert_eq!(trade.trade_amount_for(7), Some(3));
+    // rounded up in favour of the owner of the trade
+    assert_eq!(trade.trade_amount_for(1), Some(1));
+    assert_eq!(trade.trade_amount_for(5), Some(3));
+    assert_eq!(trade.trade_amount_for(0), Some(0));
+
+    trade.set_price(1, u64::MAX);
+    assert_eq!(trade.trade_amount_for(1), Some(u64::MAX));
+    assert_eq!(trade.trade_amount_for(2), None);
+}
+
+#[test]
+fn test_quote_in_ui_amounts() {
+    let offer_mint = Pubkey::new_unique();
+    let mut trade = AccountTrade {
+        offer_amount: 10_000_000_000,
+        trade_amount: 2_000_000,
+        trade_mint: Pubkey::new_unique(),
+        offer_decimals: 9,
+        trade_decimals: 6,
+        ..AccountTrade::default()
+    };
+    trade.set_price(trade.offer_amount, trade.trade_amount);
+
+    assert_eq!(trade.ui_price(), 0.2);
+    assert_eq!(
+        format_quote(&trade, &offer_mint),
+        format!(
+            "\t- offer: 10 of {}\n\t- trade: 2 of {}\n\t- price: 0.2 {} per {}",
+            offer_mint, trade.trade_mint, trade.trade_mint, offer_mint,
+        ),
+    );
+}
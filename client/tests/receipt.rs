@@ -0,0 +1,227 @@
+mod lib;
+
+use {
+    lib::*,
+    solana_program_test::*,
+    solana_sdk::{
+        instruction::Instruction,
+        program_option::COption,
+        program_pack::Pack,
+        pubkey::Pubkey,
+        signature::{Keypair, Signer},
+        system_instruction,
+    },
+    spl_token::state::Mint,
+};
+use ::trader::{
+    instruction::{self, CreateTradeArgs, TradeFillAccounts},
+    state::TradeMode,
+};
+
+// Creates a new receipt mint for the trade of test, minted by mint_authority, along with the ATA of the payer for it
+async fn create_receipt_mint(test: &TestData, mint_authority: &Pubkey) -> Pubkey {
+    let mint = Keypair::new();
+    let ixs = [
+        system_instruction::create_account(
+            &test.payer.pubkey(),
+            &mint.pubkey(),
+            minimum_balance_rent_exempt(Mint::LEN),
+            Mint::LEN as u64,
+            &spl_token::id(),
+        ),
+        spl_token::instruction::initialize_mint(&spl_token::id(), &mint.pubkey(), mint_authority, None, 0).unwrap(),
+        spl_associated_token_account::create_associated_token_account(
+            &test.payer.pubkey(),
+            &test.payer.pubkey(),
+            &mint.pubkey(),
+        ),
+    ];
+    process_ixs(&vec![&test.payer, &mint], test.payer.pubkey(), &ixs, &test.conn).await.unwrap();
+
+    mint.pubkey()
+}
+
+// Creates the trade of test in Escrow mode with a receipt, returning the receipt mint
+async fn create_receipt_trade(test: &TestData) -> Pubkey {
+    let (pda, _) = instruction::find_trade_pda(&test.program_id, &test.trade_account_keypair.pubkey());
+    let receipt_mint = create_receipt_mint(test, &pda).await;
+
+    let args = CreateTradeArgs { receipt: Some(receipt_mint), ..CreateTradeArgs::default() };
+    create_trade_with(test, args).await.unwrap();
+
+    receipt_mint
+}
+
+// Transfers the receipt from the payer to the ATA of holder, which approves the pda as its delegate if delegate,
+// returning the ATA of holder for the receipt and for the trade mint
+async fn transfer_receipt(
+    test: &TestData,
+    receipt_mint: &Pubkey,
+    holder: &Keypair,
+    delegate: bool,
+) -> (Pubkey, Pubkey) {
+    let (pda, _) = instruction::find_trade_pda(&test.program_id, &test.trade_account_keypair.pubkey());
+    let receipt_src = spl_associated_token_account::get_associated_token_address(&test.payer.pubkey(), receipt_mint);
+    let receipt_dst = spl_associated_token_account::get_associated_token_address(&holder.pubkey(), receipt_mint);
+    let trade_dst = spl_associated_token_account::get_associated_token_address(&holder.pubkey(), &test.trade_mint);
+
+    let mut ixs = vec![
+        spl_associated_token_account::create_associated_token_account(
+            &test.payer.pubkey(),
+            &holder.pubkey(),
+            receipt_mint,
+        ),
+        spl_associated_token_account::create_associated_token_account(
+            &test.payer.pubkey(),
+            &holder.pubkey(),
+            &test.trade_mint,
+        ),
+        spl_token::instruction::transfer(
+            &spl_token::id(),
+            &receipt_src,
+            &receipt_dst,
+            &test.payer.pubkey(),
+            &[&test.payer.pubkey()],
+            1,
+        ).unwrap(),
+    ];
+    let mut signers = vec![&test.payer];
+    if delegate {
+        ixs.push(spl_token::instruction::approve(
+            &spl_token::id(),
+            &receipt_dst,
+            &pda,
+            &holder.pubkey(),
+            &[&holder.pubkey()],
+            1,
+        ).unwrap());
+        signers.push(holder);
+    }
+    process_ixs(&signers, test.payer.pubkey(), &ixs, &test.conn).await.unwrap();
+
+    (receipt_dst, trade_dst)
+}
+
+fn fill_ix(test: &TestData, trade_dst: Pubkey, receipt: (Pubkey, Pubkey)) -> Instruction {
+    let accounts = TradeFillAccounts { trade_dst, receipt: Some(receipt), ..trade_fill_accounts(test) };
+    let offer_amount: u64 = spl_token::ui_amount_to_amount(10.0, 9);
+    let trade_amount: u64 = spl_token::ui_amount_to_amount(2.0, 9);
+    instruction::make_trade(&test.program_id, &test.wallet2.pubkey(), &accounts, offer_amount, trade_amount, None)
+}
+
+#[tokio::test]
+async fn test_make_receipt_trade() {
+    let test = TestData::init().await;
+    let receipt_mint = create_receipt_trade(&test).await;
+
+    // the one receipt there will ever be is minted to the owner, and delegated to the pda
+    let (pda, _) = instruction::find_trade_pda(&test.program_id, &test.trade_account_keypair.pubkey());
+    let owner_receipt = spl_associated_token_account::get_associated_token_address(&test.payer.pubkey(), &receipt_mint);
+    let receipt_account = get_spl_account(owner_receipt, &test.conn).await;
+    assert_eq!(receipt_account.amount, 1);
+    assert_eq!(receipt_account.delegate, COption::Some(pda));
+    let mint_ai = test.conn.borrow_mut().get_account(receipt_mint).await.unwrap().unwrap();
+    let mint = Mint::unpack(&mint_ai.data).unwrap();
+    assert_eq!(mint.mint_authority, COption::None);
+    assert_eq!(mint.supply, 1);
+
+    // sold to another wallet, which gets the trade amount instead of the trade dst
+    let holder = Keypair::new();
+    let (receipt, trade_dst) = transfer_receipt(&test, &receipt_mint, &holder, true).await;
+    let ix = fill_ix(&test, trade_dst, (receipt_mint, receipt));
+    process_ix(&vec![&test.payer, &test.wallet2], test.payer.pubkey(), ix, &test.conn).await.unwrap();
+
+    let offer_amount: u64 = spl_token::ui_amount_to_amount(10.0, 9);
+    assert_eq!(get_spl_account(test.offer_dst, &test.conn).await.amount, offer_amount);
+    assert!(get_spl_account(trade_dst, &test.conn).await.amount > 0);
+    assert_eq!(get_spl_account(test.trade_dst, &test.conn).await.amount, 0);
+    // and the receipt is burnt
+    assert_eq!(get_spl_account(receipt, &test.conn).await.amount, 0);
+    let mint_ai = test.conn.borrow_mut().get_account(receipt_mint).await.unwrap().unwrap();
+    assert_eq!(Mint::unpack(&mint_ai.data).unwrap().supply, 0);
+}
+
+#[tokio::test]
+#[should_panic(expected = "transport transaction error: Error processing Instruction 0: custom program error: 0x8")]
+async fn test_make_receipt_trade_paying_trade_dst() {
+    let test = TestData::init().await;
+    let receipt_mint = create_receipt_trade(&test).await;
+
+    let holder = Keypair::new();
+    let (receipt, _) = transfer_receipt(&test, &receipt_mint, &holder, true).await;
+    let ix = fill_ix(&test, test.trade_dst, (receipt_mint, receipt));
+    let panic_on = process_ix(&vec![&test.payer, &test.wallet2], test.payer.pubkey(), ix, &test.conn).await.unwrap_err();
+    panic!("{}", panic_on.to_string());
+}
+
+#[tokio::test]
+#[should_panic(expected = "transport transaction error: Error processing Instruction 0: custom program error: 0x2b")]
+async fn test_make_receipt_trade_not_delegated() {
+    let test = TestData::init().await;
+    let receipt_mint = create_receipt_trade(&test).await;
+
+    // spl-token clears the delegate on transfer, so the new holder has to approve the pda again
+    let holder = Keypair::new();
+    let (receipt, trade_dst) = transfer_receipt(&test, &receipt_mint, &holder, false).await;
+    let ix = fill_ix(&test, trade_dst, (receipt_mint, receipt));
+    let panic_on = process_ix(&vec![&test.payer, &test.wallet2], test.payer.pubkey(), ix, &test.conn).await.unwrap_err();
+    panic!("{}", panic_on.to_string());
+}
+
+#[tokio::test]
+#[should_panic(expected = "transport transaction error: Error processing Instruction 0: custom program error: 0x2a")]
+async fn test_make_receipt_trade_without_receipt() {
+    let test = TestData::init().await;
+    let receipt_mint = create_receipt_trade(&test).await;
+
+    // the owner sold the receipt, so the account it was minted to is empty
+    let holder = Keypair::new();
+    transfer_receipt(&test, &receipt_mint, &holder, true).await;
+    let owner_receipt = spl_associated_token_account::get_associated_token_address(&test.payer.pubkey(), &receipt_mint);
+    let ix = fill_ix(&test, test.trade_dst, (receipt_mint, owner_receipt));
+    let panic_on = process_ix(&vec![&test.payer, &test.wallet2], test.payer.pubkey(), ix, &test.conn).await.unwrap_err();
+    panic!("{}", panic_on.to_string());
+}
+
+#[tokio::test]
+#[should_panic(expected = "transport transaction error: Error processing Instruction 0: custom program error: 0x2c")]
+async fn test_decrease_receipt_trade_offer() {
+    let test = TestData::init().await;
+    create_receipt_trade(&test).await;
+
+    let ix = instruction::decrease_offer(
+        &test.program_id,
+        &test.payer.pubkey(),
+        &test.trade_account_keypair.pubkey(),
+        &test.offer_src,
+        &test.offer_dst,
+        spl_token::ui_amount_to_amount(1.0, 9),
+        false,
+    );
+    let panic_on = process_ix(&vec![&test.payer], test.payer.pubkey(), ix, &test.conn).await.unwrap_err();
+    panic!("{}", panic_on.to_string());
+}
+
+#[tokio::test]
+#[should_panic(expected = "transport transaction error: Error processing Instruction 0: custom program error: 0x2a")]
+async fn test_create_trade_with_minted_receipt() {
+    let test = TestData::init().await;
+
+    // a mint the owner controls could mint more receipts
+    let receipt_mint = create_receipt_mint(&test, &test.payer.pubkey()).await;
+    let args = CreateTradeArgs { receipt: Some(receipt_mint), ..CreateTradeArgs::default() };
+    let panic_on = create_trade_with(&test, args).await.unwrap_err();
+    panic!("{}", panic_on.to_string());
+}
+
+#[tokio::test]
+#[should_panic(expected = "transport transaction error: Error processing Instruction 0: invalid instruction data")]
+async fn test_create_delegated_trade_with_receipt() {
+    let test = TestData::init().await;
+
+    let (pda, _) = instruction::find_trade_pda(&test.program_id, &test.trade_account_keypair.pubkey());
+    let receipt_mint = create_receipt_mint(&test, &pda).await;
+    let args = CreateTradeArgs { mode: TradeMode::Delegate, receipt: Some(receipt_mint), ..CreateTradeArgs::default() };
+    let panic_on = create_trade_with(&test, args).await.unwrap_err();
+    panic!("{}", panic_on.to_string());
+}
@@ -0,0 +1,192 @@
+use {
+    borsh::BorshSerialize,
+    solana_sdk::{
+        pubkey::Pubkey,
+        signature::{Keypair, Signer},
+    },
+    std::{
+        collections::HashMap,
+        io::{Read, Write},
+        net::{TcpListener, TcpStream},
+        path::PathBuf,
+        thread,
+    },
+};
+use ::trader::{
+    instruction,
+    instructions::{Action, SignedOrder},
+    state::AccountNonces,
+};
+use trader_client::client::encode_signed_order;
+use trader_client::relay::{self, OrderBook, OrderStatus};
+use trader_client::Result;
+
+// The chain as seen by the relay in the tests: a fixed time and the nonces accounts of some makers
+#[derive(Clone, Default)]
+struct TestStatus {
+    now: i64,
+    nonces: HashMap<Pubkey, AccountNonces>,
+}
+
+impl OrderStatus for TestStatus {
+    fn now(&self) -> Result<i64> {
+        Ok(self.now)
+    }
+
+    fn load_nonces(&self, maker: &Pubkey) -> Result<Option<AccountNonces>> {
+        Ok(self.nonces.get(maker).cloned())
+    }
+}
+
+fn store_path(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("trader-relay-{}-{}.json", name, std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    path
+}
+
+// Runs a relay on a free local port, returning its url
+fn start_relay(path: PathBuf, status: TestStatus) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    thread::spawn(move || {
+        let mut book = OrderBook::open(path).unwrap();
+        relay::serve(listener, &mut book, &status).unwrap();
+    });
+
+    url
+}
+
+fn sign(maker: &Keypair, offer_mint: Pubkey, trade_mint: Pubkey, nonce: u64, expires_at: i64) -> (SignedOrder, String) {
+    let order = SignedOrder {
+        maker: maker.pubkey(),
+        offer_mint,
+        trade_mint,
+        trade_dst: Keypair::new().pubkey(),
+        offer_amount: 10,
+        trade_amount: 2,
+        nonce,
+        expires_at,
+    };
+    let signature = maker.sign_message(&order.try_to_vec().unwrap());
+    let encoded = encode_signed_order(&order, signature.as_ref());
+
+    (order, encoded)
+}
+
+#[test]
+fn test_relay_orders_by_pair() {
+    let url = start_relay(store_path("pair"), TestStatus::default());
+    let maker = Keypair::new();
+    let (mint_a, mint_b, mint_c) = (Keypair::new().pubkey(), Keypair::new().pubkey(), Keypair::new().pubkey());
+
+    let (order, encoded) = sign(&maker, mint_a, mint_b, 1, 0);
+    let posted = relay::post_order(&url, &encoded).unwrap();
+    relay::post_order(&url, &sign(&maker, mint_a, mint_c, 2, 0).1).unwrap();
+    relay::post_order(&url, &sign(&maker, mint_b, mint_a, 3, 0).1).unwrap();
+
+    let orders = relay::load_orders(&url, Some(&mint_a), Some(&mint_b)).unwrap();
+    assert_eq!(orders, vec![posted.clone()]);
+    assert_eq!(relay::load_orders(&url, Some(&mint_a), None).unwrap().len(), 2);
+    assert_eq!(relay::load_orders(&url, None, Some(&mint_a)).unwrap().len(), 1);
+    assert_eq!(relay::load_orders(&url, None, None).unwrap().len(), 3);
+
+    // the instruction data is the one fill_signed_order builds, whatever the accounts of the taker
+    let signature = maker.sign_message(&order.try_to_vec().unwrap());
+    let ixs = instruction::fill_signed_order(
+        &Keypair::new().pubkey(),
+        &Keypair::new().pubkey(),
+        &order,
+        signature.as_ref(),
+        &Keypair::new().pubkey(),
+        &Keypair::new().pubkey(),
+        &Keypair::new().pubkey(),
+        &Keypair::new().pubkey(),
+    );
+    assert_eq!(base64::decode(&posted.verify_data).unwrap(), ixs[0].data);
+    assert_eq!(base64::decode(&posted.fill_data).unwrap(), ixs[1].data);
+    assert_eq!(base64::decode(&posted.fill_data).unwrap(), Action::FillSignedOrder { order }.try_to_vec().unwrap());
+}
+
+#[test]
+fn test_relay_rejects_unsigned_orders() {
+    let url = start_relay(store_path("unsigned"), TestStatus::default());
+    let maker = Keypair::new();
+
+    // signed by someone else than its maker
+    let (order, _) = sign(&maker, Keypair::new().pubkey(), Keypair::new().pubkey(), 1, 0);
+    let signature = Keypair::new().sign_message(&order.try_to_vec().unwrap());
+    let err = relay::post_order(&url, &encode_signed_order(&order, signature.as_ref())).unwrap_err();
+    assert!(err.to_string().contains("400"), "{}", err);
+
+    assert!(relay::post_order(&url, "not an order").is_err());
+    assert!(relay::load_orders(&url, None, None).unwrap().is_empty());
+}
+
+#[test]
+fn test_relay_rejects_large_bodies() {
+    let url = start_relay(store_path("large"), TestStatus::default());
+
+    // the relay answers without waiting for a body it won't read
+    let mut stream = TcpStream::connect(url.trim_start_matches("http://")).unwrap();
+    write!(stream, "POST /orders HTTP/1.1\r\nContent-Length: 1000000000\r\nConnection: close\r\n\r\n").unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    assert!(response.starts_with("HTTP/1.1 413"), "{}", response);
+
+    // and keeps serving
+    let maker = Keypair::new();
+    relay::post_order(&url, &sign(&maker, Keypair::new().pubkey(), Keypair::new().pubkey(), 1, 0).1).unwrap();
+}
+
+#[test]
+fn test_relay_drops_expired_and_spent_orders() {
+    let maker = Keypair::new();
+    let (mint_a, mint_b) = (Keypair::new().pubkey(), Keypair::new().pubkey());
+
+    let mut nonces = AccountNonces::default();
+    nonces.spend(2);
+    nonces.spend_below(1);
+    let status = TestStatus { now: 100, nonces: HashMap::from([(maker.pubkey(), nonces)]) };
+    let url = start_relay(store_path("prune"), status);
+
+    // nonce 0 is below the window, 2 was filled or cancelled, and 4 expired
+    for (nonce, expires_at) in [(0, 0), (1, 0), (2, 0), (3, 101), (4, 100)] {
+        relay::post_order(&url, &sign(&maker, mint_a, mint_b, nonce, expires_at).1).unwrap();
+    }
+    // a maker without a nonces account yet
+    let other = Keypair::new();
+    relay::post_order(&url, &sign(&other, mint_a, mint_b, 0, 0).1).unwrap();
+
+    let orders = relay::load_orders(&url, Some(&mint_a), Some(&mint_b)).unwrap();
+    let live: Vec<(String, u64)> = orders.iter().map(|order| (order.maker.clone(), order.nonce)).collect();
+    assert_eq!(live, vec![
+        (maker.pubkey().to_string(), 1),
+        (maker.pubkey().to_string(), 3),
+        (other.pubkey().to_string(), 0),
+    ]);
+}
+
+#[test]
+fn test_order_book_store() {
+    let path = store_path("store");
+    let maker = Keypair::new();
+    let (mint_a, mint_b) = (Keypair::new().pubkey(), Keypair::new().pubkey());
+
+    let mut book = OrderBook::open(path.clone()).unwrap();
+    book.add(&sign(&maker, mint_a, mint_b, 1, 0).1).unwrap();
+    book.add(&sign(&maker, mint_a, mint_b, 2, 0).1).unwrap();
+    // posting an order with the same nonce replaces the previous one
+    let replaced = book.add(&sign(&maker, mint_a, mint_b, 1, 50).1).unwrap();
+
+    let book = OrderBook::open(path.clone()).unwrap();
+    let orders = book.orders(None, None);
+    assert_eq!(orders.len(), 2);
+    assert_eq!(orders[1], replaced);
+
+    let mut book = OrderBook::open(path.clone()).unwrap();
+    assert_eq!(book.prune(&TestStatus { now: 50, ..TestStatus::default() }).unwrap(), 1);
+    assert_eq!(OrderBook::open(path).unwrap().orders(None, None).len(), 1);
+
+    // an order book that can't be read isn't taken for an empty one
+    assert!(OrderBook::open(std::env::temp_dir()).is_err());
+}
@@ -0,0 +1,161 @@
+mod lib;
+
+use {
+    lib::*,
+    solana_program_test::*,
+    solana_sdk::{
+        pubkey::Pubkey,
+        signature::Signer,
+    },
+};
+use ::trader::{
+    instruction::{self, CreateTradeArgs, TradeFillAccounts},
+    state::AccountTrade,
+};
+use trader_client::router::{find_route, OpenTrade};
+
+fn open_trade(trade_mint: Pubkey, trade_amount: u64, offer_mint: Pubkey, offer_amount: u64) -> OpenTrade {
+    OpenTrade {
+        trade_id: Pubkey::new_unique(),
+        offer_mint,
+        account: AccountTrade {
+            trade_mint,
+            trade_amount,
+            offer_amount,
+            initialized: true,
+            ..AccountTrade::default()
+        },
+    }
+}
+
+fn route_ids(route: Option<Vec<OpenTrade>>) -> Vec<Pubkey> {
+    route.unwrap().iter().map(|hop| hop.trade_id).collect()
+}
+
+#[test]
+fn test_find_route_direct() {
+    let (a, b) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let cheap = open_trade(a, 10, b, 100);
+    let expensive = open_trade(a, 10, b, 50);
+    let trades = vec![expensive, cheap.clone()];
+
+    assert_eq!(route_ids(find_route(&trades, &a, &b, 10)), vec![cheap.trade_id]);
+}
+
+#[test]
+fn test_find_route_multi_hop() {
+    let (a, b, c) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+    let direct = open_trade(a, 10, c, 20);
+    let first = open_trade(a, 10, b, 30);
+    let second = open_trade(b, 30, c, 40);
+    let trades = vec![direct, first.clone(), second.clone()];
+
+    assert_eq!(route_ids(find_route(&trades, &a, &c, 10)), vec![first.trade_id, second.trade_id]);
+}
+
+#[test]
+fn test_find_route_hop_must_pay_for_the_next() {
+    let (a, b, c) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+    // the offer of the first trade is not enough to fill the second
+    let trades = vec![
+        open_trade(a, 10, b, 20),
+        open_trade(b, 30, c, 40),
+    ];
+
+    assert!(find_route(&trades, &a, &c, 10).is_none());
+}
+
+#[test]
+fn test_find_route_respects_amount() {
+    let (a, b) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let trades = vec![open_trade(a, 10, b, 100)];
+
+    assert!(find_route(&trades, &a, &b, 9).is_none());
+}
+
+#[test]
+fn test_find_route_max_hops() {
+    let mints: Vec<Pubkey> = (0..5).map(|_| Pubkey::new_unique()).collect();
+    let trades: Vec<OpenTrade> = mints.windows(2).map(|pair| open_trade(pair[0], 10, pair[1], 10)).collect();
+
+    assert_eq!(find_route(&trades, &mints[0], &mints[3], 10).unwrap().len(), 3);
+    assert!(find_route(&trades, &mints[0], &mints[4], 10).is_none());
+}
+
+#[tokio::test]
+async fn test_make_trades_chains_fills() {
+    // first trade: 10 offer tokens for 2 trade tokens
+    let test = TestData::init().await;
+    create_test_trade(&test).await;
+    let accounts = trade_fill_accounts(&test);
+
+    // second trade: 3 tokens of a new mint for 5 of the offer tokens of the first trade
+    let recent_blockhash = test.conn.borrow_mut().get_latest_blockhash().await.unwrap();
+    let mint = mint_account(&test.payer, &test.payer, recent_blockhash, &test.conn).await;
+    let offer_src = token_account(&test.payer, &test.payer, mint, recent_blockhash, &test.conn).await;
+    let second_offer: u64 = spl_token::ui_amount_to_amount(3.0, 9);
+    mint_to_account(&test.payer, &test.payer, mint, offer_src, second_offer, recent_blockhash, &test.conn).await;
+    let trade_dst = token_account(&test.payer, &test.payer, test.offer_mint, recent_blockhash, &test.conn).await;
+    let offer_dst = token_account(&test.wallet2, &test.payer, mint, recent_blockhash, &test.conn).await;
+    let fee_ata = spl_associated_token_account::get_associated_token_address(&test.wallet2.pubkey(), &test.offer_mint);
+    let fee_ata_ix = spl_associated_token_account::create_associated_token_account(
+        &test.payer.pubkey(),
+        &test.wallet2.pubkey(),
+        &test.offer_mint,
+    );
+    process_ix(&vec![&test.payer], test.payer.pubkey(), fee_ata_ix, &test.conn).await.unwrap();
+
+    let trade = TestData::create_trade_account(test.program_id, &test.payer, None, &test.conn).await;
+    let second_trade: u64 = spl_token::ui_amount_to_amount(5.0, 9);
+    let ix = instruction::create_trade(
+        &test.program_id,
+        &test.payer.pubkey(),
+        &trade.pubkey(),
+        &offer_src,
+        &mint,
+        &test.offer_mint,
+        &trade_dst,
+        second_trade,
+        CreateTradeArgs::default(),
+    );
+    process_ix(&vec![&test.payer], test.payer.pubkey(), ix, &test.conn).await.unwrap();
+
+    // the offer dst of the first trade pays for the second one
+    let second_accounts = TradeFillAccounts {
+        trade: trade.pubkey(),
+        offer_src,
+        trade_dst,
+        trade_src: test.offer_dst,
+        offer_dst,
+        offer_owner: test.payer.pubkey(),
+        fee_account: fee_ata,
+        price_feed: None,
+        split_dsts: vec![],
+        arbiter: None,
+        hook: None,
+        trade_escrow: None,
+        vesting_escrow: None,
+        receipt: None,
+        mints: Some((mint, test.offer_mint)),
+    };
+
+    let first_offer: u64 = spl_token::ui_amount_to_amount(10.0, 9);
+    let first_trade: u64 = spl_token::ui_amount_to_amount(2.0, 9);
+    let ix = make_trades_ix(
+        test.program_id,
+        test.wallet2.pubkey(),
+        vec![(first_offer, first_trade, accounts), (second_offer, second_trade, second_accounts)],
+    );
+    process_ix(&vec![&test.payer, &test.wallet2], test.payer.pubkey(), ix, &test.conn).await.unwrap();
+
+    let offer_dst_account = get_spl_account(offer_dst, &test.conn).await;
+    assert_eq!(offer_dst_account.amount, second_offer);
+
+    // what was left over of the intermediate tokens stays with the taker
+    let intermediate_account = get_spl_account(test.offer_dst, &test.conn).await;
+    assert_eq!(intermediate_account.amount, first_offer - second_trade);
+
+    let trade_dst_account = get_spl_account(trade_dst, &test.conn).await;
+    let second_fee: u64 = 30000000;
+    assert_eq!(trade_dst_account.amount, second_trade - second_fee);
+}
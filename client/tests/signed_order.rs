@@ -0,0 +1,285 @@
+mod lib;
+
+use {
+    borsh::{BorshDeserialize, BorshSerialize},
+    lib::*,
+    solana_program_test::*,
+    solana_sdk::{
+        instruction::Instruction,
+        signature::{Keypair, Signer},
+        system_instruction,
+        sysvar::clock::Clock,
+    },
+};
+use ::trader::{
+    instruction,
+    instructions::SignedOrder,
+    state::{AccountNonces, MintLimits, MintListMode, NONCE_WINDOW},
+};
+use trader_client::client::{decode_signed_order, encode_signed_order};
+
+// Creates the nonces account of the maker of test and approves it as delegate of the whole offer src
+async fn approve_signed_orders(test: &TestData) {
+    let (nonces, _) = instruction::find_nonces_pda(&test.program_id, &test.payer.pubkey());
+    let approve_ix = spl_token::instruction::approve(
+        &spl_token::id(),
+        &test.offer_src,
+        &nonces,
+        &test.payer.pubkey(),
+        &[&test.payer.pubkey()],
+        spl_token::ui_amount_to_amount(10.0, 9),
+    ).unwrap();
+    let ixs = [instruction::init_nonces(&test.program_id, &test.payer.pubkey()), approve_ix];
+    process_ixs(&vec![&test.payer], test.payer.pubkey(), &ixs, &test.conn).await.unwrap();
+}
+
+// An order of the whole offer src of test for 2 trade tokens
+fn test_order(test: &TestData, nonce: u64) -> SignedOrder {
+    SignedOrder {
+        maker: test.payer.pubkey(),
+        offer_mint: test.offer_mint,
+        trade_mint: test.trade_mint,
+        trade_dst: test.trade_dst,
+        offer_amount: spl_token::ui_amount_to_amount(10.0, 9),
+        trade_amount: spl_token::ui_amount_to_amount(2.0, 9),
+        nonce,
+        expires_at: 0,
+    }
+}
+
+fn fill_order_ixs(test: &TestData, order: &SignedOrder, signer: &Keypair) -> Vec<Instruction> {
+    let signature = signer.sign_message(&order.try_to_vec().unwrap());
+    instruction::fill_signed_order(
+        &test.program_id,
+        &test.wallet2.pubkey(),
+        order,
+        signature.as_ref(),
+        &test.offer_src,
+        &test.trade_src,
+        &test.offer_dst,
+        &test.fee_ata,
+    )
+}
+
+async fn load_nonces(test: &TestData) -> AccountNonces {
+    let (nonces, _) = instruction::find_nonces_pda(&test.program_id, &test.payer.pubkey());
+    let nonces_ai = test.conn.borrow_mut().get_account(nonces).await.unwrap().unwrap();
+    AccountNonces::try_from_slice(&nonces_ai.data).unwrap()
+}
+
+#[tokio::test]
+async fn test_fill_signed_order() {
+    let test = TestData::init().await;
+    approve_signed_orders(&test).await;
+
+    let order = test_order(&test, 7);
+    let ixs = fill_order_ixs(&test, &order, &test.payer);
+    process_ixs(&vec![&test.payer, &test.wallet2], test.payer.pubkey(), &ixs, &test.conn).await.unwrap();
+
+    // the offer is taken from the maker's own account, and the trade amount minus the fee goes to the trade dst
+    assert_eq!(get_spl_account(test.offer_dst, &test.conn).await.amount, order.offer_amount);
+    assert_eq!(get_spl_account(test.offer_src, &test.conn).await.owner, test.payer.pubkey());
+    let fee = order.offer_amount / 100;
+    assert_eq!(get_spl_account(test.trade_dst, &test.conn).await.amount, order.trade_amount - fee);
+
+    let nonces = load_nonces(&test).await;
+    assert!(nonces.is_spent(7));
+    assert!(!nonces.is_spent(6));
+}
+
+#[tokio::test]
+async fn test_init_prefunded_nonces() {
+    let test = TestData::init().await;
+
+    // lamports sent to the nonces account beforehand, enough for an empty account, don't keep it from being created
+    let (nonces, _) = instruction::find_nonces_pda(&test.program_id, &test.payer.pubkey());
+    let ix = system_instruction::transfer(&test.payer.pubkey(), &nonces, minimum_balance_rent_exempt(0));
+    process_ix(&vec![&test.payer], test.payer.pubkey(), ix, &test.conn).await.unwrap();
+
+    let ix = instruction::init_nonces(&test.program_id, &test.payer.pubkey());
+    process_ix(&vec![&test.payer], test.payer.pubkey(), ix, &test.conn).await.unwrap();
+    let nonces = load_nonces(&test).await;
+    assert_eq!(nonces.maker, test.payer.pubkey());
+    assert!(nonces.initialized && !nonces.is_spent(0));
+}
+
+#[tokio::test]
+#[should_panic(expected = "transport transaction error: Error processing Instruction 1: custom program error: 0x1d")]
+async fn test_fill_signed_order_twice() {
+    let test = TestData::init().await;
+    approve_signed_orders(&test).await;
+
+    // half of the offer each time, so the second fill is only stopped by its nonce
+    let order = SignedOrder { offer_amount: spl_token::ui_amount_to_amount(5.0, 9), ..test_order(&test, 7) };
+    let ixs = fill_order_ixs(&test, &order, &test.payer);
+    process_ixs(&vec![&test.payer, &test.wallet2], test.payer.pubkey(), &ixs, &test.conn).await.unwrap();
+
+    // paid by the taker, so the transaction isn't the same as the first one
+    let panic_on = process_ixs(&vec![&test.wallet2], test.wallet2.pubkey(), &ixs, &test.conn).await.unwrap_err();
+    panic!("{}", panic_on.to_string());
+}
+
+#[tokio::test]
+#[should_panic(expected = "transport transaction error: Error processing Instruction 1: custom program error: 0x1d")]
+async fn test_fill_cancelled_order() {
+    let test = TestData::init().await;
+    approve_signed_orders(&test).await;
+
+    let ix = instruction::cancel_orders(&test.program_id, &test.payer.pubkey(), 5, vec![9]);
+    process_ix(&vec![&test.payer], test.payer.pubkey(), ix, &test.conn).await.unwrap();
+    let nonces = load_nonces(&test).await;
+    assert_eq!(nonces.base, 5);
+    assert!(nonces.is_spent(4) && nonces.is_spent(9) && !nonces.is_spent(7));
+
+    let ixs = fill_order_ixs(&test, &test_order(&test, 9), &test.payer);
+    let panic_on = process_ixs(&vec![&test.payer, &test.wallet2], test.payer.pubkey(), &ixs, &test.conn).await.unwrap_err();
+    panic!("{}", panic_on.to_string());
+}
+
+#[tokio::test]
+#[should_panic(expected = "transport transaction error: Error processing Instruction 1: custom program error: 0x1c")]
+async fn test_fill_tampered_order() {
+    let test = TestData::init().await;
+    approve_signed_orders(&test).await;
+
+    // the signature verified is the one of the order, but the taker fills it for less
+    let order = test_order(&test, 7);
+    let mut ixs = fill_order_ixs(&test, &order, &test.payer);
+    let cheaper = SignedOrder { trade_amount: 1, ..order };
+    ixs[1] = fill_order_ixs(&test, &cheaper, &test.payer).remove(1);
+
+    let panic_on = process_ixs(&vec![&test.payer, &test.wallet2], test.payer.pubkey(), &ixs, &test.conn).await.unwrap_err();
+    panic!("{}", panic_on.to_string());
+}
+
+#[tokio::test]
+#[should_panic(expected = "transport transaction error: Error processing Instruction 1: custom program error: 0x1c")]
+async fn test_fill_order_signed_by_taker() {
+    let test = TestData::init().await;
+    approve_signed_orders(&test).await;
+
+    // a valid signature, but not the one of the maker
+    let order = test_order(&test, 7);
+    let mut ixs = fill_order_ixs(&test, &order, &test.payer);
+    let signature = test.wallet2.sign_message(&order.try_to_vec().unwrap());
+    ixs[0] = instruction::verify_signature(&test.wallet2.pubkey(), signature.as_ref(), &order.try_to_vec().unwrap());
+
+    let panic_on = process_ixs(&vec![&test.payer, &test.wallet2], test.payer.pubkey(), &ixs, &test.conn).await.unwrap_err();
+    panic!("{}", panic_on.to_string());
+}
+
+#[tokio::test]
+#[should_panic(expected = "transport transaction error: Error processing Instruction 0: custom program error: 0x1c")]
+async fn test_fill_order_without_signature() {
+    let test = TestData::init().await;
+    approve_signed_orders(&test).await;
+
+    let ixs = fill_order_ixs(&test, &test_order(&test, 7), &test.payer);
+    let panic_on = process_ixs(&vec![&test.payer, &test.wallet2], test.payer.pubkey(), &ixs[1..], &test.conn).await.unwrap_err();
+    panic!("{}", panic_on.to_string());
+}
+
+#[tokio::test]
+#[should_panic(expected = "transport transaction error: Error processing Instruction 1: custom program error: 0xb")]
+async fn test_fill_expired_order() {
+    let test = TestData::init().await;
+    approve_signed_orders(&test).await;
+
+    let clock = test.conn.borrow_mut().get_sysvar::<Clock>().await.unwrap();
+    let order = SignedOrder { expires_at: clock.unix_timestamp, ..test_order(&test, 7) };
+    let ixs = fill_order_ixs(&test, &order, &test.payer);
+    let panic_on = process_ixs(&vec![&test.payer, &test.wallet2], test.payer.pubkey(), &ixs, &test.conn).await.unwrap_err();
+    panic!("{}", panic_on.to_string());
+}
+
+#[tokio::test]
+#[should_panic(expected = "transport transaction error: Error processing Instruction 1: custom program error: 0x1e")]
+async fn test_fill_order_above_nonce_window() {
+    let test = TestData::init().await;
+    approve_signed_orders(&test).await;
+
+    let ixs = fill_order_ixs(&test, &test_order(&test, NONCE_WINDOW), &test.payer);
+    let panic_on = process_ixs(&vec![&test.payer, &test.wallet2], test.payer.pubkey(), &ixs, &test.conn).await.unwrap_err();
+    panic!("{}", panic_on.to_string());
+}
+
+#[tokio::test]
+#[should_panic(expected = "transport transaction error: Error processing Instruction 1: custom program error: 0x2d")]
+async fn test_fill_order_blocklisted_mint() {
+    let test = TestData::init().await;
+    approve_signed_orders(&test).await;
+
+    // signed orders don't go through CreateTrade, so the mint list is checked when they are filled
+    let ixs = [
+        instruction::set_mint_list_mode(&test.program_id, &test.wallet2.pubkey(), MintListMode::Blocklist),
+        instruction::add_mint(&test.program_id, &test.wallet2.pubkey(), &test.trade_mint),
+    ];
+    process_ixs(&vec![&test.payer, &test.wallet2], test.payer.pubkey(), &ixs, &test.conn).await.unwrap();
+
+    let ixs = fill_order_ixs(&test, &test_order(&test, 7), &test.payer);
+    let panic_on = process_ixs(&vec![&test.payer, &test.wallet2], test.payer.pubkey(), &ixs, &test.conn).await.unwrap_err();
+    panic!("{}", panic_on.to_string());
+}
+
+#[tokio::test]
+#[should_panic(expected = "transport transaction error: Error processing Instruction 1: custom program error: 0x2f")]
+async fn test_fill_order_over_volume_cap() {
+    let test = TestData::init().await;
+    approve_signed_orders(&test).await;
+
+    // the order is for 10 offer tokens, over the cap of its mint
+    let volume_cap = spl_token::ui_amount_to_amount(5.0, 9);
+    let limits = MintLimits { volume_cap, window: 3600, ..MintLimits::default() };
+    let ix = instruction::set_mint_limits(&test.program_id, &test.wallet2.pubkey(), &test.offer_mint, limits);
+    process_ix(&vec![&test.payer, &test.wallet2], test.payer.pubkey(), ix, &test.conn).await.unwrap();
+
+    let ixs = fill_order_ixs(&test, &test_order(&test, 7), &test.payer);
+    let panic_on = process_ixs(&vec![&test.payer, &test.wallet2], test.payer.pubkey(), &ixs, &test.conn).await.unwrap_err();
+    panic!("{}", panic_on.to_string());
+}
+
+#[test]
+fn test_nonce_window() {
+    let mut nonces = AccountNonces::default();
+    assert!(nonces.spend(3));
+    assert!(nonces.spend(100));
+    assert!(!nonces.spend(NONCE_WINDOW));
+    assert!(nonces.is_spent(3) && nonces.is_spent(100) && !nonces.is_spent(4));
+
+    // the nonces still in the window keep their state when it moves up
+    nonces.spend_below(50);
+    assert_eq!(nonces.base, 50);
+    assert!(nonces.is_spent(3) && nonces.is_spent(49) && nonces.is_spent(100));
+    assert!(!nonces.is_spent(50) && !nonces.is_spent(101));
+    assert!(nonces.spend(NONCE_WINDOW + 49));
+
+    // moving it down does nothing
+    nonces.spend_below(10);
+    assert_eq!(nonces.base, 50);
+
+    nonces.spend_below(u64::MAX);
+    assert!(nonces.is_spent(NONCE_WINDOW + 49) && !nonces.is_spent(u64::MAX));
+}
+
+#[test]
+fn test_encode_signed_order() {
+    let maker = Keypair::new();
+    let order = SignedOrder {
+        maker: maker.pubkey(),
+        offer_mint: Keypair::new().pubkey(),
+        trade_mint: Keypair::new().pubkey(),
+        trade_dst: Keypair::new().pubkey(),
+        offer_amount: 10,
+        trade_amount: 2,
+        nonce: 7,
+        expires_at: 0,
+    };
+    let signature = maker.sign_message(&order.try_to_vec().unwrap());
+
+    let encoded = encode_signed_order(&order, signature.as_ref());
+    let (decoded, decoded_signature) = decode_signed_order(&encoded).unwrap();
+    assert_eq!(decoded, order);
+    assert_eq!(decoded_signature, signature.as_ref());
+
+    assert!(decode_signed_order("not an order").is_err());
+}
@@ -0,0 +1,126 @@
+mod lib;
+
+use {
+    lib::*,
+    solana_program_test::*,
+    solana_sdk::{
+        pubkey::Pubkey,
+        signature::{Keypair, Signer},
+    },
+};
+use ::trader::{
+    instruction::{self, CreateTradeArgs, TradeFillAccounts},
+    state::{AccountTrade, ProceedsSplit},
+};
+
+// Creates the trade of test, splitting its trade amount as given
+async fn create_split_trade(test: &TestData, splits: Vec<ProceedsSplit>) -> Result<(), BanksClientError> {
+    create_trade_with(test, CreateTradeArgs { splits, ..CreateTradeArgs::default() }).await
+}
+
+// a new token account for trade tokens
+async fn trade_token_account(test: &TestData) -> Pubkey {
+    let recent_blockhash = test.conn.borrow_mut().get_latest_blockhash().await.unwrap();
+    token_account(&Keypair::new(), &test.payer, test.trade_mint, recent_blockhash, &test.conn).await
+}
+
+#[tokio::test]
+async fn test_make_split_trade() {
+    let test = TestData::init().await;
+    let second_dst = trade_token_account(&test).await;
+    create_split_trade(&test, vec![
+        ProceedsSplit { destination: test.trade_dst, share_bps: 8_000 },
+        ProceedsSplit { destination: second_dst, share_bps: 2_000 },
+    ]).await.unwrap();
+
+    let offer_amount: u64 = spl_token::ui_amount_to_amount(10.0, 9);
+    let trade_amount: u64 = spl_token::ui_amount_to_amount(2.0, 9);
+    let accounts = TradeFillAccounts { split_dsts: vec![second_dst], ..trade_fill_accounts(&test) };
+    let ix = instruction::make_trade(
+        &test.program_id,
+        &test.wallet2.pubkey(),
+        &accounts,
+        offer_amount,
+        trade_amount,
+        None,
+    );
+    process_ix(&vec![&test.payer, &test.wallet2], test.payer.pubkey(), ix, &test.conn).await.unwrap();
+
+    // the fee comes off the top, and the rest is split 80/20
+    let trade_fee: u64 = 100000000;
+    assert_eq!(get_spl_account(second_dst, &test.conn).await.amount, (trade_amount - trade_fee) / 5);
+    assert_eq!(get_spl_account(test.trade_dst, &test.conn).await.amount, (trade_amount - trade_fee) / 5 * 4);
+}
+
+#[tokio::test]
+#[should_panic(expected = "transport transaction error: Error processing Instruction 0: custom program error: 0x19")]
+async fn test_create_split_trade_checks_shares() {
+    let test = TestData::init().await;
+    let second_dst = trade_token_account(&test).await;
+
+    // the shares only add up to 90%
+    let panic_on = create_split_trade(&test, vec![
+        ProceedsSplit { destination: test.trade_dst, share_bps: 7_000 },
+        ProceedsSplit { destination: second_dst, share_bps: 2_000 },
+    ]).await.unwrap_err();
+    panic!("{}", panic_on.to_string());
+}
+
+#[tokio::test]
+#[should_panic(expected = "transport transaction error: Error processing Instruction 0: custom program error: 0x19")]
+async fn test_create_split_trade_checks_first_destination() {
+    let test = TestData::init().await;
+    let second_dst = trade_token_account(&test).await;
+
+    let panic_on = create_split_trade(&test, vec![
+        ProceedsSplit { destination: second_dst, share_bps: 5_000 },
+        ProceedsSplit { destination: test.trade_dst, share_bps: 5_000 },
+    ]).await.unwrap_err();
+    panic!("{}", panic_on.to_string());
+}
+
+#[tokio::test]
+#[should_panic(expected = "transport transaction error: Error processing Instruction 0: custom program error: 0x8")]
+async fn test_make_split_trade_wrong_destination() {
+    let test = TestData::init().await;
+    let second_dst = trade_token_account(&test).await;
+    create_split_trade(&test, vec![
+        ProceedsSplit { destination: test.trade_dst, share_bps: 8_000 },
+        ProceedsSplit { destination: second_dst, share_bps: 2_000 },
+    ]).await.unwrap();
+
+    let offer_amount: u64 = spl_token::ui_amount_to_amount(10.0, 9);
+    let trade_amount: u64 = spl_token::ui_amount_to_amount(2.0, 9);
+    let other_dst = trade_token_account(&test).await;
+    let accounts = TradeFillAccounts { split_dsts: vec![other_dst], ..trade_fill_accounts(&test) };
+    let ix = instruction::make_trade(
+        &test.program_id,
+        &test.wallet2.pubkey(),
+        &accounts,
+        offer_amount,
+        trade_amount,
+        None,
+    );
+    let panic_on = process_ix(&vec![&test.payer, &test.wallet2], test.payer.pubkey(), ix, &test.conn).await.unwrap_err();
+    panic!("{}", panic_on.to_string());
+}
+
+#[test]
+fn test_split_proceeds() {
+    let (first, second, third) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+    let mut trade = AccountTrade { trade_dst_account: first, ..AccountTrade::default() };
+
+    // without splits, everything goes to the trade dst
+    assert_eq!(trade.split_proceeds(1_000), vec![(first, 1_000)]);
+    assert!(trade.split_destinations().is_empty());
+
+    trade.splits[0] = ProceedsSplit { destination: first, share_bps: 3_334 };
+    trade.splits[1] = ProceedsSplit { destination: second, share_bps: 3_333 };
+    trade.splits[2] = ProceedsSplit { destination: third, share_bps: 3_333 };
+    assert_eq!(trade.split_destinations(), vec![second, third]);
+
+    // the remainder of the rounding goes to the first destination
+    assert_eq!(trade.split_proceeds(100), vec![(first, 34), (second, 33), (third, 33)]);
+    assert_eq!(trade.split_proceeds(1), vec![(first, 1), (second, 0), (third, 0)]);
+    assert_eq!(trade.split_proceeds(u64::MAX).iter().map(|(_, share)| *share as u128).sum::<u128>(), u64::MAX as u128);
+}
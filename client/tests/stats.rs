@@ -0,0 +1,269 @@
+mod lib;
+
+use {
+    borsh::{BorshDeserialize, BorshSerialize},
+    lib::*,
+    solana_program_test::*,
+    solana_sdk::{
+        signature::{Keypair, Signer},
+        system_instruction,
+        sysvar::clock::Clock,
+    },
+};
+use ::trader::{
+    instruction::{self, CreateTradeArgs},
+    instructions::{Arbitration, CoveredCall, Settlement, SignedOrder},
+    state::{AccountMakerStats, AccountMintStats, AccountStats, StatsAccount, TradeMode},
+};
+
+async fn init_stats(test: &TestData, stats: StatsAccount) {
+    let ix = instruction::init_stats(&test.program_id, &test.payer.pubkey(), stats);
+    process_ix(&vec![&test.payer], test.payer.pubkey(), ix, &test.conn).await.unwrap();
+}
+
+async fn load_stats<T: BorshDeserialize>(test: &TestData, stats: StatsAccount) -> T {
+    let (stats_account, _) = instruction::find_stats(&test.program_id, &stats);
+    let stats_ai = test.conn.borrow_mut().get_account(stats_account).await.unwrap().unwrap();
+    T::try_from_slice(&stats_ai.data).unwrap()
+}
+
+#[tokio::test]
+async fn test_stats_of_filled_trade() {
+    let test = TestData::init().await;
+    let maker = StatsAccount::Maker(test.payer.pubkey());
+    for stats in [
+        StatsAccount::Program,
+        StatsAccount::Mint(test.offer_mint),
+        StatsAccount::Mint(test.trade_mint),
+        maker,
+    ] {
+        init_stats(&test, stats).await;
+    }
+
+    create_test_trade(&test).await;
+    assert_eq!(load_stats::<AccountMakerStats>(&test, maker).await.created, 1);
+
+    let offer_amount: u64 = spl_token::ui_amount_to_amount(10.0, 9);
+    let trade_amount: u64 = spl_token::ui_amount_to_amount(2.0, 9);
+    let (ix, _, _) = make_trade_ix(&test, offer_amount, trade_amount, None);
+    process_ix(&vec![&test.payer, &test.wallet2], test.payer.pubkey(), ix, &test.conn).await.unwrap();
+
+    assert_eq!(load_stats::<AccountStats>(&test, StatsAccount::Program).await.trades, 1);
+
+    let offer_stats = load_stats::<AccountMintStats>(&test, StatsAccount::Mint(test.offer_mint)).await;
+    assert_eq!(offer_stats.mint, test.offer_mint);
+    assert_eq!((offer_stats.trades, offer_stats.volume, offer_stats.fees), (1, offer_amount as u128, 0));
+
+    // the fee is taken from the trade amount
+    let fee = get_spl_account(test.fee_ata, &test.conn).await.amount;
+    assert!(fee > 0);
+    let trade_stats = load_stats::<AccountMintStats>(&test, StatsAccount::Mint(test.trade_mint)).await;
+    assert_eq!((trade_stats.trades, trade_stats.volume, trade_stats.fees), (1, trade_amount as u128, fee as u128));
+
+    let maker_stats = load_stats::<AccountMakerStats>(&test, maker).await;
+    assert_eq!(maker_stats.maker, test.payer.pubkey());
+    assert_eq!((maker_stats.created, maker_stats.filled, maker_stats.cancelled), (1, 1, 0));
+}
+
+#[tokio::test]
+async fn test_stats_of_signed_order() {
+    let test = TestData::init().await;
+    let maker = StatsAccount::Maker(test.payer.pubkey());
+    init_stats(&test, StatsAccount::Program).await;
+    init_stats(&test, StatsAccount::Mint(test.offer_mint)).await;
+    init_stats(&test, maker).await;
+
+    let offer_amount: u64 = spl_token::ui_amount_to_amount(10.0, 9);
+    let (nonces, _) = instruction::find_nonces_pda(&test.program_id, &test.payer.pubkey());
+    let approve_ix = spl_token::instruction::approve(
+        &spl_token::id(),
+        &test.offer_src,
+        &nonces,
+        &test.payer.pubkey(),
+        &[&test.payer.pubkey()],
+        offer_amount,
+    ).unwrap();
+    let ixs = [instruction::init_nonces(&test.program_id, &test.payer.pubkey()), approve_ix];
+    process_ixs(&vec![&test.payer], test.payer.pubkey(), &ixs, &test.conn).await.unwrap();
+
+    let order = SignedOrder {
+        maker: test.payer.pubkey(),
+        offer_mint: test.offer_mint,
+        trade_mint: test.trade_mint,
+        trade_dst: test.trade_dst,
+        offer_amount,
+        trade_amount: spl_token::ui_amount_to_amount(2.0, 9),
+        nonce: 0,
+        expires_at: 0,
+    };
+    let signature = test.payer.sign_message(&order.try_to_vec().unwrap());
+    let ixs = instruction::fill_signed_order(
+        &test.program_id,
+        &test.wallet2.pubkey(),
+        &order,
+        signature.as_ref(),
+        &test.offer_src,
+        &test.trade_src,
+        &test.offer_dst,
+        &test.fee_ata,
+    );
+    process_ixs(&vec![&test.payer, &test.wallet2], test.payer.pubkey(), &ixs, &test.conn).await.unwrap();
+
+    // no trade was created for the order, but it is counted as filled
+    assert_eq!(load_stats::<AccountStats>(&test, StatsAccount::Program).await.trades, 1);
+    let offer_stats = load_stats::<AccountMintStats>(&test, StatsAccount::Mint(test.offer_mint)).await;
+    assert_eq!((offer_stats.trades, offer_stats.volume), (1, offer_amount as u128));
+    let maker_stats = load_stats::<AccountMakerStats>(&test, maker).await;
+    assert_eq!((maker_stats.created, maker_stats.filled), (0, 1));
+}
+
+#[tokio::test]
+async fn test_stats_count_from_init() {
+    let test = TestData::init().await;
+
+    // fills before the stats accounts exist aren't counted
+    create_test_trade(&test).await;
+    init_stats(&test, StatsAccount::Program).await;
+    init_stats(&test, StatsAccount::Maker(test.payer.pubkey())).await;
+
+    let offer_amount: u64 = spl_token::ui_amount_to_amount(10.0, 9);
+    let trade_amount: u64 = spl_token::ui_amount_to_amount(2.0, 9);
+    let (ix, _, _) = make_trade_ix(&test, offer_amount, trade_amount, None);
+    process_ix(&vec![&test.payer, &test.wallet2], test.payer.pubkey(), ix, &test.conn).await.unwrap();
+
+    assert_eq!(load_stats::<AccountStats>(&test, StatsAccount::Program).await.trades, 1);
+    let maker_stats = load_stats::<AccountMakerStats>(&test, StatsAccount::Maker(test.payer.pubkey())).await;
+    assert_eq!((maker_stats.created, maker_stats.filled), (0, 1));
+}
+
+#[tokio::test]
+async fn test_stats_of_cancelled_trade() {
+    let test = TestData::init().await;
+    let maker = StatsAccount::Maker(test.payer.pubkey());
+    init_stats(&test, maker).await;
+
+    let trade_amount: u64 = spl_token::ui_amount_to_amount(2.0, 9);
+    let (ix, _, _) = init_expiring_trade_ix(&test, trade_amount, TradeMode::Delegate, 0, 0, None);
+    process_ix(&vec![&test.payer], test.payer.pubkey(), ix, &test.conn).await.unwrap();
+
+    // the trade goes stale once the owner takes the allowance back
+    let revoke_ix = spl_token::instruction::revoke(
+        &spl_token::id(),
+        &test.offer_src,
+        &test.payer.pubkey(),
+        &[&test.payer.pubkey()],
+    ).unwrap();
+    process_ix(&vec![&test.payer], test.payer.pubkey(), revoke_ix, &test.conn).await.unwrap();
+
+    let ix = instruction::close_stale_trade(
+        &test.program_id,
+        &test.wallet2.pubkey(),
+        &test.trade_account_keypair.pubkey(),
+        &test.offer_src,
+        &test.payer.pubkey(),
+    );
+    process_ix(&vec![&test.payer, &test.wallet2], test.payer.pubkey(), ix, &test.conn).await.unwrap();
+
+    let maker_stats = load_stats::<AccountMakerStats>(&test, maker).await;
+    assert_eq!((maker_stats.created, maker_stats.filled, maker_stats.cancelled), (1, 0, 1));
+}
+
+// Creates the trade of test with the args for an hour from now, along with the stats account of its owner, then moves
+// the clock to an hour from now
+async fn create_ending_trade(
+    test: &TestData,
+    context: &mut ProgramTestContext,
+    args: impl FnOnce(i64) -> CreateTradeArgs,
+) {
+    init_stats(test, StatsAccount::Maker(test.payer.pubkey())).await;
+    let ends_at = test.conn.borrow_mut().get_sysvar::<Clock>().await.unwrap().unix_timestamp + 3600;
+    create_trade_with(test, args(ends_at)).await.unwrap();
+    warp_clock_to(context, ends_at).await;
+}
+
+async fn assert_cancelled(test: &TestData) {
+    let maker_stats = load_stats::<AccountMakerStats>(test, StatsAccount::Maker(test.payer.pubkey())).await;
+    assert_eq!((maker_stats.created, maker_stats.filled, maker_stats.cancelled), (1, 0, 1));
+}
+
+#[tokio::test]
+async fn test_stats_of_unfilled_forward_trade() {
+    let (test, mut context) = TestData::init_with_context().await;
+    create_ending_trade(&test, &mut context, |settles_at| CreateTradeArgs {
+        mode: TradeMode::Forward,
+        settlement: Some(Settlement { settles_at }),
+        ..CreateTradeArgs::default()
+    }).await;
+
+    let ix = instruction::settle(&test.program_id, &test.trade_account_keypair.pubkey(), &load_trade(&test).await);
+    process_ix(&vec![&test.payer], test.payer.pubkey(), ix, &test.conn).await.unwrap();
+    assert_cancelled(&test).await;
+}
+
+#[tokio::test]
+async fn test_stats_of_released_trade() {
+    let (test, mut context) = TestData::init_with_context().await;
+    let arbiter = Keypair::new();
+    create_ending_trade(&test, &mut context, |dispute_ends_at| CreateTradeArgs {
+        mode: TradeMode::Arbitrated,
+        arbitration: Some(Arbitration { arbiter: arbiter.pubkey(), dispute_ends_at }),
+        ..CreateTradeArgs::default()
+    }).await;
+
+    let ix = instruction::arbiter_release(
+        &test.program_id,
+        &arbiter.pubkey(),
+        &test.trade_account_keypair.pubkey(),
+        &test.offer_src,
+        &test.payer.pubkey(),
+        None,
+    );
+    process_ix(&vec![&test.payer, &arbiter], test.payer.pubkey(), ix, &test.conn).await.unwrap();
+    assert_cancelled(&test).await;
+}
+
+#[tokio::test]
+async fn test_stats_of_expired_option() {
+    let (test, mut context) = TestData::init_with_context().await;
+    create_ending_trade(&test, &mut context, |expires_at| CreateTradeArgs {
+        mode: TradeMode::CoveredCall,
+        expires_at,
+        call: Some(CoveredCall { premium: 1 }),
+        ..CreateTradeArgs::default()
+    }).await;
+
+    let ix = instruction::reclaim_underlying(
+        &test.program_id,
+        &test.payer.pubkey(),
+        &test.trade_account_keypair.pubkey(),
+        &test.offer_src,
+    );
+    process_ix(&vec![&test.payer], test.payer.pubkey(), ix, &test.conn).await.unwrap();
+    assert_cancelled(&test).await;
+}
+
+#[tokio::test]
+async fn test_init_prefunded_stats() {
+    let test = TestData::init().await;
+    let maker = StatsAccount::Maker(test.payer.pubkey());
+
+    // lamports sent to the stats account beforehand, enough for an empty account, don't keep it from being created
+    let (stats_account, _) = instruction::find_stats(&test.program_id, &maker);
+    let ix = system_instruction::transfer(&test.payer.pubkey(), &stats_account, minimum_balance_rent_exempt(0));
+    process_ix(&vec![&test.payer], test.payer.pubkey(), ix, &test.conn).await.unwrap();
+
+    init_stats(&test, maker).await;
+    assert_eq!(load_stats::<AccountMakerStats>(&test, maker).await.maker, test.payer.pubkey());
+}
+
+#[tokio::test]
+#[should_panic(expected = "transport transaction error: Error processing Instruction 0: account already initialized")]
+async fn test_init_stats_twice() {
+    let test = TestData::init().await;
+    init_stats(&test, StatsAccount::Program).await;
+
+    // paid by another wallet, so it isn't taken for the same transaction
+    let ix = instruction::init_stats(&test.program_id, &test.wallet2.pubkey(), StatsAccount::Program);
+    let panic_on = process_ix(&vec![&test.payer, &test.wallet2], test.payer.pubkey(), ix, &test.conn).await.unwrap_err();
+    panic!("{}", panic_on.to_string());
+}
@@ -0,0 +1,131 @@
+mod lib;
+
+use {
+    borsh::BorshDeserialize,
+    lib::*,
+    solana_program_test::*,
+    solana_sdk::{
+        pubkey::Pubkey,
+        signature::{Keypair, Signer},
+    },
+};
+use ::trader::{
+    instruction::{self, TradeFillAccounts},
+    state::{AccountTrade, TradeMode},
+};
+
+#[tokio::test]
+async fn test_transfer_trade_authority() {
+    let test = TestData::init().await;
+    create_test_trade(&test).await;
+
+    let new_authority = Keypair::new();
+    let recent_blockhash = test.conn.borrow_mut().get_latest_blockhash().await.unwrap();
+    let trade_dst = token_account(&new_authority, &test.payer, test.trade_mint, recent_blockhash, &test.conn).await;
+
+    let ix = instruction::transfer_trade_authority(
+        &test.program_id,
+        &test.payer.pubkey(),
+        &test.trade_account_keypair.pubkey(),
+        &new_authority.pubkey(),
+        true,
+        Some(&trade_dst),
+    );
+    process_ix(&vec![&test.payer, &new_authority], test.payer.pubkey(), ix, &test.conn).await.unwrap();
+
+    let trade_ai = test.conn.borrow_mut().get_account(test.trade_account_keypair.pubkey()).await.unwrap().unwrap();
+    let trade_account = AccountTrade::try_from_slice(&trade_ai.data).unwrap();
+    assert_eq!(trade_account.authority, new_authority.pubkey());
+    assert_eq!(trade_account.trade_dst_account, trade_dst);
+
+    // the trade is settled with the new owner
+    let offer_amount: u64 = spl_token::ui_amount_to_amount(10.0, 9);
+    let trade_amount: u64 = spl_token::ui_amount_to_amount(2.0, 9);
+    let accounts = TradeFillAccounts { offer_owner: new_authority.pubkey(), trade_dst, ..trade_fill_accounts(&test) };
+    let ix = instruction::make_trade(
+        &test.program_id,
+        &test.wallet2.pubkey(),
+        &accounts,
+        offer_amount,
+        trade_amount,
+        None,
+    );
+    process_ix(&vec![&test.payer, &test.wallet2], test.payer.pubkey(), ix, &test.conn).await.unwrap();
+
+    let trade_fee: u64 = 100000000;
+    assert_eq!(get_spl_account(trade_dst, &test.conn).await.amount, trade_amount - trade_fee);
+    assert_eq!(get_spl_account(test.offer_src, &test.conn).await.owner, new_authority.pubkey());
+    // the rent of the trade account went to the new owner
+    let balance = test.conn.borrow_mut().get_balance(new_authority.pubkey()).await.unwrap();
+    assert_eq!(balance, trade_ai.lamports);
+}
+
+#[tokio::test]
+#[should_panic(expected = "transport transaction error: Error processing Instruction 0: custom program error: 0x0")]
+async fn test_transfer_trade_authority_previous_owner() {
+    let test = TestData::init().await;
+    create_test_trade(&test).await;
+
+    let new_authority = Pubkey::new_unique();
+    let ix = instruction::transfer_trade_authority(
+        &test.program_id,
+        &test.payer.pubkey(),
+        &test.trade_account_keypair.pubkey(),
+        &new_authority,
+        false,
+        None,
+    );
+    process_ix(&vec![&test.payer], test.payer.pubkey(), ix, &test.conn).await.unwrap();
+
+    // the previous owner can't change the trade anymore
+    let ix = instruction::decrease_offer(
+        &test.program_id,
+        &test.payer.pubkey(),
+        &test.trade_account_keypair.pubkey(),
+        &test.offer_src,
+        &test.offer_dst,
+        1,
+        false,
+    );
+    let panic_on = process_ix(&vec![&test.payer], test.payer.pubkey(), ix, &test.conn).await.unwrap_err();
+    panic!("{}", panic_on.to_string());
+}
+
+#[tokio::test]
+#[should_panic(expected = "transport transaction error: Error processing Instruction 0: custom program error: 0x18")]
+async fn test_transfer_delegated_trade_authority() {
+    let test = TestData::init().await;
+
+    let (ix, _, _) = init_trade_with_mode_ix(&test, 2, TradeMode::Delegate, None);
+    process_ix(&vec![&test.payer], test.payer.pubkey(), ix, &test.conn).await.unwrap();
+
+    let ix = instruction::transfer_trade_authority(
+        &test.program_id,
+        &test.payer.pubkey(),
+        &test.trade_account_keypair.pubkey(),
+        &Pubkey::new_unique(),
+        false,
+        None,
+    );
+    let panic_on = process_ix(&vec![&test.payer], test.payer.pubkey(), ix, &test.conn).await.unwrap_err();
+    panic!("{}", panic_on.to_string());
+}
+
+#[tokio::test]
+#[should_panic(expected = "transport transaction error: Error processing Instruction 0: custom program error: 0x7")]
+async fn test_transfer_trade_authority_checks_trade_dst() {
+    let test = TestData::init().await;
+    create_test_trade(&test).await;
+
+    // offer_dst holds offer tokens
+    let ix = instruction::transfer_trade_authority(
+        &test.program_id,
+        &test.payer.pubkey(),
+        &test.trade_account_keypair.pubkey(),
+        &Pubkey::new_unique(),
+        false,
+        Some(&test.offer_dst),
+    );
+    let panic_on = process_ix(&vec![&test.payer], test.payer.pubkey(), ix, &test.conn).await.unwrap_err();
+    panic!("{}", panic_on.to_string());
+}
@@ -0,0 +1,215 @@
+mod lib;
+
+use {
+    lib::*,
+    solana_program_test::*,
+    solana_sdk::{
+        instruction::Instruction,
+        signature::{Keypair, Signer},
+        sysvar::clock::Clock,
+    },
+};
+use ::trader::{
+    instruction::{self, CreateTradeArgs, TradeFillAccounts},
+    instructions::Vesting,
+    state::{AccountTrade, TradeMode},
+};
+
+const CLIFF: i64 = 600;
+const DURATION: i64 = 3600;
+
+// Creates the trade of test in Escrow mode, vesting its offer once filled
+async fn create_vesting_trade(test: &TestData) {
+    let vesting = Vesting { cliff: CLIFF, duration: DURATION };
+    create_trade_with(test, CreateTradeArgs { vesting: Some(vesting), ..CreateTradeArgs::default() }).await.unwrap();
+}
+
+// Creates the vesting escrow, the ATA of the pda for the offer mint, and fills the trade by wallet2, returning when it
+// was filled
+async fn fill_vesting_trade(test: &TestData) -> i64 {
+    let (pda, _) = instruction::find_trade_pda(&test.program_id, &test.trade_account_keypair.pubkey());
+    let escrow_ix = spl_associated_token_account::create_associated_token_account(
+        &test.payer.pubkey(),
+        &pda,
+        &test.offer_mint,
+    );
+    process_ix(&vec![&test.payer], test.payer.pubkey(), escrow_ix, &test.conn).await.unwrap();
+
+    let escrow = instruction::find_vesting_escrow(
+        &test.program_id,
+        &test.trade_account_keypair.pubkey(),
+        &test.offer_mint,
+    );
+    let accounts = TradeFillAccounts { vesting_escrow: Some(escrow), ..trade_fill_accounts(test) };
+    let offer_amount: u64 = spl_token::ui_amount_to_amount(10.0, 9);
+    let trade_amount: u64 = spl_token::ui_amount_to_amount(2.0, 9);
+    let ix = instruction::make_trade(
+        &test.program_id,
+        &test.wallet2.pubkey(),
+        &accounts,
+        offer_amount,
+        trade_amount,
+        None,
+    );
+    process_ix(&vec![&test.payer, &test.wallet2], test.payer.pubkey(), ix, &test.conn).await.unwrap();
+
+    test.conn.borrow_mut().get_sysvar::<Clock>().await.unwrap().unix_timestamp
+}
+
+fn claim_ix(test: &TestData, trade_account: &AccountTrade) -> Instruction {
+    instruction::claim_vested(&test.program_id, &test.trade_account_keypair.pubkey(), trade_account)
+}
+
+#[tokio::test]
+async fn test_claim_vested_offer() {
+    let (test, mut context) = TestData::init_with_context().await;
+    create_vesting_trade(&test).await;
+    let filled_at = fill_vesting_trade(&test).await;
+
+    // the offer is held by the pda while it vests, and the trade amount is delivered right away
+    let offer_amount: u64 = spl_token::ui_amount_to_amount(10.0, 9);
+    let escrow = instruction::find_vesting_escrow(
+        &test.program_id,
+        &test.trade_account_keypair.pubkey(),
+        &test.offer_mint,
+    );
+    assert_eq!(get_spl_account(escrow, &test.conn).await.amount, offer_amount);
+    assert_eq!(get_spl_account(test.offer_dst, &test.conn).await.amount, 0);
+    assert!(get_spl_account(test.trade_dst, &test.conn).await.amount > 0);
+    assert_eq!(get_spl_account(test.offer_src, &test.conn).await.owner, test.payer.pubkey());
+    let trade_account = load_trade(&test).await;
+    assert_eq!(trade_account.taker, test.wallet2.pubkey());
+    assert_eq!(trade_account.vesting_start, filled_at);
+
+    // halfway through, half of the offer can be claimed
+    warp_clock_to(&mut context, filled_at + DURATION / 2).await;
+    let ix = claim_ix(&test, &trade_account);
+    process_ix(&vec![&test.wallet2], test.wallet2.pubkey(), ix, &test.conn).await.unwrap();
+    assert_eq!(get_spl_account(test.offer_dst, &test.conn).await.amount, offer_amount / 2);
+    assert_eq!(load_trade(&test).await.claimed, offer_amount / 2);
+
+    // and the rest once it's over, which closes the escrow and the trade
+    warp_clock_to(&mut context, filled_at + DURATION).await;
+    let ix = claim_ix(&test, &trade_account);
+    process_ix(&vec![&test.payer, &test.wallet2], test.payer.pubkey(), ix, &test.conn).await.unwrap();
+    assert_eq!(get_spl_account(test.offer_dst, &test.conn).await.amount, offer_amount);
+    assert_eq!(test.conn.borrow_mut().get_account(escrow).await.unwrap(), None);
+    assert_eq!(test.conn.borrow_mut().get_account(test.trade_account_keypair.pubkey()).await.unwrap(), None);
+}
+
+#[tokio::test]
+async fn test_claim_vested_offer_with_dust_in_escrow() {
+    let (test, mut context) = TestData::init_with_context().await;
+    create_vesting_trade(&test).await;
+    let filled_at = fill_vesting_trade(&test).await;
+
+    // anyone can send tokens to the escrow, which would keep it from being closed
+    let escrow = instruction::find_vesting_escrow(
+        &test.program_id,
+        &test.trade_account_keypair.pubkey(),
+        &test.offer_mint,
+    );
+    let recent_blockhash = test.conn.borrow_mut().get_latest_blockhash().await.unwrap();
+    mint_to_account(&test.payer, &test.payer, test.offer_mint, escrow, 1, recent_blockhash, &test.conn).await;
+
+    // the last claim takes the dust along with the offer
+    warp_clock_to(&mut context, filled_at + DURATION).await;
+    let ix = claim_ix(&test, &load_trade(&test).await);
+    process_ix(&vec![&test.payer, &test.wallet2], test.payer.pubkey(), ix, &test.conn).await.unwrap();
+    let offer_amount: u64 = spl_token::ui_amount_to_amount(10.0, 9);
+    assert_eq!(get_spl_account(test.offer_dst, &test.conn).await.amount, offer_amount + 1);
+    assert_eq!(test.conn.borrow_mut().get_account(escrow).await.unwrap(), None);
+    assert_eq!(test.conn.borrow_mut().get_account(test.trade_account_keypair.pubkey()).await.unwrap(), None);
+}
+
+#[tokio::test]
+#[should_panic(expected = "transport transaction error: Error processing Instruction 0: custom program error: 0x27")]
+async fn test_claim_before_cliff() {
+    let test = TestData::init().await;
+    create_vesting_trade(&test).await;
+    fill_vesting_trade(&test).await;
+
+    let ix = claim_ix(&test, &load_trade(&test).await);
+    let panic_on = process_ix(&vec![&test.wallet2], test.wallet2.pubkey(), ix, &test.conn).await.unwrap_err();
+    panic!("{}", panic_on.to_string());
+}
+
+#[tokio::test]
+#[should_panic(expected = "transport transaction error: Error processing Instruction 0: custom program error: 0x0")]
+async fn test_claim_by_other_wallet() {
+    let (test, mut context) = TestData::init_with_context().await;
+    create_vesting_trade(&test).await;
+    let filled_at = fill_vesting_trade(&test).await;
+
+    // the offer only vests for the taker
+    warp_clock_to(&mut context, filled_at + DURATION).await;
+    let other = Keypair::new();
+    let mut ix = claim_ix(&test, &load_trade(&test).await);
+    ix.accounts[0].pubkey = other.pubkey();
+    let panic_on = process_ix(&vec![&test.payer, &other], test.payer.pubkey(), ix, &test.conn).await.unwrap_err();
+    panic!("{}", panic_on.to_string());
+}
+
+#[tokio::test]
+#[should_panic(expected = "transport transaction error: Error processing Instruction 0: custom program error: 0x24")]
+async fn test_fill_vesting_trade_twice() {
+    let test = TestData::init().await;
+    create_vesting_trade(&test).await;
+    fill_vesting_trade(&test).await;
+
+    let accounts = TradeFillAccounts {
+        vesting_escrow: Some(instruction::find_vesting_escrow(
+            &test.program_id,
+            &test.trade_account_keypair.pubkey(),
+            &test.offer_mint,
+        )),
+        ..trade_fill_accounts(&test)
+    };
+    let offer_amount: u64 = spl_token::ui_amount_to_amount(10.0, 9);
+    let trade_amount: u64 = spl_token::ui_amount_to_amount(2.0, 9);
+    let ix = instruction::make_trade(
+        &test.program_id,
+        &test.wallet2.pubkey(),
+        &accounts,
+        offer_amount,
+        trade_amount,
+        None,
+    );
+    // paid for by the taker this time, so it isn't the same transaction
+    let panic_on = process_ix(&vec![&test.wallet2], test.wallet2.pubkey(), ix, &test.conn).await.unwrap_err();
+    panic!("{}", panic_on.to_string());
+}
+
+#[tokio::test]
+#[should_panic(expected = "transport transaction error: Error processing Instruction 0: custom program error: 0x26")]
+async fn test_claim_trade_not_vesting() {
+    let test = TestData::init().await;
+    create_test_trade(&test).await;
+
+    let trade_account = AccountTrade { taker: test.wallet2.pubkey(), ..load_trade(&test).await };
+    let ix = claim_ix(&test, &trade_account);
+    let panic_on = process_ix(&vec![&test.wallet2], test.wallet2.pubkey(), ix, &test.conn).await.unwrap_err();
+    panic!("{}", panic_on.to_string());
+}
+
+#[tokio::test]
+#[should_panic(expected = "transport transaction error: Error processing Instruction 0: invalid program argument")]
+async fn test_create_trade_cliff_after_end() {
+    let test = TestData::init().await;
+
+    let vesting = Vesting { cliff: DURATION + 1, duration: DURATION };
+    let args = CreateTradeArgs { vesting: Some(vesting), ..CreateTradeArgs::default() };
+    let panic_on = create_trade_with(&test, args).await.unwrap_err();
+    panic!("{}", panic_on.to_string());
+}
+
+#[tokio::test]
+#[should_panic(expected = "transport transaction error: Error processing Instruction 0: invalid instruction data")]
+async fn test_create_forward_trade_with_vesting() {
+    let test = TestData::init().await;
+
+    let vesting = Vesting { cliff: 0, duration: DURATION };
+    let args = CreateTradeArgs { mode: TradeMode::Forward, vesting: Some(vesting), ..CreateTradeArgs::default() };
+    let panic_on = create_trade_with(&test, args).await.unwrap_err();
+    panic!("{}", panic_on.to_string());
+}
@@ -0,0 +1,70 @@
+//! A stand-in compliance hook for the trader program, see `trader::instructions::ComplianceHook`. It screens both
+//! sides of a trade against the `AccessList` stored in the account it's invoked with.
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint,
+    msg,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+};
+use thiserror::Error;
+use trader::instructions::ComplianceCheck;
+
+#[cfg(not(feature = "exclude_entrypoint"))]
+entrypoint!(process_instruction);
+
+#[derive(Clone, Debug, Eq, Error, PartialEq)]
+pub enum HookError {
+    #[error("Wallet not allowed")]
+    WalletNotAllowed,
+}
+
+impl From<HookError> for ProgramError {
+    fn from(e: HookError) -> Self {
+        ProgramError::Custom(e as u32)
+    }
+}
+
+// Layout of the list account, owned by the hook program. With allow set only the wallets listed can trade, otherwise
+// they are the ones that can't
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Default, PartialEq)]
+pub struct AccessList {
+    pub allow: bool,
+    pub wallets: Vec<Pubkey>,
+}
+
+impl AccessList {
+    pub fn allows(&self, wallet: &Pubkey) -> bool {
+        self.wallets.contains(wallet) == self.allow
+    }
+}
+
+// 0. `[]` the list account
+// The instruction data is a `ComplianceCheck`, and the trade is allowed when both the maker and the taker are
+pub fn process_instruction(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> entrypoint::ProgramResult {
+    let check = ComplianceCheck::try_from_slice(instruction_data)
+        .map_err(|_| ProgramError::InvalidInstructionData)?;
+
+    let list_ai = next_account_info(&mut accounts.iter())?;
+    // anyone else could write a list that allows everyone
+    if list_ai.owner != program_id {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    let list = AccessList::deserialize(&mut &list_ai.data.borrow()[..])
+        .map_err(|_| ProgramError::InvalidAccountData)?;
+
+    for wallet in [check.maker, check.taker] {
+        if !list.allows(&wallet) {
+            msg!("{} is not allowed to trade", wallet);
+            return Err(HookError::WalletNotAllowed.into());
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,26 @@
+//! Merkle allowlists of the wallets that can fill a trade. Each leaf commits to a wallet and its cap, the most offer
+//! tokens it can get from a fill, and the trade only stores the root of the tree. Takers prove they are in it with the
+//! hashes of the siblings of their leaf, from the bottom of the tree up.
+
+use solana_program::{hash::hashv, pubkey::Pubkey};
+
+// The cap of a wallet that can fill trades of any size
+pub const NO_CAP: u64 = u64::MAX;
+
+// Leaves and nodes are hashed with a different prefix, so that a node can't be passed off as a leaf
+const LEAF_PREFIX: &[u8] = &[0];
+const NODE_PREFIX: &[u8] = &[1];
+
+pub fn leaf(wallet: &Pubkey, cap: u64) -> [u8; 32] {
+    hashv(&[LEAF_PREFIX, wallet.as_ref(), &cap.to_le_bytes()]).to_bytes()
+}
+
+// The parent of two nodes. They are sorted first, so that proofs don't need to tell which side each sibling is on
+pub fn parent(a: &[u8; 32], b: &[u8; 32]) -> [u8; 32] {
+    let (left, right) = if a <= b { (a, b) } else { (b, a) };
+    hashv(&[NODE_PREFIX, left, right]).to_bytes()
+}
+
+pub fn verify(root: &[u8; 32], leaf: [u8; 32], proof: &[[u8; 32]]) -> bool {
+    proof.iter().fold(leaf, |node, sibling| parent(&node, sibling)) == *root
+}
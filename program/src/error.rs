@@ -30,6 +30,126 @@ pub enum TradeError {
 
     #[error("Unexpected account")]
     UnexpectedAccount,
+
+    #[error("Insufficient delegation")]
+    InsufficientDelegation,
+
+    #[error("Insufficient offer balance")]
+    InsufficientOfferBalance,
+
+    #[error("Trade expired")]
+    TradeExpired,
+
+    #[error("Trade not stale")]
+    TradeNotStale,
+
+    #[error("Offer account frozen")]
+    OfferAccountFrozen,
+
+    #[error("Native offer account")]
+    NativeOfferAccount,
+
+    #[error("Offer account has a close authority")]
+    OfferCloseAuthority,
+
+    #[error("Invalid pda")]
+    InvalidPda,
+
+    #[error("Invalid token program")]
+    InvalidTokenProgram,
+
+    #[error("Account not writable")]
+    AccountNotWritable,
+
+    #[error("Duplicate account")]
+    DuplicateAccount,
+
+    #[error("Offer mint missmatch")]
+    OfferMintMissmatch,
+
+    #[error("Stale price")]
+    StalePrice,
+
+    #[error("Invalid price")]
+    InvalidPrice,
+
+    #[error("Price below limit")]
+    PriceBelowLimit,
+
+    #[error("Offer not in escrow")]
+    OfferNotInEscrow,
+
+    #[error("Invalid splits")]
+    InvalidSplits,
+
+    #[error("Arbiter signature missing")]
+    ArbiterSignatureMissing,
+
+    #[error("Dispute not over")]
+    DisputeNotOver,
+
+    #[error("Invalid signature")]
+    InvalidSignature,
+
+    #[error("Nonce already used")]
+    NonceUsed,
+
+    #[error("Nonce out of range")]
+    NonceOutOfRange,
+
+    #[error("Not an option")]
+    NotAnOption,
+
+    #[error("Trade is an option")]
+    OptionTrade,
+
+    #[error("Option already bought")]
+    OptionAlreadyBought,
+
+    #[error("Option not expired")]
+    OptionNotExpired,
+
+    #[error("Not a forward trade")]
+    NotAForward,
+
+    #[error("Trade awaits settlement")]
+    SettlementPending,
+
+    #[error("Settlement not due")]
+    SettlementNotDue,
+
+    #[error("Not a vesting trade")]
+    NotVesting,
+
+    #[error("Nothing vested")]
+    NothingVested,
+
+    #[error("Taker not in the allowlist")]
+    NotAllowlisted,
+
+    #[error("Allowlist cap exceeded")]
+    AllowlistCapExceeded,
+
+    #[error("Account doesn't hold the receipt")]
+    WrongReceipt,
+
+    #[error("Receipt not delegated")]
+    ReceiptNotDelegated,
+
+    #[error("Not allowed on trades with a receipt")]
+    ReceiptTrade,
+
+    #[error("Mint not allowed")]
+    MintNotAllowed,
+
+    #[error("Amount outside the limits of the mint")]
+    AmountOutsideLimits,
+
+    #[error("Volume cap of the mint exceeded")]
+    VolumeCapExceeded,
+
+    #[error("Mint list not empty")]
+    MintListNotEmpty,
 }
 
 impl From<TradeError> for ProgramError {
@@ -0,0 +1,150 @@
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{
+    log::sol_log_data,
+    pubkey::Pubkey,
+};
+use crate::state::{MintLimits, MintListMode, StatsAccount};
+
+/// Events emitted by the program with `sol_log_data` on every state change. Each event is borsh encoded and logged
+/// as a single data field, which the runtime renders as `Program data: <base64>`.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub enum TradeEvent {
+    TradeCreated {
+        trade: Pubkey,
+        authority: Pubkey,
+        offer_token_account: Pubkey,
+        trade_mint: Pubkey,
+        offer_amount: u64,
+        trade_amount: u64,
+    },
+
+    TradeFilled {
+        trade: Pubkey,
+        authority: Pubkey,
+        taker: Pubkey,
+        offer_amount: u64,
+        trade_amount: u64,
+    },
+
+    TradeCancelled {
+        trade: Pubkey,
+        authority: Pubkey,
+    },
+
+    // trade is the nonces account of the maker for signed orders
+    FeeCollected {
+        trade: Pubkey,
+        fee_account: Pubkey,
+        mint: Pubkey,
+        amount: u64,
+    },
+
+    OfferChanged {
+        trade: Pubkey,
+        authority: Pubkey,
+        offer_amount: u64,
+        trade_amount: u64,
+    },
+
+    AuthorityTransferred {
+        trade: Pubkey,
+        authority: Pubkey,
+        new_authority: Pubkey,
+    },
+
+    // offer_dst is the token account of the taker the offer was released to, None if it was returned to the owner
+    ArbiterReleased {
+        trade: Pubkey,
+        authority: Pubkey,
+        arbiter: Pubkey,
+        offer_dst: Option<Pubkey>,
+    },
+
+    // nonces is the account keeping the nonces of the signed orders of the maker
+    NoncesInitialised {
+        maker: Pubkey,
+        nonces: Pubkey,
+    },
+
+    SignedOrderFilled {
+        maker: Pubkey,
+        taker: Pubkey,
+        nonce: u64,
+        offer_amount: u64,
+        trade_amount: u64,
+    },
+
+    // every order of the maker with a nonce below min_nonce is cancelled along with nonces
+    OrdersCancelled {
+        maker: Pubkey,
+        min_nonce: u64,
+        nonces: Vec<u64>,
+    },
+
+    OptionBought {
+        trade: Pubkey,
+        authority: Pubkey,
+        holder: Pubkey,
+        premium: u64,
+    },
+
+    // the underlying of an expired option returned to the writer
+    OptionReclaimed {
+        trade: Pubkey,
+        authority: Pubkey,
+    },
+
+    // a trade in Forward mode filled by the taker, whose trade amount after the fee is held in escrow until the trade
+    // is settled, which emits TradeFilled
+    ForwardCommitted {
+        trade: Pubkey,
+        authority: Pubkey,
+        taker: Pubkey,
+        offer_amount: u64,
+        trade_amount: u64,
+    },
+
+    // claimed is the total claimed so far, the trade is closed once it reaches the offer amount
+    VestedClaimed {
+        trade: Pubkey,
+        taker: Pubkey,
+        amount: u64,
+        claimed: u64,
+    },
+
+    // the receipt of a trade burnt as it was filled, holder being who got the trade amount for it
+    ReceiptRedeemed {
+        trade: Pubkey,
+        receipt_mint: Pubkey,
+        holder: Pubkey,
+    },
+
+    MintListModeSet {
+        mode: MintListMode,
+    },
+
+    // listed is false when the mint was removed from the mint list
+    MintListUpdated {
+        mint: Pubkey,
+        listed: bool,
+    },
+
+    MintLimitsSet {
+        mint: Pubkey,
+        limits: MintLimits,
+    },
+
+    // account is the stats account created, of what stats says
+    StatsInitialised {
+        stats: StatsAccount,
+        account: Pubkey,
+    },
+}
+
+impl TradeEvent {
+    pub fn emit(&self) {
+        // serializing into a Vec can't fail
+        let data = self.try_to_vec().unwrap();
+        sol_log_data(&[&data]);
+    }
+}
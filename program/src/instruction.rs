@@ -0,0 +1,704 @@
+//! Builders for the instructions of the trader program. These are the single source of truth for the accounts each
+//! `Action` expects and their order, see `instructions.rs` for the meaning of each one.
+
+use borsh::BorshSerialize;
+use crate::{
+    instructions::{
+        Action, AllowlistProof, Arbitration, ComplianceHook, CoveredCall, OraclePeg, Settlement, SignedOrder, TradeFill,
+        Vesting, ED25519_OFFSETS_SIZE, ED25519_OFFSETS_START,
+    },
+    state::{
+        AccountTrade, MintLimits, MintListMode, ProceedsSplit, StatsAccount, TradeMode, LISTED_MINT_SEED,
+        MINT_LIMITS_SEED, MINT_LIST_SEED, NONCES_SEED,
+    },
+};
+use solana_program::{
+    clock::UnixTimestamp,
+    ed25519_program,
+    instruction::{AccountMeta, Instruction},
+    pubkey::{Pubkey, PUBKEY_BYTES},
+    system_program,
+    sysvar,
+};
+
+/// The accounts of a trade being filled by MakeTrade or MakeTrades.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TradeFillAccounts {
+    pub trade: Pubkey,
+    pub offer_src: Pubkey,
+    pub trade_dst: Pubkey,
+    pub trade_src: Pubkey,
+    pub offer_dst: Pubkey,
+    pub offer_owner: Pubkey,
+    pub fee_account: Pubkey,
+    // the price account of an oracle-pegged trade, which goes after all other accounts
+    pub price_feed: Option<Pubkey>,
+    // the destinations of the trade amount after trade_dst, if the trade splits it, which follow the price account
+    pub split_dsts: Vec<Pubkey>,
+    // the arbiter of a trade in Arbitrated mode, who signs along with the taker and goes last
+    pub arbiter: Option<Pubkey>,
+    // the compliance hook program of the trade and its account, if it has one, which go last
+    pub hook: Option<(Pubkey, Pubkey)>,
+    // the trade escrow of a trade in Forward mode, see find_trade_escrow, which goes last
+    pub trade_escrow: Option<Pubkey>,
+    // the vesting escrow of a vesting trade, see find_vesting_escrow, which goes after the trade escrow
+    pub vesting_escrow: Option<Pubkey>,
+    // the receipt mint of a trade with a receipt and the token account holding it, which go last. Unlike the other
+    // trailing accounts it isn't set by set_trailing_accounts, as the holder of the receipt isn't part of the trade
+    pub receipt: Option<(Pubkey, Pubkey)>,
+    // the offer mint and the trade mint of the trade, whose mint limits accounts, see find_mint_limits, and stats
+    // accounts along with those of the program and of the offer owner, see find_stats, go after all other accounts.
+    // Every fill needs them
+    pub mints: Option<(Pubkey, Pubkey)>,
+}
+
+impl TradeFillAccounts {
+    // The accounts of the trade, without the trailing ones
+    pub fn to_account_metas(&self, program_id: &Pubkey) -> Vec<AccountMeta> {
+        let (pda, _) = find_trade_pda(program_id, &self.trade);
+
+        vec![
+            AccountMeta::new(self.trade, false),
+            AccountMeta::new_readonly(pda, false),
+            AccountMeta::new(self.offer_src, false),
+            AccountMeta::new(self.trade_dst, false),
+            AccountMeta::new(self.trade_src, false),
+            AccountMeta::new(self.offer_dst, false),
+            AccountMeta::new(self.offer_owner, false),
+            AccountMeta::new(self.fee_account, false),
+        ]
+    }
+
+    // The accounts of the trade that go after all other accounts
+    pub fn to_trailing_account_metas(&self, program_id: &Pubkey) -> Vec<AccountMeta> {
+        self.price_feed.iter().map(|price_feed| AccountMeta::new_readonly(*price_feed, false))
+            .chain(self.split_dsts.iter().map(|dst| AccountMeta::new(*dst, false)))
+            .chain(self.arbiter.iter().map(|arbiter| AccountMeta::new_readonly(*arbiter, true)))
+            .chain(self.hook.iter().flat_map(|(program, account)| vec![
+                AccountMeta::new_readonly(*program, false),
+                AccountMeta::new_readonly(*account, false),
+            ]))
+            .chain(self.trade_escrow.iter().map(|escrow| AccountMeta::new(*escrow, false)))
+            .chain(self.vesting_escrow.iter().map(|escrow| AccountMeta::new(*escrow, false)))
+            .chain(self.receipt.iter().flat_map(|(mint, account)| vec![
+                AccountMeta::new(*mint, false),
+                AccountMeta::new(*account, false),
+            ]))
+            .chain(self.mints.iter().flat_map(|(offer_mint, trade_mint)| vec![
+                AccountMeta::new(find_mint_limits(program_id, offer_mint).0, false),
+                AccountMeta::new(find_mint_limits(program_id, trade_mint).0, false),
+                AccountMeta::new(find_stats(program_id, &StatsAccount::Program).0, false),
+                AccountMeta::new(find_stats(program_id, &StatsAccount::Mint(*offer_mint)).0, false),
+                AccountMeta::new(find_stats(program_id, &StatsAccount::Mint(*trade_mint)).0, false),
+                AccountMeta::new(find_stats(program_id, &StatsAccount::Maker(self.offer_owner)).0, false),
+            ]))
+            .collect()
+    }
+
+    // Sets the trailing accounts to the ones the trade needs, from its state
+    pub fn set_trailing_accounts(&mut self, program_id: &Pubkey, trade: &AccountTrade) {
+        self.price_feed = if trade.is_pegged() { Some(trade.price_feed) } else { None };
+        self.split_dsts = trade.split_destinations();
+        self.arbiter = if trade.is_arbitrated() { Some(trade.arbiter) } else { None };
+        self.hook = if trade.is_hooked() { Some((trade.hook_program, trade.hook_account)) } else { None };
+        self.trade_escrow = if trade.is_forward() {
+            Some(find_trade_escrow(program_id, &self.trade, &trade.trade_mint))
+        } else {
+            None
+        };
+        self.vesting_escrow = if trade.is_vesting() {
+            Some(find_vesting_escrow(program_id, &self.trade, &trade.offer_mint))
+        } else {
+            None
+        };
+        self.mints = Some((trade.offer_mint, trade.trade_mint));
+    }
+}
+
+/// Finds the PDA of a trade, which takes over the offer while the trade is open.
+pub fn find_trade_pda(program_id: &Pubkey, trade: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[trade.as_ref()], program_id)
+}
+
+/// Finds the token account holding the trade amount of a trade in Forward mode until it is settled, the ATA of its PDA
+/// for the trade mint. The taker creates it before filling the trade.
+pub fn find_trade_escrow(program_id: &Pubkey, trade: &Pubkey, trade_mint: &Pubkey) -> Pubkey {
+    let (pda, _) = find_trade_pda(program_id, trade);
+    spl_associated_token_account::get_associated_token_address(&pda, trade_mint)
+}
+
+/// Finds the token account holding the offer of a vesting trade while it vests, the ATA of its PDA for the offer mint.
+/// The taker creates it before filling the trade.
+pub fn find_vesting_escrow(program_id: &Pubkey, trade: &Pubkey, offer_mint: &Pubkey) -> Pubkey {
+    let (pda, _) = find_trade_pda(program_id, trade);
+    spl_associated_token_account::get_associated_token_address(&pda, offer_mint)
+}
+
+/// Finds the mint list of the program, which may not exist.
+pub fn find_mint_list(program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[MINT_LIST_SEED], program_id)
+}
+
+/// Finds the account of a mint in the mint list, which only exists while the mint is listed.
+pub fn find_listed_mint(program_id: &Pubkey, mint: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[LISTED_MINT_SEED, mint.as_ref()], program_id)
+}
+
+/// Finds the limits account of a mint, which only exists once the program authority set limits on the mint.
+pub fn find_mint_limits(program_id: &Pubkey, mint: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[MINT_LIMITS_SEED, mint.as_ref()], program_id)
+}
+
+/// Finds a stats account, of the program, of a mint or of a maker, which only exists once someone created it with
+/// init_stats.
+pub fn find_stats(program_id: &Pubkey, stats: &StatsAccount) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&stats.seeds(), program_id)
+}
+
+/// Finds the nonces account of a maker, which tracks the nonces of its signed orders and is the delegate of the token
+/// accounts they are filled from.
+pub fn find_nonces_pda(program_id: &Pubkey, maker: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[NONCES_SEED, maker.as_ref()], program_id)
+}
+
+/// The mode and the options of a trade created with create_trade, which default to a trade in Escrow mode that never
+/// expires, without a bounty and without any of the options.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CreateTradeArgs {
+    pub mode: TradeMode,
+    pub expires_at: UnixTimestamp,
+    pub bounty: u64,
+    // the price account of an oracle-pegged trade, along with its peg
+    pub peg: Option<(Pubkey, OraclePeg)>,
+    pub splits: Vec<ProceedsSplit>,
+    pub arbitration: Option<Arbitration>,
+    pub hook: Option<ComplianceHook>,
+    pub call: Option<CoveredCall>,
+    pub settlement: Option<Settlement>,
+    pub vesting: Option<Vesting>,
+    pub allowlist: Option<[u8; 32]>,
+    // the receipt mint of a trade with a receipt, see Action::CreateTrade
+    pub receipt: Option<Pubkey>,
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn create_trade(
+    program_id: &Pubkey,
+    authority: &Pubkey,
+    trade: &Pubkey,
+    offer_src: &Pubkey,
+    offer_mint: &Pubkey,
+    trade_mint: &Pubkey,
+    trade_dst: &Pubkey,
+    trade_amount: u64,
+    args: CreateTradeArgs,
+) -> Instruction {
+    let CreateTradeArgs {
+        mode, expires_at, bounty, peg, splits, arbitration, hook, call, settlement, vesting, allowlist, receipt,
+    } = args;
+    let (pda, bump_seed) = find_trade_pda(program_id, trade);
+
+    let mut account_metas = vec![
+        AccountMeta::new_readonly(*authority, true),
+        AccountMeta::new(*trade, false),
+        AccountMeta::new(*offer_src, false),
+        AccountMeta::new_readonly(*trade_mint, false),
+        AccountMeta::new_readonly(*trade_dst, false),
+        AccountMeta::new_readonly(pda, false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new_readonly(*offer_mint, false),
+    ];
+    if let Some((price_feed, _)) = &peg {
+        account_metas.push(AccountMeta::new_readonly(*price_feed, false));
+    }
+    // the receipt is minted to the ATA of the owner for the receipt mint
+    if let Some(receipt_mint) = &receipt {
+        account_metas.push(AccountMeta::new(*receipt_mint, false));
+        account_metas.push(AccountMeta::new(
+            spl_associated_token_account::get_associated_token_address(authority, receipt_mint),
+            false,
+        ));
+    }
+    // both mints are checked against the mint list
+    account_metas.push(AccountMeta::new_readonly(find_mint_list(program_id).0, false));
+    account_metas.push(AccountMeta::new_readonly(find_listed_mint(program_id, offer_mint).0, false));
+    account_metas.push(AccountMeta::new_readonly(find_listed_mint(program_id, trade_mint).0, false));
+    // and against their limits
+    account_metas.push(AccountMeta::new_readonly(find_mint_limits(program_id, offer_mint).0, false));
+    account_metas.push(AccountMeta::new_readonly(find_mint_limits(program_id, trade_mint).0, false));
+    // the owner counts the trade as created
+    account_metas.push(AccountMeta::new(find_stats(program_id, &StatsAccount::Maker(*authority)).0, false));
+
+    let action = Action::CreateTrade {
+        bump_seed,
+        trade: trade_amount,
+        mode,
+        expires_at,
+        bounty,
+        peg: peg.map(|(_, peg)| peg),
+        splits,
+        arbitration,
+        hook,
+        call,
+        settlement,
+        vesting,
+        allowlist,
+        receipt: receipt.is_some(),
+    };
+
+    Instruction::new_with_bytes(*program_id, &action.try_to_vec().unwrap(), account_metas)
+}
+
+pub fn make_trade(
+    program_id: &Pubkey,
+    taker: &Pubkey,
+    accounts: &TradeFillAccounts,
+    expected_offer: u64,
+    expected_trade: u64,
+    allowlist: Option<AllowlistProof>,
+) -> Instruction {
+    let action = Action::MakeTrade {
+        expected_offer,
+        expected_trade,
+        allowlist,
+    };
+
+    let mut account_metas = vec![AccountMeta::new_readonly(*taker, true)];
+    account_metas.extend(accounts.to_account_metas(program_id));
+    account_metas.push(AccountMeta::new_readonly(spl_token::id(), false));
+    account_metas.extend(accounts.to_trailing_account_metas(program_id));
+
+    Instruction::new_with_bytes(*program_id, &action.try_to_vec().unwrap(), account_metas)
+}
+
+pub fn make_trades(
+    program_id: &Pubkey,
+    taker: &Pubkey,
+    fills: &[(TradeFill, TradeFillAccounts)],
+) -> Instruction {
+    let action = Action::MakeTrades {
+        trades: fills.iter().map(|(fill, _)| fill.clone()).collect(),
+    };
+
+    let mut account_metas = vec![
+        AccountMeta::new_readonly(*taker, true),
+        AccountMeta::new_readonly(spl_token::id(), false),
+    ];
+    for (_, accounts) in fills {
+        account_metas.extend(accounts.to_account_metas(program_id));
+    }
+    for (_, accounts) in fills {
+        account_metas.extend(accounts.to_trailing_account_metas(program_id));
+    }
+
+    Instruction::new_with_bytes(*program_id, &action.try_to_vec().unwrap(), account_metas)
+}
+
+fn change_offer(
+    program_id: &Pubkey,
+    authority: &Pubkey,
+    trade: &Pubkey,
+    offer_src: &Pubkey,
+    token_account: &Pubkey,
+    action: Action,
+) -> Instruction {
+    let (pda, _) = find_trade_pda(program_id, trade);
+
+    Instruction::new_with_bytes(
+        *program_id,
+        &action.try_to_vec().unwrap(),
+        vec![
+            AccountMeta::new_readonly(*authority, true),
+            AccountMeta::new(*trade, false),
+            AccountMeta::new_readonly(pda, false),
+            AccountMeta::new(*offer_src, false),
+            AccountMeta::new(*token_account, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ],
+    )
+}
+
+/// Moves amount from token_src into the offer of a trade.
+pub fn increase_offer(
+    program_id: &Pubkey,
+    authority: &Pubkey,
+    trade: &Pubkey,
+    offer_src: &Pubkey,
+    token_src: &Pubkey,
+    amount: u64,
+    proportional: bool,
+) -> Instruction {
+    let action = Action::IncreaseOffer { amount, proportional };
+    change_offer(program_id, authority, trade, offer_src, token_src, action)
+}
+
+/// Moves amount out of the offer of a trade into token_dst.
+pub fn decrease_offer(
+    program_id: &Pubkey,
+    authority: &Pubkey,
+    trade: &Pubkey,
+    offer_src: &Pubkey,
+    token_dst: &Pubkey,
+    amount: u64,
+    proportional: bool,
+) -> Instruction {
+    let action = Action::DecreaseOffer { amount, proportional };
+    change_offer(program_id, authority, trade, offer_src, token_dst, action)
+}
+
+/// Hands a trade over to new_authority, optionally along with a new trade dst. The new authority only needs to sign
+/// when new_authority_signs is set.
+pub fn transfer_trade_authority(
+    program_id: &Pubkey,
+    authority: &Pubkey,
+    trade: &Pubkey,
+    new_authority: &Pubkey,
+    new_authority_signs: bool,
+    trade_dst: Option<&Pubkey>,
+) -> Instruction {
+    let mut account_metas = vec![
+        AccountMeta::new_readonly(*authority, true),
+        AccountMeta::new(*trade, false),
+        AccountMeta::new_readonly(*new_authority, new_authority_signs),
+    ];
+    if let Some(trade_dst) = trade_dst {
+        account_metas.push(AccountMeta::new_readonly(*trade_dst, false));
+    }
+
+    Instruction::new_with_bytes(
+        *program_id,
+        &Action::TransferTradeAuthority.try_to_vec().unwrap(),
+        account_metas,
+    )
+}
+
+/// Settles a dispute over a trade in Arbitrated mode, releasing the offer to offer_dst, a token account of the taker,
+/// or back to the owner of the trade when there is none.
+pub fn arbiter_release(
+    program_id: &Pubkey,
+    arbiter: &Pubkey,
+    trade: &Pubkey,
+    offer_src: &Pubkey,
+    offer_owner: &Pubkey,
+    offer_dst: Option<&Pubkey>,
+) -> Instruction {
+    let (pda, _) = find_trade_pda(program_id, trade);
+
+    let mut account_metas = vec![
+        AccountMeta::new_readonly(*arbiter, true),
+        AccountMeta::new(*trade, false),
+        AccountMeta::new_readonly(pda, false),
+        AccountMeta::new(*offer_src, false),
+        AccountMeta::new(*offer_owner, false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new(find_stats(program_id, &StatsAccount::Maker(*offer_owner)).0, false),
+    ];
+    if let Some(offer_dst) = offer_dst {
+        account_metas.push(AccountMeta::new(*offer_dst, false));
+    }
+
+    let action = Action::ArbiterRelease { to_taker: offer_dst.is_some() };
+    Instruction::new_with_bytes(*program_id, &action.try_to_vec().unwrap(), account_metas)
+}
+
+/// Buys the option of a trade in CoveredCall mode, paying premium from premium_src to the trade dst.
+pub fn buy_option(
+    program_id: &Pubkey,
+    buyer: &Pubkey,
+    trade: &Pubkey,
+    premium_src: &Pubkey,
+    trade_dst: &Pubkey,
+    premium: u64,
+) -> Instruction {
+    Instruction::new_with_bytes(
+        *program_id,
+        &Action::BuyOption { premium }.try_to_vec().unwrap(),
+        vec![
+            AccountMeta::new_readonly(*buyer, true),
+            AccountMeta::new(*trade, false),
+            AccountMeta::new(*premium_src, false),
+            AccountMeta::new(*trade_dst, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ],
+    )
+}
+
+/// Exercises the option of a trade in CoveredCall mode as its holder, with the same accounts as make_trade.
+pub fn exercise_option(
+    program_id: &Pubkey,
+    holder: &Pubkey,
+    accounts: &TradeFillAccounts,
+    expected_offer: u64,
+    expected_trade: u64,
+) -> Instruction {
+    let mut ix = make_trade(program_id, holder, accounts, expected_offer, expected_trade, None);
+    ix.data = Action::ExerciseOption { expected_offer, expected_trade }.try_to_vec().unwrap();
+
+    ix
+}
+
+/// Returns the offer account of an expired option to the owner of the trade, who gets the trade account rent.
+pub fn reclaim_underlying(
+    program_id: &Pubkey,
+    authority: &Pubkey,
+    trade: &Pubkey,
+    offer_src: &Pubkey,
+) -> Instruction {
+    let (pda, _) = find_trade_pda(program_id, trade);
+
+    Instruction::new_with_bytes(
+        *program_id,
+        &Action::ReclaimUnderlying.try_to_vec().unwrap(),
+        vec![
+            AccountMeta::new(*authority, true),
+            AccountMeta::new(*trade, false),
+            AccountMeta::new_readonly(pda, false),
+            AccountMeta::new(*offer_src, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new(find_stats(program_id, &StatsAccount::Maker(*authority)).0, false),
+        ],
+    )
+}
+
+pub fn close_stale_trade(
+    program_id: &Pubkey,
+    cranker: &Pubkey,
+    trade: &Pubkey,
+    offer_src: &Pubkey,
+    offer_owner: &Pubkey,
+) -> Instruction {
+    let (pda, _) = find_trade_pda(program_id, trade);
+
+    Instruction::new_with_bytes(
+        *program_id,
+        &Action::CloseStaleTrade.try_to_vec().unwrap(),
+        vec![
+            AccountMeta::new(*cranker, true),
+            AccountMeta::new(*trade, false),
+            AccountMeta::new_readonly(pda, false),
+            AccountMeta::new(*offer_src, false),
+            AccountMeta::new(*offer_owner, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new(find_stats(program_id, &StatsAccount::Maker(*offer_owner)).0, false),
+        ],
+    )
+}
+
+/// Settles a trade in Forward mode, whose state is trade_account. The accounts the offer and the trade amount are
+/// delivered to are only passed if the trade was filled, and the stats account of its owner only if it wasn't.
+pub fn settle(program_id: &Pubkey, trade: &Pubkey, trade_account: &AccountTrade) -> Instruction {
+    let (pda, _) = find_trade_pda(program_id, trade);
+
+    let mut account_metas = vec![
+        AccountMeta::new(*trade, false),
+        AccountMeta::new_readonly(pda, false),
+        AccountMeta::new(trade_account.offer_token_account, false),
+        AccountMeta::new(trade_account.authority, false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+    ];
+    if trade_account.is_committed() {
+        account_metas.extend([
+            AccountMeta::new(trade_account.offer_dst_account, false),
+            AccountMeta::new(find_trade_escrow(program_id, trade, &trade_account.trade_mint), false),
+            AccountMeta::new(trade_account.trade_dst_account, false),
+            AccountMeta::new(trade_account.taker, false),
+        ]);
+    } else {
+        let (maker_stats, _) = find_stats(program_id, &StatsAccount::Maker(trade_account.authority));
+        account_metas.push(AccountMeta::new(maker_stats, false));
+    }
+
+    Instruction::new_with_bytes(*program_id, &Action::Settle.try_to_vec().unwrap(), account_metas)
+}
+
+/// Claims the share of the offer of a vesting trade, whose state is trade_account, that vested since the last claim.
+pub fn claim_vested(program_id: &Pubkey, trade: &Pubkey, trade_account: &AccountTrade) -> Instruction {
+    let (pda, _) = find_trade_pda(program_id, trade);
+
+    Instruction::new_with_bytes(
+        *program_id,
+        &Action::ClaimVested.try_to_vec().unwrap(),
+        vec![
+            AccountMeta::new(trade_account.taker, true),
+            AccountMeta::new(*trade, false),
+            AccountMeta::new_readonly(pda, false),
+            AccountMeta::new(find_vesting_escrow(program_id, trade, &trade_account.offer_mint), false),
+            AccountMeta::new(trade_account.offer_dst_account, false),
+            AccountMeta::new(trade_account.authority, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ],
+    )
+}
+
+pub fn init_nonces(program_id: &Pubkey, maker: &Pubkey) -> Instruction {
+    let (nonces, bump_seed) = find_nonces_pda(program_id, maker);
+
+    Instruction::new_with_bytes(
+        *program_id,
+        &Action::InitNonces { bump_seed }.try_to_vec().unwrap(),
+        vec![
+            AccountMeta::new(*maker, true),
+            AccountMeta::new(nonces, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    )
+}
+
+/// Cancels the signed orders of maker with the given nonces, and every one with a nonce below min_nonce.
+pub fn cancel_orders(program_id: &Pubkey, maker: &Pubkey, min_nonce: u64, nonces: Vec<u64>) -> Instruction {
+    let (nonces_account, _) = find_nonces_pda(program_id, maker);
+
+    Instruction::new_with_bytes(
+        *program_id,
+        &Action::CancelOrders { min_nonce, nonces }.try_to_vec().unwrap(),
+        vec![
+            AccountMeta::new_readonly(*maker, true),
+            AccountMeta::new(nonces_account, false),
+        ],
+    )
+}
+
+/// Has the ed25519 program verify signature, by signer over message. The signature, public key and message all go in
+/// the data of the instruction itself.
+pub fn verify_signature(signer: &Pubkey, signature: &[u8], message: &[u8]) -> Instruction {
+    // the number of signatures and a padding byte, followed by the offsets of a single signature
+    let public_key_offset = ED25519_OFFSETS_START + ED25519_OFFSETS_SIZE;
+    let signature_offset = public_key_offset + PUBKEY_BYTES;
+    let message_offset = signature_offset + signature.len();
+
+    let mut data = vec![1, 0];
+    for offset in [
+        signature_offset,
+        u16::MAX as usize,
+        public_key_offset,
+        u16::MAX as usize,
+        message_offset,
+        message.len(),
+        u16::MAX as usize,
+    ] {
+        data.extend_from_slice(&(offset as u16).to_le_bytes());
+    }
+    data.extend_from_slice(signer.as_ref());
+    data.extend_from_slice(signature);
+    data.extend_from_slice(message);
+
+    Instruction::new_with_bytes(ed25519_program::id(), &data, vec![])
+}
+
+/// Fills order, signed by its maker with signature. Returns the ed25519 program instruction verifying the signature
+/// followed by the fill, which must go right after it in the transaction.
+#[allow(clippy::too_many_arguments)]
+pub fn fill_signed_order(
+    program_id: &Pubkey,
+    taker: &Pubkey,
+    order: &SignedOrder,
+    signature: &[u8],
+    offer_src: &Pubkey,
+    trade_src: &Pubkey,
+    offer_dst: &Pubkey,
+    fee_account: &Pubkey,
+) -> Vec<Instruction> {
+    let (nonces, _) = find_nonces_pda(program_id, &order.maker);
+    let message = order.try_to_vec().unwrap();
+
+    let fill_ix = Instruction::new_with_bytes(
+        *program_id,
+        &Action::FillSignedOrder { order: order.clone() }.try_to_vec().unwrap(),
+        vec![
+            AccountMeta::new_readonly(*taker, true),
+            AccountMeta::new(nonces, false),
+            AccountMeta::new(*offer_src, false),
+            AccountMeta::new(order.trade_dst, false),
+            AccountMeta::new(*trade_src, false),
+            AccountMeta::new(*offer_dst, false),
+            AccountMeta::new(*fee_account, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(sysvar::instructions::id(), false),
+            AccountMeta::new_readonly(find_mint_list(program_id).0, false),
+            AccountMeta::new_readonly(find_listed_mint(program_id, &order.offer_mint).0, false),
+            AccountMeta::new_readonly(find_listed_mint(program_id, &order.trade_mint).0, false),
+            AccountMeta::new(find_mint_limits(program_id, &order.offer_mint).0, false),
+            AccountMeta::new(find_mint_limits(program_id, &order.trade_mint).0, false),
+            AccountMeta::new(find_stats(program_id, &StatsAccount::Program).0, false),
+            AccountMeta::new(find_stats(program_id, &StatsAccount::Mint(order.offer_mint)).0, false),
+            AccountMeta::new(find_stats(program_id, &StatsAccount::Mint(order.trade_mint)).0, false),
+            AccountMeta::new(find_stats(program_id, &StatsAccount::Maker(order.maker)).0, false),
+        ],
+    );
+
+    vec![verify_signature(&order.maker, signature, &message), fill_ix]
+}
+
+/// Creates the mint list if it doesn't exist yet, and sets its mode. Only the program authority can change the list.
+pub fn set_mint_list_mode(program_id: &Pubkey, authority: &Pubkey, mode: MintListMode) -> Instruction {
+    let (mint_list, bump_seed) = find_mint_list(program_id);
+
+    Instruction::new_with_bytes(
+        *program_id,
+        &Action::SetMintListMode { bump_seed, mode }.try_to_vec().unwrap(),
+        vec![
+            AccountMeta::new(*authority, true),
+            AccountMeta::new(mint_list, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    )
+}
+
+pub fn add_mint(program_id: &Pubkey, authority: &Pubkey, mint: &Pubkey) -> Instruction {
+    let (listed_mint, bump_seed) = find_listed_mint(program_id, mint);
+
+    Instruction::new_with_bytes(
+        *program_id,
+        &Action::AddMint { bump_seed }.try_to_vec().unwrap(),
+        vec![
+            AccountMeta::new(*authority, true),
+            AccountMeta::new(listed_mint, false),
+            AccountMeta::new_readonly(*mint, false),
+            AccountMeta::new(find_mint_list(program_id).0, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    )
+}
+
+pub fn remove_mint(program_id: &Pubkey, authority: &Pubkey, mint: &Pubkey) -> Instruction {
+    let (listed_mint, _) = find_listed_mint(program_id, mint);
+
+    Instruction::new_with_bytes(
+        *program_id,
+        &Action::RemoveMint.try_to_vec().unwrap(),
+        vec![
+            AccountMeta::new(*authority, true),
+            AccountMeta::new(listed_mint, false),
+            AccountMeta::new(find_mint_list(program_id).0, false),
+        ],
+    )
+}
+
+pub fn set_mint_limits(program_id: &Pubkey, authority: &Pubkey, mint: &Pubkey, limits: MintLimits) -> Instruction {
+    let (mint_limits, bump_seed) = find_mint_limits(program_id, mint);
+
+    Instruction::new_with_bytes(
+        *program_id,
+        &Action::SetMintLimits { bump_seed, limits }.try_to_vec().unwrap(),
+        vec![
+            AccountMeta::new(*authority, true),
+            AccountMeta::new(mint_limits, false),
+            AccountMeta::new_readonly(*mint, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    )
+}
+
+pub fn init_stats(program_id: &Pubkey, payer: &Pubkey, stats: StatsAccount) -> Instruction {
+    let (stats_account, bump_seed) = find_stats(program_id, &stats);
+
+    Instruction::new_with_bytes(
+        *program_id,
+        &Action::InitStats { bump_seed, stats }.try_to_vec().unwrap(),
+        vec![
+            AccountMeta::new(*payer, true),
+            AccountMeta::new(stats_account, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    )
+}
@@ -1,27 +1,423 @@
 use borsh::{BorshDeserialize, BorshSerialize};
+use crate::state::{MintLimits, MintListMode, ProceedsSplit, StatsAccount, TradeMode};
+use solana_program::{clock::UnixTimestamp, pubkey::Pubkey};
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub struct TradeFill {
+    pub expected_offer: u64,
+    pub expected_trade: u64,
+    pub allowlist: Option<AllowlistProof>,
+}
+
+// The proof that the taker of a trade is in its allowlist with cap, see `allowlist`. proof holds the hashes of the
+// siblings of the leaf of the taker, from the bottom of the tree up
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct AllowlistProof {
+    pub cap: u64,
+    pub proof: Vec<[u8; 32]>,
+}
+
+// Prices a trade against a price account instead of a fixed trade amount, see `state::PriceFeed`
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct OraclePeg {
+    pub spread_bps: u16,
+    pub max_price_age: u32,
+}
+
+// The third party of a trade in Arbitrated mode, who co-signs its settlement and can release the offer on its own from
+// dispute_ends_at, a unix timestamp
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct Arbitration {
+    pub arbiter: Pubkey,
+    pub dispute_ends_at: UnixTimestamp,
+}
+
+// A program called by MakeTrade before any funds move, ex: to screen the counterparties. It is invoked with account
+// as its only account and a `ComplianceCheck` as instruction data, and rejects the trade by returning an error. Hooks
+// are chosen by the owner of each trade, and trades without one are not screened
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct ComplianceHook {
+    pub program: Pubkey,
+    pub account: Pubkey,
+}
+
+// The instruction data the compliance hook of a trade is invoked with
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct ComplianceCheck {
+    pub trade: Pubkey,
+    pub maker: Pubkey,
+    pub taker: Pubkey,
+    pub offer_mint: Pubkey,
+    pub trade_mint: Pubkey,
+    pub offer_amount: u64,
+    pub trade_amount: u64,
+}
+
+// The terms of a trade in CoveredCall mode, which is bought by paying premium in trade tokens to the trade dst. The
+// trade amount is the strike, and expires_at of the trade the expiry of the option
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct CoveredCall {
+    pub premium: u64,
+}
+
+// The settlement date of a trade in Forward mode, a unix timestamp. The trade can be filled until then, and is settled
+// from then on
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct Settlement {
+    pub settles_at: UnixTimestamp,
+}
+
+// The linear vesting schedule of the offer of a trade, in seconds from when the trade is filled. The offer is held in
+// the vesting escrow and claimed by the taker as it vests: nothing before cliff, and all of it once duration is over
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct Vesting {
+    pub cliff: i64,
+    pub duration: i64,
+}
+
+// Where the offsets of the signature, public key and message start in the data of an ed25519 program instruction,
+// after the number of signatures and a padding byte, and the size of the offsets of a single signature: 7 u16
+pub const ED25519_OFFSETS_START: usize = 2;
+pub const ED25519_OFFSETS_SIZE: usize = 14;
+
+// An order signed off-chain by its maker and filled with FillSignedOrder, without a trade account. It is borsh
+// encoded as the message the maker signs. The offer is taken from a token account of the maker with the offer mint,
+// which approved the nonces account of the maker as its delegate, and the trade amount goes to trade_dst.
+// expires_at is the unix timestamp from which the order can't be filled anymore, 0 if it never expires.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct SignedOrder {
+    pub maker: Pubkey,
+    pub offer_mint: Pubkey,
+    pub trade_mint: Pubkey,
+    pub trade_dst: Pubkey,
+    pub offer_amount: u64,
+    pub trade_amount: u64,
+    pub nonce: u64,
+    pub expires_at: UnixTimestamp,
+}
 
 #[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
 pub enum Action {
     // 0. `[signer]` Account of the owner of the trade
     // 1. `[writable]` trade account
-    // 2. `[]` token account - the account holding the offer amount
-    // 3. `[writable]` pda account
-    // 4. `[]` token program account
+    // 2. `[writable]` token account - the account holding the offer amount
+    // 3. `[]` the mint of the token wanted in exchange
+    // 4. `[]` the token account to store the trade amount in
+    // 5. `[]` pda account
+    // 6. `[]` token program account
+    // 7. `[]` the mint of the offer token account
+    // 8. `[]` price account, only for oracle-pegged trades
+    // Followed by `[writable]` the receipt mint and `[writable]` the token account of the owner to mint the receipt
+    // to, if receipt
+    // Followed by `[]` the mint list and `[]` the listed mint accounts of the offer mint and of the trade mint, which
+    // may not exist. Both mints must be allowed by the mint list, if there is one
+    // Followed by `[]` the mint limits accounts of the offer mint and of the trade mint, which may not exist. The offer
+    // and, unless the trade is pegged, the trade amount must be within the limits of their mint
+    // Followed by `[writable]` the stats account of the owner, which may not exist, see `state::StatsAccount`
+    // In Escrow and Arbitrated modes the ownership of the token account is transfered to the pda account, in Delegate
+    // mode the pda account is approved as delegate of the offer amount instead.
+    // expires_at is the unix timestamp from which the trade can be closed by anyone with CloseStaleTrade, 0 if it
+    // never expires. bounty is paid out of the trade account balance to whoever closes it.
+    // The trade is stored as the price of trade for the balance of the offer account, see `AccountTrade::price_num`.
+    // With a peg, the trade amount is taken from the price account when the trade is filled and trade is the minimum
+    // trade amount accepted.
+    // splits, if any, share the trade amount between up to `state::MAX_SPLITS` destinations. The first one must be the
+    // trade dst and the shares must add up to 10000 basis points.
+    // arbitration is given if and only if the mode is Arbitrated, with the dispute ending in the future.
+    // hook, if any, is invoked every time the trade is filled and can reject it.
+    // call is given if and only if the mode is CoveredCall, which also needs expires_at and can't be used with a peg,
+    // splits or a hook.
+    // settlement is given if and only if the mode is Forward, with the settlement date in the future and no later
+    // than expires_at, if any. Forward trades can't be used with a peg or splits.
+    // vesting, only in Escrow and Delegate modes, has the taker claim the offer as it vests with ClaimVested instead of
+    // getting it when the trade is filled. Its duration must be positive and no shorter than its cliff.
+    // allowlist, the root of a Merkle allowlist, has only the wallets in it fill the trade, proving it in MakeTrade.
+    // It can't be used in CoveredCall mode.
+    // receipt, only in Escrow mode and without splits, mints the one token of the receipt mint to the owner, approving
+    // the pda as its delegate. The receipt mint must be new, with the pda as mint authority, no freeze authority and
+    // no decimals. Whoever holds the receipt when the trade is filled gets the trade amount, and the receipt is burnt.
     CreateTrade{ 
         bump_seed: u8,
         trade: u64,
+        mode: TradeMode,
+        expires_at: UnixTimestamp,
+        bounty: u64,
+        peg: Option<OraclePeg>,
+        splits: Vec<ProceedsSplit>,
+        arbitration: Option<Arbitration>,
+        hook: Option<ComplianceHook>,
+        call: Option<CoveredCall>,
+        settlement: Option<Settlement>,
+        vesting: Option<Vesting>,
+        allowlist: Option<[u8; 32]>,
+        receipt: bool,
     },
 
     // 0. `[signer]` Account of the person accepting the trade (user B)
     // 1. `[writable]` trade account
-    // 2. `[writable]` pda account    
-    // 3. `[writable]` the token account to store the trade amount in (user A)
+    // 2. `[]` pda account
+    // 3. `[writable]` the token account holding the offer amount (user A)
+    // 4. `[writable]` the token account to store the trade amount in (user A)
+    // 5. `[writable]` the token account to get the trade amount from (user B)
+    // 6. `[writable]` the token account to store the offer amount in (user B)
+    // 7. `[writable]` the wallet of the owner of the trade, where the trade account rent goes to (user A)
+    // 8. `[writable]` the fee account
+    // 9. `[]` token program id
+    // 10. `[]` the price account, only if the trade is oracle-pegged. expected_trade is then the maximum trade amount
+    // the taker pays
+    // Followed by `[writable]` the destinations of the trade amount after the trade dst, if the trade splits it
+    // Followed by `[signer]` the arbiter, if the trade is in Arbitrated mode
+    // Followed by `[]` the compliance hook program and `[]` its account, if the trade has a hook
+    // Followed by `[writable]` the trade escrow, the ATA of the pda for the trade mint, if the trade is in Forward
+    // mode. The trade amount after the fee goes there instead of the trade dst, and the offer stays in escrow until
+    // Settle
+    // Followed by `[writable]` the vesting escrow, the ATA of the pda for the offer mint, if the trade vests its offer.
+    // The offer goes there instead of the offer dst, which gets it with ClaimVested
+    // Followed by `[writable]` the receipt mint and `[writable]` the token account holding the receipt, if the trade
+    // has one. The trade dst is then a token account of the holder of the receipt, who must have approved the pda as
+    // its delegate, and the receipt is burnt
+    // Followed by `[writable]` the mint limits accounts of the offer mint and of the trade mint, which may not exist.
+    // The offer and trade amounts must be within the limits of their mint, and add to the volume filled in the window
+    // of the mint, which must stay within its cap
+    // Followed by `[writable]` the stats accounts of the program, of the offer mint, of the trade mint and of the owner
+    // of the trade, which may not exist, see `state::StatsAccount`
+    // allowlist is the proof that the taker is in the allowlist of the trade, only if it has one. The offer must not
+    // exceed the cap of the taker
+    MakeTrade{ 
+        expected_offer: u64,
+        expected_trade: u64,
+        allowlist: Option<AllowlistProof>,
+    },
+
+    // 0. `[signer]` Account of the person accepting the trades (user B)
+    // 1. `[]` token program id
+    // For each trade, in the same order as `trades`:
+    // 2 + 8*i. `[writable]` trade account
+    // 3 + 8*i. `[]` pda account
+    // 4 + 8*i. `[writable]` the token account holding the offer amount (user A)
+    // 5 + 8*i. `[writable]` the token account to store the trade amount in (user A)
+    // 6 + 8*i. `[writable]` the token account to get the trade amount from (user B)
+    // 7 + 8*i. `[writable]` the token account to store the offer amount in (user B)
+    // 8 + 8*i. `[writable]` the wallet of the owner of the trade, where the trade account rent goes to (user A)
+    // 9 + 8*i. `[writable]` the fee account
+    // Followed by, for each trade in the same order, its price account if it is oracle-pegged, the destinations of
+    // its trade amount after the trade dst if it splits it, its arbiter, as a signer, if it is in Arbitrated mode and
+    // its compliance hook program and account if it has one, its trade escrow if it is in Forward mode and its
+    // vesting escrow if it vests its offer, its receipt mint and receipt account if it has a receipt, the mint
+    // limits accounts of its offer mint and trade mint and the stats accounts of the program, of its offer mint, of its
+    // trade mint and of its owner.
+    // Accounts may be shared between trades. Using the offer dst of a trade as the trade src of a following one chains
+    // the fills, so the tokens received from the first pay for the second.
+    MakeTrades{
+        trades: Vec<TradeFill>,
+    },
+
+    // 0. `[writable, signer]` Account of the person closing the trade, where the bounty goes to
+    // 1. `[writable]` trade account
+    // 2. `[]` pda account
+    // 3. `[writable]` the token account holding the offer amount (user A)
+    // 4. `[writable]` the wallet of the owner of the trade, where the rest of the trade account rent goes to (user A)
+    // 5. `[]` token program id
+    // 6. `[writable]` the stats account of the owner of the trade, which may not exist, see `state::StatsAccount`
+    // Anyone can close a trade once it has expired, or when it is in Delegate mode and can't be filled anymore
+    // because its owner revoked the delegation or moved the offer away.
+    CloseStaleTrade,
+
+    // 0. `[signer]` Account of the owner of the trade (user A)
+    // 1. `[writable]` trade account
+    // 2. `[]` pda account
+    // 3. `[writable]` the token account holding the offer amount (user A)
+    // 4. `[writable]` the token account the added offer is taken from (user A)
+    // 5. `[]` token program id
+    // Adds amount to the offer of an open trade. In Delegate mode the pda is approved for the new offer amount. With
+    // proportional the trade amount grows at the price of the trade, otherwise it stays the same and the price drops.
+    IncreaseOffer {
+        amount: u64,
+        proportional: bool,
+    },
+
+    // 0. `[signer]` Account of the owner of the trade (user A)
+    // 1. `[writable]` trade account
+    // 2. `[]` pda account
+    // 3. `[writable]` the token account holding the offer amount (user A)
+    // 4. `[writable]` the token account the withdrawn offer goes to
+    // 5. `[]` token program id
+    // Withdraws amount from the offer of an open trade, which must keep a non zero offer. With proportional the trade
+    // amount shrinks at the price of the trade, otherwise it stays the same and the price goes up. The offer of a
+    // trade with a receipt can't be decreased, as the receipt may have been sold for it.
+    DecreaseOffer {
+        amount: u64,
+        proportional: bool,
+    },
+
+    // 0. `[signer]` Account of the owner of the trade (user A)
+    // 1. `[writable]` trade account
+    // 2. `[]` the wallet of the new owner of the trade, which may also sign
+    // 3. `[]` the token account to store the trade amount in from now on, optional. It replaces the first destination
+    // of a trade that splits its trade amount
+    // The new owner gets the offer account back when the trade is cancelled and the trade account rent when it is
+    // filled or closed. Trades in Delegate mode can't change owner, as the offer account stays with its owner.
+    TransferTradeAuthority,
+
+    // 0. `[signer]` Account of the arbiter of the trade
+    // 1. `[writable]` trade account
+    // 2. `[]` pda account
+    // 3. `[writable]` the token account holding the offer amount (user A)
+    // 4. `[writable]` the wallet of the owner of the trade, where the trade account rent goes to (user A)
+    // 5. `[]` token program id
+    // 6. `[writable]` the stats account of the owner of the trade, which may not exist, see `state::StatsAccount`
+    // 7. `[writable]` the token account of the taker to store the offer amount in, only when to_taker is set
+    // Settles a dispute over a trade in Arbitrated mode once the dispute timeout is over. The offer goes to the taker
+    // with to_taker, who is expected to have paid outside of the program, otherwise the offer account is returned to
+    // its owner. Either way the trade is closed.
+    ArbiterRelease {
+        to_taker: bool,
+    },
+
+    // 0. `[writable, signer]` Account of the maker, who pays for the nonces account
+    // 1. `[writable]` the nonces account of the maker, a pda, see `instruction::find_nonces_pda`
+    // 2. `[]` system program
+    // Creates the account keeping the nonces of the orders signed by the maker, see `state::AccountNonces`. The
+    // maker approves it as the delegate of the token accounts its signed orders are filled from.
+    InitNonces {
+        bump_seed: u8,
+    },
+
+    // 0. `[signer]` Account of the maker
+    // 1. `[writable]` the nonces account of the maker
+    // Cancels the signed orders of the maker with the given nonces, and every one with a nonce below min_nonce.
+    // Nonces above the window of the nonces account can only be cancelled by moving the window up with min_nonce.
+    CancelOrders {
+        min_nonce: u64,
+        nonces: Vec<u64>,
+    },
+
+    // 0. `[signer]` Account of the person filling the order (user B)
+    // 1. `[writable]` the nonces account of the maker
+    // 2. `[writable]` the token account of the maker holding the offer (user A)
+    // 3. `[writable]` trade_dst of the order (user A)
     // 4. `[writable]` the token account to get the trade amount from (user B)
     // 5. `[writable]` the token account to store the offer amount in (user B)
-    // 6. `[]` token program id
-    // 7. `[]` trade program id    
-    MakeTrade{ 
+    // 6. `[writable]` the fee account
+    // 7. `[]` token program id
+    // 8. `[]` instructions sysvar
+    // 9. `[]` the mint list, which may not exist
+    // 10. `[]` the listed mint account of the offer mint, which may not exist
+    // 11. `[]` the listed mint account of the trade mint, which may not exist
+    // 12. `[writable]` the mint limits account of the offer mint, which may not exist
+    // 13. `[writable]` the mint limits account of the trade mint, which may not exist
+    // 14. `[writable]` the stats account of the program, which may not exist, see `state::StatsAccount`
+    // 15. `[writable]` the stats account of the offer mint, which may not exist
+    // 16. `[writable]` the stats account of the trade mint, which may not exist
+    // 17. `[writable]` the stats account of the maker, which may not exist
+    // Fills an order signed by its maker as a whole, spending its nonce. The instruction right before this one must
+    // be an ed25519 program instruction verifying the signature of the maker over the borsh encoded order. Both mints
+    // must be allowed by the mint list, if there is one, as when a trade is created, and both amounts are checked
+    // against the limits of their mint and counted in their volume cap, as when a trade is filled. The fill is counted
+    // in the stats as the fill of a trade of the maker.
+    FillSignedOrder {
+        order: SignedOrder,
+    },
+
+    // 0. `[signer]` Account of the person buying the option (user B)
+    // 1. `[writable]` trade account
+    // 2. `[writable]` the token account to get the premium from (user B)
+    // 3. `[writable]` the token account to store the trade amount in, where the premium goes (user A)
+    // 4. `[]` token program id
+    // Buys the option of a trade in CoveredCall mode before it expires, paying premium, which must be the premium of
+    // the trade. The buyer becomes the holder of the option, and no one else can buy it afterwards.
+    BuyOption {
+        premium: u64,
+    },
+
+    // Same accounts as MakeTrade, the first one being the holder of the option
+    // Exercises the option of a trade in CoveredCall mode before it expires. The holder pays the trade amount, the
+    // strike, and the fee as when a trade is filled, and gets the offer.
+    ExerciseOption {
         expected_offer: u64,
         expected_trade: u64,
     },
+
+    // 0. `[writable, signer]` Account of the owner of the trade, where the trade account rent goes to (user A)
+    // 1. `[writable]` trade account
+    // 2. `[]` pda account
+    // 3. `[writable]` the token account holding the offer amount (user A)
+    // 4. `[]` token program id
+    // 5. `[writable]` the stats account of the owner of the trade, which may not exist, see `state::StatsAccount`
+    // Returns the offer account to the owner of a trade in CoveredCall mode once the option expired without being
+    // exercised, whether it was bought or not, and closes the trade.
+    ReclaimUnderlying,
+
+    // 0. `[writable]` trade account
+    // 1. `[]` pda account
+    // 2. `[writable]` the token account holding the offer amount (user A)
+    // 3. `[writable]` the wallet of the owner of the trade, where the trade account rent goes to (user A)
+    // 4. `[]` token program id
+    // Only if the trade was filled:
+    // 5. `[writable]` the token account to store the offer amount in, given when the trade was filled (user B)
+    // 6. `[writable]` the trade escrow, the ATA of the pda for the trade mint
+    // 7. `[writable]` the token account to store the trade amount in (user A)
+    // 8. `[writable]` the wallet of the taker, where the trade escrow rent goes to (user B)
+    // Otherwise:
+    // 5. `[writable]` the stats account of the owner of the trade, which may not exist, see `state::StatsAccount`
+    // Settles a trade in Forward mode once its settlement date has passed, which anyone can do. If the trade was
+    // filled the offer goes to the taker and the trade escrow to the trade dst, otherwise the offer account is returned
+    // to its owner. Either way the trade is closed.
+    Settle,
+
+    // 0. `[writable, signer]` Account of the taker of the trade, where the vesting escrow rent goes to (user B)
+    // 1. `[writable]` trade account
+    // 2. `[]` pda account
+    // 3. `[writable]` the vesting escrow, the ATA of the pda for the offer mint
+    // 4. `[writable]` the token account to store the offer amount in, given when the trade was filled (user B)
+    // 5. `[writable]` the wallet of the owner of the trade, where the trade account rent goes to (user A)
+    // 6. `[]` token program id
+    // Sends the share of the offer of a filled trade that vested since the last claim to the offer dst. Once all of it
+    // is claimed the vesting escrow and the trade are closed.
+    ClaimVested,
+
+    // 0. `[writable, signer]` the program authority, who pays for the mint list
+    // 1. `[writable]` the mint list, a pda, see `instruction::find_mint_list`
+    // 2. `[]` system program
+    // Creates the mint list of the program if it doesn't exist yet, and sets whether the mints in it are the only
+    // ones that can be traded or the ones that can't be. The mode of an existing list can only be changed once all of
+    // its mints are removed.
+    SetMintListMode {
+        bump_seed: u8,
+        mode: MintListMode,
+    },
+
+    // 0. `[writable, signer]` the program authority, who pays for the listed mint account
+    // 1. `[writable]` the listed mint account, a pda, see `instruction::find_listed_mint`
+    // 2. `[]` the mint to add to the mint list
+    // 3. `[writable]` the mint list, which must exist
+    // 4. `[]` system program
+    AddMint {
+        bump_seed: u8,
+    },
+
+    // 0. `[writable, signer]` the program authority, where the listed mint account rent goes to
+    // 1. `[writable]` the listed mint account of the mint to remove from the mint list
+    // 2. `[writable]` the mint list
+    RemoveMint,
+
+    // 0. `[writable, signer]` the program authority, who pays for the mint limits account
+    // 1. `[writable]` the mint limits account, a pda, see `instruction::find_mint_limits`
+    // 2. `[]` the mint
+    // 3. `[]` system program
+    // Creates the limits account of the mint if it doesn't exist yet, and sets its limits. The volume filled in the
+    // current window is kept.
+    SetMintLimits {
+        bump_seed: u8,
+        limits: MintLimits,
+    },
+
+    // 0. `[writable, signer]` Account paying for the stats account
+    // 1. `[writable]` the stats account, a pda, see `instruction::find_stats`
+    // 2. `[]` system program
+    // Creates the stats account of the program, of a mint or of a maker, which counts their trades from then on.
+    InitStats {
+        bump_seed: u8,
+        stats: StatsAccount,
+    },
 }
@@ -1,5 +1,8 @@
+pub mod allowlist;
 pub mod entrypoint;
 pub mod error;
+pub mod events;
+pub mod instruction;
 pub mod instructions;
 pub mod processor;
 pub mod state;
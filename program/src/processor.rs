@@ -1,26 +1,56 @@
-use crate::{instructions::Action, error::TradeError};
+use crate::{
+    allowlist,
+    instructions::{Action, ComplianceCheck, SignedOrder, TradeFill, ED25519_OFFSETS_SIZE, ED25519_OFFSETS_START},
+    error::TradeError,
+    events::TradeEvent,
+};
 use crate::state;
 use borsh::{BorshDeserialize, BorshSerialize};
 use solana_program::sysvar::Sysvar;
 use solana_program::{
-    account_info::{next_account_info, AccountInfo},
+    account_info::{next_account_info, next_account_infos, AccountInfo},
+    clock::{Clock, UnixTimestamp},
+    ed25519_program,
     entrypoint,
+    instruction::{AccountMeta, Instruction},
     program::{invoke, invoke_signed},
     msg,
     pubkey::{Pubkey, PUBKEY_BYTES},
     program_error::ProgramError,
     program_memory::sol_memcmp,
+    program_option::COption,
     rent::Rent,
     program_pack::Pack,
+    system_instruction,
+    sysvar::instructions,
 };
-use spl_token::state::Account;
+use spl_token::state::{Account, Mint};
 use std::ops::{Mul, Sub};
+use std::slice::Iter;
 use std::str::FromStr;
 
 // Public address of the wallet that has the authority on the program
 const PROGRAM_AUTHORITY: &str = "Ej3Dy8i7PWZb52Chg1GEaCG17StWH5gQSHmti4hE4HvC";
 const FEE_PERCENTAGE: f64 = 0.01; // 1%
+// Number of accounts each trade takes in MakeTrade and MakeTrades, not counting the taker and the token program
+const MAKE_TRADE_ACCOUNTS: usize = 8;
+
+// The accounts some trades need on top of the usual ones, ex: price accounts of oracle-pegged trades. They come after
+// every other account, in the same order as the trades
+struct TrailingAccounts<'a, 'b> {
+    iter: Iter<'b, AccountInfo<'a>>,
+    // index of the next one in the instruction
+    index: usize,
+}
 
+impl<'a, 'b> TrailingAccounts<'a, 'b> {
+    fn next(&mut self) -> Result<(usize, &'b AccountInfo<'a>), ProgramError> {
+        let account_ai = next_account_info(&mut self.iter)?;
+        self.index += 1;
+
+        Ok((self.index - 1, account_ai))
+    }
+}
 
 pub struct Processor {}
 
@@ -35,15 +65,21 @@ impl Processor {
         let accounts_iter = &mut accounts.iter();
 
         match instruction {
-            Action::CreateTrade { trade, bump_seed } => {
+            Action::CreateTrade {
+                trade, bump_seed, mode, expires_at, bounty, peg, splits, arbitration, hook, call, settlement, vesting,
+                allowlist, receipt,
+            } => {
                 msg!("Creating trade...");
+
+                Self::check_no_duplicates(0, accounts)?;
                 
                 let authority = next_account_info(accounts_iter)?;
                 if !authority.is_signer {
-                    Err(TradeError::WrongAuthority)?;
+                    Err(Self::account_error(0, TradeError::WrongAuthority))?;
                 }
 
                 let trade_ai = next_account_info(accounts_iter)?;
+                Self::check_writable(1, trade_ai)?;
                 let mut trade_account = state::AccountTrade::try_from_slice(&trade_ai.data.borrow())?;
                 if trade_account.initialized {
                     return Err(ProgramError::AccountAlreadyInitialized)?;
@@ -55,8 +91,68 @@ impl Processor {
                 if !rent.is_exempt(trade_ai.lamports(), trade_ai.data_len()) {
                     return Err(ProgramError::AccountNotRentExempt)?;
                 }
+                // the bounty comes out of the trade account balance when the trade is closed
+                if bounty > trade_ai.lamports() {
+                    return Err(ProgramError::InsufficientFunds)?;
+                }
+                let now = Clock::get()?.unix_timestamp;
+                if expires_at != 0 && expires_at <= now {
+                    return Err(TradeError::TradeExpired)?;
+                }
+                match (mode, &arbitration) {
+                    (state::TradeMode::Arbitrated, Some(arbitration)) if arbitration.dispute_ends_at > now => {},
+                    (state::TradeMode::Arbitrated, Some(_)) => return Err(ProgramError::InvalidArgument)?,
+                    (state::TradeMode::Arbitrated, None) | (_, Some(_)) => {
+                        return Err(ProgramError::InvalidInstructionData)?;
+                    },
+                    _ => {},
+                }
+                // options have a fixed strike paid in full to the trade dst, and must expire
+                match (mode, &call) {
+                    (state::TradeMode::CoveredCall, Some(_))
+                        if expires_at != 0 && peg.is_none() && splits.is_empty() && hook.is_none() => {},
+                    (state::TradeMode::CoveredCall, _) | (_, Some(_)) => {
+                        return Err(ProgramError::InvalidInstructionData)?;
+                    },
+                    _ => {},
+                }
+                // forward trades are filled before their settlement date at a fixed trade amount, paid in full to the
+                // trade dst on settlement
+                let fixed_amount = peg.is_none() && splits.is_empty();
+                match (mode, &settlement) {
+                    (state::TradeMode::Forward, Some(settlement)) if fixed_amount && settlement.settles_at > now
+                        && (expires_at == 0 || settlement.settles_at <= expires_at) => {},
+                    (state::TradeMode::Forward, Some(_)) if fixed_amount => return Err(ProgramError::InvalidArgument)?,
+                    (state::TradeMode::Forward, _) | (_, Some(_)) => {
+                        return Err(ProgramError::InvalidInstructionData)?;
+                    },
+                    _ => {},
+                }
+                // the offer of a vesting trade is moved to the vesting escrow when filled, which the other modes
+                // deliver differently
+                match (mode, &vesting) {
+                    (state::TradeMode::Escrow | state::TradeMode::Delegate, Some(vesting))
+                        if vesting.duration > 0 && vesting.cliff >= 0 && vesting.cliff <= vesting.duration => {},
+                    (state::TradeMode::Escrow | state::TradeMode::Delegate, Some(_)) => {
+                        return Err(ProgramError::InvalidArgument)?;
+                    },
+                    (_, Some(_)) => return Err(ProgramError::InvalidInstructionData)?,
+                    _ => {},
+                }
+                // options are bought with BuyOption, which takes no proof, and a zero root would let anyone in
+                match (mode, &allowlist) {
+                    (state::TradeMode::CoveredCall, Some(_)) => return Err(ProgramError::InvalidInstructionData)?,
+                    (_, Some(root)) if *root == [0; 32] => return Err(ProgramError::InvalidArgument)?,
+                    _ => {},
+                }
+                // the holder of the receipt gets all of the trade amount when the trade is filled, with the offer
+                // delivered right away
+                if receipt && (mode != state::TradeMode::Escrow || !splits.is_empty()) {
+                    return Err(ProgramError::InvalidInstructionData)?;
+                }
                 
                 let offer_token_ai = next_account_info(accounts_iter)?;
+                Self::check_writable(2, offer_token_ai)?;
                 // TODO: is this check useful?
                 if *offer_token_ai.owner != spl_token::id() {
                     return Err(ProgramError::IncorrectProgramId)?;
@@ -66,9 +162,134 @@ impl Processor {
                 if offer_token.amount == 0 {
                     return Err(ProgramError::InsufficientFunds)?;
                 }
+                // a frozen account can't be transfered from, so the trade could never be filled
+                if offer_token.is_frozen() {
+                    return Err(TradeError::OfferAccountFrozen)?;
+                }
+                // the balance of a native account follows its lamports, which can be changed without spl-token
+                if offer_token.is_native() {
+                    return Err(TradeError::NativeOfferAccount)?;
+                }
+                // anyone else with close authority could close the offer account once the trade is filled. The owner
+                // can have it, but it is cleared below, as the offer account will be in the hands of the trade
+                if offer_token.close_authority.is_some() && offer_token.close_authority != COption::Some(*authority.key) {
+                    return Err(TradeError::OfferCloseAuthority)?;
+                }
+                // existing delegates are cleared by spl-token when the owner changes in Escrow mode, and replaced by
+                // the pda in Delegate mode
 
                 let trade_mint_ai = next_account_info(accounts_iter)?;
+                if *trade_mint_ai.owner != spl_token::id() {
+                    return Err(Self::account_error(3, TradeError::TradeMintMissmatch))?;
+                }
+                let trade_mint = Mint::unpack(&trade_mint_ai.data.borrow())
+                    .map_err(|_| Self::account_error(3, TradeError::TradeMintMissmatch))?;
                 let trade_dst_ai = next_account_info(accounts_iter)?;
+                // the trade dst may not exist yet, ex: an ATA created by the taker, but if it does it must be able to
+                // receive the trade amount
+                if *trade_dst_ai.owner == spl_token::id() {
+                    let trade_dst = Account::unpack_from_slice(&trade_dst_ai.data.borrow())?;
+                    if sol_memcmp(trade_dst.mint.as_ref(), trade_mint_ai.key.as_ref(), PUBKEY_BYTES) != 0 {
+                        return Err(Self::account_error(4, TradeError::TradeMintMissmatch))?;
+                    }
+                }
+
+                let pda_pubkey = next_account_info(accounts_iter)?;
+                Self::check_pda(program_id, 5, pda_pubkey, trade_ai.key, bump_seed)?;
+                let token_prog_ai = next_account_info(accounts_iter)?;
+                Self::check_token_program(6, token_prog_ai)?;
+
+                let offer_mint_ai = next_account_info(accounts_iter)?;
+                if sol_memcmp(offer_mint_ai.key.as_ref(), offer_token.mint.as_ref(), PUBKEY_BYTES) != 0 {
+                    return Err(Self::account_error(7, TradeError::OfferMintMissmatch))?;
+                }
+                // the mint was checked by spl-token when the offer account was created
+                let offer_mint = Mint::unpack(&offer_mint_ai.data.borrow())?;
+
+                let receipt_index = if peg.is_some() { 9 } else { 8 };
+                if let Some(peg) = peg {
+                    let price_feed_ai = next_account_info(accounts_iter)?;
+                    state::PriceFeed::deserialize(&mut &price_feed_ai.data.borrow()[..])
+                        .map_err(|_| Self::account_error(8, TradeError::InvalidPrice))?;
+
+                    trade_account.price_feed = *price_feed_ai.key;
+                    trade_account.price_feed_owner = *price_feed_ai.owner;
+                    trade_account.spread_bps = peg.spread_bps;
+                    trade_account.max_price_age = peg.max_price_age;
+                }
+
+                let receipt_ais = if receipt {
+                    let receipt_mint_ai = next_account_info(accounts_iter)?;
+                    Self::check_writable(receipt_index, receipt_mint_ai)?;
+                    if *receipt_mint_ai.owner != spl_token::id() {
+                        return Err(Self::account_error(receipt_index, TradeError::WrongReceipt))?;
+                    }
+                    let receipt_mint = Mint::unpack(&receipt_mint_ai.data.borrow())
+                        .map_err(|_| Self::account_error(receipt_index, TradeError::WrongReceipt))?;
+                    // only the pda can mint it, so the receipt is the one token of the mint, and no one can freeze it
+                    if receipt_mint.mint_authority != COption::Some(*pda_pubkey.key) || receipt_mint.supply != 0
+                        || receipt_mint.decimals != 0 || receipt_mint.freeze_authority.is_some() {
+                        return Err(Self::account_error(receipt_index, TradeError::WrongReceipt))?;
+                    }
+
+                    let receipt_account_ai = next_account_info(accounts_iter)?;
+                    Self::check_writable(receipt_index + 1, receipt_account_ai)?;
+                    let receipt_account = Account::unpack_from_slice(&receipt_account_ai.data.borrow())?;
+                    if sol_memcmp(receipt_account.mint.as_ref(), receipt_mint_ai.key.as_ref(), PUBKEY_BYTES) != 0
+                        || sol_memcmp(receipt_account.owner.as_ref(), authority.key.as_ref(), PUBKEY_BYTES) != 0 {
+                        return Err(Self::account_error(receipt_index + 1, TradeError::WrongReceipt))?;
+                    }
+
+                    trade_account.receipt_mint = *receipt_mint_ai.key;
+                    Some((receipt_mint_ai, receipt_account_ai))
+                } else {
+                    None
+                };
+
+                // the mint list keeps unwanted mints on either side of a trade off the program
+                let list_index = if receipt { receipt_index + 2 } else { receipt_index };
+                let mint_list_ai = next_account_info(accounts_iter)?;
+                let offer_listed_ai = next_account_info(accounts_iter)?;
+                let trade_listed_ai = next_account_info(accounts_iter)?;
+                Self::check_mint_allowed(
+                    program_id,
+                    list_index,
+                    mint_list_ai,
+                    list_index + 1,
+                    offer_listed_ai,
+                    &offer_token.mint,
+                )?;
+                Self::check_mint_allowed(
+                    program_id,
+                    list_index,
+                    mint_list_ai,
+                    list_index + 2,
+                    trade_listed_ai,
+                    trade_mint_ai.key,
+                )?;
+
+                // the trade amount of a pegged trade is only known once filled, where it is checked
+                let limits_index = list_index + 3;
+                let offer_limits_ai = next_account_info(accounts_iter)?;
+                let offer_limits =
+                    Self::load_mint_limits(program_id, limits_index, offer_limits_ai, &offer_token.mint)?;
+                Self::check_amount_limits(limits_index, offer_limits.as_ref(), offer_token.amount)?;
+                let trade_limits_ai = next_account_info(accounts_iter)?;
+                let trade_limits =
+                    Self::load_mint_limits(program_id, limits_index + 1, trade_limits_ai, trade_mint_ai.key)?;
+                if !trade_account.is_pegged() {
+                    Self::check_amount_limits(limits_index + 1, trade_limits.as_ref(), trade)?;
+                }
+
+                let maker_stats_ai = next_account_info(accounts_iter)?;
+                Self::check_writable(limits_index + 2, maker_stats_ai)?;
+                Self::update_stats(
+                    program_id,
+                    limits_index + 2,
+                    maker_stats_ai,
+                    &state::StatsAccount::Maker(*authority.key),
+                    |stats: &mut state::AccountMakerStats| stats.created = stats.created.saturating_add(1),
+                )?;
 
                 trade_account.bump_seed = bump_seed;
                 trade_account.offer_token_account = *offer_token_ai.key;
@@ -79,222 +300,2713 @@ impl Processor {
                 trade_account.initialized = true;
                 trade_account.trade_mint = *trade_mint_ai.key;
                 trade_account.program_id = *program_id;
+                trade_account.mode = mode;
+                trade_account.expires_at = expires_at;
+                trade_account.bounty = bounty;
+                if !splits.is_empty() {
+                    let shares: u32 = splits.iter().map(|split| split.share_bps as u32).sum();
+                    let first_is_trade_dst = splits[0].destination == *trade_dst_ai.key;
+                    let duplicates = splits.iter().enumerate()
+                        .any(|(i, split)| splits[..i].iter().any(|other| other.destination == split.destination));
+                    if splits.len() > state::MAX_SPLITS || shares != 10_000 || !first_is_trade_dst || duplicates
+                        || splits.iter().any(|split| split.share_bps == 0) {
+                        return Err(TradeError::InvalidSplits)?;
+                    }
+
+                    trade_account.splits[..splits.len()].copy_from_slice(&splits);
+                }
+                if let Some(arbitration) = arbitration {
+                    trade_account.arbiter = arbitration.arbiter;
+                    trade_account.dispute_ends_at = arbitration.dispute_ends_at;
+                }
+                if let Some(hook) = hook {
+                    trade_account.hook_program = hook.program;
+                    trade_account.hook_account = hook.account;
+                }
+                if let Some(call) = call {
+                    trade_account.premium = call.premium;
+                }
+                if let Some(settlement) = settlement {
+                    trade_account.settles_at = settlement.settles_at;
+                }
+                trade_account.offer_mint = offer_token.mint;
+                if let Some(vesting) = vesting {
+                    trade_account.vesting_cliff = vesting.cliff;
+                    trade_account.vesting_duration = vesting.duration;
+                }
+                if let Some(root) = allowlist {
+                    trade_account.allowlist_root = root;
+                }
+                trade_account.set_price(offer_token.amount, trade);
+                trade_account.offer_decimals = offer_mint.decimals;
+                trade_account.trade_decimals = trade_mint.decimals;
                 trade_account.serialize(&mut *trade_ai.data.borrow_mut())?;
 
                 msg!("Trade account initialised...");
 
-                // transfer authority of the token account to trader program - this will avoid
-                // part A having to somehow sign the transfer when making the deal
+                if offer_token.close_authority.is_some() {
+                    let clear_close_authority_ix = spl_token::instruction::set_authority(
+                        &spl_token::id(),
+                        offer_token_ai.key,
+                        None,
+                        spl_token::instruction::AuthorityType::CloseAccount,
+                        authority.key,
+                        &[authority.key],
+                    )?;
 
-                let pda_pubkey = next_account_info(accounts_iter)?;
-                let token_prog_ai = next_account_info(accounts_iter)?;
+                    invoke(
+                        &clear_close_authority_ix,
+                        &[
+                            offer_token_ai.clone(),
+                            authority.clone(),
+                            token_prog_ai.clone(),
+                        ],
+                    )?;
 
-                let owner_change_ix = spl_token::instruction::set_authority(
-                    &spl_token::id(),
-                    offer_token_ai.key,
-                    Some(&pda_pubkey.key),
-                    spl_token::instruction::AuthorityType::AccountOwner,
-                    authority.key,
-                    &[&authority.key],
-                )?;
-        
-                invoke(
-                    &owner_change_ix,
-                    &[
-                        offer_token_ai.clone(),
-                        authority.clone(),
-                        token_prog_ai.clone(),
-                    ],
-                )?;
+                    msg!("Cleared close authority of the offer account..");
+                }
+
+                match mode {
+                    state::TradeMode::Escrow
+                    | state::TradeMode::Arbitrated
+                    | state::TradeMode::CoveredCall
+                    | state::TradeMode::Forward => {
+                        // transfer authority of the token account to trader program - this will avoid
+                        // part A having to somehow sign the transfer when making the deal
+
+                        let owner_change_ix = spl_token::instruction::set_authority(
+                            &spl_token::id(),
+                            offer_token_ai.key,
+                            Some(pda_pubkey.key),
+                            spl_token::instruction::AuthorityType::AccountOwner,
+                            authority.key,
+                            &[authority.key],
+                        )?;
+
+                        invoke(
+                            &owner_change_ix,
+                            &[
+                                offer_token_ai.clone(),
+                                authority.clone(),
+                                token_prog_ai.clone(),
+                            ],
+                        )?;
+
+                        msg!("Transfered authority..");
+                    },
+                    state::TradeMode::Delegate => {
+                        // the tokens stay with part A, who allows the trader program to transfer the offer amount
+                        // when making the deal. Note that an account only has one delegate, so approving another
+                        // one afterwards makes this trade fail when taken
 
-                msg!("Transfered authority..");
+                        let approve_ix = spl_token::instruction::approve(
+                            &spl_token::id(),
+                            offer_token_ai.key,
+                            pda_pubkey.key,
+                            authority.key,
+                            &[authority.key],
+                            offer_token.amount,
+                        )?;
+
+                        invoke(
+                            &approve_ix,
+                            &[
+                                offer_token_ai.clone(),
+                                pda_pubkey.clone(),
+                                authority.clone(),
+                                token_prog_ai.clone(),
+                            ],
+                        )?;
+
+                        msg!("Approved delegate..");
+                    },
+                }
+
+                if let Some((receipt_mint_ai, receipt_account_ai)) = receipt_ais {
+                    let signer_seeds: &[&[u8]] = &[trade_ai.key.as_ref(), &[bump_seed]];
+                    let mint_receipt_ix = spl_token::instruction::mint_to(
+                        &spl_token::id(),
+                        receipt_mint_ai.key,
+                        receipt_account_ai.key,
+                        pda_pubkey.key,
+                        &[pda_pubkey.key],
+                        1,
+                    )?;
+
+                    invoke_signed(
+                        &mint_receipt_ix,
+                        &[
+                            receipt_mint_ai.clone(),
+                            receipt_account_ai.clone(),
+                            pda_pubkey.clone(),
+                            token_prog_ai.clone(),
+                        ],
+                        &[signer_seeds],
+                    )?;
+
+                    // no other receipt can ever be minted
+                    let close_mint_ix = spl_token::instruction::set_authority(
+                        &spl_token::id(),
+                        receipt_mint_ai.key,
+                        None,
+                        spl_token::instruction::AuthorityType::MintTokens,
+                        pda_pubkey.key,
+                        &[pda_pubkey.key],
+                    )?;
+
+                    invoke_signed(
+                        &close_mint_ix,
+                        &[
+                            receipt_mint_ai.clone(),
+                            pda_pubkey.clone(),
+                            token_prog_ai.clone(),
+                        ],
+                        &[signer_seeds],
+                    )?;
+
+                    // the pda burns the receipt when the trade is filled. Whoever gets the receipt next must approve
+                    // it again, as spl-token clears the delegate on transfer
+                    let approve_ix = spl_token::instruction::approve(
+                        &spl_token::id(),
+                        receipt_account_ai.key,
+                        pda_pubkey.key,
+                        authority.key,
+                        &[authority.key],
+                        1,
+                    )?;
+
+                    invoke(
+                        &approve_ix,
+                        &[
+                            receipt_account_ai.clone(),
+                            pda_pubkey.clone(),
+                            authority.clone(),
+                            token_prog_ai.clone(),
+                        ],
+                    )?;
+
+                    msg!("Minted receipt..");
+                }
+
+                TradeEvent::TradeCreated {
+                    trade: *trade_ai.key,
+                    authority: *authority.key,
+                    offer_token_account: *offer_token_ai.key,
+                    trade_mint: *trade_mint_ai.key,
+                    offer_amount: trade_account.offer_amount,
+                    trade_amount: trade_account.trade_amount,
+                }.emit();
             },
 
-            Action::MakeTrade{ expected_offer, expected_trade} => {
+            Action::MakeTrade{ expected_offer, expected_trade, allowlist } => {
                 msg!("Making trade...");
-                
+                let fill = TradeFill { expected_offer, expected_trade, allowlist };
+                Self::make_single_trade(program_id, accounts, &fill, false)?;
+            },
+
+            Action::MakeTrades{ trades } => {
+                msg!("Making {} trades...", trades.len());
+
                 let authority_ai = next_account_info(accounts_iter)?;
                 if !authority_ai.is_signer {
-                    Err(TradeError::WrongAuthority)?;
+                    Err(Self::account_error(0, TradeError::WrongAuthority))?;
                 }
+                let token_program_ai = next_account_info(accounts_iter)?;
+                Self::check_token_program(1, token_program_ai)?;
+
+                if trades.is_empty() {
+                    Err(ProgramError::InvalidInstructionData)?
+                }
+
+                // every trade takes the same accounts as MakeTrade, in the same order, from the remaining accounts
+                let mut trade_accounts = Vec::with_capacity(trades.len());
+                for _ in 0..trades.len() {
+                    trade_accounts.push(next_account_infos(accounts_iter, MAKE_TRADE_ACCOUNTS)?);
+                }
+                let mut trailing = TrailingAccounts {
+                    iter: accounts_iter.as_slice().iter(),
+                    index: 2 + trades.len() * MAKE_TRADE_ACCOUNTS,
+                };
+
+                for (i, (fill, accounts)) in trades.iter().zip(trade_accounts).enumerate() {
+                    Self::make_trade(
+                        program_id,
+                        authority_ai,
+                        2 + i * MAKE_TRADE_ACCOUNTS,
+                        accounts,
+                        token_program_ai,
+                        &mut trailing,
+                        fill,
+                        false,
+                    )?;
+                }
+
+                if trailing.iter.next().is_some() {
+                    Err(Self::account_error(trailing.index, TradeError::UnexpectedAccount))?
+                }
+            }
+
+            Action::CloseStaleTrade => {
+                msg!("Closing stale trade...");
+
+                let cranker_ai = next_account_info(accounts_iter)?;
+                if !cranker_ai.is_signer {
+                    Err(Self::account_error(0, TradeError::WrongAuthority))?;
+                }
+                Self::check_writable(0, cranker_ai)?;
 
                 let trade_account_ai = next_account_info(accounts_iter)?;
+                Self::check_writable(1, trade_account_ai)?;
                 let trade_account = state::AccountTrade::try_from_slice(&trade_account_ai.data.borrow())?;
                 if !trade_account.initialized {
                     return Err(TradeError::TradeNotInitialised)?;
                 }
 
-                let pda_ai = next_account_info(accounts_iter)?;
-                // to avoid the need of this account, I could create a temporary PDA that was deleted afterwards
-                // instead of using the original token account.
-                // Another detail about this implementation is that this account should not be an ATA because at some
-                // point its authority is moved to the program which changes its data
-                let original_pda_addr_ai = next_account_info(accounts_iter)?;
-                let trade_dst_ai = next_account_info(accounts_iter)?;
-                if sol_memcmp(trade_dst_ai.key.as_ref(), trade_account.trade_dst_account.as_ref(), PUBKEY_BYTES) != 0 {
-                    return Err(TradeError::UnexpectedAccount)?;
+                if sol_memcmp(program_id.as_ref(), trade_account.program_id.as_ref(), PUBKEY_BYTES) != 0 {
+                    Err(ProgramError::IncorrectProgramId)?
                 }
-                let trade_src_ai = next_account_info(accounts_iter)?;
-                let trade_src = Account::unpack_from_slice(&trade_src_ai.data.borrow())?;
-                // I exepect this to fail anyway if the offer dst is from a different mint. Is it worth to check here?
-                if sol_memcmp(trade_src.mint.as_ref(), trade_account.trade_mint.as_ref(), PUBKEY_BYTES) != 0 {
-                    return Err(TradeError::TradeMintMissmatch)?;
+                // the taker of a forward or vesting trade already paid, so it is only closed by Settle or ClaimVested
+                if trade_account.is_committed() {
+                    return Err(Self::account_error(1, TradeError::SettlementPending))?;
+                }
+
+                let pda_ai = next_account_info(accounts_iter)?;
+                Self::check_pda(program_id, 2, pda_ai, trade_account_ai.key, trade_account.bump_seed)?;
+                let offer_token_ai = next_account_info(accounts_iter)?;
+                Self::check_writable(3, offer_token_ai)?;
+                if sol_memcmp(trade_account.offer_token_account.as_ref(), offer_token_ai.key.as_ref(), PUBKEY_BYTES) != 0 {
+                    Err(Self::account_error(3, TradeError::WrongTokenAccount))?
                 }
-                let offer_dst_ai = next_account_info(accounts_iter)?;
                 let offer_owner_ai = next_account_info(accounts_iter)?;
-                let fee_account_ai = next_account_info(accounts_iter)?;
+                Self::check_writable(4, offer_owner_ai)?;
+                if sol_memcmp(trade_account.authority.as_ref(), offer_owner_ai.key.as_ref(), PUBKEY_BYTES) != 0 {
+                    Err(Self::account_error(4, TradeError::WrongAuthority))?
+                }
                 let token_program_ai = next_account_info(accounts_iter)?;
+                Self::check_token_program(5, token_program_ai)?;
+                let maker_stats_ai = next_account_info(accounts_iter)?;
+                Self::check_writable(6, maker_stats_ai)?;
+
+                let offer_token = Account::unpack_from_slice(&offer_token_ai.data.borrow())?;
+                let now = Clock::get()?.unix_timestamp;
+                if !trade_account.is_expired(now) && !trade_account.is_abandoned(&offer_token, pda_ai.key) {
+                    return Err(TradeError::TradeNotStale)?;
+                }
+
+                // the offer tokens never left the offer account, so returning the offer is just a matter of giving
+                // the account back to its owner. In Delegate mode the owner never lost it, and whatever is left of
+                // the delegation can't be used without the trade account
+                if trade_account.mode.escrows_offer() {
+                    let owner_change_ix = spl_token::instruction::set_authority(
+                        &spl_token::id(),
+                        offer_token_ai.key,
+                        Some(&trade_account.authority),
+                        spl_token::instruction::AuthorityType::AccountOwner,
+                        pda_ai.key,
+                        &[pda_ai.key],
+                    )?;
+
+                    invoke_signed(
+                        &owner_change_ix,
+                        &[
+                            offer_token_ai.clone(),
+                            pda_ai.clone(),
+                            token_program_ai.clone(),
+                        ],
+                        &[&[trade_account_ai.key.as_ref(), &[trade_account.bump_seed]]],
+                    )?;
+
+                    msg!("Returned authority of the offer account...");
+                }
+
+                // pay the bounty to whoever closed the trade and the rest of the rent back to the owner
+                let bounty = trade_account.bounty.min(trade_account_ai.lamports());
+                **cranker_ai.try_borrow_mut_lamports()? = cranker_ai
+                    .lamports()
+                    .checked_add(bounty)
+                    .ok_or(TradeError::ValueOverflow)?;
+                **offer_owner_ai.try_borrow_mut_lamports()? = offer_owner_ai
+                    .lamports()
+                    .checked_add(trade_account_ai.lamports().sub(bounty))
+                    .ok_or(TradeError::ValueOverflow)?;
+                // close account
+                **trade_account_ai.try_borrow_mut_lamports()? = 0;
+                // clean data for security reasons
+                *trade_account_ai.try_borrow_mut_data()? = &mut [];
+
+                msg!("Trade account closed, paid a bounty of {} lamports...", bounty);
+
+                Self::update_stats(
+                    program_id,
+                    6,
+                    maker_stats_ai,
+                    &state::StatsAccount::Maker(trade_account.authority),
+                    |stats: &mut state::AccountMakerStats| stats.cancelled = stats.cancelled.saturating_add(1),
+                )?;
+
+                TradeEvent::TradeCancelled {
+                    trade: *trade_account_ai.key,
+                    authority: trade_account.authority,
+                }.emit();
+            }
+
+            Action::IncreaseOffer { amount, proportional } => {
+                msg!("Increasing offer...");
+                Self::change_offer(program_id, accounts, amount, proportional, true)?;
+            }
 
-                if sol_memcmp(trade_account.offer_token_account.as_ref(), original_pda_addr_ai.key.as_ref(), PUBKEY_BYTES) != 0 {
-                    Err(TradeError::WrongTokenAccount)?
+            Action::DecreaseOffer { amount, proportional } => {
+                msg!("Decreasing offer...");
+                Self::change_offer(program_id, accounts, amount, proportional, false)?;
+            }
+
+            Action::TransferTradeAuthority => {
+                msg!("Transfering trade authority...");
+
+                Self::check_no_duplicates(0, accounts)?;
+
+                let authority_ai = next_account_info(accounts_iter)?;
+                if !authority_ai.is_signer {
+                    Err(Self::account_error(0, TradeError::WrongAuthority))?;
                 }
 
+                let trade_account_ai = next_account_info(accounts_iter)?;
+                Self::check_writable(1, trade_account_ai)?;
+                let mut trade_account = state::AccountTrade::try_from_slice(&trade_account_ai.data.borrow())?;
+                if !trade_account.initialized {
+                    return Err(TradeError::TradeNotInitialised)?;
+                }
                 if sol_memcmp(program_id.as_ref(), trade_account.program_id.as_ref(), PUBKEY_BYTES) != 0 {
                     Err(ProgramError::IncorrectProgramId)?
                 }
+                if sol_memcmp(trade_account.authority.as_ref(), authority_ai.key.as_ref(), PUBKEY_BYTES) != 0 {
+                    Err(Self::account_error(0, TradeError::WrongAuthority))?
+                }
+                if !trade_account.mode.escrows_offer() {
+                    return Err(TradeError::OfferNotInEscrow)?;
+                }
 
-                // I'll leave the checks agains the account balance to the spl-token program
-                if expected_offer != trade_account.offer_amount {
-                    msg!("Expected offer of {}, but got {}", expected_offer, trade_account.offer_amount);
-                    return Err(TradeError::UnexpectedOfferAmount)?;
+                let new_authority_ai = next_account_info(accounts_iter)?;
+                if let Ok(trade_dst_ai) = next_account_info(accounts_iter) {
+                    // same as when the trade is created, the trade dst may not exist yet
+                    if *trade_dst_ai.owner == spl_token::id() {
+                        let trade_dst = Account::unpack_from_slice(&trade_dst_ai.data.borrow())?;
+                        if sol_memcmp(trade_dst.mint.as_ref(), trade_account.trade_mint.as_ref(), PUBKEY_BYTES) != 0 {
+                            return Err(Self::account_error(3, TradeError::TradeMintMissmatch))?;
+                        }
+                    }
+
+                    trade_account.trade_dst_account = *trade_dst_ai.key;
+                    if !trade_account.splits().is_empty() {
+                        if trade_account.split_destinations().contains(trade_dst_ai.key) {
+                            return Err(Self::account_error(3, TradeError::InvalidSplits))?;
+                        }
+                        trade_account.splits[0].destination = *trade_dst_ai.key;
+                    }
                 }
 
-                if expected_trade != trade_account.trade_amount {
-                    msg!("Expected trade of {}, but got {}", expected_trade, trade_account.trade_amount);
-                    return Err(TradeError::UnexpectedTradeAmount)?;
+                trade_account.authority = *new_authority_ai.key;
+                trade_account.serialize(&mut *trade_account_ai.data.borrow_mut())?;
+
+                msg!("Trade authority transfered...");
+
+                TradeEvent::AuthorityTransferred {
+                    trade: *trade_account_ai.key,
+                    authority: *authority_ai.key,
+                    new_authority: *new_authority_ai.key,
+                }.emit();
+            }
+
+            Action::ArbiterRelease { to_taker } => {
+                msg!("Releasing arbitrated trade...");
+
+                Self::check_no_duplicates(0, accounts)?;
+
+                let arbiter_ai = next_account_info(accounts_iter)?;
+                if !arbiter_ai.is_signer {
+                    Err(Self::account_error(0, TradeError::WrongAuthority))?;
                 }
 
-                // transfer fee
+                let trade_account_ai = next_account_info(accounts_iter)?;
+                Self::check_writable(1, trade_account_ai)?;
+                let trade_account = state::AccountTrade::try_from_slice(&trade_account_ai.data.borrow())?;
+                if !trade_account.initialized {
+                    return Err(TradeError::TradeNotInitialised)?;
+                }
+                if sol_memcmp(program_id.as_ref(), trade_account.program_id.as_ref(), PUBKEY_BYTES) != 0 {
+                    Err(ProgramError::IncorrectProgramId)?
+                }
+                // trades in other modes have no arbiter
+                if !trade_account.is_arbitrated()
+                    || sol_memcmp(trade_account.arbiter.as_ref(), arbiter_ai.key.as_ref(), PUBKEY_BYTES) != 0 {
+                    Err(Self::account_error(0, TradeError::WrongAuthority))?
+                }
 
-                let prog_authority = Pubkey::from_str(PROGRAM_AUTHORITY).unwrap();
-                let fee_ata = spl_associated_token_account::get_associated_token_address(&prog_authority, &trade_src.mint);
-                // make sure the fee account passed is the correct one
-                if sol_memcmp(fee_account_ai.key.as_ref(), fee_ata.as_ref(), PUBKEY_BYTES) != 0 {
-                    Err(TradeError::WrongAuthority)?
+                let pda_ai = next_account_info(accounts_iter)?;
+                Self::check_pda(program_id, 2, pda_ai, trade_account_ai.key, trade_account.bump_seed)?;
+                let offer_token_ai = next_account_info(accounts_iter)?;
+                Self::check_writable(3, offer_token_ai)?;
+                if sol_memcmp(trade_account.offer_token_account.as_ref(), offer_token_ai.key.as_ref(), PUBKEY_BYTES) != 0 {
+                    Err(Self::account_error(3, TradeError::WrongTokenAccount))?
                 }
-                
-                // Not sure on how to do the rouding
-                // using floor or ceil instead of rount result in missing symbols in the ELF file...
-                let lamports_fee = (trade_account.offer_amount as f64).mul(FEE_PERCENTAGE).round() as u64;
-                msg!("Applying a transfer fee of {} lamports", lamports_fee);
+                let offer_owner_ai = next_account_info(accounts_iter)?;
+                Self::check_writable(4, offer_owner_ai)?;
+                if sol_memcmp(trade_account.authority.as_ref(), offer_owner_ai.key.as_ref(), PUBKEY_BYTES) != 0 {
+                    Err(Self::account_error(4, TradeError::WrongAuthority))?
+                }
+                let token_program_ai = next_account_info(accounts_iter)?;
+                Self::check_token_program(5, token_program_ai)?;
+                let maker_stats_ai = next_account_info(accounts_iter)?;
+                Self::check_writable(6, maker_stats_ai)?;
 
-                let fee_transfer_ix = spl_token::instruction::transfer(
-                    &spl_token::id(),
-                    trade_src_ai.key,
-                    &fee_account_ai.key,
-                    &authority_ai.key,
-                    &[&authority_ai.key],
-                    lamports_fee,
-                )?;
-                
-                invoke(
-                    &fee_transfer_ix,
-                    &[
-                        trade_src_ai.clone(),
-                        fee_account_ai.clone(),
-                        authority_ai.clone(),
-                        token_program_ai.clone(),
-                    ],
-                )?;
+                let now = Clock::get()?.unix_timestamp;
+                if now < trade_account.dispute_ends_at {
+                    msg!("The dispute ends at {}, it's now {}", trade_account.dispute_ends_at, now);
+                    return Err(TradeError::DisputeNotOver)?;
+                }
 
-                msg!("Fee transfered to {}...", fee_account_ai.key.to_string());
+                let offer_dst_ai = if to_taker {
+                    let offer_dst_ai = next_account_info(accounts_iter)?;
+                    Self::check_writable(7, offer_dst_ai)?;
+                    let offer_token = Account::unpack_from_slice(&offer_token_ai.data.borrow())?;
+                    let offer_dst = Account::unpack_from_slice(&offer_dst_ai.data.borrow())?;
+                    if sol_memcmp(offer_dst.mint.as_ref(), offer_token.mint.as_ref(), PUBKEY_BYTES) != 0 {
+                        return Err(Self::account_error(7, TradeError::OfferMintMissmatch))?;
+                    }
 
-                // transfer offer from pda to destination
+                    let transfer_offer_ix = spl_token::instruction::transfer(
+                        &spl_token::id(),
+                        offer_token_ai.key,
+                        offer_dst_ai.key,
+                        pda_ai.key,
+                        &[pda_ai.key],
+                        trade_account.offer_amount,
+                    )?;
 
-                let transfer_offer_ix = spl_token::instruction::transfer(
+                    invoke_signed(
+                        &transfer_offer_ix,
+                        &[
+                            offer_token_ai.clone(),
+                            offer_dst_ai.clone(),
+                            pda_ai.clone(),
+                            token_program_ai.clone(),
+                        ],
+                        &[&[trade_account_ai.key.as_ref(), &[trade_account.bump_seed]]],
+                    )?;
+
+                    msg!("Offer amount released to the taker...");
+                    Some(*offer_dst_ai.key)
+                } else {
+                    None
+                };
+
+                // the offer account goes back to its owner either way, empty if the offer went to the taker
+                let owner_change_ix = spl_token::instruction::set_authority(
                     &spl_token::id(),
-                    original_pda_addr_ai.key,
-                    offer_dst_ai.key,
-                    &pda_ai.key,
-                    &[&pda_ai.key],
-                    expected_offer,
+                    offer_token_ai.key,
+                    Some(&trade_account.authority),
+                    spl_token::instruction::AuthorityType::AccountOwner,
+                    pda_ai.key,
+                    &[pda_ai.key],
                 )?;
-                
+
                 invoke_signed(
-                    &transfer_offer_ix,
+                    &owner_change_ix,
                     &[
-                        original_pda_addr_ai.clone(),
-                        offer_dst_ai.clone(),
+                        offer_token_ai.clone(),
                         pda_ai.clone(),
                         token_program_ai.clone(),
                     ],
                     &[&[trade_account_ai.key.as_ref(), &[trade_account.bump_seed]]],
                 )?;
 
-                msg!(
-                    "Offer amount transfered from {} with PDA {} to {}...",
-                    original_pda_addr_ai.key.to_string(), pda_ai.key.to_string(), offer_dst_ai.key.to_string(),
-                );
-
-                // transfer trade amount
-
-                let transfer_trade_ix = spl_token::instruction::transfer(
-                    &spl_token::id(),
-                    trade_src_ai.key,
-                    trade_dst_ai.key,
-                    &authority_ai.key,
-                    &[&authority_ai.key],
-                    expected_trade.sub(lamports_fee),
-                )?;
-                
-                invoke(
-                    &transfer_trade_ix,
-                    &[
-                        trade_src_ai.clone(),
-                        trade_dst_ai.clone(),
-                        authority_ai.clone(),
-                        token_program_ai.clone(),
-                    ],
-                )?;
-
-                msg!(
-                    "Trade amount transfered from {} to {}...",
-                    trade_src_ai.key.to_string(), trade_dst_ai.key.to_string(),
-                );
+                msg!("Returned authority of the offer account...");
 
-                let trade_account_balance = trade_account_ai.lamports();
-                // TODO: Store this in the trade account and make sure they match, to prevent the taker from using a
-                //  different account to return lamports
                 **offer_owner_ai.try_borrow_mut_lamports()? = offer_owner_ai
                     .lamports()
                     .checked_add(trade_account_ai.lamports())
                     .ok_or(TradeError::ValueOverflow)?;
                 // close account
                 **trade_account_ai.try_borrow_mut_lamports()? = 0;
-                let bump_seed = trade_account.bump_seed;
-                let offer_authority = trade_account.authority;
                 // clean data for security reasons
                 *trade_account_ai.try_borrow_mut_data()? = &mut [];
 
-                msg!("Trade account closed. Returned {} lamports to account {}", trade_account_balance, offer_owner_ai.key.to_string());
+                msg!("Trade account closed...");
+
+                // a trade released to the taker counts as filled, otherwise as cancelled
+                Self::update_stats(
+                    program_id,
+                    6,
+                    maker_stats_ai,
+                    &state::StatsAccount::Maker(trade_account.authority),
+                    |stats: &mut state::AccountMakerStats| if to_taker {
+                        stats.filled = stats.filled.saturating_add(1);
+                    } else {
+                        stats.cancelled = stats.cancelled.saturating_add(1);
+                    },
+                )?;
+
+                TradeEvent::ArbiterReleased {
+                    trade: *trade_account_ai.key,
+                    authority: trade_account.authority,
+                    arbiter: *arbiter_ai.key,
+                    offer_dst: offer_dst_ai,
+                }.emit();
+            }
+
+            Action::InitNonces { bump_seed } => {
+                msg!("Creating nonces account...");
+
+                let maker_ai = next_account_info(accounts_iter)?;
+                if !maker_ai.is_signer {
+                    Err(Self::account_error(0, TradeError::WrongAuthority))?;
+                }
+                Self::check_writable(0, maker_ai)?;
+                let nonces_ai = next_account_info(accounts_iter)?;
+                Self::check_writable(1, nonces_ai)?;
+                Self::check_nonces_pda(program_id, 1, nonces_ai, maker_ai.key, bump_seed)?;
+                if nonces_ai.owner == program_id {
+                    return Err(ProgramError::AccountAlreadyInitialized)?;
+                }
+                let system_program_ai = next_account_info(accounts_iter)?;
+
+                Self::create_pda_account(
+                    program_id,
+                    maker_ai,
+                    nonces_ai,
+                    system_program_ai,
+                    state::AccountNonces::size(),
+                    &[state::NONCES_SEED, maker_ai.key.as_ref(), &[bump_seed]],
+                )?;
+
+                let nonces = state::AccountNonces {
+                    initialized: true,
+                    maker: *maker_ai.key,
+                    bump_seed,
+                    ..state::AccountNonces::default()
+                };
+                nonces.serialize(&mut *nonces_ai.data.borrow_mut())?;
+
+                msg!("Nonces account created...");
+
+                TradeEvent::NoncesInitialised { maker: *maker_ai.key, nonces: *nonces_ai.key }.emit();
+            }
 
-                // return authotiry of the offer token account to the original owner
+            Action::CancelOrders { min_nonce, nonces: cancelled } => {
+                msg!("Cancelling signed orders...");
 
-                // makes sure it's returning authority to the right owner
-                if sol_memcmp(offer_authority.as_ref(), offer_owner_ai.key.as_ref(), PUBKEY_BYTES) != 0 {
-                    Err(TradeError::WrongAuthority)?
+                let maker_ai = next_account_info(accounts_iter)?;
+                if !maker_ai.is_signer {
+                    Err(Self::account_error(0, TradeError::WrongAuthority))?;
                 }
+                let nonces_ai = next_account_info(accounts_iter)?;
+                Self::check_writable(1, nonces_ai)?;
+                let mut nonces = Self::load_nonces(program_id, 1, nonces_ai, maker_ai.key)?;
 
-                let owner_change_ix = spl_token::instruction::set_authority(
+                nonces.spend_below(min_nonce);
+                for nonce in &cancelled {
+                    if !nonces.is_spent(*nonce) && !nonces.spend(*nonce) {
+                        msg!("Nonce {} is above the window starting at {}", nonce, nonces.base);
+                        return Err(TradeError::NonceOutOfRange)?;
+                    }
+                }
+                nonces.serialize(&mut *nonces_ai.data.borrow_mut())?;
+
+                TradeEvent::OrdersCancelled {
+                    maker: *maker_ai.key,
+                    min_nonce,
+                    nonces: cancelled,
+                }.emit();
+            }
+
+            Action::FillSignedOrder { order } => {
+                msg!("Filling signed order...");
+                Self::fill_signed_order(program_id, accounts, &order)?;
+            }
+
+            Action::BuyOption { premium } => {
+                msg!("Buying option...");
+
+                Self::check_no_duplicates(0, accounts)?;
+
+                let buyer_ai = next_account_info(accounts_iter)?;
+                if !buyer_ai.is_signer {
+                    Err(Self::account_error(0, TradeError::WrongAuthority))?;
+                }
+
+                let trade_account_ai = next_account_info(accounts_iter)?;
+                Self::check_writable(1, trade_account_ai)?;
+                let mut trade_account = state::AccountTrade::try_from_slice(&trade_account_ai.data.borrow())?;
+                if !trade_account.initialized {
+                    return Err(TradeError::TradeNotInitialised)?;
+                }
+                if sol_memcmp(program_id.as_ref(), trade_account.program_id.as_ref(), PUBKEY_BYTES) != 0 {
+                    Err(ProgramError::IncorrectProgramId)?
+                }
+                if !trade_account.is_option() {
+                    return Err(Self::account_error(1, TradeError::NotAnOption))?;
+                }
+                if trade_account.is_option_bought() {
+                    return Err(TradeError::OptionAlreadyBought)?;
+                }
+
+                let premium_src_ai = next_account_info(accounts_iter)?;
+                Self::check_writable(2, premium_src_ai)?;
+                let premium_src = Account::unpack_from_slice(&premium_src_ai.data.borrow())?;
+                if sol_memcmp(premium_src.mint.as_ref(), trade_account.trade_mint.as_ref(), PUBKEY_BYTES) != 0 {
+                    return Err(Self::account_error(2, TradeError::TradeMintMissmatch))?;
+                }
+                let trade_dst_ai = next_account_info(accounts_iter)?;
+                Self::check_writable(3, trade_dst_ai)?;
+                if sol_memcmp(trade_dst_ai.key.as_ref(), trade_account.trade_dst_account.as_ref(), PUBKEY_BYTES) != 0 {
+                    return Err(Self::account_error(3, TradeError::UnexpectedAccount))?;
+                }
+                let token_program_ai = next_account_info(accounts_iter)?;
+                Self::check_token_program(4, token_program_ai)?;
+
+                let now = Clock::get()?.unix_timestamp;
+                if trade_account.is_expired(now) {
+                    return Err(TradeError::TradeExpired)?;
+                }
+                if premium != trade_account.premium {
+                    msg!("Expected premium of {}, but got {}", premium, trade_account.premium);
+                    return Err(TradeError::UnexpectedTradeAmount)?;
+                }
+
+                let transfer_premium_ix = spl_token::instruction::transfer(
                     &spl_token::id(),
-                    original_pda_addr_ai.key,
-                    Some(&offer_authority),
-                    spl_token::instruction::AuthorityType::AccountOwner,
-                    &pda_ai.key,
-                    &[&pda_ai.key],
+                    premium_src_ai.key,
+                    trade_dst_ai.key,
+                    buyer_ai.key,
+                    &[buyer_ai.key],
+                    premium,
                 )?;
-        
-                invoke_signed(
-                    &owner_change_ix,
+
+                invoke(
+                    &transfer_premium_ix,
                     &[
-                        original_pda_addr_ai.clone(),
-                        pda_ai.clone(),
+                        premium_src_ai.clone(),
+                        trade_dst_ai.clone(),
+                        buyer_ai.clone(),
+                        token_program_ai.clone(),
+                    ],
+                )?;
+
+                msg!("Premium transfered...");
+
+                trade_account.holder = *buyer_ai.key;
+                trade_account.serialize(&mut *trade_account_ai.data.borrow_mut())?;
+
+                TradeEvent::OptionBought {
+                    trade: *trade_account_ai.key,
+                    authority: trade_account.authority,
+                    holder: *buyer_ai.key,
+                    premium,
+                }.emit();
+            }
+
+            Action::ExerciseOption { expected_offer, expected_trade } => {
+                msg!("Exercising option...");
+                let fill = TradeFill { expected_offer, expected_trade, allowlist: None };
+                Self::make_single_trade(program_id, accounts, &fill, true)?;
+            }
+
+            Action::ReclaimUnderlying => {
+                msg!("Reclaiming underlying of expired option...");
+
+                Self::check_no_duplicates(0, accounts)?;
+
+                let authority_ai = next_account_info(accounts_iter)?;
+                if !authority_ai.is_signer {
+                    Err(Self::account_error(0, TradeError::WrongAuthority))?;
+                }
+                Self::check_writable(0, authority_ai)?;
+
+                let trade_account_ai = next_account_info(accounts_iter)?;
+                Self::check_writable(1, trade_account_ai)?;
+                let trade_account = state::AccountTrade::try_from_slice(&trade_account_ai.data.borrow())?;
+                if !trade_account.initialized {
+                    return Err(TradeError::TradeNotInitialised)?;
+                }
+                if sol_memcmp(program_id.as_ref(), trade_account.program_id.as_ref(), PUBKEY_BYTES) != 0 {
+                    Err(ProgramError::IncorrectProgramId)?
+                }
+                if sol_memcmp(trade_account.authority.as_ref(), authority_ai.key.as_ref(), PUBKEY_BYTES) != 0 {
+                    Err(Self::account_error(0, TradeError::WrongAuthority))?
+                }
+                if !trade_account.is_option() {
+                    return Err(Self::account_error(1, TradeError::NotAnOption))?;
+                }
+
+                let pda_ai = next_account_info(accounts_iter)?;
+                Self::check_pda(program_id, 2, pda_ai, trade_account_ai.key, trade_account.bump_seed)?;
+                let offer_token_ai = next_account_info(accounts_iter)?;
+                Self::check_writable(3, offer_token_ai)?;
+                if sol_memcmp(trade_account.offer_token_account.as_ref(), offer_token_ai.key.as_ref(), PUBKEY_BYTES) != 0 {
+                    Err(Self::account_error(3, TradeError::WrongTokenAccount))?
+                }
+                let token_program_ai = next_account_info(accounts_iter)?;
+                Self::check_token_program(4, token_program_ai)?;
+                let maker_stats_ai = next_account_info(accounts_iter)?;
+                Self::check_writable(5, maker_stats_ai)?;
+
+                let now = Clock::get()?.unix_timestamp;
+                if !trade_account.is_expired(now) {
+                    msg!("The option expires at {}, it's now {}", trade_account.expires_at, now);
+                    return Err(TradeError::OptionNotExpired)?;
+                }
+
+                let owner_change_ix = spl_token::instruction::set_authority(
+                    &spl_token::id(),
+                    offer_token_ai.key,
+                    Some(&trade_account.authority),
+                    spl_token::instruction::AuthorityType::AccountOwner,
+                    pda_ai.key,
+                    &[pda_ai.key],
+                )?;
+
+                invoke_signed(
+                    &owner_change_ix,
+                    &[
+                        offer_token_ai.clone(),
+                        pda_ai.clone(),
+                        token_program_ai.clone(),
+                    ],
+                    &[&[trade_account_ai.key.as_ref(), &[trade_account.bump_seed]]],
+                )?;
+
+                msg!("Returned authority of the offer account...");
+
+                **authority_ai.try_borrow_mut_lamports()? = authority_ai
+                    .lamports()
+                    .checked_add(trade_account_ai.lamports())
+                    .ok_or(TradeError::ValueOverflow)?;
+                // close account
+                **trade_account_ai.try_borrow_mut_lamports()? = 0;
+                // clean data for security reasons
+                *trade_account_ai.try_borrow_mut_data()? = &mut [];
+
+                msg!("Trade account closed...");
+
+                Self::update_stats(
+                    program_id,
+                    5,
+                    maker_stats_ai,
+                    &state::StatsAccount::Maker(trade_account.authority),
+                    |stats: &mut state::AccountMakerStats| stats.cancelled = stats.cancelled.saturating_add(1),
+                )?;
+
+                TradeEvent::OptionReclaimed {
+                    trade: *trade_account_ai.key,
+                    authority: trade_account.authority,
+                }.emit();
+            }
+
+            Action::Settle => {
+                msg!("Settling forward trade...");
+
+                Self::check_no_duplicates(0, accounts)?;
+
+                let trade_account_ai = next_account_info(accounts_iter)?;
+                Self::check_writable(0, trade_account_ai)?;
+                let trade_account = state::AccountTrade::try_from_slice(&trade_account_ai.data.borrow())?;
+                if !trade_account.initialized {
+                    return Err(TradeError::TradeNotInitialised)?;
+                }
+                if sol_memcmp(program_id.as_ref(), trade_account.program_id.as_ref(), PUBKEY_BYTES) != 0 {
+                    Err(ProgramError::IncorrectProgramId)?
+                }
+                if !trade_account.is_forward() {
+                    return Err(Self::account_error(0, TradeError::NotAForward))?;
+                }
+
+                let pda_ai = next_account_info(accounts_iter)?;
+                Self::check_pda(program_id, 1, pda_ai, trade_account_ai.key, trade_account.bump_seed)?;
+                let offer_token_ai = next_account_info(accounts_iter)?;
+                Self::check_writable(2, offer_token_ai)?;
+                if sol_memcmp(trade_account.offer_token_account.as_ref(), offer_token_ai.key.as_ref(), PUBKEY_BYTES) != 0 {
+                    Err(Self::account_error(2, TradeError::WrongTokenAccount))?
+                }
+                let offer_owner_ai = next_account_info(accounts_iter)?;
+                Self::check_writable(3, offer_owner_ai)?;
+                if sol_memcmp(trade_account.authority.as_ref(), offer_owner_ai.key.as_ref(), PUBKEY_BYTES) != 0 {
+                    Err(Self::account_error(3, TradeError::WrongAuthority))?
+                }
+                let token_program_ai = next_account_info(accounts_iter)?;
+                Self::check_token_program(4, token_program_ai)?;
+
+                let now = Clock::get()?.unix_timestamp;
+                if !trade_account.is_settlement_due(now) {
+                    msg!("The trade settles at {}, it's now {}", trade_account.settles_at, now);
+                    return Err(TradeError::SettlementNotDue)?;
+                }
+
+                let signer_seeds: &[&[u8]] = &[trade_account_ai.key.as_ref(), &[trade_account.bump_seed]];
+
+                // deliver both sides of a filled trade, otherwise the offer account just goes back to its owner below
+
+                if trade_account.is_committed() {
+                    let offer_dst_ai = next_account_info(accounts_iter)?;
+                    Self::check_writable(5, offer_dst_ai)?;
+                    if sol_memcmp(offer_dst_ai.key.as_ref(), trade_account.offer_dst_account.as_ref(), PUBKEY_BYTES) != 0 {
+                        return Err(Self::account_error(5, TradeError::UnexpectedAccount))?;
+                    }
+                    let escrow_ai = next_account_info(accounts_iter)?;
+                    Self::check_writable(6, escrow_ai)?;
+                    let escrow_key = spl_associated_token_account::get_associated_token_address(
+                        pda_ai.key,
+                        &trade_account.trade_mint,
+                    );
+                    if sol_memcmp(escrow_ai.key.as_ref(), escrow_key.as_ref(), PUBKEY_BYTES) != 0 {
+                        return Err(Self::account_error(6, TradeError::UnexpectedAccount))?;
+                    }
+                    let trade_dst_ai = next_account_info(accounts_iter)?;
+                    Self::check_writable(7, trade_dst_ai)?;
+                    if sol_memcmp(trade_dst_ai.key.as_ref(), trade_account.trade_dst_account.as_ref(), PUBKEY_BYTES) != 0 {
+                        return Err(Self::account_error(7, TradeError::UnexpectedAccount))?;
+                    }
+                    let taker_ai = next_account_info(accounts_iter)?;
+                    Self::check_writable(8, taker_ai)?;
+                    if sol_memcmp(taker_ai.key.as_ref(), trade_account.taker.as_ref(), PUBKEY_BYTES) != 0 {
+                        return Err(Self::account_error(8, TradeError::UnexpectedAccount))?;
+                    }
+
+                    let transfer_offer_ix = spl_token::instruction::transfer(
+                        &spl_token::id(),
+                        offer_token_ai.key,
+                        offer_dst_ai.key,
+                        pda_ai.key,
+                        &[pda_ai.key],
+                        trade_account.offer_amount,
+                    )?;
+
+                    invoke_signed(
+                        &transfer_offer_ix,
+                        &[
+                            offer_token_ai.clone(),
+                            offer_dst_ai.clone(),
+                            pda_ai.clone(),
+                            token_program_ai.clone(),
+                        ],
+                        &[signer_seeds],
+                    )?;
+
+                    msg!("Offer amount transfered...");
+
+                    // whatever is in escrow, the trade amount after the fee, goes to the trade dst
+                    let escrow = Account::unpack_from_slice(&escrow_ai.data.borrow())?;
+                    let transfer_trade_ix = spl_token::instruction::transfer(
+                        &spl_token::id(),
+                        escrow_ai.key,
+                        trade_dst_ai.key,
+                        pda_ai.key,
+                        &[pda_ai.key],
+                        escrow.amount,
+                    )?;
+
+                    invoke_signed(
+                        &transfer_trade_ix,
+                        &[
+                            escrow_ai.clone(),
+                            trade_dst_ai.clone(),
+                            pda_ai.clone(),
+                            token_program_ai.clone(),
+                        ],
+                        &[signer_seeds],
+                    )?;
+
+                    msg!("Trade amount transfered...");
+
+                    let close_escrow_ix = spl_token::instruction::close_account(
+                        &spl_token::id(),
+                        escrow_ai.key,
+                        taker_ai.key,
+                        pda_ai.key,
+                        &[pda_ai.key],
+                    )?;
+
+                    invoke_signed(
+                        &close_escrow_ix,
+                        &[
+                            escrow_ai.clone(),
+                            taker_ai.clone(),
+                            pda_ai.clone(),
+                            token_program_ai.clone(),
+                        ],
+                        &[signer_seeds],
+                    )?;
+
+                    msg!("Trade escrow closed...");
+
+                    TradeEvent::TradeFilled {
+                        trade: *trade_account_ai.key,
+                        authority: trade_account.authority,
+                        taker: trade_account.taker,
+                        offer_amount: trade_account.offer_amount,
+                        trade_amount: escrow.amount,
+                    }.emit();
+                } else {
+                    let maker_stats_ai = next_account_info(accounts_iter)?;
+                    Self::check_writable(5, maker_stats_ai)?;
+                    Self::update_stats(
+                        program_id,
+                        5,
+                        maker_stats_ai,
+                        &state::StatsAccount::Maker(trade_account.authority),
+                        |stats: &mut state::AccountMakerStats| stats.cancelled = stats.cancelled.saturating_add(1),
+                    )?;
+
+                    TradeEvent::TradeCancelled {
+                        trade: *trade_account_ai.key,
+                        authority: trade_account.authority,
+                    }.emit();
+                }
+
+                let owner_change_ix = spl_token::instruction::set_authority(
+                    &spl_token::id(),
+                    offer_token_ai.key,
+                    Some(&trade_account.authority),
+                    spl_token::instruction::AuthorityType::AccountOwner,
+                    pda_ai.key,
+                    &[pda_ai.key],
+                )?;
+
+                invoke_signed(
+                    &owner_change_ix,
+                    &[
+                        offer_token_ai.clone(),
+                        pda_ai.clone(),
+                        token_program_ai.clone(),
+                    ],
+                    &[signer_seeds],
+                )?;
+
+                msg!("Returned authority of the offer account...");
+
+                **offer_owner_ai.try_borrow_mut_lamports()? = offer_owner_ai
+                    .lamports()
+                    .checked_add(trade_account_ai.lamports())
+                    .ok_or(TradeError::ValueOverflow)?;
+                // close account
+                **trade_account_ai.try_borrow_mut_lamports()? = 0;
+                // clean data for security reasons
+                *trade_account_ai.try_borrow_mut_data()? = &mut [];
+
+                msg!("Trade account closed...");
+            }
+
+            Action::ClaimVested => {
+                msg!("Claiming vested offer...");
+
+                Self::check_no_duplicates(0, accounts)?;
+
+                let taker_ai = next_account_info(accounts_iter)?;
+                if !taker_ai.is_signer {
+                    Err(Self::account_error(0, TradeError::WrongAuthority))?;
+                }
+                Self::check_writable(0, taker_ai)?;
+
+                let trade_account_ai = next_account_info(accounts_iter)?;
+                Self::check_writable(1, trade_account_ai)?;
+                let mut trade_account = state::AccountTrade::try_from_slice(&trade_account_ai.data.borrow())?;
+                if !trade_account.initialized {
+                    return Err(TradeError::TradeNotInitialised)?;
+                }
+                if sol_memcmp(program_id.as_ref(), trade_account.program_id.as_ref(), PUBKEY_BYTES) != 0 {
+                    Err(ProgramError::IncorrectProgramId)?
+                }
+                if !trade_account.is_vesting() || !trade_account.is_committed() {
+                    return Err(Self::account_error(1, TradeError::NotVesting))?;
+                }
+                if sol_memcmp(trade_account.taker.as_ref(), taker_ai.key.as_ref(), PUBKEY_BYTES) != 0 {
+                    Err(Self::account_error(0, TradeError::WrongAuthority))?
+                }
+
+                let pda_ai = next_account_info(accounts_iter)?;
+                Self::check_pda(program_id, 2, pda_ai, trade_account_ai.key, trade_account.bump_seed)?;
+                let escrow_ai = next_account_info(accounts_iter)?;
+                Self::check_writable(3, escrow_ai)?;
+                let escrow_key = spl_associated_token_account::get_associated_token_address(
+                    pda_ai.key,
+                    &trade_account.offer_mint,
+                );
+                if sol_memcmp(escrow_ai.key.as_ref(), escrow_key.as_ref(), PUBKEY_BYTES) != 0 {
+                    return Err(Self::account_error(3, TradeError::UnexpectedAccount))?;
+                }
+                let offer_dst_ai = next_account_info(accounts_iter)?;
+                Self::check_writable(4, offer_dst_ai)?;
+                if sol_memcmp(offer_dst_ai.key.as_ref(), trade_account.offer_dst_account.as_ref(), PUBKEY_BYTES) != 0 {
+                    return Err(Self::account_error(4, TradeError::UnexpectedAccount))?;
+                }
+                let offer_owner_ai = next_account_info(accounts_iter)?;
+                Self::check_writable(5, offer_owner_ai)?;
+                if sol_memcmp(trade_account.authority.as_ref(), offer_owner_ai.key.as_ref(), PUBKEY_BYTES) != 0 {
+                    Err(Self::account_error(5, TradeError::WrongAuthority))?
+                }
+                let token_program_ai = next_account_info(accounts_iter)?;
+                Self::check_token_program(6, token_program_ai)?;
+
+                let now = Clock::get()?.unix_timestamp;
+                let amount = trade_account.vested_amount(now).saturating_sub(trade_account.claimed);
+                if amount == 0 {
+                    msg!("{} of {} claimed, none vested since", trade_account.claimed, trade_account.offer_amount);
+                    return Err(TradeError::NothingVested)?;
+                }
+
+                trade_account.claimed += amount;
+                let last_claim = trade_account.claimed >= trade_account.offer_amount;
+                // the last claim empties the escrow, along with anything sent to it besides the offer, so it can be
+                // closed
+                let transfer_amount = if last_claim {
+                    Account::unpack_from_slice(&escrow_ai.data.borrow())?.amount
+                } else {
+                    amount
+                };
+
+                let signer_seeds: &[&[u8]] = &[trade_account_ai.key.as_ref(), &[trade_account.bump_seed]];
+                let transfer_offer_ix = spl_token::instruction::transfer(
+                    &spl_token::id(),
+                    escrow_ai.key,
+                    offer_dst_ai.key,
+                    pda_ai.key,
+                    &[pda_ai.key],
+                    transfer_amount,
+                )?;
+
+                invoke_signed(
+                    &transfer_offer_ix,
+                    &[
+                        escrow_ai.clone(),
+                        offer_dst_ai.clone(),
+                        pda_ai.clone(),
+                        token_program_ai.clone(),
+                    ],
+                    &[signer_seeds],
+                )?;
+
+                msg!("Claimed {} of the offer...", transfer_amount);
+
+                TradeEvent::VestedClaimed {
+                    trade: *trade_account_ai.key,
+                    taker: trade_account.taker,
+                    amount,
+                    claimed: trade_account.claimed,
+                }.emit();
+
+                if !last_claim {
+                    trade_account.serialize(&mut *trade_account_ai.data.borrow_mut())?;
+                    return Ok(());
+                }
+
+                // all of the offer was claimed, so there's nothing left to keep track of
+
+                let close_escrow_ix = spl_token::instruction::close_account(
+                    &spl_token::id(),
+                    escrow_ai.key,
+                    taker_ai.key,
+                    pda_ai.key,
+                    &[pda_ai.key],
+                )?;
+
+                invoke_signed(
+                    &close_escrow_ix,
+                    &[
+                        escrow_ai.clone(),
+                        taker_ai.clone(),
+                        pda_ai.clone(),
+                        token_program_ai.clone(),
                     ],
-                    &[&[trade_account_ai.key.as_ref(), &[bump_seed]]],
+                    &[signer_seeds],
+                )?;
+
+                msg!("Vesting escrow closed...");
+
+                **offer_owner_ai.try_borrow_mut_lamports()? = offer_owner_ai
+                    .lamports()
+                    .checked_add(trade_account_ai.lamports())
+                    .ok_or(TradeError::ValueOverflow)?;
+                // close account
+                **trade_account_ai.try_borrow_mut_lamports()? = 0;
+                // clean data for security reasons
+                *trade_account_ai.try_borrow_mut_data()? = &mut [];
+
+                msg!("Trade account closed...");
+            }
+
+            Action::SetMintListMode { bump_seed, mode } => {
+                msg!("Setting mint list mode...");
+
+                let authority_ai = next_account_info(accounts_iter)?;
+                Self::check_program_authority(0, authority_ai)?;
+                Self::check_writable(0, authority_ai)?;
+                let mint_list_ai = next_account_info(accounts_iter)?;
+                Self::check_writable(1, mint_list_ai)?;
+                let pda = Pubkey::create_program_address(&[state::MINT_LIST_SEED, &[bump_seed]], program_id)
+                    .map_err(|_| Self::account_error(1, TradeError::InvalidPda))?;
+                if sol_memcmp(mint_list_ai.key.as_ref(), pda.as_ref(), PUBKEY_BYTES) != 0 {
+                    return Err(Self::account_error(1, TradeError::InvalidPda))?;
+                }
+                let system_program_ai = next_account_info(accounts_iter)?;
+
+                // the list is created the first time its mode is set
+                let listed = if mint_list_ai.owner == program_id {
+                    let mint_list = Self::load_mint_list(program_id, 1, mint_list_ai)?;
+                    if mint_list.mode != mode && mint_list.listed > 0 {
+                        msg!("{} mints listed in {:?} mode", mint_list.listed, mint_list.mode);
+                        return Err(Self::account_error(1, TradeError::MintListNotEmpty))?;
+                    }
+
+                    mint_list.listed
+                } else {
+                    Self::create_pda_account(
+                        program_id,
+                        authority_ai,
+                        mint_list_ai,
+                        system_program_ai,
+                        state::AccountMintList::size(),
+                        &[state::MINT_LIST_SEED, &[bump_seed]],
+                    )?;
+
+                    msg!("Mint list created...");
+                    0
+                };
+
+                let mint_list = state::AccountMintList { initialized: true, bump_seed, mode, listed };
+                mint_list.serialize(&mut *mint_list_ai.data.borrow_mut())?;
+
+                TradeEvent::MintListModeSet { mode }.emit();
+            }
+
+            Action::AddMint { bump_seed } => {
+                msg!("Adding mint to the mint list...");
+
+                let authority_ai = next_account_info(accounts_iter)?;
+                Self::check_program_authority(0, authority_ai)?;
+                Self::check_writable(0, authority_ai)?;
+                let listed_mint_ai = next_account_info(accounts_iter)?;
+                Self::check_writable(1, listed_mint_ai)?;
+                let mint_ai = next_account_info(accounts_iter)?;
+                let pda = Pubkey::create_program_address(
+                    &[state::LISTED_MINT_SEED, mint_ai.key.as_ref(), &[bump_seed]],
+                    program_id,
+                ).map_err(|_| Self::account_error(1, TradeError::InvalidPda))?;
+                if sol_memcmp(listed_mint_ai.key.as_ref(), pda.as_ref(), PUBKEY_BYTES) != 0 {
+                    return Err(Self::account_error(1, TradeError::InvalidPda))?;
+                }
+                // listing a mint twice would fail to create its account anyway
+                if listed_mint_ai.owner == program_id {
+                    return Err(ProgramError::AccountAlreadyInitialized)?;
+                }
+                if *mint_ai.owner != spl_token::id() || Mint::unpack(&mint_ai.data.borrow()).is_err() {
+                    return Err(Self::account_error(2, TradeError::UnexpectedAccount))?;
+                }
+                let mint_list_ai = next_account_info(accounts_iter)?;
+                Self::check_writable(3, mint_list_ai)?;
+                let mut mint_list = Self::load_mint_list(program_id, 3, mint_list_ai)?;
+                let system_program_ai = next_account_info(accounts_iter)?;
+
+                Self::create_pda_account(
+                    program_id,
+                    authority_ai,
+                    listed_mint_ai,
+                    system_program_ai,
+                    state::AccountListedMint::size(),
+                    &[state::LISTED_MINT_SEED, mint_ai.key.as_ref(), &[bump_seed]],
                 )?;
 
-                msg!("Returned authority of {} back to {}", 
-                    original_pda_addr_ai.key.to_string(), offer_authority.to_string());
+                let listed_mint = state::AccountListedMint { initialized: true, bump_seed, mint: *mint_ai.key };
+                listed_mint.serialize(&mut *listed_mint_ai.data.borrow_mut())?;
+                mint_list.listed = mint_list.listed.saturating_add(1);
+                mint_list.serialize(&mut *mint_list_ai.data.borrow_mut())?;
+
+                TradeEvent::MintListUpdated { mint: *mint_ai.key, listed: true }.emit();
+            }
+
+            Action::RemoveMint => {
+                msg!("Removing mint from the mint list...");
+
+                let authority_ai = next_account_info(accounts_iter)?;
+                Self::check_program_authority(0, authority_ai)?;
+                Self::check_writable(0, authority_ai)?;
+                let listed_mint_ai = next_account_info(accounts_iter)?;
+                Self::check_writable(1, listed_mint_ai)?;
+                if listed_mint_ai.owner != program_id {
+                    return Err(Self::account_error(1, TradeError::UnexpectedAccount))?;
+                }
+                let listed_mint = state::AccountListedMint::try_from_slice(&listed_mint_ai.data.borrow())
+                    .map_err(|_| Self::account_error(1, TradeError::UnexpectedAccount))?;
+                if !listed_mint.initialized {
+                    return Err(Self::account_error(1, TradeError::TradeNotInitialised))?;
+                }
+                let mint_list_ai = next_account_info(accounts_iter)?;
+                Self::check_writable(2, mint_list_ai)?;
+                let mut mint_list = Self::load_mint_list(program_id, 2, mint_list_ai)?;
+                mint_list.listed = mint_list.listed.saturating_sub(1);
+                mint_list.serialize(&mut *mint_list_ai.data.borrow_mut())?;
+
+                **authority_ai.try_borrow_mut_lamports()? = authority_ai
+                    .lamports()
+                    .checked_add(listed_mint_ai.lamports())
+                    .ok_or(TradeError::ValueOverflow)?;
+                **listed_mint_ai.try_borrow_mut_lamports()? = 0;
+                *listed_mint_ai.try_borrow_mut_data()? = &mut [];
+
+                TradeEvent::MintListUpdated { mint: listed_mint.mint, listed: false }.emit();
+            }
+
+            Action::SetMintLimits { bump_seed, limits } => {
+                msg!("Setting mint limits...");
+
+                let authority_ai = next_account_info(accounts_iter)?;
+                Self::check_program_authority(0, authority_ai)?;
+                Self::check_writable(0, authority_ai)?;
+                let mint_limits_ai = next_account_info(accounts_iter)?;
+                Self::check_writable(1, mint_limits_ai)?;
+                let mint_ai = next_account_info(accounts_iter)?;
+                let pda = Pubkey::create_program_address(
+                    &[state::MINT_LIMITS_SEED, mint_ai.key.as_ref(), &[bump_seed]],
+                    program_id,
+                ).map_err(|_| Self::account_error(1, TradeError::InvalidPda))?;
+                if sol_memcmp(mint_limits_ai.key.as_ref(), pda.as_ref(), PUBKEY_BYTES) != 0 {
+                    return Err(Self::account_error(1, TradeError::InvalidPda))?;
+                }
+                if *mint_ai.owner != spl_token::id() || Mint::unpack(&mint_ai.data.borrow()).is_err() {
+                    return Err(Self::account_error(2, TradeError::UnexpectedAccount))?;
+                }
+                let system_program_ai = next_account_info(accounts_iter)?;
+
+                // a cap needs a window to reset, and no amount could be traded with the minimum over the maximum
+                if (limits.max_amount != 0 && limits.min_amount > limits.max_amount)
+                    || (limits.volume_cap != 0 && limits.window <= 0) {
+                    return Err(ProgramError::InvalidArgument)?;
+                }
+
+                let mut mint_limits = if mint_limits_ai.owner != program_id {
+                    Self::create_pda_account(
+                        program_id,
+                        authority_ai,
+                        mint_limits_ai,
+                        system_program_ai,
+                        state::AccountMintLimits::size(),
+                        &[state::MINT_LIMITS_SEED, mint_ai.key.as_ref(), &[bump_seed]],
+                    )?;
+
+                    msg!("Mint limits account created...");
+
+                    state::AccountMintLimits {
+                        initialized: true,
+                        bump_seed,
+                        mint: *mint_ai.key,
+                        window_start: Clock::get()?.unix_timestamp,
+                        ..state::AccountMintLimits::default()
+                    }
+                } else {
+                    state::AccountMintLimits::try_from_slice(&mint_limits_ai.data.borrow())?
+                };
+                mint_limits.limits = limits;
+                mint_limits.serialize(&mut *mint_limits_ai.data.borrow_mut())?;
+
+                TradeEvent::MintLimitsSet { mint: *mint_ai.key, limits }.emit();
+            }
+
+            Action::InitStats { bump_seed, stats } => {
+                msg!("Creating stats account...");
+
+                let payer_ai = next_account_info(accounts_iter)?;
+                if !payer_ai.is_signer {
+                    Err(Self::account_error(0, TradeError::WrongAuthority))?;
+                }
+                Self::check_writable(0, payer_ai)?;
+                let stats_ai = next_account_info(accounts_iter)?;
+                Self::check_writable(1, stats_ai)?;
+                let bump = [bump_seed];
+                let mut seeds = stats.seeds();
+                seeds.push(&bump);
+                let pda = Pubkey::create_program_address(&seeds, program_id)
+                    .map_err(|_| Self::account_error(1, TradeError::InvalidPda))?;
+                if sol_memcmp(stats_ai.key.as_ref(), pda.as_ref(), PUBKEY_BYTES) != 0 {
+                    return Err(Self::account_error(1, TradeError::InvalidPda))?;
+                }
+                if stats_ai.owner == program_id {
+                    return Err(ProgramError::AccountAlreadyInitialized)?;
+                }
+                let system_program_ai = next_account_info(accounts_iter)?;
+
+                Self::create_pda_account(program_id, payer_ai, stats_ai, system_program_ai, stats.size(), &seeds)?;
+
+                let data = &mut *stats_ai.data.borrow_mut();
+                match stats {
+                    state::StatsAccount::Program => {
+                        let program_stats = state::AccountStats { initialized: true, bump_seed, trades: 0 };
+                        program_stats.serialize(data)?;
+                    },
+                    state::StatsAccount::Mint(mint) => {
+                        let mint_stats = state::AccountMintStats {
+                            initialized: true,
+                            bump_seed,
+                            mint,
+                            ..state::AccountMintStats::default()
+                        };
+                        mint_stats.serialize(data)?;
+                    },
+                    state::StatsAccount::Maker(maker) => {
+                        let maker_stats = state::AccountMakerStats {
+                            initialized: true,
+                            bump_seed,
+                            maker,
+                            ..state::AccountMakerStats::default()
+                        };
+                        maker_stats.serialize(data)?;
+                    },
+                }
+
+                msg!("Stats account created...");
+
+                TradeEvent::StatsInitialised { stats, account: *stats_ai.key }.emit();
+            }
+        }
+
+        Ok(())
+    }
+
+    // Moves amount into the offer of a trade, or out of it when increase is false, see IncreaseOffer and
+    // DecreaseOffer for the accounts expected.
+    fn change_offer(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        amount: u64,
+        proportional: bool,
+        increase: bool,
+    ) -> entrypoint::ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+        Self::check_no_duplicates(0, accounts)?;
+
+        let authority_ai = next_account_info(accounts_iter)?;
+        if !authority_ai.is_signer {
+            Err(Self::account_error(0, TradeError::WrongAuthority))?;
+        }
+
+        let trade_account_ai = next_account_info(accounts_iter)?;
+        Self::check_writable(1, trade_account_ai)?;
+        let mut trade_account = state::AccountTrade::try_from_slice(&trade_account_ai.data.borrow())?;
+        if !trade_account.initialized {
+            return Err(TradeError::TradeNotInitialised)?;
+        }
+        if sol_memcmp(program_id.as_ref(), trade_account.program_id.as_ref(), PUBKEY_BYTES) != 0 {
+            Err(ProgramError::IncorrectProgramId)?
+        }
+        if sol_memcmp(trade_account.authority.as_ref(), authority_ai.key.as_ref(), PUBKEY_BYTES) != 0 {
+            Err(Self::account_error(0, TradeError::WrongAuthority))?
+        }
+        // the underlying of an option is part of its terms
+        if trade_account.is_option() {
+            return Err(TradeError::OptionTrade)?;
+        }
+        // the taker of a forward or vesting trade already paid for the offer
+        if trade_account.is_committed() {
+            return Err(TradeError::SettlementPending)?;
+        }
+        // the holder of the receipt, who may have paid for it, is owed the trade amount of the whole offer
+        if !increase && trade_account.has_receipt() {
+            return Err(TradeError::ReceiptTrade)?;
+        }
+
+        let pda_ai = next_account_info(accounts_iter)?;
+        Self::check_pda(program_id, 2, pda_ai, trade_account_ai.key, trade_account.bump_seed)?;
+        let offer_token_ai = next_account_info(accounts_iter)?;
+        Self::check_writable(3, offer_token_ai)?;
+        if sol_memcmp(trade_account.offer_token_account.as_ref(), offer_token_ai.key.as_ref(), PUBKEY_BYTES) != 0 {
+            Err(Self::account_error(3, TradeError::WrongTokenAccount))?
+        }
+        let token_ai = next_account_info(accounts_iter)?;
+        Self::check_writable(4, token_ai)?;
+        let token_program_ai = next_account_info(accounts_iter)?;
+        Self::check_token_program(5, token_program_ai)?;
+
+        let offer_token = Account::unpack_from_slice(&offer_token_ai.data.borrow())?;
+        let token = Account::unpack_from_slice(&token_ai.data.borrow())?;
+        if sol_memcmp(token.mint.as_ref(), offer_token.mint.as_ref(), PUBKEY_BYTES) != 0 {
+            Err(Self::account_error(4, TradeError::OfferMintMissmatch))?
+        }
+
+        if amount == 0 {
+            return Err(ProgramError::InvalidArgument)?;
+        }
+        let offer_amount = if increase {
+            if trade_account.is_expired(Clock::get()?.unix_timestamp) {
+                return Err(TradeError::TradeExpired)?;
+            }
+
+            trade_account.offer_amount.checked_add(amount).ok_or(TradeError::ValueOverflow)?
+        } else {
+            // the whole offer is taken back by closing the trade instead
+            match trade_account.offer_amount.checked_sub(amount) {
+                Some(offer_amount) if offer_amount > 0 => offer_amount,
+                _ => return Err(ProgramError::InsufficientFunds)?,
+            }
+        };
+        let trade_amount = if proportional {
+            trade_account.trade_amount_for(offer_amount).ok_or(TradeError::ValueOverflow)?
+        } else {
+            trade_account.trade_amount
+        };
+
+        if increase {
+            let transfer_ix = spl_token::instruction::transfer(
+                &spl_token::id(),
+                token_ai.key,
+                offer_token_ai.key,
+                authority_ai.key,
+                &[authority_ai.key],
+                amount,
+            )?;
+
+            invoke(
+                &transfer_ix,
+                &[
+                    token_ai.clone(),
+                    offer_token_ai.clone(),
+                    authority_ai.clone(),
+                    token_program_ai.clone(),
+                ],
+            )?;
+
+            if trade_account.mode == state::TradeMode::Delegate {
+                let approve_ix = spl_token::instruction::approve(
+                    &spl_token::id(),
+                    offer_token_ai.key,
+                    pda_ai.key,
+                    authority_ai.key,
+                    &[authority_ai.key],
+                    offer_amount,
+                )?;
+
+                invoke(
+                    &approve_ix,
+                    &[
+                        offer_token_ai.clone(),
+                        pda_ai.clone(),
+                        authority_ai.clone(),
+                        token_program_ai.clone(),
+                    ],
+                )?;
+            }
+        } else {
+            // the pda is the owner of the offer account in Escrow mode and its delegate in Delegate mode, where the
+            // transfer also takes amount out of the delegation
+            let transfer_ix = spl_token::instruction::transfer(
+                &spl_token::id(),
+                offer_token_ai.key,
+                token_ai.key,
+                pda_ai.key,
+                &[pda_ai.key],
+                amount,
+            )?;
+
+            invoke_signed(
+                &transfer_ix,
+                &[
+                    offer_token_ai.clone(),
+                    token_ai.clone(),
+                    pda_ai.clone(),
+                    token_program_ai.clone(),
+                ],
+                &[&[trade_account_ai.key.as_ref(), &[trade_account.bump_seed]]],
+            )?;
+        }
+
+        msg!("Offer changed from {} to {}...", trade_account.offer_amount, offer_amount);
+
+        // a proportional change keeps the price as it is
+        if !proportional {
+            trade_account.set_price(offer_amount, trade_amount);
+        }
+        trade_account.offer_amount = offer_amount;
+        trade_account.trade_amount = trade_amount;
+        trade_account.serialize(&mut *trade_account_ai.data.borrow_mut())?;
+
+        TradeEvent::OfferChanged {
+            trade: *trade_account_ai.key,
+            authority: trade_account.authority,
+            offer_amount,
+            trade_amount,
+        }.emit();
+
+        Ok(())
+    }
+
+    // Fills an order signed off-chain by its maker, see FillSignedOrder for the accounts expected.
+    fn fill_signed_order(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        order: &SignedOrder,
+    ) -> entrypoint::ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+        Self::check_no_duplicates(0, accounts)?;
+
+        let taker_ai = next_account_info(accounts_iter)?;
+        if !taker_ai.is_signer {
+            Err(Self::account_error(0, TradeError::WrongAuthority))?;
+        }
+        // every account but the taker, the token program and the instructions sysvar is written to
+        for (i, account) in accounts.iter().enumerate().take(7).skip(1) {
+            Self::check_writable(i, account)?;
+        }
+
+        let nonces_ai = next_account_info(accounts_iter)?;
+        let mut nonces = Self::load_nonces(program_id, 1, nonces_ai, &order.maker)?;
+        let offer_src_ai = next_account_info(accounts_iter)?;
+        let offer_src = Account::unpack_from_slice(&offer_src_ai.data.borrow())?;
+        if sol_memcmp(offer_src.owner.as_ref(), order.maker.as_ref(), PUBKEY_BYTES) != 0 {
+            return Err(Self::account_error(2, TradeError::WrongTokenAccount))?;
+        }
+        if sol_memcmp(offer_src.mint.as_ref(), order.offer_mint.as_ref(), PUBKEY_BYTES) != 0 {
+            return Err(Self::account_error(2, TradeError::OfferMintMissmatch))?;
+        }
+        let trade_dst_ai = next_account_info(accounts_iter)?;
+        if sol_memcmp(trade_dst_ai.key.as_ref(), order.trade_dst.as_ref(), PUBKEY_BYTES) != 0 {
+            return Err(Self::account_error(3, TradeError::UnexpectedAccount))?;
+        }
+        let trade_src_ai = next_account_info(accounts_iter)?;
+        let trade_src = Account::unpack_from_slice(&trade_src_ai.data.borrow())?;
+        if sol_memcmp(trade_src.mint.as_ref(), order.trade_mint.as_ref(), PUBKEY_BYTES) != 0 {
+            return Err(Self::account_error(4, TradeError::TradeMintMissmatch))?;
+        }
+        let offer_dst_ai = next_account_info(accounts_iter)?;
+        let offer_dst = Account::unpack_from_slice(&offer_dst_ai.data.borrow())?;
+        if sol_memcmp(offer_dst.mint.as_ref(), order.offer_mint.as_ref(), PUBKEY_BYTES) != 0 {
+            return Err(Self::account_error(5, TradeError::OfferMintMissmatch))?;
+        }
+        let fee_account_ai = next_account_info(accounts_iter)?;
+        let token_program_ai = next_account_info(accounts_iter)?;
+        Self::check_token_program(7, token_program_ai)?;
+        let instructions_ai = next_account_info(accounts_iter)?;
+
+        Self::check_ed25519_signature(8, instructions_ai, &order.maker, &order.try_to_vec()?)?;
+
+        // no trade account was created for the order, so both mints are checked against the mint list here
+        let mint_list_ai = next_account_info(accounts_iter)?;
+        let offer_listed_ai = next_account_info(accounts_iter)?;
+        let trade_listed_ai = next_account_info(accounts_iter)?;
+        Self::check_mint_allowed(program_id, 9, mint_list_ai, 10, offer_listed_ai, &order.offer_mint)?;
+        Self::check_mint_allowed(program_id, 9, mint_list_ai, 11, trade_listed_ai, &order.trade_mint)?;
+        let offer_limits_ai = next_account_info(accounts_iter)?;
+        Self::check_writable(12, offer_limits_ai)?;
+        let trade_limits_ai = next_account_info(accounts_iter)?;
+        Self::check_writable(13, trade_limits_ai)?;
+        let program_stats_ai = next_account_info(accounts_iter)?;
+        let offer_stats_ai = next_account_info(accounts_iter)?;
+        let trade_stats_ai = next_account_info(accounts_iter)?;
+        let maker_stats_ai = next_account_info(accounts_iter)?;
+        for (i, stats_ai) in [program_stats_ai, offer_stats_ai, trade_stats_ai, maker_stats_ai].iter().enumerate() {
+            Self::check_writable(14 + i, stats_ai)?;
+        }
+
+        let now = Clock::get()?.unix_timestamp;
+        if order.expires_at != 0 && now >= order.expires_at {
+            return Err(TradeError::TradeExpired)?;
+        }
+        // the nonce is spent before any funds move, so the order can't be filled twice
+        if nonces.is_spent(order.nonce) {
+            msg!("Nonce {} of {} already used or cancelled", order.nonce, order.maker);
+            return Err(TradeError::NonceUsed)?;
+        }
+        if !nonces.spend(order.nonce) {
+            msg!("Nonce {} is above the window starting at {}", order.nonce, nonces.base);
+            return Err(TradeError::NonceOutOfRange)?;
+        }
+        nonces.serialize(&mut *nonces_ai.data.borrow_mut())?;
+
+        // the maker keeps the offer account, so make sure the offer can still be taken from it
+        if offer_src.delegate != COption::Some(*nonces_ai.key) || offer_src.delegated_amount < order.offer_amount {
+            return Err(TradeError::InsufficientDelegation)?;
+        }
+        if offer_src.amount < order.offer_amount {
+            return Err(TradeError::InsufficientOfferBalance)?;
+        }
+
+        // the fill counts against the volume cap of both mints, as the fill of a trade does
+        Self::record_fill(program_id, 12, offer_limits_ai, &order.offer_mint, order.offer_amount, now)?;
+        Self::record_fill(program_id, 13, trade_limits_ai, &order.trade_mint, order.trade_amount, now)?;
+
+        let lamports_fee = Self::transfer_fee(
+            nonces_ai.key,
+            6,
+            order.offer_amount,
+            taker_ai,
+            trade_src_ai,
+            &order.trade_mint,
+            fee_account_ai,
+            token_program_ai,
+        )?;
+
+        // the fill is counted as that of a trade of the maker
+        Self::update_stats(
+            program_id,
+            14,
+            program_stats_ai,
+            &state::StatsAccount::Program,
+            |stats: &mut state::AccountStats| stats.trades = stats.trades.saturating_add(1),
+        )?;
+        let sides = [
+            (offer_stats_ai, order.offer_mint, order.offer_amount, 0),
+            (trade_stats_ai, order.trade_mint, order.trade_amount, lamports_fee),
+        ];
+        for (i, (stats_ai, mint, volume, fees)) in sides.into_iter().enumerate() {
+            Self::update_stats(
+                program_id,
+                15 + i,
+                stats_ai,
+                &state::StatsAccount::Mint(mint),
+                |stats: &mut state::AccountMintStats| {
+                    stats.trades = stats.trades.saturating_add(1);
+                    stats.volume = stats.volume.saturating_add(volume as u128);
+                    stats.fees = stats.fees.saturating_add(fees as u128);
+                },
+            )?;
+        }
+        Self::update_stats(
+            program_id,
+            17,
+            maker_stats_ai,
+            &state::StatsAccount::Maker(order.maker),
+            |stats: &mut state::AccountMakerStats| stats.filled = stats.filled.saturating_add(1),
+        )?;
+
+        // the nonces account is the delegate of the offer account
+        let transfer_offer_ix = spl_token::instruction::transfer(
+            &spl_token::id(),
+            offer_src_ai.key,
+            offer_dst_ai.key,
+            nonces_ai.key,
+            &[nonces_ai.key],
+            order.offer_amount,
+        )?;
+
+        invoke_signed(
+            &transfer_offer_ix,
+            &[
+                offer_src_ai.clone(),
+                offer_dst_ai.clone(),
+                nonces_ai.clone(),
+                token_program_ai.clone(),
+            ],
+            &[&[state::NONCES_SEED, order.maker.as_ref(), &[nonces.bump_seed]]],
+        )?;
+
+        msg!("Offer amount transfered...");
+
+        let transfer_trade_ix = spl_token::instruction::transfer(
+            &spl_token::id(),
+            trade_src_ai.key,
+            trade_dst_ai.key,
+            taker_ai.key,
+            &[taker_ai.key],
+            order.trade_amount.checked_sub(lamports_fee).ok_or(TradeError::ValueOverflow)?,
+        )?;
+
+        invoke(
+            &transfer_trade_ix,
+            &[
+                trade_src_ai.clone(),
+                trade_dst_ai.clone(),
+                taker_ai.clone(),
+                token_program_ai.clone(),
+            ],
+        )?;
+
+        msg!("Trade amount transfered...");
+
+        TradeEvent::SignedOrderFilled {
+            maker: order.maker,
+            taker: *taker_ai.key,
+            nonce: order.nonce,
+            offer_amount: order.offer_amount,
+            trade_amount: order.trade_amount,
+        }.emit();
+
+        Ok(())
+    }
+
+    // Fills the trade of a MakeTrade instruction, or exercises the option of an ExerciseOption one with exercise.
+    // `accounts` are those of the instruction: the taker, the accounts of the trade being filled (trade, pda, offer
+    // src, trade dst, trade src, offer dst, offer owner and fee account), the token program, then the trailing ones
+    // make_trade takes.
+    fn make_single_trade(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        fill: &TradeFill,
+        exercise: bool,
+    ) -> entrypoint::ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+
+        let authority_ai = next_account_info(accounts_iter)?;
+        if !authority_ai.is_signer {
+            Err(Self::account_error(0, TradeError::WrongAuthority))?;
+        }
+
+        let trade_accounts = next_account_infos(accounts_iter, MAKE_TRADE_ACCOUNTS)?;
+        let token_program_ai = next_account_info(accounts_iter)?;
+        Self::check_token_program(1 + MAKE_TRADE_ACCOUNTS, token_program_ai)?;
+        let mut trailing = TrailingAccounts {
+            iter: accounts_iter.as_slice().iter(),
+            index: 2 + MAKE_TRADE_ACCOUNTS,
+        };
+
+        Self::make_trade(program_id, authority_ai, 1, trade_accounts, token_program_ai, &mut trailing, fill, exercise)
+    }
+
+    // Fills a trade, taking its accounts from trade_accounts, starting at index in the instruction. With exercise, the
+    // trade must be an option, exercised by its holder, otherwise it can't be one.
+    #[allow(clippy::too_many_arguments)]
+    fn make_trade<'a>(
+        program_id: &Pubkey,
+        authority_ai: &AccountInfo<'a>,
+        index: usize,
+        trade_accounts: &[AccountInfo<'a>],
+        token_program_ai: &AccountInfo<'a>,
+        trailing: &mut TrailingAccounts<'a, '_>,
+        fill: &TradeFill,
+        exercise: bool,
+    ) -> entrypoint::ProgramResult {
+        let expected_offer = fill.expected_offer;
+        let expected_trade = fill.expected_trade;
+        let accounts_iter = &mut trade_accounts.iter();
+
+        let trade_account_ai = next_account_info(accounts_iter)?;
+        let mut trade_account = state::AccountTrade::try_from_slice(&trade_account_ai.data.borrow())?;
+        if !trade_account.initialized {
+            return Err(Self::account_error(index, TradeError::TradeNotInitialised))?;
+        }
+        // options are only filled when their holder exercises them
+        if trade_account.is_option() != exercise {
+            let error = if exercise { TradeError::NotAnOption } else { TradeError::OptionTrade };
+            return Err(Self::account_error(index, error))?;
+        }
+        // a forward or vesting trade is filled once, then waits for its settlement or for the offer to vest
+        if trade_account.is_committed() {
+            return Err(Self::account_error(index, TradeError::SettlementPending))?;
+        }
+        if exercise && sol_memcmp(trade_account.holder.as_ref(), authority_ai.key.as_ref(), PUBKEY_BYTES) != 0 {
+            Err(Self::account_error(0, TradeError::WrongAuthority))?
+        }
+        // only the wallets in the allowlist of the trade can fill it, and not for more than their cap
+        if trade_account.is_allowlisted() {
+            let proof = fill.allowlist.as_ref().ok_or_else(|| Self::account_error(0, TradeError::NotAllowlisted))?;
+            let leaf = allowlist::leaf(authority_ai.key, proof.cap);
+            if !allowlist::verify(&trade_account.allowlist_root, leaf, &proof.proof) {
+                return Err(Self::account_error(0, TradeError::NotAllowlisted))?;
+            }
+            if trade_account.offer_amount > proof.cap {
+                msg!("Offer of {} over the cap of {}", trade_account.offer_amount, proof.cap);
+                return Err(TradeError::AllowlistCapExceeded)?;
+            }
+        }
+
+        // every account but the pda is written to
+        for (i, account) in trade_accounts.iter().enumerate() {
+            if i != 1 {
+                Self::check_writable(index + i, account)?;
+            }
+        }
+        Self::check_no_duplicates(index, trade_accounts)?;
+
+        let pda_ai = next_account_info(accounts_iter)?;
+        Self::check_pda(program_id, index + 1, pda_ai, trade_account_ai.key, trade_account.bump_seed)?;
+        // to avoid the need of this account, I could create a temporary PDA that was deleted afterwards
+        // instead of using the original token account.
+        // Another detail about this implementation is that this account should not be an ATA because at some
+        // point its authority is moved to the program which changes its data
+        let original_pda_addr_ai = next_account_info(accounts_iter)?;
+        let trade_dst_ai = next_account_info(accounts_iter)?;
+        // the trade amount of a trade with a receipt goes to its holder instead, checked along with the receipt
+        if !trade_account.has_receipt()
+            && sol_memcmp(trade_dst_ai.key.as_ref(), trade_account.trade_dst_account.as_ref(), PUBKEY_BYTES) != 0 {
+            return Err(Self::account_error(index + 3, TradeError::UnexpectedAccount))?;
+        }
+        let trade_src_ai = next_account_info(accounts_iter)?;
+        let trade_src = Account::unpack_from_slice(&trade_src_ai.data.borrow())?;
+        if sol_memcmp(trade_src.mint.as_ref(), trade_account.trade_mint.as_ref(), PUBKEY_BYTES) != 0 {
+            return Err(Self::account_error(index + 4, TradeError::TradeMintMissmatch))?;
+        }
+        let offer_dst_ai = next_account_info(accounts_iter)?;
+        let offer_owner_ai = next_account_info(accounts_iter)?;
+        let fee_account_ai = next_account_info(accounts_iter)?;
+
+        if sol_memcmp(trade_account.offer_token_account.as_ref(), original_pda_addr_ai.key.as_ref(), PUBKEY_BYTES) != 0 {
+            Err(Self::account_error(index + 2, TradeError::WrongTokenAccount))?
+        }
+        let offer_src = Account::unpack_from_slice(&original_pda_addr_ai.data.borrow())?;
+        let offer_dst = Account::unpack_from_slice(&offer_dst_ai.data.borrow())?;
+        if sol_memcmp(offer_dst.mint.as_ref(), offer_src.mint.as_ref(), PUBKEY_BYTES) != 0 {
+            return Err(Self::account_error(index + 5, TradeError::OfferMintMissmatch))?;
+        }
+
+        // makes sure the authority of the offer account and the rent are returned to the right owner
+        if sol_memcmp(trade_account.authority.as_ref(), offer_owner_ai.key.as_ref(), PUBKEY_BYTES) != 0 {
+            Err(Self::account_error(index + 6, TradeError::WrongAuthority))?
+        }
+
+        if sol_memcmp(program_id.as_ref(), trade_account.program_id.as_ref(), PUBKEY_BYTES) != 0 {
+            Err(ProgramError::IncorrectProgramId)?
+        }
+
+        let now = Clock::get()?.unix_timestamp;
+        if trade_account.is_expired(now) || trade_account.is_settlement_due(now) {
+            return Err(TradeError::TradeExpired)?;
+        }
+
+        // I'll leave the checks agains the account balance to the spl-token program
+        if expected_offer != trade_account.offer_amount {
+            msg!("Expected offer of {}, but got {}", expected_offer, trade_account.offer_amount);
+            return Err(TradeError::UnexpectedOfferAmount)?;
+        }
+
+        // the trade amount at the price of the trade, the minimum for pegged trades
+        let price_amount = trade_account.trade_amount_for(trade_account.offer_amount)
+            .ok_or(TradeError::ValueOverflow)?;
+        let trade_amount = if trade_account.is_pegged() {
+            let (feed_index, price_feed_ai) = trailing.next()?;
+            if sol_memcmp(price_feed_ai.key.as_ref(), trade_account.price_feed.as_ref(), PUBKEY_BYTES) != 0
+                || sol_memcmp(price_feed_ai.owner.as_ref(), trade_account.price_feed_owner.as_ref(), PUBKEY_BYTES) != 0
+            {
+                return Err(Self::account_error(feed_index, TradeError::UnexpectedAccount))?;
+            }
+            let price = state::PriceFeed::deserialize(&mut &price_feed_ai.data.borrow()[..])
+                .map_err(|_| Self::account_error(feed_index, TradeError::InvalidPrice))?;
+            if price.price <= 0 {
+                return Err(TradeError::InvalidPrice)?;
+            }
+            // a price from the future would never go stale
+            if price.publish_time > now {
+                msg!("Price published at {}, it's now {}", price.publish_time, now);
+                return Err(TradeError::InvalidPrice)?;
+            }
+            if now.saturating_sub(price.publish_time) > trade_account.max_price_age as i64 {
+                msg!("Price published at {}, it's now {}", price.publish_time, now);
+                return Err(TradeError::StalePrice)?;
+            }
+
+            let amount = price.trade_amount(trade_account.offer_amount, trade_account.spread_bps)
+                .ok_or(TradeError::ValueOverflow)?;
+            if amount < price_amount {
+                msg!("Trade of {} at the current price, below the limit of {}", amount, price_amount);
+                return Err(TradeError::PriceBelowLimit)?;
+            }
+            if amount > expected_trade {
+                msg!("Expected trade of at most {}, but got {}", expected_trade, amount);
+                return Err(TradeError::UnexpectedTradeAmount)?;
+            }
+
+            amount
+        } else {
+            if expected_trade != price_amount {
+                msg!("Expected trade of {}, but got {}", expected_trade, price_amount);
+                return Err(TradeError::UnexpectedTradeAmount)?;
+            }
+
+            price_amount
+        };
+
+        // the destinations of the trade amount after the trade dst, if the trade splits it
+        let mut split_dst_ais = Vec::with_capacity(state::MAX_SPLITS);
+        for destination in trade_account.split_destinations() {
+            let (dst_index, dst_ai) = trailing.next()?;
+            Self::check_writable(dst_index, dst_ai)?;
+            if sol_memcmp(dst_ai.key.as_ref(), destination.as_ref(), PUBKEY_BYTES) != 0 {
+                return Err(Self::account_error(dst_index, TradeError::UnexpectedAccount))?;
+            }
+            split_dst_ais.push(dst_ai);
+        }
+
+        // the arbiter co-signs the fill of a trade in Arbitrated mode
+        if trade_account.is_arbitrated() {
+            let (arbiter_index, arbiter_ai) = trailing.next()?;
+            if sol_memcmp(arbiter_ai.key.as_ref(), trade_account.arbiter.as_ref(), PUBKEY_BYTES) != 0 {
+                return Err(Self::account_error(arbiter_index, TradeError::UnexpectedAccount))?;
+            }
+            if !arbiter_ai.is_signer {
+                return Err(Self::account_error(arbiter_index, TradeError::ArbiterSignatureMissing))?;
+            }
+        }
+
+        let hook_ais = if trade_account.is_hooked() {
+            let (program_index, hook_program_ai) = trailing.next()?;
+            if sol_memcmp(hook_program_ai.key.as_ref(), trade_account.hook_program.as_ref(), PUBKEY_BYTES) != 0 {
+                return Err(Self::account_error(program_index, TradeError::UnexpectedAccount))?;
+            }
+            let (account_index, hook_account_ai) = trailing.next()?;
+            if sol_memcmp(hook_account_ai.key.as_ref(), trade_account.hook_account.as_ref(), PUBKEY_BYTES) != 0 {
+                return Err(Self::account_error(account_index, TradeError::UnexpectedAccount))?;
+            }
+
+            Some((hook_program_ai, hook_account_ai))
+        } else {
+            None
+        };
+
+        // the trade amount of a forward trade is held by the pda until the trade is settled
+        let escrow_ai = if trade_account.is_forward() {
+            let (escrow_index, escrow_ai) = trailing.next()?;
+            Self::check_writable(escrow_index, escrow_ai)?;
+            let escrow = spl_associated_token_account::get_associated_token_address(
+                pda_ai.key,
+                &trade_account.trade_mint,
+            );
+            if sol_memcmp(escrow_ai.key.as_ref(), escrow.as_ref(), PUBKEY_BYTES) != 0 {
+                return Err(Self::account_error(escrow_index, TradeError::UnexpectedAccount))?;
+            }
+
+            Some(escrow_ai)
+        } else {
+            None
+        };
+
+        // and the offer of a vesting trade until it is claimed
+        let vesting_escrow_ai = if trade_account.is_vesting() {
+            let (escrow_index, escrow_ai) = trailing.next()?;
+            Self::check_writable(escrow_index, escrow_ai)?;
+            let escrow = spl_associated_token_account::get_associated_token_address(pda_ai.key, &offer_src.mint);
+            if sol_memcmp(escrow_ai.key.as_ref(), escrow.as_ref(), PUBKEY_BYTES) != 0 {
+                return Err(Self::account_error(escrow_index, TradeError::UnexpectedAccount))?;
+            }
+
+            Some(escrow_ai)
+        } else {
+            None
+        };
+
+        // and the receipt of the trade, burnt as its holder gets the trade amount
+        let receipt_ais = if trade_account.has_receipt() {
+            let (mint_index, receipt_mint_ai) = trailing.next()?;
+            Self::check_writable(mint_index, receipt_mint_ai)?;
+            if sol_memcmp(receipt_mint_ai.key.as_ref(), trade_account.receipt_mint.as_ref(), PUBKEY_BYTES) != 0 {
+                return Err(Self::account_error(mint_index, TradeError::UnexpectedAccount))?;
+            }
+            let (receipt_index, receipt_account_ai) = trailing.next()?;
+            Self::check_writable(receipt_index, receipt_account_ai)?;
+            let receipt_account = Account::unpack_from_slice(&receipt_account_ai.data.borrow())
+                .map_err(|_| Self::account_error(receipt_index, TradeError::WrongReceipt))?;
+            if sol_memcmp(receipt_account.mint.as_ref(), trade_account.receipt_mint.as_ref(), PUBKEY_BYTES) != 0
+                || receipt_account.amount != 1 {
+                return Err(Self::account_error(receipt_index, TradeError::WrongReceipt))?;
+            }
+            if receipt_account.delegate != COption::Some(*pda_ai.key) || receipt_account.delegated_amount < 1 {
+                return Err(Self::account_error(receipt_index, TradeError::ReceiptNotDelegated))?;
+            }
+            let trade_dst = Account::unpack_from_slice(&trade_dst_ai.data.borrow())?;
+            if sol_memcmp(trade_dst.owner.as_ref(), receipt_account.owner.as_ref(), PUBKEY_BYTES) != 0 {
+                return Err(Self::account_error(index + 3, TradeError::UnexpectedAccount))?;
+            }
+
+            Some((receipt_mint_ai, receipt_account_ai, receipt_account.owner))
+        } else {
+            None
+        };
+
+        // the limits of both mints, which also count the fill against their volume cap
+        let (offer_limits_index, offer_limits_ai) = trailing.next()?;
+        Self::check_writable(offer_limits_index, offer_limits_ai)?;
+        Self::record_fill(program_id, offer_limits_index, offer_limits_ai, &offer_src.mint, expected_offer, now)?;
+        let (trade_limits_index, trade_limits_ai) = trailing.next()?;
+        Self::check_writable(trade_limits_index, trade_limits_ai)?;
+        Self::record_fill(
+            program_id,
+            trade_limits_index,
+            trade_limits_ai,
+            &trade_account.trade_mint,
+            trade_amount,
+            now,
+        )?;
+
+        // and the stats accounts, updated once the fee is known
+        let (stats_index, program_stats_ai) = trailing.next()?;
+        let (_, offer_stats_ai) = trailing.next()?;
+        let (_, trade_stats_ai) = trailing.next()?;
+        let (_, maker_stats_ai) = trailing.next()?;
+        for (i, stats_ai) in [program_stats_ai, offer_stats_ai, trade_stats_ai, maker_stats_ai].iter().enumerate() {
+            Self::check_writable(stats_index + i, stats_ai)?;
+        }
+
+        if trade_account.mode == state::TradeMode::Delegate {
+            // the owner keeps control of the offer account, so make sure the offer can still be taken from it
+            if offer_src.delegate != COption::Some(*pda_ai.key) || offer_src.delegated_amount < expected_offer {
+                return Err(TradeError::InsufficientDelegation)?;
+            }
+
+            if offer_src.amount < expected_offer {
+                return Err(TradeError::InsufficientOfferBalance)?;
+            }
+        }
+
+        // the compliance hook gets the last word before any funds move, and aborts the trade by failing
+        if let Some((hook_program_ai, hook_account_ai)) = hook_ais {
+            let check = ComplianceCheck {
+                trade: *trade_account_ai.key,
+                maker: trade_account.authority,
+                taker: *authority_ai.key,
+                offer_mint: offer_src.mint,
+                trade_mint: trade_account.trade_mint,
+                offer_amount: expected_offer,
+                trade_amount,
+            };
+            let hook_ix = Instruction::new_with_bytes(
+                *hook_program_ai.key,
+                &check.try_to_vec()?,
+                vec![AccountMeta::new_readonly(*hook_account_ai.key, false)],
+            );
+
+            invoke(
+                &hook_ix,
+                &[
+                    hook_account_ai.clone(),
+                    hook_program_ai.clone(),
+                ],
+            )?;
+
+            msg!("Trade allowed by its compliance hook...");
+        }
+
+        // transfer fee
+
+        let lamports_fee = Self::transfer_fee(
+            trade_account_ai.key,
+            index + 7,
+            trade_account.offer_amount,
+            authority_ai,
+            trade_src_ai,
+            &trade_src.mint,
+            fee_account_ai,
+            token_program_ai,
+        )?;
+
+        Self::update_stats(
+            program_id,
+            stats_index,
+            program_stats_ai,
+            &state::StatsAccount::Program,
+            |stats: &mut state::AccountStats| stats.trades = stats.trades.saturating_add(1),
+        )?;
+        // both mints differ, the duplicate check rejects the same stats account on both sides
+        let sides = [
+            (offer_stats_ai, offer_src.mint, expected_offer, 0),
+            (trade_stats_ai, trade_account.trade_mint, trade_amount, lamports_fee),
+        ];
+        for (i, (stats_ai, mint, volume, fees)) in sides.into_iter().enumerate() {
+            Self::update_stats(
+                program_id,
+                stats_index + 1 + i,
+                stats_ai,
+                &state::StatsAccount::Mint(mint),
+                |stats: &mut state::AccountMintStats| {
+                    stats.trades = stats.trades.saturating_add(1);
+                    stats.volume = stats.volume.saturating_add(volume as u128);
+                    stats.fees = stats.fees.saturating_add(fees as u128);
+                },
+            )?;
+        }
+        Self::update_stats(
+            program_id,
+            stats_index + 3,
+            maker_stats_ai,
+            &state::StatsAccount::Maker(trade_account.authority),
+            |stats: &mut state::AccountMakerStats| stats.filled = stats.filled.saturating_add(1),
+        )?;
+
+        // both sides of a forward trade stay in escrow, and are delivered by Settle
+
+        if let Some(escrow_ai) = escrow_ai {
+            let escrowed_amount = trade_amount.sub(lamports_fee);
+            let transfer_trade_ix = spl_token::instruction::transfer(
+                &spl_token::id(),
+                trade_src_ai.key,
+                escrow_ai.key,
+                authority_ai.key,
+                &[authority_ai.key],
+                escrowed_amount,
+            )?;
+
+            invoke(
+                &transfer_trade_ix,
+                &[
+                    trade_src_ai.clone(),
+                    escrow_ai.clone(),
+                    authority_ai.clone(),
+                    token_program_ai.clone(),
+                ],
+            )?;
+
+            msg!("Trade amount transfered to escrow...");
+
+            trade_account.taker = *authority_ai.key;
+            trade_account.offer_dst_account = *offer_dst_ai.key;
+            trade_account.serialize(&mut *trade_account_ai.data.borrow_mut())?;
+
+            TradeEvent::ForwardCommitted {
+                trade: *trade_account_ai.key,
+                authority: trade_account.authority,
+                taker: *authority_ai.key,
+                offer_amount: expected_offer,
+                trade_amount,
+            }.emit();
+
+            return Ok(());
+        }
+
+        // transfer offer from pda to destination, or to the vesting escrow
+
+        let offer_to_ai = vesting_escrow_ai.unwrap_or(offer_dst_ai);
+        let transfer_offer_ix = spl_token::instruction::transfer(
+            &spl_token::id(),
+            original_pda_addr_ai.key,
+            offer_to_ai.key,
+            pda_ai.key,
+            &[pda_ai.key],
+            expected_offer,
+        )?;
+        
+        invoke_signed(
+            &transfer_offer_ix,
+            &[
+                original_pda_addr_ai.clone(),
+                offer_to_ai.clone(),
+                pda_ai.clone(),
+                token_program_ai.clone(),
+            ],
+            &[&[trade_account_ai.key.as_ref(), &[trade_account.bump_seed]]],
+        )?;
+
+        msg!("Offer amount transfered...");
+
+        // transfer trade amount, to each of its destinations
+
+        let proceeds = trade_account.split_proceeds(trade_amount.sub(lamports_fee));
+        let dst_ais = std::iter::once(trade_dst_ai).chain(split_dst_ais);
+        for ((_, amount), dst_ai) in proceeds.into_iter().zip(dst_ais) {
+            let transfer_trade_ix = spl_token::instruction::transfer(
+                &spl_token::id(),
+                trade_src_ai.key,
+                dst_ai.key,
+                authority_ai.key,
+                &[authority_ai.key],
+                amount,
+            )?;
+
+            invoke(
+                &transfer_trade_ix,
+                &[
+                    trade_src_ai.clone(),
+                    dst_ai.clone(),
+                    authority_ai.clone(),
+                    token_program_ai.clone(),
+                ],
+            )?;
+        }
+
+        msg!("Trade amount transfered...");
+
+        if let Some((receipt_mint_ai, receipt_account_ai, holder)) = receipt_ais {
+            let burn_receipt_ix = spl_token::instruction::burn(
+                &spl_token::id(),
+                receipt_account_ai.key,
+                receipt_mint_ai.key,
+                pda_ai.key,
+                &[pda_ai.key],
+                1,
+            )?;
+
+            invoke_signed(
+                &burn_receipt_ix,
+                &[
+                    receipt_account_ai.clone(),
+                    receipt_mint_ai.clone(),
+                    pda_ai.clone(),
+                    token_program_ai.clone(),
+                ],
+                &[&[trade_account_ai.key.as_ref(), &[trade_account.bump_seed]]],
+            )?;
+
+            msg!("Receipt burnt...");
+
+            TradeEvent::ReceiptRedeemed {
+                trade: *trade_account_ai.key,
+                receipt_mint: trade_account.receipt_mint,
+                holder,
+            }.emit();
+        }
+
+        let bump_seed = trade_account.bump_seed;
+        let offer_authority = trade_account.authority;
+        let mode = trade_account.mode;
+        if trade_account.is_vesting() {
+            // the trade account keeps track of what was claimed until all of the offer is
+            trade_account.taker = *authority_ai.key;
+            trade_account.offer_dst_account = *offer_dst_ai.key;
+            trade_account.vesting_start = now;
+            trade_account.serialize(&mut *trade_account_ai.data.borrow_mut())?;
+
+            msg!("Offer vesting from {}...", now);
+        } else {
+            **offer_owner_ai.try_borrow_mut_lamports()? = offer_owner_ai
+                .lamports()
+                .checked_add(trade_account_ai.lamports())
+                .ok_or(TradeError::ValueOverflow)?;
+            // close account
+            **trade_account_ai.try_borrow_mut_lamports()? = 0;
+            // clean data for security reasons
+            *trade_account_ai.try_borrow_mut_data()? = &mut [];
+
+            msg!("Trade account closed...");
+        }
+
+        // return authotiry of the offer token account to the original owner
+
+        // in Delegate mode the owner never lost it, and the delegation is cleared by spl-token once the delegated
+        // amount is used
+        if mode.escrows_offer() {
+            let owner_change_ix = spl_token::instruction::set_authority(
+                &spl_token::id(),
+                original_pda_addr_ai.key,
+                Some(&offer_authority),
+                spl_token::instruction::AuthorityType::AccountOwner,
+                pda_ai.key,
+                &[pda_ai.key],
+            )?;
+
+            invoke_signed(
+                &owner_change_ix,
+                &[
+                    original_pda_addr_ai.clone(),
+                    pda_ai.clone(),
+                ],
+                &[&[trade_account_ai.key.as_ref(), &[bump_seed]]],
+            )?;
+
+            msg!("Returned authority of the offer account...");
+        }
+
+        TradeEvent::TradeFilled {
+            trade: *trade_account_ai.key,
+            authority: offer_authority,
+            taker: *authority_ai.key,
+            offer_amount: expected_offer,
+            trade_amount,
+        }.emit();
+
+        Ok(())
+    }
+
+    // Takes the trade fee, a share of offer_amount, from trade_src into the fee account, which must be the ATA of the
+    // program authority for the trade mint. `index` is the index of the fee account in the instruction. Returns the fee
+    #[allow(clippy::too_many_arguments)]
+    fn transfer_fee<'a>(
+        trade: &Pubkey,
+        index: usize,
+        offer_amount: u64,
+        authority_ai: &AccountInfo<'a>,
+        trade_src_ai: &AccountInfo<'a>,
+        trade_mint: &Pubkey,
+        fee_account_ai: &AccountInfo<'a>,
+        token_program_ai: &AccountInfo<'a>,
+    ) -> Result<u64, ProgramError> {
+        let prog_authority = Pubkey::from_str(PROGRAM_AUTHORITY).unwrap();
+        let fee_ata = spl_associated_token_account::get_associated_token_address(&prog_authority, trade_mint);
+        // make sure the fee account passed is the correct one
+        if sol_memcmp(fee_account_ai.key.as_ref(), fee_ata.as_ref(), PUBKEY_BYTES) != 0 {
+            Err(Self::account_error(index, TradeError::WrongAuthority))?
+        }
+        
+        // Not sure on how to do the rouding
+        // using floor or ceil instead of rount result in missing symbols in the ELF file...
+        let lamports_fee = (offer_amount as f64).mul(FEE_PERCENTAGE).round() as u64;
+        msg!("Applying a transfer fee of {} lamports", lamports_fee);
+
+        let fee_transfer_ix = spl_token::instruction::transfer(
+            &spl_token::id(),
+            trade_src_ai.key,
+            fee_account_ai.key,
+            authority_ai.key,
+            &[authority_ai.key],
+            lamports_fee,
+        )?;
+        
+        invoke(
+            &fee_transfer_ix,
+            &[
+                trade_src_ai.clone(),
+                fee_account_ai.clone(),
+                authority_ai.clone(),
+                token_program_ai.clone(),
+            ],
+        )?;
+
+        TradeEvent::FeeCollected {
+            trade: *trade,
+            fee_account: *fee_account_ai.key,
+            mint: *trade_mint,
+            amount: lamports_fee,
+        }.emit();
+
+        Ok(lamports_fee)
+    }
+
+    // Custom errors can't carry any data, so the index of the offending account is logged instead
+    fn account_error(index: usize, error: TradeError) -> ProgramError {
+        msg!("Account {}: {}", index, error);
+        error.into()
+    }
+
+    fn check_writable(index: usize, account: &AccountInfo) -> entrypoint::ProgramResult {
+        if !account.is_writable {
+            return Err(Self::account_error(index, TradeError::AccountNotWritable));
+        }
+
+        Ok(())
+    }
+
+    fn check_token_program(index: usize, account: &AccountInfo) -> entrypoint::ProgramResult {
+        if sol_memcmp(account.key.as_ref(), spl_token::id().as_ref(), PUBKEY_BYTES) != 0 {
+            return Err(Self::account_error(index, TradeError::InvalidTokenProgram));
+        }
+
+        Ok(())
+    }
+
+    // the pda of a trade is derived from the trade account, see `instruction::find_trade_pda`
+    fn check_pda(
+        program_id: &Pubkey,
+        index: usize,
+        pda_ai: &AccountInfo,
+        trade: &Pubkey,
+        bump_seed: u8,
+    ) -> entrypoint::ProgramResult {
+        let pda = Pubkey::create_program_address(&[trade.as_ref(), &[bump_seed]], program_id)
+            .map_err(|_| Self::account_error(index, TradeError::InvalidPda))?;
+        if sol_memcmp(pda_ai.key.as_ref(), pda.as_ref(), PUBKEY_BYTES) != 0 {
+            return Err(Self::account_error(index, TradeError::InvalidPda));
+        }
+
+        Ok(())
+    }
+
+    // Creates the pda account with signer_seeds, owned by the program, paid by payer_ai. Anyone can send lamports to
+    // the pda beforehand, which makes create_account fail, so such an account is topped up to rent exemption,
+    // allocated and assigned instead.
+    fn create_pda_account<'a>(
+        program_id: &Pubkey,
+        payer_ai: &AccountInfo<'a>,
+        pda_ai: &AccountInfo<'a>,
+        system_program_ai: &AccountInfo<'a>,
+        size: usize,
+        signer_seeds: &[&[u8]],
+    ) -> entrypoint::ProgramResult {
+        let rent = Rent::get()?.minimum_balance(size);
+        if pda_ai.lamports() == 0 {
+            let create_ix = system_instruction::create_account(payer_ai.key, pda_ai.key, rent, size as u64, program_id);
+
+            return invoke_signed(
+                &create_ix,
+                &[
+                    payer_ai.clone(),
+                    pda_ai.clone(),
+                    system_program_ai.clone(),
+                ],
+                &[signer_seeds],
+            );
+        }
+
+        let missing = rent.saturating_sub(pda_ai.lamports());
+        if missing > 0 {
+            let transfer_ix = system_instruction::transfer(payer_ai.key, pda_ai.key, missing);
+
+            invoke(
+                &transfer_ix,
+                &[
+                    payer_ai.clone(),
+                    pda_ai.clone(),
+                    system_program_ai.clone(),
+                ],
+            )?;
+        }
+
+        let allocate_ix = system_instruction::allocate(pda_ai.key, size as u64);
+        invoke_signed(&allocate_ix, &[pda_ai.clone(), system_program_ai.clone()], &[signer_seeds])?;
+        let assign_ix = system_instruction::assign(pda_ai.key, program_id);
+        invoke_signed(&assign_ix, &[pda_ai.clone(), system_program_ai.clone()], &[signer_seeds])
+    }
+
+    // the program authority signs every change to the mint list and to the mint limits
+    fn check_program_authority(index: usize, authority_ai: &AccountInfo) -> entrypoint::ProgramResult {
+        let prog_authority = Pubkey::from_str(PROGRAM_AUTHORITY).unwrap();
+        if !authority_ai.is_signer
+            || sol_memcmp(authority_ai.key.as_ref(), prog_authority.as_ref(), PUBKEY_BYTES) != 0 {
+            return Err(Self::account_error(index, TradeError::WrongAuthority));
+        }
+
+        Ok(())
+    }
+
+    // Loads the mint list at index, which must have been created by SetMintListMode
+    fn load_mint_list(
+        program_id: &Pubkey,
+        index: usize,
+        mint_list_ai: &AccountInfo,
+    ) -> Result<state::AccountMintList, ProgramError> {
+        if mint_list_ai.owner != program_id {
+            return Err(Self::account_error(index, TradeError::UnexpectedAccount));
+        }
+        let mint_list = state::AccountMintList::try_from_slice(&mint_list_ai.data.borrow())
+            .map_err(|_| Self::account_error(index, TradeError::UnexpectedAccount))?;
+        let pda = Pubkey::create_program_address(&[state::MINT_LIST_SEED, &[mint_list.bump_seed]], program_id)
+            .map_err(|_| Self::account_error(index, TradeError::InvalidPda))?;
+        if sol_memcmp(mint_list_ai.key.as_ref(), pda.as_ref(), PUBKEY_BYTES) != 0 {
+            return Err(Self::account_error(index, TradeError::InvalidPda));
+        }
+
+        Ok(mint_list)
+    }
+
+    // Checks that mint can be traded, given the mint list at list_index and the listed mint account of mint right
+    // after it. Nothing is listed until the mint list is created.
+    fn check_mint_allowed(
+        program_id: &Pubkey,
+        list_index: usize,
+        mint_list_ai: &AccountInfo,
+        mint_index: usize,
+        listed_mint_ai: &AccountInfo,
+        mint: &Pubkey,
+    ) -> entrypoint::ProgramResult {
+        // once created, the accounts hold their bump seed, which is cheaper to check than finding the pda
+        if mint_list_ai.owner != program_id {
+            let (mint_list_key, _) = Pubkey::find_program_address(&[state::MINT_LIST_SEED], program_id);
+            if sol_memcmp(mint_list_ai.key.as_ref(), mint_list_key.as_ref(), PUBKEY_BYTES) != 0 {
+                return Err(Self::account_error(list_index, TradeError::InvalidPda));
+            }
+
+            return Ok(());
+        }
+        let mint_list = Self::load_mint_list(program_id, list_index, mint_list_ai)?;
+
+        // the listed mint account only belongs to the program once created by AddMint, and is emptied by RemoveMint
+        let listed_mint = if listed_mint_ai.owner == program_id {
+            state::AccountListedMint::try_from_slice(&listed_mint_ai.data.borrow())
+                .ok()
+                .filter(|listed_mint| listed_mint.initialized)
+        } else {
+            None
+        };
+        let listed_mint_key = match &listed_mint {
+            Some(listed_mint) => Pubkey::create_program_address(
+                &[state::LISTED_MINT_SEED, mint.as_ref(), &[listed_mint.bump_seed]],
+                program_id,
+            ).map_err(|_| Self::account_error(mint_index, TradeError::InvalidPda))?,
+            None => Pubkey::find_program_address(&[state::LISTED_MINT_SEED, mint.as_ref()], program_id).0,
+        };
+        if sol_memcmp(listed_mint_ai.key.as_ref(), listed_mint_key.as_ref(), PUBKEY_BYTES) != 0 {
+            return Err(Self::account_error(mint_index, TradeError::InvalidPda));
+        }
+        if !mint_list.allows(listed_mint.is_some()) {
+            msg!("Mint {} not allowed by the mint list", mint);
+            return Err(Self::account_error(mint_index, TradeError::MintNotAllowed));
+        }
+
+        Ok(())
+    }
+
+    // Loads the limits of mint from the mint limits account at index, None if the mint has none
+    fn load_mint_limits(
+        program_id: &Pubkey,
+        index: usize,
+        mint_limits_ai: &AccountInfo,
+        mint: &Pubkey,
+    ) -> Result<Option<state::AccountMintLimits>, ProgramError> {
+        // once created, the account holds the bump seed, which is cheaper to check than finding the pda
+        if mint_limits_ai.owner == program_id {
+            let mint_limits = state::AccountMintLimits::try_from_slice(&mint_limits_ai.data.borrow())
+                .map_err(|_| Self::account_error(index, TradeError::UnexpectedAccount))?;
+            let pda = Pubkey::create_program_address(
+                &[state::MINT_LIMITS_SEED, mint.as_ref(), &[mint_limits.bump_seed]],
+                program_id,
+            ).map_err(|_| Self::account_error(index, TradeError::InvalidPda))?;
+            if sol_memcmp(mint_limits_ai.key.as_ref(), pda.as_ref(), PUBKEY_BYTES) != 0 {
+                return Err(Self::account_error(index, TradeError::InvalidPda));
+            }
+
+            return Ok(Some(mint_limits));
+        }
+
+        let (pda, _) = Pubkey::find_program_address(&[state::MINT_LIMITS_SEED, mint.as_ref()], program_id);
+        if sol_memcmp(mint_limits_ai.key.as_ref(), pda.as_ref(), PUBKEY_BYTES) != 0 {
+            return Err(Self::account_error(index, TradeError::InvalidPda));
+        }
+
+        Ok(None)
+    }
+
+    fn check_amount_limits(
+        index: usize,
+        mint_limits: Option<&state::AccountMintLimits>,
+        amount: u64,
+    ) -> entrypoint::ProgramResult {
+        match mint_limits {
+            Some(mint_limits) if !mint_limits.allows_amount(amount) => {
+                msg!("Amount of {} outside the limits of mint {}", amount, mint_limits.mint);
+                Err(Self::account_error(index, TradeError::AmountOutsideLimits))
+            },
+            _ => Ok(()),
+        }
+    }
+
+    // Checks the amount of mint filled against the limits in the mint limits account at index, if any, and adds it to
+    // the volume of their window. Both mints of a trade can be the same, so the account is written before the other
+    // one is loaded.
+    fn record_fill(
+        program_id: &Pubkey,
+        index: usize,
+        mint_limits_ai: &AccountInfo,
+        mint: &Pubkey,
+        amount: u64,
+        now: UnixTimestamp,
+    ) -> entrypoint::ProgramResult {
+        let mut mint_limits = match Self::load_mint_limits(program_id, index, mint_limits_ai, mint)? {
+            Some(mint_limits) => mint_limits,
+            None => return Ok(()),
+        };
+        Self::check_amount_limits(index, Some(&mint_limits), amount)?;
+        if !mint_limits.add_volume(amount, now) {
+            msg!("Fill of {} over the volume cap of mint {} until {}", amount, mint,
+                mint_limits.window_start.saturating_add(mint_limits.limits.window));
+            return Err(Self::account_error(index, TradeError::VolumeCapExceeded));
+        }
+
+        mint_limits.serialize(&mut *mint_limits_ai.data.borrow_mut())?;
+
+        Ok(())
+    }
+
+    // Applies update to the stats account at index, if it was created. Both mints of a trade can be the same, so the
+    // account is written before another one is loaded.
+    fn update_stats<T: state::Stats>(
+        program_id: &Pubkey,
+        index: usize,
+        stats_ai: &AccountInfo,
+        stats: &state::StatsAccount,
+        update: impl FnOnce(&mut T),
+    ) -> entrypoint::ProgramResult {
+        // once created, the account holds the bump seed, which is cheaper to check than finding the pda
+        if stats_ai.owner != program_id {
+            let (pda, _) = Pubkey::find_program_address(&stats.seeds(), program_id);
+            if sol_memcmp(stats_ai.key.as_ref(), pda.as_ref(), PUBKEY_BYTES) != 0 {
+                return Err(Self::account_error(index, TradeError::InvalidPda));
+            }
+
+            return Ok(());
+        }
+
+        let mut account = T::try_from_slice(&stats_ai.data.borrow())
+            .map_err(|_| Self::account_error(index, TradeError::UnexpectedAccount))?;
+        let bump_seed = [account.bump_seed()];
+        let mut seeds = stats.seeds();
+        seeds.push(&bump_seed);
+        let pda = Pubkey::create_program_address(&seeds, program_id)
+            .map_err(|_| Self::account_error(index, TradeError::InvalidPda))?;
+        if sol_memcmp(stats_ai.key.as_ref(), pda.as_ref(), PUBKEY_BYTES) != 0 {
+            return Err(Self::account_error(index, TradeError::InvalidPda));
+        }
+
+        update(&mut account);
+        account.serialize(&mut *stats_ai.data.borrow_mut())?;
+
+        Ok(())
+    }
+
+    // the nonces account of a maker is derived from the maker, see `instruction::find_nonces_pda`
+    fn check_nonces_pda(
+        program_id: &Pubkey,
+        index: usize,
+        nonces_ai: &AccountInfo,
+        maker: &Pubkey,
+        bump_seed: u8,
+    ) -> entrypoint::ProgramResult {
+        let pda = Pubkey::create_program_address(&[state::NONCES_SEED, maker.as_ref(), &[bump_seed]], program_id)
+            .map_err(|_| Self::account_error(index, TradeError::InvalidPda))?;
+        if sol_memcmp(nonces_ai.key.as_ref(), pda.as_ref(), PUBKEY_BYTES) != 0 {
+            return Err(Self::account_error(index, TradeError::InvalidPda));
+        }
+
+        Ok(())
+    }
+
+    fn load_nonces(
+        program_id: &Pubkey,
+        index: usize,
+        nonces_ai: &AccountInfo,
+        maker: &Pubkey,
+    ) -> Result<state::AccountNonces, ProgramError> {
+        if nonces_ai.owner != program_id {
+            return Err(Self::account_error(index, TradeError::UnexpectedAccount));
+        }
+        let nonces = state::AccountNonces::try_from_slice(&nonces_ai.data.borrow())
+            .map_err(|_| Self::account_error(index, TradeError::UnexpectedAccount))?;
+        if !nonces.initialized {
+            return Err(Self::account_error(index, TradeError::TradeNotInitialised));
+        }
+        if sol_memcmp(nonces.maker.as_ref(), maker.as_ref(), PUBKEY_BYTES) != 0 {
+            return Err(Self::account_error(index, TradeError::WrongAuthority));
+        }
+        Self::check_nonces_pda(program_id, index, nonces_ai, maker, nonces.bump_seed)?;
+
+        Ok(nonces)
+    }
+
+    // Checks that the instruction before the current one has the ed25519 program verify a signature of signer over
+    // message. The runtime rejects the whole transaction when the signature is wrong, so only what was verified
+    // needs to be checked here.
+    fn check_ed25519_signature(
+        index: usize,
+        instructions_ai: &AccountInfo,
+        signer: &Pubkey,
+        message: &[u8],
+    ) -> entrypoint::ProgramResult {
+        if !instructions::check_id(instructions_ai.key) {
+            return Err(Self::account_error(index, TradeError::UnexpectedAccount));
+        }
+        let verify_ix = instructions::get_instruction_relative(-1, instructions_ai)
+            .map_err(|_| TradeError::InvalidSignature)?;
+        if verify_ix.program_id != ed25519_program::id() {
+            return Err(TradeError::InvalidSignature)?;
+        }
+
+        // a single signature, with the public key and message in the data of the verify instruction itself
+        let data = &verify_ix.data;
+        if data.len() < ED25519_OFFSETS_START + ED25519_OFFSETS_SIZE || data[0] != 1 {
+            return Err(TradeError::InvalidSignature)?;
+        }
+        let offset = |i: usize| {
+            let at = ED25519_OFFSETS_START + 2 * i;
+            u16::from_le_bytes([data[at], data[at + 1]])
+        };
+        let (public_key_offset, public_key_ix) = (offset(2) as usize, offset(3));
+        let (message_offset, message_size, message_ix) = (offset(4) as usize, offset(5) as usize, offset(6));
+        if public_key_ix != u16::MAX || message_ix != u16::MAX {
+            return Err(TradeError::InvalidSignature)?;
+        }
+
+        let public_key = data.get(public_key_offset..public_key_offset + PUBKEY_BYTES);
+        let signed = data.get(message_offset..message_offset + message_size);
+        if public_key != Some(signer.as_ref()) || signed != Some(message) {
+            msg!("The signature verified is not the one of {} over the order", signer);
+            return Err(TradeError::InvalidSignature)?;
+        }
+
+        Ok(())
+    }
+
+    // passing the same account twice, ex: as the source and destination of a transfer, would break the trade
+    fn check_no_duplicates(index: usize, accounts: &[AccountInfo]) -> entrypoint::ProgramResult {
+        for (i, account) in accounts.iter().enumerate() {
+            if accounts[..i].iter().any(|other| other.key == account.key) {
+                return Err(Self::account_error(index + i, TradeError::DuplicateAccount));
             }
         }
 
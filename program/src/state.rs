@@ -1,7 +1,10 @@
 use borsh::{BorshDeserialize, BorshSerialize};
 use solana_program::{
+    clock::UnixTimestamp,
+    program_option::COption,
     pubkey::Pubkey,
 };
+use spl_token::state::Account;
 
 #[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Default)]
 pub struct AccountTemp {
@@ -20,6 +23,157 @@ impl AccountTemp {
     }
 }
 
+// Most destinations the trade amount can be split between
+pub const MAX_SPLITS: usize = 4;
+
+// A destination of the trade amount and its share of it, in basis points
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, Default, PartialEq)]
+pub struct ProceedsSplit {
+    pub destination: Pubkey,
+    pub share_bps: u16,
+}
+
+// Layout of the price accounts read by oracle-pegged trades, borsh encoded at the start of the account data. The price
+// of one unit of the offer token, in units of the trade token, is price * 10^expo, as published by the owner of the
+// account at publish_time.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Default, PartialEq)]
+pub struct PriceFeed {
+    pub price: i64,
+    pub expo: i32,
+    pub publish_time: UnixTimestamp,
+}
+
+impl PriceFeed {
+    // The trade amount for offer_amount at this price, plus spread_bps basis points. Rounds up, so the owner of the
+    // trade never gets less than the price. None if the price isn't positive or on overflow.
+    pub fn trade_amount(&self, offer_amount: u64, spread_bps: u16) -> Option<u64> {
+        if self.price <= 0 {
+            return None;
+        }
+
+        let amount = (offer_amount as u128).checked_mul(self.price as u128)?;
+        let scale = 10u128.checked_pow(self.expo.unsigned_abs())?;
+        let amount = if self.expo < 0 {
+            div_ceil(amount, scale)?
+        } else {
+            amount.checked_mul(scale)?
+        };
+        let amount = div_ceil(amount.checked_mul(10_000 + spread_bps as u128)?, 10_000)?;
+
+        u64::try_from(amount).ok()
+    }
+}
+
+fn div_ceil(a: u128, b: u128) -> Option<u128> {
+    Some(a.checked_add(b - 1)? / b)
+}
+
+fn gcd(a: u64, b: u64) -> u64 {
+    if b == 0 { a } else { gcd(b, a % b) }
+}
+
+// Seed of the nonces account of a maker, along with the maker
+pub const NONCES_SEED: &[u8] = b"nonces";
+
+// Words of the bitmap of nonces of signed orders kept by each maker, which tracks NONCE_WINDOW nonces at a time
+pub const NONCE_WORDS: usize = 16;
+pub const NONCE_WINDOW: u64 = 64 * NONCE_WORDS as u64;
+
+// The nonces of the orders signed by a maker that were filled or cancelled, see `instructions::SignedOrder`. Nonces
+// below base are all spent, and the ones from base up to base + NONCE_WINDOW are tracked one by one. Its pda is also
+// the delegate of the token accounts the orders are filled from.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Default, PartialEq)]
+pub struct AccountNonces {
+    pub initialized: bool,
+    pub maker: Pubkey,
+    pub bump_seed: u8,
+    pub base: u64,
+    // bit i of word w is set once nonce base + 64 * w + i is spent
+    pub spent: [u64; NONCE_WORDS],
+}
+
+impl AccountNonces {
+    pub fn size() -> usize {
+        AccountNonces::default().try_to_vec().unwrap().len()
+    }
+
+    // whether the nonce is tracked one by one, spent or not
+    pub fn in_window(&self, nonce: u64) -> bool {
+        nonce >= self.base && nonce - self.base < NONCE_WINDOW
+    }
+
+    pub fn is_spent(&self, nonce: u64) -> bool {
+        if nonce < self.base {
+            return true;
+        }
+        if !self.in_window(nonce) {
+            return false;
+        }
+
+        let bit = nonce - self.base;
+        self.spent[(bit / 64) as usize] & (1 << (bit % 64)) != 0
+    }
+
+    // Marks a nonce in the window as spent. Returns false if it isn't in the window
+    pub fn spend(&mut self, nonce: u64) -> bool {
+        if !self.in_window(nonce) {
+            return false;
+        }
+
+        let bit = nonce - self.base;
+        self.spent[(bit / 64) as usize] |= 1 << (bit % 64);
+        true
+    }
+
+    // Spends every nonce below min_nonce, moving the window up to start at it
+    pub fn spend_below(&mut self, min_nonce: u64) {
+        if min_nonce <= self.base {
+            return;
+        }
+
+        let previous = self.clone();
+        self.base = min_nonce;
+        self.spent = [0; NONCE_WORDS];
+        // the nonces of the previous window still in the new one keep their state
+        for nonce in min_nonce..previous.base.saturating_add(NONCE_WINDOW) {
+            if previous.is_spent(nonce) {
+                self.spend(nonce);
+            }
+        }
+    }
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, PartialEq)]
+pub enum TradeMode {
+    // the authority of the offer account is transfered to the trade PDA until the trade is done
+    Escrow,
+    // the offer account stays with its owner, who approves the trade PDA as delegate of the offer amount
+    Delegate,
+    // the offer account is held as in Escrow mode, but the trade is only filled with the signature of the arbiter
+    // along with the taker's. Once the dispute timeout is over the arbiter alone can release the offer to either side
+    Arbitrated,
+    // the offer account is held as in Escrow mode, as the underlying of a call option. Whoever buys the option by
+    // paying its premium can exercise it until the trade expires, paying the trade amount as strike. Once expired the
+    // offer account goes back to its owner
+    CoveredCall,
+    // the offer account is held as in Escrow mode, and the taker moves the trade amount into escrow when filling the
+    // trade. Both sides are only delivered by Settle once the settlement date has passed
+    Forward,
+}
+
+impl TradeMode {
+    // whether the authority of the offer account is held by the trade PDA
+    pub fn escrows_offer(&self) -> bool {
+        *self != TradeMode::Delegate
+    }
+}
+
+impl Default for TradeMode {
+    fn default() -> Self {
+        TradeMode::Escrow
+    }
+}
+
 #[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Default)]
 pub struct AccountTrade {
     pub bump_seed: u8,
@@ -31,6 +185,61 @@ pub struct AccountTrade {
     pub initialized: bool,
     pub trade_mint: Pubkey,
     pub program_id: Pubkey,
+    pub mode: TradeMode,
+    // unix timestamp after which the trade can't be filled anymore and anyone can close it, 0 if it never expires
+    pub expires_at: UnixTimestamp,
+    // lamports, out of the trade account balance, paid to whoever closes the trade once it is stale
+    pub bounty: u64,
+    // the price account of an oracle-pegged trade, the default pubkey for trades with a fixed trade amount. The trade
+    // amount of a pegged trade is taken from the price, plus the spread, and is the minimum the owner accepts
+    pub price_feed: Pubkey,
+    // in basis points, added to the price of pegged trades
+    pub spread_bps: u16,
+    // in seconds, how old the price of a pegged trade can be when the trade is filled
+    pub max_price_age: u32,
+    // the price of the trade as a reduced fraction, in trade base units per offer base units. Settlement amounts are
+    // computed from it, and for pegged trades it is the minimum price
+    pub price_num: u64,
+    pub price_den: u64,
+    // decimals of the offer and trade mints, to quote the trade in UI amounts
+    pub offer_decimals: u8,
+    pub trade_decimals: u8,
+    // how the trade amount is split, the first destination being trade_dst_account. Unused entries have a zero share,
+    // and trades without any pay everything to trade_dst_account
+    pub splits: [ProceedsSplit; MAX_SPLITS],
+    // the arbiter of a trade in Arbitrated mode, the default pubkey otherwise
+    pub arbiter: Pubkey,
+    // unix timestamp from which the arbiter can release the offer of a trade in Arbitrated mode without a taker
+    pub dispute_ends_at: UnixTimestamp,
+    // the compliance hook called before the trade is settled, see `instructions::ComplianceHook`. The default pubkey
+    // for trades without one
+    pub hook_program: Pubkey,
+    pub hook_account: Pubkey,
+    // the premium, in trade tokens, paid to buy a trade in CoveredCall mode, and the holder of the option once bought.
+    // The default pubkey until then
+    pub premium: u64,
+    pub holder: Pubkey,
+    // unix timestamp from which a trade in Forward mode is settled, 0 for other trades. Once filled, the taker and the
+    // token account the offer is delivered to, on settlement or as it vests, the default pubkeys until then
+    pub settles_at: UnixTimestamp,
+    pub taker: Pubkey,
+    pub offer_dst_account: Pubkey,
+    pub offer_mint: Pubkey,
+    // in seconds from the fill, the linear vesting schedule of the offer, a zero duration if the offer is delivered
+    // when the trade is filled. Nothing vests before the cliff, and everything once the duration is over
+    pub vesting_cliff: i64,
+    pub vesting_duration: i64,
+    // unix timestamp at which a vesting trade was filled, and how much of its offer was claimed since
+    pub vesting_start: UnixTimestamp,
+    pub claimed: u64,
+    // the root of the Merkle allowlist of the wallets that can fill the trade, see `allowlist`. Zeroes if anyone can
+    pub allowlist_root: [u8; 32],
+    // the mint of the one-of-one receipt minted to the owner on creation, whose holder gets the trade amount. The
+    // default pubkey for trades paying trade_dst_account
+    pub receipt_mint: Pubkey,
+    // the owner of the price account of an oracle-pegged trade when it was created, which must still own it when the
+    // trade is filled
+    pub price_feed_owner: Pubkey,
 }
 
 impl AccountTrade {
@@ -41,4 +250,354 @@ impl AccountTrade {
 
         encoded.len()
     }
+
+    // Sets the price to trade_amount for offer_amount
+    pub fn set_price(&mut self, offer_amount: u64, trade_amount: u64) {
+        let divisor = gcd(offer_amount, trade_amount).max(1);
+        self.price_num = trade_amount / divisor;
+        self.price_den = offer_amount / divisor;
+    }
+
+    // The trade amount for offer_amount at the price of the trade. Rounds up, so the owner of the trade never gets
+    // less than the price. None if the trade has no price or on overflow.
+    pub fn trade_amount_for(&self, offer_amount: u64) -> Option<u64> {
+        if self.price_den == 0 {
+            return None;
+        }
+
+        let amount = div_ceil(offer_amount as u128 * self.price_num as u128, self.price_den as u128)?;
+        u64::try_from(amount).ok()
+    }
+
+    // The price in trade tokens per offer token, with a single division to keep it as exact as possible
+    pub fn ui_price(&self) -> f64 {
+        (self.price_num as f64 * 10f64.powi(self.offer_decimals as i32))
+            / (self.price_den as f64 * 10f64.powi(self.trade_decimals as i32))
+    }
+
+    // The entries of splits in use
+    pub fn splits(&self) -> &[ProceedsSplit] {
+        let count = self.splits.iter().take_while(|split| split.share_bps > 0).count();
+        &self.splits[..count]
+    }
+
+    // The destinations of the trade amount after trade_dst_account, which are passed along with the trade when filled
+    pub fn split_destinations(&self) -> Vec<Pubkey> {
+        self.splits().iter().skip(1).map(|split| split.destination).collect()
+    }
+
+    // Splits amount between the destinations of the trade. Shares are rounded down and the remainder goes to the
+    // first destination.
+    pub fn split_proceeds(&self, amount: u64) -> Vec<(Pubkey, u64)> {
+        let splits = self.splits();
+        if splits.is_empty() {
+            return vec![(self.trade_dst_account, amount)];
+        }
+
+        let mut proceeds: Vec<(Pubkey, u64)> = splits.iter()
+            .map(|split| (split.destination, (amount as u128 * split.share_bps as u128 / 10_000) as u64))
+            .collect();
+        let paid: u64 = proceeds[1..].iter().map(|(_, share)| share).sum();
+        proceeds[0].1 = amount - paid;
+
+        proceeds
+    }
+
+    pub fn is_pegged(&self) -> bool {
+        self.price_feed != Pubkey::default()
+    }
+
+    pub fn is_hooked(&self) -> bool {
+        self.hook_program != Pubkey::default()
+    }
+
+    pub fn is_arbitrated(&self) -> bool {
+        self.mode == TradeMode::Arbitrated
+    }
+
+    pub fn is_option(&self) -> bool {
+        self.mode == TradeMode::CoveredCall
+    }
+
+    pub fn is_option_bought(&self) -> bool {
+        self.holder != Pubkey::default()
+    }
+
+    pub fn is_forward(&self) -> bool {
+        self.mode == TradeMode::Forward
+    }
+
+    // whether a trade that stays open once filled, in Forward mode or vesting its offer, was filled
+    pub fn is_committed(&self) -> bool {
+        self.taker != Pubkey::default()
+    }
+
+    pub fn is_allowlisted(&self) -> bool {
+        self.allowlist_root != [0; 32]
+    }
+
+    pub fn has_receipt(&self) -> bool {
+        self.receipt_mint != Pubkey::default()
+    }
+
+    pub fn is_vesting(&self) -> bool {
+        self.vesting_duration > 0
+    }
+
+    // How much of the offer has vested at now, growing linearly from the start of the vesting to its end, and zero
+    // before the cliff
+    pub fn vested_amount(&self, now: UnixTimestamp) -> u64 {
+        let elapsed = now.saturating_sub(self.vesting_start);
+        if !self.is_committed() || elapsed < self.vesting_cliff {
+            return 0;
+        }
+        if elapsed >= self.vesting_duration {
+            return self.offer_amount;
+        }
+
+        (self.offer_amount as u128 * elapsed as u128 / self.vesting_duration as u128) as u64
+    }
+
+    // whether a trade in Forward mode can be settled, filled or not
+    pub fn is_settlement_due(&self, now: UnixTimestamp) -> bool {
+        self.is_forward() && now >= self.settles_at
+    }
+
+    pub fn is_expired(&self, now: UnixTimestamp) -> bool {
+        self.expires_at != 0 && now >= self.expires_at
+    }
+
+    // A trade in Delegate mode is abandoned when its owner revoked the delegation or moved the offer out of the offer
+    // account, so it can't be filled anymore. Trades in Escrow mode can't be abandoned.
+    pub fn is_abandoned(&self, offer_token: &Account, pda: &Pubkey) -> bool {
+        self.mode == TradeMode::Delegate && (
+            offer_token.delegate != COption::Some(*pda)
+            || offer_token.delegated_amount < self.offer_amount
+            || offer_token.amount < self.offer_amount
+        )
+    }
+}
+
+// Seed of the mint list of the program, and of the account of each mint listed in it, along with the mint
+pub const MINT_LIST_SEED: &[u8] = b"mint-list";
+pub const LISTED_MINT_SEED: &[u8] = b"listed-mint";
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, PartialEq)]
+pub enum MintListMode {
+    // only listed mints can be traded
+    Allowlist,
+    // listed mints can't be traded
+    Blocklist,
+}
+
+impl Default for MintListMode {
+    fn default() -> Self {
+        MintListMode::Blocklist
+    }
+}
+
+// The mint list of the program, managed by the program authority and checked against both mints of every trade
+// created. Mints are listed each with their own account, see `AccountListedMint`. Until the list is created any mint
+// can be traded.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Default, PartialEq)]
+pub struct AccountMintList {
+    pub initialized: bool,
+    pub bump_seed: u8,
+    pub mode: MintListMode,
+    // the number of listed mints. The mode only changes while there are none, so that the mints blocked by a
+    // blocklist don't become the only ones allowed
+    pub listed: u64,
+}
+
+impl AccountMintList {
+    pub fn size() -> usize {
+        AccountMintList::default().try_to_vec().unwrap().len()
+    }
+
+    // whether a mint can be traded, given whether it is listed
+    pub fn allows(&self, listed: bool) -> bool {
+        match self.mode {
+            MintListMode::Allowlist => listed,
+            MintListMode::Blocklist => !listed,
+        }
+    }
+}
+
+// A mint in the mint list, which is removed by closing its account
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Default, PartialEq)]
+pub struct AccountListedMint {
+    pub initialized: bool,
+    pub bump_seed: u8,
+    pub mint: Pubkey,
+}
+
+impl AccountListedMint {
+    pub fn size() -> usize {
+        AccountListedMint::default().try_to_vec().unwrap().len()
+    }
+}
+
+// Seed of the limits account of a mint, along with the mint
+pub const MINT_LIMITS_SEED: &[u8] = b"mint-limits";
+
+// The limits the program authority sets on the trades of a mint, 0 lifting any of them
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, Default, PartialEq)]
+pub struct MintLimits {
+    // the smallest and the largest amounts of the mint a trade can be created or filled for
+    pub min_amount: u64,
+    pub max_amount: u64,
+    // the most of the mint that can be filled, by all trades together, within window seconds
+    pub volume_cap: u64,
+    pub window: i64,
+}
+
+// The limits of a mint, checked against both mints of every trade created or filled. Until the account is created
+// the mint has no limits.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Default, PartialEq)]
+pub struct AccountMintLimits {
+    pub initialized: bool,
+    pub bump_seed: u8,
+    pub mint: Pubkey,
+    pub limits: MintLimits,
+    // the start of the current window and how much of the mint was filled since
+    pub window_start: UnixTimestamp,
+    pub window_volume: u64,
+}
+
+impl AccountMintLimits {
+    pub fn size() -> usize {
+        AccountMintLimits::default().try_to_vec().unwrap().len()
+    }
+
+    pub fn allows_amount(&self, amount: u64) -> bool {
+        amount >= self.limits.min_amount && (self.limits.max_amount == 0 || amount <= self.limits.max_amount)
+    }
+
+    // Adds amount to the volume filled in the window at now, a new window starting once the current one is over.
+    // Returns false, leaving the volume as it was, when it would exceed the cap.
+    pub fn add_volume(&mut self, amount: u64, now: UnixTimestamp) -> bool {
+        if self.limits.volume_cap == 0 {
+            return true;
+        }
+        if now >= self.window_start.saturating_add(self.limits.window) {
+            self.window_start = now;
+            self.window_volume = 0;
+        }
+
+        match self.window_volume.checked_add(amount) {
+            Some(volume) if volume <= self.limits.volume_cap => {
+                self.window_volume = volume;
+                true
+            },
+            _ => false,
+        }
+    }
+}
+
+// Seeds of the stats account of the program, and of the stats accounts of each mint and of each maker, along with the
+// mint or the maker
+pub const STATS_SEED: &[u8] = b"stats";
+pub const MINT_STATS_SEED: &[u8] = b"mint-stats";
+pub const MAKER_STATS_SEED: &[u8] = b"maker-stats";
+
+// The stats accounts, which anyone can create and which count from then on. Trades are counted when filled by
+// MakeTrade, MakeTrades, ExerciseOption or FillSignedOrder, along with their volume and fee.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, PartialEq)]
+pub enum StatsAccount {
+    // see `AccountStats`
+    Program,
+    // see `AccountMintStats`
+    Mint(Pubkey),
+    // see `AccountMakerStats`
+    Maker(Pubkey),
+}
+
+impl StatsAccount {
+    // the seeds of the pda of the account, without the bump seed
+    pub fn seeds(&self) -> Vec<&[u8]> {
+        match self {
+            StatsAccount::Program => vec![STATS_SEED],
+            StatsAccount::Mint(mint) => vec![MINT_STATS_SEED, mint.as_ref()],
+            StatsAccount::Maker(maker) => vec![MAKER_STATS_SEED, maker.as_ref()],
+        }
+    }
+
+    pub fn size(&self) -> usize {
+        match self {
+            StatsAccount::Program => AccountStats::size(),
+            StatsAccount::Mint(_) => AccountMintStats::size(),
+            StatsAccount::Maker(_) => AccountMakerStats::size(),
+        }
+    }
+}
+
+// Implemented by the stats accounts, which keep the bump seed of their pda
+pub trait Stats: BorshSerialize + BorshDeserialize {
+    fn bump_seed(&self) -> u8;
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Default, PartialEq)]
+pub struct AccountStats {
+    pub initialized: bool,
+    pub bump_seed: u8,
+    pub trades: u64,
+}
+
+impl AccountStats {
+    pub fn size() -> usize {
+        AccountStats::default().try_to_vec().unwrap().len()
+    }
+}
+
+impl Stats for AccountStats {
+    fn bump_seed(&self) -> u8 {
+        self.bump_seed
+    }
+}
+
+// The trades filled with the mint on either side, how much of the mint they moved, and the fees collected in the mint
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Default, PartialEq)]
+pub struct AccountMintStats {
+    pub initialized: bool,
+    pub bump_seed: u8,
+    pub mint: Pubkey,
+    pub trades: u64,
+    pub volume: u128,
+    pub fees: u128,
+}
+
+impl AccountMintStats {
+    pub fn size() -> usize {
+        AccountMintStats::default().try_to_vec().unwrap().len()
+    }
+}
+
+impl Stats for AccountMintStats {
+    fn bump_seed(&self) -> u8 {
+        self.bump_seed
+    }
+}
+
+// The trades the maker created and got filled, and the ones cancelled, that is closed by CloseStaleTrade, Settle without
+// a taker, ReclaimUnderlying or ArbiterRelease back to the maker
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Default, PartialEq)]
+pub struct AccountMakerStats {
+    pub initialized: bool,
+    pub bump_seed: u8,
+    pub maker: Pubkey,
+    pub created: u64,
+    pub filled: u64,
+    pub cancelled: u64,
+}
+
+impl AccountMakerStats {
+    pub fn size() -> usize {
+        AccountMakerStats::default().try_to_vec().unwrap().len()
+    }
+}
+
+impl Stats for AccountMakerStats {
+    fn bump_seed(&self) -> u8 {
+        self.bump_seed
+    }
 }